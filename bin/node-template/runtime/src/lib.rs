@@ -355,6 +355,7 @@ impl_runtime_apis! {
 		}
 	}
 
+	#[api_version(3)]
 	impl sp_api::Metadata<Block> for Runtime {
 		fn metadata() -> OpaqueMetadata {
 			OpaqueMetadata::new(Runtime::metadata().into())
@@ -367,6 +368,10 @@ impl_runtime_apis! {
 		fn metadata_versions() -> sp_std::vec::Vec<u32> {
 			Runtime::metadata_versions()
 		}
+
+		fn apis_metadata() -> OpaqueMetadata {
+			Runtime::apis_metadata()
+		}
 	}
 
 	impl sp_block_builder::BlockBuilder<Block> for Runtime {
@@ -71,6 +71,8 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		keystore: KeystoreConfig::InMemory,
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(64 * 1024 * 1024),
+		trie_cache_persistence: false,
+		index_storage_changes: false,
 		state_pruning: Some(PruningMode::ArchiveAll),
 		blocks_pruning: BlocksPruning::KeepAll,
 		chain_spec: spec,
@@ -111,6 +113,11 @@ fn new_node(tokio_handle: Handle) -> node_cli::service::NewFullBase {
 		base_path: Some(base_path),
 		informant_output_format: Default::default(),
 		wasm_runtime_overrides: None,
+		wasm_profile: None,
+		wasm_profile_metrics: false,
+		wasm_deterministic_stack_limit: None,
+		wasm_allocator: Default::default(),
+		overlay_memory_limit: None,
 	};
 
 	node_cli::service::new_full_base(config, false, |_, _| ())
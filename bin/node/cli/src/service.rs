@@ -30,7 +30,7 @@ use node_executor::ExecutorDispatch;
 use node_primitives::Block;
 use sc_client_api::BlockBackend;
 use sc_consensus_babe::{self, SlotProportion};
-use sc_executor::NativeElseWasmExecutor;
+use sc_executor::{NativeElseWasmExecutor, RuntimeCallProfiler};
 use sc_network::{event::Event, NetworkEventStream, NetworkService};
 use sc_network_common::sync::warp::WarpSyncParams;
 use sc_network_sync::SyncingService;
@@ -163,13 +163,30 @@ pub fn new_partial(
 		})
 		.transpose()?;
 
-	let executor = NativeElseWasmExecutor::<ExecutorDispatch>::new(
+	let mut executor = NativeElseWasmExecutor::<ExecutorDispatch>::new(
 		config.wasm_method,
 		config.default_heap_pages,
 		config.max_runtime_instances,
 		config.runtime_cache_size,
 	);
 
+	executor.set_deterministic_stack_limit(config.wasm_deterministic_stack_limit);
+	executor.set_allocator(config.wasm_allocator);
+
+	if config.wasm_profile.is_some() || config.wasm_profile_metrics {
+		let mut profiler = RuntimeCallProfiler::new();
+		if let Some(path) = &config.wasm_profile {
+			profiler = profiler.with_export(path)?;
+		}
+		if config.wasm_profile_metrics {
+			match config.prometheus_registry() {
+				Some(registry) => profiler = profiler.with_prometheus(registry)?,
+				None => log::warn!("`--wasm-profile-metrics` has no effect without Prometheus"),
+			}
+		}
+		executor.set_profiler(profiler);
+	}
+
 	let (client, backend, keystore_container, task_manager) =
 		sc_service::new_full_parts::<Block, RuntimeApi, _>(
 			config,
@@ -0,0 +1,203 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compares the allocators on an allocation pattern modelled after the import of a block.
+
+use criterion::{
+	criterion_group, criterion_main,
+	measurement::{Measurement, ValueFormatter},
+	Criterion, Throughput,
+};
+use sc_allocator::{AllocatorKind, HeapAllocator, Memory};
+use sp_wasm_interface::Pointer;
+
+const PAGE_SIZE: usize = 65536;
+const HEAP_BASE: u32 = 1024 * 1024;
+const EXTRINSICS: usize = 2000;
+const INITIAL_PROOF_SIZE: u32 = 64 * 1024;
+const PROOF_GROWTH: u32 = 256 * 1024;
+
+/// A growable linear memory without a maximum size.
+struct LinearMemory(Vec<u8>);
+
+impl Memory for LinearMemory {
+	fn with_access_mut<R>(&mut self, run: impl FnOnce(&mut [u8]) -> R) -> R {
+		run(&mut self.0)
+	}
+
+	fn with_access<R>(&self, run: impl FnOnce(&[u8]) -> R) -> R {
+		run(&self.0)
+	}
+
+	fn grow(&mut self, additional: u32) -> Result<(), ()> {
+		self.0.resize(self.0.len() + additional as usize * PAGE_SIZE, 0);
+		Ok(())
+	}
+
+	fn pages(&self) -> u32 {
+		(self.0.len() / PAGE_SIZE) as u32
+	}
+
+	fn max_pages(&self) -> Option<u32> {
+		None
+	}
+}
+
+/// A deterministic xorshift generator, so that every run sees the same sizes.
+struct Rng(u64);
+
+impl Rng {
+	fn range(&mut self, min: u32, max: u32) -> u32 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		min + (self.0 % (max - min) as u64) as u32
+	}
+}
+
+/// Runs the block workload and returns the address space the allocator used.
+///
+/// Every extrinsic is decoded into a medium sized buffer, reads a couple of small storage values
+/// and deposits an event that lives until the end of the block. Every few extrinsics the storage
+/// proof is re-encoded into a larger buffer, like the PoV of a parachain block that is built up
+/// while the block is executed. The final proof is about 5MiB large.
+fn import_block(kind: AllocatorKind) -> u32 {
+	let mut mem = LinearMemory(vec![0; HEAP_BASE as usize]);
+	let mut heap = HeapAllocator::new(kind, HEAP_BASE);
+	let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+	let allocate = |heap: &mut HeapAllocator, mem: &mut LinearMemory, size| -> Pointer<u8> {
+		heap.allocate(mem, size).expect("the workload fits into the heap")
+	};
+
+	let mut events = Vec::with_capacity(EXTRINSICS);
+	let mut proof_size = INITIAL_PROOF_SIZE;
+	let mut proof = allocate(&mut heap, &mut mem, proof_size);
+
+	for i in 0..EXTRINSICS {
+		let extrinsic = allocate(&mut heap, &mut mem, rng.range(128, 4096));
+
+		for _ in 0..8 {
+			let value = allocate(&mut heap, &mut mem, rng.range(8, 160));
+			heap.deallocate(&mut mem, value).unwrap();
+		}
+		events.push(allocate(&mut heap, &mut mem, rng.range(48, 256)));
+
+		heap.deallocate(&mut mem, extrinsic).unwrap();
+
+		if i % 100 == 99 {
+			proof_size += PROOF_GROWTH;
+			let grown = allocate(&mut heap, &mut mem, proof_size);
+			heap.deallocate(&mut mem, proof).unwrap();
+			proof = grown;
+		}
+	}
+
+	heap.stats().address_space_used
+}
+
+/// Measures the address space an allocator used instead of the time it took.
+///
+/// The value has to be reported by the benchmark through `iter_custom`, `start` and `end` don't
+/// measure anything.
+struct AddressSpace;
+
+impl Measurement for AddressSpace {
+	type Intermediate = ();
+	type Value = u64;
+
+	fn start(&self) -> Self::Intermediate {}
+
+	fn end(&self, _: Self::Intermediate) -> Self::Value {
+		0
+	}
+
+	fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+		v1 + v2
+	}
+
+	fn zero(&self) -> Self::Value {
+		0
+	}
+
+	fn to_f64(&self, value: &Self::Value) -> f64 {
+		*value as f64
+	}
+
+	fn formatter(&self) -> &dyn ValueFormatter {
+		&BytesFormatter
+	}
+}
+
+struct BytesFormatter;
+
+impl ValueFormatter for BytesFormatter {
+	fn scale_values(&self, typical_value: f64, values: &mut [f64]) -> &'static str {
+		let (factor, unit) = if typical_value < 1024.0 {
+			(1.0, "B")
+		} else if typical_value < 1024.0 * 1024.0 {
+			(1024.0, "KiB")
+		} else {
+			(1024.0 * 1024.0, "MiB")
+		};
+		values.iter_mut().for_each(|v| *v /= factor);
+		unit
+	}
+
+	fn scale_throughputs(
+		&self,
+		_typical_value: f64,
+		_throughput: &Throughput,
+		_values: &mut [f64],
+	) -> &'static str {
+		"B"
+	}
+
+	fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+		"B"
+	}
+}
+
+const ALLOCATORS: [(&str, AllocatorKind); 2] = [
+	("freeing_bump", AllocatorKind::FreeingBump),
+	("segregated_fits", AllocatorKind::SegregatedFits),
+];
+
+fn bench_block_workload(c: &mut Criterion) {
+	let mut group = c.benchmark_group("block_workload");
+	for (name, kind) in ALLOCATORS {
+		group.bench_function(name, |b| b.iter(|| import_block(kind)));
+	}
+}
+
+fn bench_block_workload_address_space(c: &mut Criterion<AddressSpace>) {
+	let mut group = c.benchmark_group("block_workload_address_space");
+	// The workload is deterministic, every sample reports the same value.
+	group.sample_size(10);
+	for (name, kind) in ALLOCATORS {
+		group.bench_function(name, |b| {
+			b.iter_custom(|iters| (0..iters).map(|_| import_block(kind) as u64).sum())
+		});
+	}
+}
+
+criterion_group!(benches, bench_block_workload);
+criterion_group! {
+	name = address_space;
+	config = Criterion::default().with_measurement(AddressSpace);
+	targets = bench_block_workload_address_space
+}
+criterion_main!(benches, address_space);
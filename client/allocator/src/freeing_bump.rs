@@ -80,18 +80,18 @@ use std::{
 ///
 /// The alignment of 8 is chosen because it is the maximum size of a primitive type supported by the
 /// target version of wasm32: i64's natural alignment is 8.
-const ALIGNMENT: u32 = 8;
+pub(crate) const ALIGNMENT: u32 = 8;
 
 // Each pointer is prefixed with 8 bytes, which identify the list index
 // to which it belongs.
 const HEADER_SIZE: u32 = 8;
 
 /// Create an allocator error.
-fn error(msg: &'static str) -> Error {
+pub(crate) fn error(msg: &'static str) -> Error {
 	Error::Other(msg)
 }
 
-const LOG_TARGET: &str = "wasm-heap";
+pub(crate) const LOG_TARGET: &str = "wasm-heap";
 
 // The minimum possible allocation size is chosen to be 8 bytes because in that case we would have
 // easier time to provide the guaranteed alignment of 8.
@@ -102,8 +102,8 @@ const LOG_TARGET: &str = "wasm-heap";
 //
 // This number corresponds to the number of powers between the minimum possible allocation and
 // maximum possible allocation, or: 2^3...2^25 (both ends inclusive, hence 23).
-const N_ORDERS: usize = 23;
-const MIN_POSSIBLE_ALLOCATION: u32 = 8; // 2^3 bytes, 8 bytes
+pub(crate) const N_ORDERS: usize = 23;
+pub(crate) const MIN_POSSIBLE_ALLOCATION: u32 = 8; // 2^3 bytes, 8 bytes
 
 /// The exponent for the power of two sized block adjusted to the minimum size.
 ///
@@ -120,7 +120,7 @@ const MIN_POSSIBLE_ALLOCATION: u32 = 8; // 2^3 bytes, 8 bytes
 ///
 /// and so on.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-struct Order(u32);
+pub(crate) struct Order(u32);
 
 impl Order {
 	/// Create `Order` object from a raw order.
@@ -139,7 +139,7 @@ impl Order {
 	/// The size is clamped, so that the following holds:
 	///
 	/// `MIN_POSSIBLE_ALLOCATION <= size <= MAX_POSSIBLE_ALLOCATION`
-	fn from_size(size: u32) -> Result<Self, Error> {
+	pub(crate) fn from_size(size: u32) -> Result<Self, Error> {
 		let clamped_size = if size > MAX_POSSIBLE_ALLOCATION {
 			log::warn!(target: LOG_TARGET, "going to fail due to allocating {:?}", size);
 			return Err(Error::RequestedAllocationTooLarge)
@@ -164,10 +164,15 @@ impl Order {
 	/// Returns the corresponding size in bytes for this order.
 	///
 	/// Note that it is always a power of two.
-	fn size(&self) -> u32 {
+	pub(crate) fn size(&self) -> u32 {
 		MIN_POSSIBLE_ALLOCATION << self.0
 	}
 
+	/// Returns the index of this order, starting at `0` for the smallest one.
+	pub(crate) fn index(&self) -> usize {
+		self.0 as usize
+	}
+
 	/// Extract the order as `u32`.
 	fn into_raw(self) -> u32 {
 		self.0
@@ -315,6 +320,74 @@ impl IndexMut<Order> for FreeLists {
 	}
 }
 
+/// Allocation stats of a single size class.
+///
+/// The allocations are grouped into power of two size classes by the size of the memory reserved
+/// for them, i.e. the requested size plus any rounding and headers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SizeClassStats {
+	/// The maximum number of bytes reserved for an allocation of this class.
+	pub size: u32,
+
+	/// The current number of live allocations.
+	pub allocations: u32,
+
+	/// The number of bytes currently reserved for live allocations.
+	pub bytes_allocated: u32,
+
+	/// The sum of the sizes requested by every allocation ever made.
+	pub bytes_requested_sum: u128,
+
+	/// The sum of the bytes reserved for every allocation ever made.
+	///
+	/// The difference to [`Self::bytes_requested_sum`] is lost to internal fragmentation.
+	pub bytes_allocated_sum: u128,
+
+	/// The current number of free chunks the allocator keeps for reuse.
+	pub free_chunks: u32,
+
+	/// The number of bytes in free chunks.
+	///
+	/// This memory is lost to external fragmentation as long as it can't be used for allocations
+	/// of other sizes.
+	pub bytes_free: u32,
+}
+
+impl SizeClassStats {
+	/// Note a new allocation that reserved `reserved` bytes for `requested` bytes.
+	pub(crate) fn note_allocated(&mut self, requested: u32, reserved: u32) {
+		self.allocations += 1;
+		self.bytes_allocated += reserved;
+		self.bytes_requested_sum += u128::from(requested);
+		self.bytes_allocated_sum += u128::from(reserved);
+	}
+
+	/// Note that an allocation that reserved `reserved` bytes was freed.
+	pub(crate) fn note_deallocated(&mut self, reserved: u32) -> Result<(), Error> {
+		self.allocations = self
+			.allocations
+			.checked_sub(1)
+			.ok_or_else(|| error("underflow of the allocations count"))?;
+		self.bytes_allocated = self
+			.bytes_allocated
+			.checked_sub(reserved)
+			.ok_or_else(|| error("underflow of the allocated bytes count"))?;
+		Ok(())
+	}
+
+	/// Note that a free chunk of `size` bytes was added or, if `added` is `false`, removed.
+	pub(crate) fn note_free_chunk(&mut self, size: u32, added: bool) {
+		if added {
+			self.free_chunks += 1;
+			self.bytes_free += size;
+		} else {
+			self.free_chunks -= 1;
+			self.bytes_free -= size;
+		}
+	}
+}
+
 /// Memory allocation stats gathered during the lifetime of the allocator.
 #[derive(Clone, Debug, Default)]
 #[non_exhaustive]
@@ -342,6 +415,28 @@ pub struct AllocationStats {
 	/// Currently the bumper's only ever incremented, so this is simultaneously
 	/// the current value as well as the peak value.
 	pub address_space_used: u32,
+
+	/// The stats of every size class, starting with the smallest one.
+	pub size_classes: Vec<SizeClassStats>,
+}
+
+impl AllocationStats {
+	/// Create empty stats with one entry per size class.
+	pub(crate) fn new() -> Self {
+		let size_classes = (0..N_ORDERS as u32)
+			.map(|order| SizeClassStats { size: Order(order).size(), ..Default::default() })
+			.collect();
+		Self { size_classes, ..Default::default() }
+	}
+
+	/// Returns the stats of the size class of allocations reserving `size` bytes.
+	pub(crate) fn size_class_mut(&mut self, size: u32) -> &mut SizeClassStats {
+		// Sizes beyond the largest class can only be observed for free chunks, which are
+		// accounted to the largest class.
+		let index = Order::from_size(size.min(MAX_POSSIBLE_ALLOCATION))
+			.map_or(N_ORDERS - 1, |order| order.index());
+		&mut self.size_classes[index]
+	}
 }
 
 /// Convert the given `size` in bytes into the number of pages.
@@ -386,7 +481,7 @@ impl FreeingBumpHeapAllocator {
 			free_lists: FreeLists::new(),
 			poisoned: false,
 			last_observed_memory_size: 0,
-			stats: AllocationStats::default(),
+			stats: AllocationStats::new(),
 		}
 	}
 
@@ -419,6 +514,7 @@ impl FreeingBumpHeapAllocator {
 		Self::observe_memory_size(&mut self.last_observed_memory_size, mem)?;
 		let order = Order::from_size(size)?;
 
+		let size_class = &mut self.stats.size_classes[order.index()];
 		let header_ptr: u32 = match self.free_lists[order] {
 			Link::Ptr(header_ptr) => {
 				assert!(
@@ -432,6 +528,7 @@ impl FreeingBumpHeapAllocator {
 					.into_free()
 					.ok_or_else(|| error("free list points to a occupied header"))?;
 				self.free_lists[order] = next_free;
+				size_class.note_free_chunk(order.size() + HEADER_SIZE, false);
 
 				header_ptr
 			},
@@ -443,6 +540,7 @@ impl FreeingBumpHeapAllocator {
 
 		// Write the order in the occupied header.
 		Header::Occupied(order).write_into(mem, header_ptr)?;
+		size_class.note_allocated(size, order.size() + HEADER_SIZE);
 
 		self.stats.bytes_allocated += order.size() + HEADER_SIZE;
 		self.stats.bytes_allocated_sum += u128::from(order.size() + HEADER_SIZE);
@@ -493,6 +591,9 @@ impl FreeingBumpHeapAllocator {
 			.bytes_allocated
 			.checked_sub(order.size() + HEADER_SIZE)
 			.ok_or_else(|| error("underflow of the currently allocated bytes count"))?;
+		let size_class = &mut self.stats.size_classes[order.index()];
+		size_class.note_deallocated(order.size() + HEADER_SIZE)?;
+		size_class.note_free_chunk(order.size() + HEADER_SIZE, true);
 
 		log::trace!("after deallocation: {:?}", self.stats);
 
@@ -509,7 +610,11 @@ impl FreeingBumpHeapAllocator {
 	///
 	/// Returns the `bumper` from before the increase. Returns an `Error::AllocatorOutOfSpace` if
 	/// the operation would exhaust the heap.
-	fn bump(bumper: &mut u32, size: u32, memory: &mut impl Memory) -> Result<u32, Error> {
+	pub(crate) fn bump(
+		bumper: &mut u32,
+		size: u32,
+		memory: &mut impl Memory,
+	) -> Result<u32, Error> {
 		let required_size = u64::from(*bumper) + u64::from(size);
 
 		if required_size > memory.size() {
@@ -562,7 +667,7 @@ impl FreeingBumpHeapAllocator {
 		Ok(res)
 	}
 
-	fn observe_memory_size(
+	pub(crate) fn observe_memory_size(
 		last_observed_memory_size: &mut u64,
 		mem: &mut impl Memory,
 	) -> Result<(), Error> {
@@ -581,7 +686,7 @@ impl FreeingBumpHeapAllocator {
 /// accessible up to the reported size.
 ///
 /// The linear memory can grow in size with the wasm page granularity (64KiB), but it cannot shrink.
-trait MemoryExt: Memory {
+pub(crate) trait MemoryExt: Memory {
 	/// Read a u64 from the heap in LE form. Returns an error if any of the bytes read are out of
 	/// bounds.
 	fn read_le_u64(&self, ptr: u32) -> Result<u64, Error> {
@@ -19,14 +19,21 @@
 //!
 //! This crate provides the following allocator implementations:
 //! - A freeing-bump allocator: [`FreeingBumpHeapAllocator`](freeing_bump::FreeingBumpHeapAllocator)
+//! - A segregated-fits allocator:
+//!   [`SegregatedFitsHeapAllocator`](segregated_fits::SegregatedFitsHeapAllocator)
+//!
+//! [`HeapAllocator`] dispatches to the implementation selected by an [`AllocatorKind`].
 
 #![warn(missing_docs)]
 
 mod error;
 mod freeing_bump;
+mod segregated_fits;
 
 pub use error::Error;
-pub use freeing_bump::{AllocationStats, FreeingBumpHeapAllocator};
+pub use freeing_bump::{AllocationStats, FreeingBumpHeapAllocator, SizeClassStats};
+pub use segregated_fits::SegregatedFitsHeapAllocator;
+use sp_wasm_interface::{Pointer, WordSize};
 
 /// The size of one wasm page in bytes.
 ///
@@ -59,3 +66,61 @@ pub trait Memory {
 	/// If `None` is returned, there is no maximum (besides the maximum defined in the wasm spec).
 	fn max_pages(&self) -> Option<u32>;
 }
+
+/// The allocator implementations to choose from.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AllocatorKind {
+	/// The [`FreeingBumpHeapAllocator`].
+	#[default]
+	FreeingBump,
+	/// The [`SegregatedFitsHeapAllocator`].
+	SegregatedFits,
+}
+
+/// One of the allocator implementations, selected by an [`AllocatorKind`].
+pub enum HeapAllocator {
+	/// A [`FreeingBumpHeapAllocator`].
+	FreeingBump(FreeingBumpHeapAllocator),
+	/// A [`SegregatedFitsHeapAllocator`].
+	SegregatedFits(SegregatedFitsHeapAllocator),
+}
+
+impl HeapAllocator {
+	/// Creates a new allocator of the given `kind` for the heap starting at `heap_base`.
+	pub fn new(kind: AllocatorKind, heap_base: u32) -> Self {
+		match kind {
+			AllocatorKind::FreeingBump =>
+				Self::FreeingBump(FreeingBumpHeapAllocator::new(heap_base)),
+			AllocatorKind::SegregatedFits =>
+				Self::SegregatedFits(SegregatedFitsHeapAllocator::new(heap_base)),
+		}
+	}
+
+	/// Allocates `size` bytes and returns a pointer to them.
+	pub fn allocate(
+		&mut self,
+		mem: &mut impl Memory,
+		size: WordSize,
+	) -> Result<Pointer<u8>, Error> {
+		match self {
+			Self::FreeingBump(allocator) => allocator.allocate(mem, size),
+			Self::SegregatedFits(allocator) => allocator.allocate(mem, size),
+		}
+	}
+
+	/// Deallocates the allocation at `ptr`.
+	pub fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		match self {
+			Self::FreeingBump(allocator) => allocator.deallocate(mem, ptr),
+			Self::SegregatedFits(allocator) => allocator.deallocate(mem, ptr),
+		}
+	}
+
+	/// Returns the allocation stats for this allocator.
+	pub fn stats(&self) -> AllocationStats {
+		match self {
+			Self::FreeingBump(allocator) => allocator.stats(),
+			Self::SegregatedFits(allocator) => allocator.stats(),
+		}
+	}
+}
@@ -0,0 +1,460 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module implements a segregated-fits allocator with coalescing.
+//!
+//! The heap is split into chunks, every chunk is either allocated or free. Chunks are carved out
+//! of the end of the heap, the so called wilderness:
+//!
+//! ```ignore
+//! +-----------+--------+-----------+---------------------------------+
+//! | <alloc'd> | <free> | <alloc'd> | <wilderness>                    |
+//! +-----------+--------+-----------+---------------------------------+
+//!                                  ^
+//!                                  |_ bumper
+//! ```
+//!
+//! Sizes are only rounded up to the alignment of 8 bytes. The free chunks are kept in one list per
+//! power of two size class. An allocation takes the smallest free chunk that fits from the first
+//! non-empty list that can serve it and splits off the remainder. Only if there is no such chunk,
+//! the allocation is taken from the wilderness.
+//!
+//! On deallocation a chunk is merged with its free neighbours. A free chunk that borders the
+//! wilderness is given back to it. Therefore, in contrast to the
+//! [`FreeingBumpHeapAllocator`](crate::FreeingBumpHeapAllocator), freed memory can be reused for
+//! allocations of any size and the heap doesn't fill up with chunks of sizes nobody asks for
+//! anymore.
+//!
+//! All the bookkeeping is done on the host side, the allocator never writes to the linear memory.
+//! Chunks don't carry headers and a corrupted linear memory can't corrupt the allocator.
+
+use crate::{
+	freeing_bump::{
+		error, FreeingBumpHeapAllocator, ALIGNMENT, LOG_TARGET, MIN_POSSIBLE_ALLOCATION, N_ORDERS,
+	},
+	AllocationStats, Error, Memory,
+};
+use sp_core::MAX_POSSIBLE_ALLOCATION;
+use sp_wasm_interface::{Pointer, WordSize};
+use std::{
+	cmp::max,
+	collections::{BTreeMap, BTreeSet},
+};
+
+/// Returns the index of the free list for chunks of `size` bytes.
+///
+/// Every chunk in the list with index `i` is at least `MIN_POSSIBLE_ALLOCATION << i` bytes large.
+fn free_list_index(size: u32) -> usize {
+	let floor_log2 = |size: u32| 31 - size.leading_zeros();
+	let index = floor_log2(size.max(MIN_POSSIBLE_ALLOCATION)) - floor_log2(MIN_POSSIBLE_ALLOCATION);
+	(index as usize).min(N_ORDERS - 1)
+}
+
+/// An implementation of a segregated-fits allocator with coalescing.
+///
+/// Refer to the module-level documentation for further details.
+pub struct SegregatedFitsHeapAllocator {
+	original_heap_base: u32,
+	/// The start of the wilderness.
+	bumper: u32,
+	/// The sizes of the live allocations by their address.
+	allocations: BTreeMap<u32, u32>,
+	/// The free chunks by their address, used for finding the neighbours to merge with.
+	free_chunks: BTreeMap<u32, u32>,
+	/// The free chunks as `(size, address)`, segregated into lists by their size.
+	free_lists: Vec<BTreeSet<(u32, u32)>>,
+	poisoned: bool,
+	last_observed_memory_size: u64,
+	stats: AllocationStats,
+}
+
+impl Drop for SegregatedFitsHeapAllocator {
+	fn drop(&mut self) {
+		log::debug!(target: LOG_TARGET, "allocator dropped: {:?}", self.stats)
+	}
+}
+
+impl SegregatedFitsHeapAllocator {
+	/// Creates a new allocation heap which follows a segregated-fits strategy.
+	///
+	/// # Arguments
+	///
+	/// - `heap_base` - the offset from the beginning of the linear memory where the heap starts.
+	pub fn new(heap_base: u32) -> Self {
+		let aligned_heap_base = (heap_base + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT;
+
+		SegregatedFitsHeapAllocator {
+			original_heap_base: aligned_heap_base,
+			bumper: aligned_heap_base,
+			allocations: BTreeMap::new(),
+			free_chunks: BTreeMap::new(),
+			free_lists: vec![BTreeSet::new(); N_ORDERS],
+			poisoned: false,
+			last_observed_memory_size: 0,
+			stats: AllocationStats::new(),
+		}
+	}
+
+	/// Gets requested number of bytes to allocate and returns a pointer.
+	/// The maximum size which can be allocated at once is 32 MiB.
+	/// The size is rounded up to the next multiple of 8 bytes.
+	///
+	/// The identity or the type of the passed memory object does not matter. However, the size of
+	/// memory cannot shrink compared to the memory passed in previous invocations.
+	///
+	/// NOTE: Once the allocator has returned an error all subsequent requests will return an error.
+	///
+	/// # Arguments
+	///
+	/// - `mem` - a slice representing the linear memory on which this allocator operates.
+	/// - `size` - size in bytes of the allocation request
+	pub fn allocate(
+		&mut self,
+		mem: &mut impl Memory,
+		size: WordSize,
+	) -> Result<Pointer<u8>, Error> {
+		if self.poisoned {
+			return Err(error("the allocator has been poisoned"))
+		}
+
+		let result = self.allocate_impl(mem, size);
+		self.poisoned = result.is_err();
+		result
+	}
+
+	fn allocate_impl(
+		&mut self,
+		mem: &mut impl Memory,
+		size: WordSize,
+	) -> Result<Pointer<u8>, Error> {
+		FreeingBumpHeapAllocator::observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+		if size > MAX_POSSIBLE_ALLOCATION {
+			log::warn!(target: LOG_TARGET, "going to fail due to allocating {:?}", size);
+			return Err(Error::RequestedAllocationTooLarge)
+		}
+		let chunk_size =
+			(max(size, MIN_POSSIBLE_ALLOCATION) + ALIGNMENT - 1) / ALIGNMENT * ALIGNMENT;
+
+		let ptr = match self.take_free_chunk(chunk_size) {
+			Some((ptr, free_size)) => {
+				if free_size > chunk_size {
+					self.insert_free_chunk(ptr + chunk_size, free_size - chunk_size);
+				}
+				ptr
+			},
+			None => FreeingBumpHeapAllocator::bump(&mut self.bumper, chunk_size, mem)?,
+		};
+
+		self.allocations.insert(ptr, chunk_size);
+		self.stats.size_class_mut(chunk_size).note_allocated(size, chunk_size);
+		self.stats.bytes_allocated += chunk_size;
+		self.stats.bytes_allocated_sum += u128::from(chunk_size);
+		self.stats.bytes_allocated_peak =
+			max(self.stats.bytes_allocated_peak, self.stats.bytes_allocated);
+		self.stats.address_space_used =
+			max(self.stats.address_space_used, self.bumper - self.original_heap_base);
+
+		log::trace!(target: LOG_TARGET, "after allocation: {:?}", self.stats);
+
+		Ok(Pointer::new(ptr))
+	}
+
+	/// Deallocates the space which was allocated for a pointer.
+	///
+	/// The identity or the type of the passed memory object does not matter. However, the size of
+	/// memory cannot shrink compared to the memory passed in previous invocations.
+	///
+	/// NOTE: Once the allocator has returned an error all subsequent requests will return an error.
+	///
+	/// # Arguments
+	///
+	/// - `mem` - a slice representing the linear memory on which this allocator operates.
+	/// - `ptr` - pointer to the allocated chunk
+	pub fn deallocate(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		if self.poisoned {
+			return Err(error("the allocator has been poisoned"))
+		}
+
+		let result = self.deallocate_impl(mem, ptr);
+		self.poisoned = result.is_err();
+		result
+	}
+
+	fn deallocate_impl(&mut self, mem: &mut impl Memory, ptr: Pointer<u8>) -> Result<(), Error> {
+		FreeingBumpHeapAllocator::observe_memory_size(&mut self.last_observed_memory_size, mem)?;
+
+		let mut start = u32::from(ptr);
+		let size = self
+			.allocations
+			.remove(&start)
+			.ok_or_else(|| error("Invalid pointer for deallocation"))?;
+
+		self.stats.size_class_mut(size).note_deallocated(size)?;
+		self.stats.bytes_allocated = self
+			.stats
+			.bytes_allocated
+			.checked_sub(size)
+			.ok_or_else(|| error("underflow of the currently allocated bytes count"))?;
+
+		// Merge the chunk with its free neighbours.
+		let mut end = start + size;
+		if let Some((&prev, &prev_size)) = self.free_chunks.range(..start).next_back() {
+			if prev + prev_size == start {
+				self.remove_free_chunk(prev, prev_size);
+				start = prev;
+			}
+		}
+		if let Some(&next_size) = self.free_chunks.get(&end) {
+			self.remove_free_chunk(end, next_size);
+			end += next_size;
+		}
+
+		if end == self.bumper {
+			// Give the chunk back to the wilderness.
+			self.bumper = start;
+		} else {
+			self.insert_free_chunk(start, end - start);
+		}
+
+		log::trace!(target: LOG_TARGET, "after deallocation: {:?}", self.stats);
+
+		Ok(())
+	}
+
+	/// Returns the allocation stats for this allocator.
+	pub fn stats(&self) -> AllocationStats {
+		self.stats.clone()
+	}
+
+	/// Remove the smallest free chunk with at least `size` bytes.
+	///
+	/// Returns the address and size of the chunk.
+	fn take_free_chunk(&mut self, size: u32) -> Option<(u32, u32)> {
+		let first_list = free_list_index(size);
+		let (size, ptr) = self.free_lists[first_list]
+			.range((size, 0)..)
+			.next()
+			.or_else(|| {
+				// Every chunk in the following lists is large enough.
+				self.free_lists[first_list + 1..].iter().find_map(|list| list.iter().next())
+			})
+			.copied()?;
+
+		self.remove_free_chunk(ptr, size);
+		Some((ptr, size))
+	}
+
+	fn insert_free_chunk(&mut self, ptr: u32, size: u32) {
+		self.free_chunks.insert(ptr, size);
+		self.free_lists[free_list_index(size)].insert((size, ptr));
+		self.stats.size_class_mut(size).note_free_chunk(size, true);
+	}
+
+	fn remove_free_chunk(&mut self, ptr: u32, size: u32) {
+		self.free_chunks.remove(&ptr);
+		self.free_lists[free_list_index(size)].remove(&(size, ptr));
+		self.stats.size_class_mut(size).note_free_chunk(size, false);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{FreeingBumpHeapAllocator, PAGE_SIZE};
+
+	#[derive(Debug)]
+	struct MemoryInstance {
+		data: Vec<u8>,
+		max_wasm_pages: u32,
+	}
+
+	impl MemoryInstance {
+		fn with_pages(pages: u32) -> Self {
+			Self { data: vec![0; (pages * PAGE_SIZE) as usize], max_wasm_pages: pages }
+		}
+	}
+
+	impl Memory for MemoryInstance {
+		fn with_access<R>(&self, run: impl FnOnce(&[u8]) -> R) -> R {
+			run(&self.data)
+		}
+
+		fn with_access_mut<R>(&mut self, run: impl FnOnce(&mut [u8]) -> R) -> R {
+			run(&mut self.data)
+		}
+
+		fn pages(&self) -> u32 {
+			(self.data.len() / PAGE_SIZE as usize) as u32
+		}
+
+		fn max_pages(&self) -> Option<u32> {
+			Some(self.max_wasm_pages)
+		}
+
+		fn grow(&mut self, pages: u32) -> Result<(), ()> {
+			if self.pages() + pages > self.max_wasm_pages {
+				Err(())
+			} else {
+				self.data.resize(((self.pages() + pages) * PAGE_SIZE) as usize, 0);
+				Ok(())
+			}
+		}
+	}
+
+	#[test]
+	fn should_allocate_aligned_and_adjacent_chunks() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(13);
+
+		let ptr1 = heap.allocate(&mut mem, 1).unwrap();
+		let ptr2 = heap.allocate(&mut mem, 9).unwrap();
+		let ptr3 = heap.allocate(&mut mem, 24).unwrap();
+
+		assert_eq!(ptr1, Pointer::new(16));
+		assert_eq!(ptr2, Pointer::new(24));
+		assert_eq!(ptr3, Pointer::new(40));
+		assert_eq!(heap.stats().bytes_allocated, 48);
+	}
+
+	#[test]
+	fn should_reuse_the_best_fitting_free_chunk() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		let large = heap.allocate(&mut mem, 256).unwrap();
+		let _guard1 = heap.allocate(&mut mem, 8).unwrap();
+		let small = heap.allocate(&mut mem, 64).unwrap();
+		let _guard2 = heap.allocate(&mut mem, 8).unwrap();
+
+		heap.deallocate(&mut mem, large).unwrap();
+		heap.deallocate(&mut mem, small).unwrap();
+
+		// The small chunk fits best.
+		assert_eq!(heap.allocate(&mut mem, 40).unwrap(), small);
+		// The remainder of the small chunk is too small, so the large chunk is split.
+		assert_eq!(heap.allocate(&mut mem, 100).unwrap(), large);
+		assert_eq!(heap.allocate(&mut mem, 24).unwrap(), Pointer::new(u32::from(small) + 40));
+		assert_eq!(heap.allocate(&mut mem, 150).unwrap(), Pointer::new(u32::from(large) + 104));
+	}
+
+	#[test]
+	fn should_coalesce_free_neighbours() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		let ptr1 = heap.allocate(&mut mem, 64).unwrap();
+		let ptr2 = heap.allocate(&mut mem, 64).unwrap();
+		let ptr3 = heap.allocate(&mut mem, 64).unwrap();
+		let _guard = heap.allocate(&mut mem, 8).unwrap();
+
+		heap.deallocate(&mut mem, ptr1).unwrap();
+		heap.deallocate(&mut mem, ptr3).unwrap();
+		heap.deallocate(&mut mem, ptr2).unwrap();
+
+		let stats = heap.stats();
+		let free_chunks: u32 = stats.size_classes.iter().map(|class| class.free_chunks).sum();
+		assert_eq!(free_chunks, 1);
+		assert_eq!(heap.allocate(&mut mem, 192).unwrap(), ptr1);
+	}
+
+	#[test]
+	fn should_give_free_chunks_back_to_the_wilderness() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		let ptr1 = heap.allocate(&mut mem, 64).unwrap();
+		let ptr2 = heap.allocate(&mut mem, 64).unwrap();
+		heap.deallocate(&mut mem, ptr1).unwrap();
+		heap.deallocate(&mut mem, ptr2).unwrap();
+
+		assert_eq!(heap.bumper, 0);
+		assert!(heap.free_chunks.is_empty());
+		assert_eq!(heap.stats().address_space_used, 128);
+	}
+
+	#[test]
+	fn should_reuse_memory_for_different_sizes() {
+		// This is the caveat of the freeing-bump allocator: a freed chunk can only be reused for
+		// allocations of the same size class.
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut freeing_bump = FreeingBumpHeapAllocator::new(0);
+		let ptr = freeing_bump.allocate(&mut mem, PAGE_SIZE / 2).unwrap();
+		freeing_bump.deallocate(&mut mem, ptr).unwrap();
+		freeing_bump.allocate(&mut mem, PAGE_SIZE / 4).unwrap();
+		assert_eq!(freeing_bump.allocate(&mut mem, PAGE_SIZE / 4), Err(Error::AllocatorOutOfSpace));
+
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+		let ptr = heap.allocate(&mut mem, PAGE_SIZE / 2).unwrap();
+		heap.deallocate(&mut mem, ptr).unwrap();
+		for _ in 0..4 {
+			heap.allocate(&mut mem, PAGE_SIZE / 4).unwrap();
+		}
+	}
+
+	#[test]
+	fn should_not_allocate_if_too_large() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		assert_eq!(heap.allocate(&mut mem, PAGE_SIZE + 1), Err(Error::AllocatorOutOfSpace));
+		// The allocator is poisoned afterwards.
+		assert!(heap.allocate(&mut mem, 8).is_err());
+
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+		assert_eq!(
+			heap.allocate(&mut mem, MAX_POSSIBLE_ALLOCATION + 1),
+			Err(Error::RequestedAllocationTooLarge)
+		);
+	}
+
+	#[test]
+	fn should_reject_invalid_pointers() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		let ptr = heap.allocate(&mut mem, 64).unwrap();
+		assert!(heap.deallocate(&mut mem, Pointer::new(u32::from(ptr) + 8)).is_err());
+
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+		let ptr = heap.allocate(&mut mem, 64).unwrap();
+		heap.deallocate(&mut mem, ptr).unwrap();
+		assert!(heap.deallocate(&mut mem, ptr).is_err());
+	}
+
+	#[test]
+	fn should_track_size_class_stats() {
+		let mut mem = MemoryInstance::with_pages(1);
+		let mut heap = SegregatedFitsHeapAllocator::new(0);
+
+		let ptr = heap.allocate(&mut mem, 33).unwrap();
+		let _guard = heap.allocate(&mut mem, 8).unwrap();
+
+		let stats = heap.stats();
+		// 33 bytes are rounded up to 40 bytes, which belong to the 64 byte class.
+		let class = &stats.size_classes[3];
+		assert_eq!(class.size, 64);
+		assert_eq!((class.allocations, class.bytes_allocated), (1, 40));
+		assert_eq!((class.bytes_requested_sum, class.bytes_allocated_sum), (33, 40));
+
+		heap.deallocate(&mut mem, ptr).unwrap();
+		let stats = heap.stats();
+		let class = &stats.size_classes[3];
+		assert_eq!((class.allocations, class.bytes_allocated), (0, 0));
+		assert_eq!((class.free_chunks, class.bytes_free), (1, 40));
+	}
+}
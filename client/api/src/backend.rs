@@ -415,6 +415,26 @@ where
 	}
 }
 
+/// The key of a [`StorageDiffEntry`].
+///
+/// Keys of the main storage are ordered before the keys of any child storage.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StorageDiffKey {
+	/// The unprefixed storage key of the child trie, `None` for the main storage.
+	pub child_storage_key: Option<StorageKey>,
+	/// The storage key.
+	pub key: StorageKey,
+}
+
+/// A single entry of the storage diff between two blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageDiffEntry {
+	/// The key that was changed.
+	pub key: StorageDiffKey,
+	/// The value of the key at the target block, `None` if it was removed.
+	pub value: Option<StorageData>,
+}
+
 /// Provides access to storage primitives
 pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 	/// Given a block's `Hash` and a key, return the value under the key in that block.
@@ -476,6 +496,20 @@ pub trait StorageProvider<Block: BlockT, B: Backend<Block>> {
 		child_info: &ChildInfo,
 		key: &StorageKey,
 	) -> sp_blockchain::Result<Option<Block::Hash>>;
+
+	/// Returns the storage entries that changed between the blocks `from` and `to`, where `from`
+	/// must be an ancestor of `to`.
+	///
+	/// Entries are sorted by their key. At most `count` entries are returned, starting after
+	/// `start_key` if it is given. Requires the backend to index the storage changes of all blocks
+	/// after `from` up to and including `to`, see [`Backend::storage_changes`].
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		start_key: Option<&StorageDiffKey>,
+		count: usize,
+	) -> sp_blockchain::Result<Vec<StorageDiffEntry>>;
 }
 
 /// Client backend.
@@ -576,6 +610,16 @@ pub trait Backend<Block: BlockT>: AuxStore + Send + Sync {
 	/// Returns state backend with post-state of given block.
 	fn state_at(&self, hash: Block::Hash) -> sp_blockchain::Result<Self::State>;
 
+	/// Returns the storage changes that were applied by the block with the given `hash`.
+	///
+	/// Returns `None` if the changes of the block were not indexed.
+	fn storage_changes(
+		&self,
+		_hash: Block::Hash,
+	) -> sp_blockchain::Result<Option<(StorageCollection, ChildStorageCollection)>> {
+		Ok(None)
+	}
+
 	/// Attempts to revert the chain by `n` blocks. If `revert_finalized` is set it will attempt to
 	/// revert past any finalized block, this is unsafe and can potentially leave the node in an
 	/// inconsistent state. All blocks higher than the best block are also reverted and not counting
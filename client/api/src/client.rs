@@ -31,7 +31,7 @@ use crate::{blockchain::Info, notifications::StorageEventStream, FinalizeSummary
 
 use sc_transaction_pool_api::ChainEvent;
 use sc_utils::mpsc::{TracingUnboundedReceiver, TracingUnboundedSender};
-use sp_blockchain;
+use sp_blockchain::{self, TransactionHashAlgorithm};
 
 /// Type that implements `futures::Stream` of block import events.
 pub type ImportNotifications<Block> = TracingUnboundedReceiver<BlockImportNotification<Block>>;
@@ -149,6 +149,34 @@ pub trait BlockBackend<Block: BlockT> {
 		Ok(self.indexed_transaction(hash)?.is_some())
 	}
 
+	/// Get single indexed transaction by its digest under the given hashing algorithm.
+	///
+	/// Note that only Blake2b-256 digests are guaranteed to be supported, see
+	/// [`sp_blockchain::Backend::indexed_transaction_by`].
+	fn indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		let mut hash = Block::Hash::default();
+		if algorithm != TransactionHashAlgorithm::Blake2b256 || digest.len() != hash.as_ref().len()
+		{
+			return Ok(None)
+		}
+
+		hash.as_mut().copy_from_slice(digest);
+		self.indexed_transaction(hash)
+	}
+
+	/// Check if transaction index exists under the given digest.
+	fn has_indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> sp_blockchain::Result<bool> {
+		Ok(self.indexed_transaction_by(algorithm, digest)?.is_some())
+	}
+
 	/// Tells whether the current client configuration requires full-sync mode.
 	fn requires_full_sync(&self) -> bool;
 }
@@ -24,6 +24,7 @@
 
 use codec::Decode;
 use parking_lot::RwLock;
+use sc_executor::{RuntimeCallBlock, RuntimeCallBlockExt};
 use sc_transaction_pool_api::OffchainSubmitTransaction;
 use sp_core::{
 	offchain::{self, OffchainDbExt, OffchainWorkerExt, TransactionPoolExt},
@@ -34,7 +35,7 @@ use sp_externalities::{Extension, Extensions};
 use sp_keystore::{KeystoreExt, KeystorePtr};
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block as BlockT, NumberFor},
+	traits::{Block as BlockT, NumberFor, UniqueSaturatedInto},
 };
 pub use sp_state_machine::ExecutionStrategy;
 use sp_state_machine::{DefaultHandler, ExecutionManager};
@@ -264,6 +265,10 @@ impl<Block: BlockT> ExecutionExtensions<Block> {
 		}
 
 		extensions.register(ReadRuntimeVersionExt::new(self.read_runtime_version.clone()));
+		extensions.register(RuntimeCallBlockExt(RuntimeCallBlock {
+			hash: block_hash.as_ref().to_vec(),
+			number: block_number.unique_saturated_into(),
+		}));
 
 		extensions
 	}
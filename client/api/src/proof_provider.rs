@@ -66,6 +66,24 @@ pub trait ProofProvider<Block: BlockT> {
 		size_limit: usize,
 	) -> sp_blockchain::Result<(CompactProof, u32)>;
 
+	/// Reads at most `limit` storage values at a given block, returning compact read proof.
+	///
+	/// Only keys of the top trie (or of `child_info`) starting with `prefix`, strictly
+	/// after `start_key` and strictly before `end_key` are read. The proof always includes
+	/// the key following the last read one, so it also proves whether the end of the range
+	/// has been reached. Reading stops early once the proof exceeds `size_limit` bytes, after
+	/// at least one value.
+	fn read_range_proof(
+		&self,
+		hash: Block::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_key: Option<&[u8]>,
+		end_key: Option<&[u8]>,
+		limit: u32,
+		size_limit: usize,
+	) -> sp_blockchain::Result<CompactProof>;
+
 	/// Given a `Hash` iterate over all storage values starting at `start_key`.
 	/// Returns collected keys and values.
 	/// Returns the collected keys values content of the top trie followed by the
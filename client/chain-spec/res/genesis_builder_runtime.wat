@@ -0,0 +1,54 @@
+;; A minimal runtime implementing the `GenesisBuilder` runtime api, used by the tests.
+;;
+;; - `create_default_config` returns `{"a":1,"b":2}`.
+;; - `get_preset` returns the patch `{"b":3}` for the `development` preset and `None` otherwise.
+;; - `build_config` stores the SCALE encoded JSON it was called with under `:genesis` and
+;;   returns `Ok(())`.
+(module
+ (import "env" "ext_storage_set_version_1" (func $ext_storage_set_version_1 (param i64 i64)))
+ (global $__stack_pointer (mut i32) (i32.const 1048576))
+ (global $global$1 i32 (i32.const 1048648))
+ (global $global$2 i32 (i32.const 1048832))
+ (memory $0 17)
+ ;; SCALE encoded `Vec<u8>` of the default config.
+ (data (i32.const 1048576) "\34{\"a\":1,\"b\":2}")
+ ;; SCALE encoded `Some(Vec<u8>)` of the `development` preset.
+ (data (i32.const 1048592) "\01\1c{\"b\":3}")
+ ;; SCALE encoded `None` and `Ok(())`.
+ (data (i32.const 1048608) "\00")
+ ;; SCALE encoded `PresetId` of `development`.
+ (data (i32.const 1048624) "\2cdevelopment")
+ (data (i32.const 1048640) ":genesis")
+ (export "memory" (memory $0))
+ (export "__data_end" (global $global$1))
+ (export "__heap_base" (global $global$2))
+ (export "GenesisBuilder_create_default_config" (func $create_default_config))
+ (export "GenesisBuilder_get_preset" (func $get_preset))
+ (export "GenesisBuilder_build_config" (func $build_config))
+ (func $create_default_config (param $0 i32) (param $1 i32) (result i64)
+  (i64.const 60130590720)
+ )
+ (func $get_preset (param $0 i32) (param $1 i32) (result i64)
+  (if (result i64)
+   (i32.and
+    (i32.eq (local.get $1) (i32.const 12))
+    (i32.and
+     (i64.eq (i64.load (local.get $0)) (i64.load (i32.const 1048624)))
+     (i32.eq (i32.load offset=8 (local.get $0)) (i32.load (i32.const 1048632)))
+    )
+   )
+   (then (i64.const 38655754256))
+   (else (i64.const 4296015904))
+  )
+ )
+ (func $build_config (param $0 i32) (param $1 i32) (result i64)
+  (call $ext_storage_set_version_1
+   (i64.const 34360787008)
+   (i64.or
+    (i64.shl (i64.extend_i32_u (local.get $1)) (i64.const 32))
+    (i64.extend_i32_u (local.get $0))
+   )
+  )
+  (i64.const 4296015904)
+ )
+)
@@ -19,23 +19,30 @@
 //! Substrate chain configurations.
 #![warn(missing_docs)]
 
-use crate::{extension::GetExtension, ChainType, Properties, RuntimeGenesis};
+use crate::{
+	extension::GetExtension, genesis_config_builder::GenesisConfigBuilderRuntimeCaller, ChainType,
+	Properties, RuntimeGenesis,
+};
 use sc_network::config::MultiaddrWithPeerId;
 use sc_telemetry::TelemetryEndpoints;
 use serde::{Deserialize, Serialize};
 use serde_json as json;
 use sp_core::{
-	storage::{ChildInfo, Storage, StorageChild, StorageData, StorageKey},
+	storage::{well_known_keys, ChildInfo, Storage, StorageChild, StorageData, StorageKey},
 	Bytes,
 };
 use sp_runtime::BuildStorage;
-use std::{borrow::Cow, collections::BTreeMap, fs::File, path::PathBuf, sync::Arc};
+use std::{
+	borrow::Cow, collections::BTreeMap, fs::File, marker::PhantomData, path::PathBuf, sync::Arc,
+};
 
 enum GenesisSource<G> {
 	File(PathBuf),
 	Binary(Cow<'static, [u8]>),
 	Factory(Arc<dyn Fn() -> G + Send + Sync>),
 	Storage(Storage),
+	/// Genesis built by the `GenesisBuilder` runtime api of the given runtime code.
+	GenesisBuilderApi(RuntimeGenesisInner),
 }
 
 impl<G> Clone for GenesisSource<G> {
@@ -45,6 +52,7 @@ impl<G> Clone for GenesisSource<G> {
 			Self::Binary(ref d) => Self::Binary(d.clone()),
 			Self::Factory(ref f) => Self::Factory(f.clone()),
 			Self::Storage(ref s) => Self::Storage(s.clone()),
+			Self::GenesisBuilderApi(ref inner) => Self::GenesisBuilderApi(inner.clone()),
 		}
 	}
 }
@@ -104,6 +112,7 @@ impl<G: RuntimeGenesis> GenesisSource<G> {
 
 				Ok(Genesis::Raw(RawGenesis { top, children_default }))
 			},
+			Self::GenesisBuilderApi(inner) => Ok(Genesis::RuntimeGenesis(inner.clone())),
 		}
 	}
 }
@@ -125,6 +134,22 @@ impl<G: RuntimeGenesis, E> BuildStorage for ChainSpec<G, E> {
 				});
 				Ok(())
 			},
+			Genesis::RuntimeGenesis(inner) => {
+				let Storage { top, children_default } = inner.build_storage()?;
+				storage.top.extend(top);
+				children_default.into_iter().for_each(|(k, child)| {
+					storage
+						.children_default
+						.entry(k)
+						.or_insert_with(|| StorageChild {
+							data: Default::default(),
+							child_info: child.child_info,
+						})
+						.data
+						.extend(child.data);
+				});
+				Ok(())
+			},
 			// The `StateRootHash` variant exists as a way to keep note that other clients support
 			// it, but Substrate itself isn't capable of loading chain specs with just a hash at the
 			// moment.
@@ -144,6 +169,63 @@ pub struct RawGenesis {
 	pub children_default: BTreeMap<StorageKey, GenesisStorage>,
 }
 
+impl From<Storage> for RawGenesis {
+	fn from(storage: Storage) -> Self {
+		let top = storage.top.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect();
+		let children_default = storage
+			.children_default
+			.into_iter()
+			.map(|(sk, child)| {
+				(
+					StorageKey(sk),
+					child.data.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect(),
+				)
+			})
+			.collect();
+
+		RawGenesis { top, children_default }
+	}
+}
+
+/// The JSON representation of the `GenesisConfig` of a [`RuntimeGenesisInner`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+enum RuntimeGenesisConfigJson {
+	/// The complete `GenesisConfig`.
+	Config(json::Value),
+	/// A patch for the default `GenesisConfig` of the runtime.
+	Patch(json::Value),
+	/// The name of a preset provided by the runtime, see `sp_genesis_builder::GenesisBuilder`.
+	NamedPreset(String),
+}
+
+/// Genesis built by the `GenesisBuilder` runtime api of the given runtime `code`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeGenesisInner {
+	/// The runtime wasm code, hex encoded in JSON.
+	#[serde(with = "sp_core::bytes")]
+	code: Vec<u8>,
+	#[serde(flatten)]
+	json_blob: RuntimeGenesisConfigJson,
+}
+
+impl RuntimeGenesisInner {
+	/// Build the genesis storage, including the runtime code.
+	fn build_storage(self) -> Result<Storage, String> {
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(&self.code[..]);
+		let mut storage = match self.json_blob {
+			RuntimeGenesisConfigJson::Config(config) => caller.get_storage_for_config(config),
+			RuntimeGenesisConfigJson::Patch(patch) => caller.get_storage_for_patch(patch),
+			RuntimeGenesisConfigJson::NamedPreset(name) =>
+				caller.get_storage_for_named_preset(&name),
+		}?;
+
+		storage.top.insert(well_known_keys::CODE.into(), self.code);
+		Ok(storage)
+	}
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(deny_unknown_fields)]
@@ -152,6 +234,9 @@ enum Genesis<G> {
 	Raw(RawGenesis),
 	/// State root hash of the genesis storage.
 	StateRootHash(StorageData),
+	/// The runtime code and the JSON representation of its `GenesisConfig`, built by the
+	/// `GenesisBuilder` runtime api.
+	RuntimeGenesis(RuntimeGenesisInner),
 }
 
 /// A configuration of a client. Does not include runtime storage initialization.
@@ -207,6 +292,14 @@ impl<G, E: Clone> Clone for ChainSpec<G, E> {
 }
 
 impl<G, E> ChainSpec<G, E> {
+	/// Returns a [`ChainSpecBuilder`] for a chain spec whose genesis is built by the
+	/// `GenesisBuilder` runtime api of the given runtime `code`.
+	///
+	/// This only requires the wasm blob of the runtime, not its native `GenesisConfig` type.
+	pub fn builder(code: &[u8], extensions: E) -> ChainSpecBuilder<G, E> {
+		ChainSpecBuilder::new(code, extensions)
+	}
+
 	/// A list of bootnode addresses.
 	pub fn boot_nodes(&self) -> &[MultiaddrWithPeerId] {
 		&self.client_spec.boot_nodes
@@ -335,27 +428,8 @@ struct JsonContainer<G, E> {
 impl<G: RuntimeGenesis, E: serde::Serialize + Clone + 'static> ChainSpec<G, E> {
 	fn json_container(&self, raw: bool) -> Result<JsonContainer<G, E>, String> {
 		let genesis = match (raw, self.genesis.resolve()?) {
-			(true, Genesis::Runtime(g)) => {
-				let storage = g.build_storage()?;
-				let top =
-					storage.top.into_iter().map(|(k, v)| (StorageKey(k), StorageData(v))).collect();
-				let children_default = storage
-					.children_default
-					.into_iter()
-					.map(|(sk, child)| {
-						(
-							StorageKey(sk),
-							child
-								.data
-								.into_iter()
-								.map(|(k, v)| (StorageKey(k), StorageData(v)))
-								.collect(),
-						)
-					})
-					.collect();
-
-				Genesis::Raw(RawGenesis { top, children_default })
-			},
+			(true, Genesis::Runtime(g)) => Genesis::Raw(g.build_storage()?.into()),
+			(true, Genesis::RuntimeGenesis(inner)) => Genesis::Raw(inner.build_storage()?.into()),
 			(_, genesis) => genesis,
 		};
 		Ok(JsonContainer { client_spec: self.client_spec.clone(), genesis })
@@ -442,6 +516,140 @@ where
 	}
 }
 
+/// Builder for a [`ChainSpec`] whose genesis is built by the `GenesisBuilder` runtime api.
+///
+/// Created by [`ChainSpec::builder`]. By default the genesis is the default `GenesisConfig` of
+/// the runtime.
+pub struct ChainSpecBuilder<G, E = NoExtension> {
+	code: Vec<u8>,
+	extensions: E,
+	name: String,
+	id: String,
+	chain_type: ChainType,
+	genesis: RuntimeGenesisConfigJson,
+	boot_nodes: Vec<MultiaddrWithPeerId>,
+	telemetry_endpoints: Option<TelemetryEndpoints>,
+	protocol_id: Option<String>,
+	fork_id: Option<String>,
+	properties: Option<Properties>,
+	_genesis: PhantomData<G>,
+}
+
+impl<G, E> ChainSpecBuilder<G, E> {
+	/// Creates a new builder for the given runtime `code` and `extensions`.
+	pub fn new(code: &[u8], extensions: E) -> Self {
+		Self {
+			code: code.into(),
+			extensions,
+			name: "Development".into(),
+			id: "dev".into(),
+			chain_type: ChainType::Live,
+			genesis: RuntimeGenesisConfigJson::Patch(json::json!({})),
+			boot_nodes: Vec::new(),
+			telemetry_endpoints: None,
+			protocol_id: None,
+			fork_id: None,
+			properties: None,
+			_genesis: PhantomData,
+		}
+	}
+
+	/// Sets the spec name.
+	pub fn with_name(mut self, name: &str) -> Self {
+		self.name = name.into();
+		self
+	}
+
+	/// Sets the spec id.
+	pub fn with_id(mut self, id: &str) -> Self {
+		self.id = id.into();
+		self
+	}
+
+	/// Sets the type of the chain.
+	pub fn with_chain_type(mut self, chain_type: ChainType) -> Self {
+		self.chain_type = chain_type;
+		self
+	}
+
+	/// Sets the list of bootnode addresses.
+	pub fn with_boot_nodes(mut self, boot_nodes: Vec<MultiaddrWithPeerId>) -> Self {
+		self.boot_nodes = boot_nodes;
+		self
+	}
+
+	/// Sets the telemetry endpoints.
+	pub fn with_telemetry_endpoints(mut self, telemetry_endpoints: TelemetryEndpoints) -> Self {
+		self.telemetry_endpoints = Some(telemetry_endpoints);
+		self
+	}
+
+	/// Sets the network protocol id.
+	pub fn with_protocol_id(mut self, protocol_id: &str) -> Self {
+		self.protocol_id = Some(protocol_id.into());
+		self
+	}
+
+	/// Sets the optional network fork identifier.
+	pub fn with_fork_id(mut self, fork_id: &str) -> Self {
+		self.fork_id = Some(fork_id.into());
+		self
+	}
+
+	/// Sets additional loosely-typed properties of the chain.
+	pub fn with_properties(mut self, properties: Properties) -> Self {
+		self.properties = Some(properties);
+		self
+	}
+
+	/// Use the named preset of the runtime as genesis, see
+	/// `sp_genesis_builder::GenesisBuilder::get_preset`.
+	pub fn with_genesis_config_preset_name(mut self, name: &str) -> Self {
+		self.genesis = RuntimeGenesisConfigJson::NamedPreset(name.into());
+		self
+	}
+
+	/// Use the given `patch` of the default `GenesisConfig` of the runtime as genesis.
+	///
+	/// The patch is merged into the default `GenesisConfig` with [`crate::json_merge`].
+	pub fn with_genesis_config_patch(mut self, patch: json::Value) -> Self {
+		self.genesis = RuntimeGenesisConfigJson::Patch(patch);
+		self
+	}
+
+	/// Use the given complete `GenesisConfig` of the runtime as genesis.
+	pub fn with_genesis_config(mut self, config: json::Value) -> Self {
+		self.genesis = RuntimeGenesisConfigJson::Config(config);
+		self
+	}
+
+	/// Builds the [`ChainSpec`].
+	pub fn build(self) -> ChainSpec<G, E> {
+		let client_spec = ClientSpec {
+			name: self.name,
+			id: self.id,
+			chain_type: self.chain_type,
+			boot_nodes: self.boot_nodes,
+			telemetry_endpoints: self.telemetry_endpoints,
+			protocol_id: self.protocol_id,
+			fork_id: self.fork_id,
+			properties: self.properties,
+			extensions: self.extensions,
+			consensus_engine: (),
+			genesis: Default::default(),
+			code_substitutes: BTreeMap::new(),
+		};
+
+		ChainSpec {
+			client_spec,
+			genesis: GenesisSource::GenesisBuilderApi(RuntimeGenesisInner {
+				code: self.code,
+				json_blob: self.genesis,
+			}),
+		}
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -536,4 +744,73 @@ mod tests {
 			);
 		}
 	}
+
+	fn genesis_builder_spec() -> ChainSpecBuilder<(), NoExtension> {
+		let code = wat::parse_str(include_str!("../res/genesis_builder_runtime.wat")).unwrap();
+		ChainSpec::builder(&code, None).with_name("Test").with_id("test")
+	}
+
+	fn built_config(storage: &Storage) -> json::Value {
+		use codec::Decode;
+
+		let config = String::decode(&mut &storage.top[&b":genesis"[..]][..]).unwrap();
+		json::from_str(&config).unwrap()
+	}
+
+	#[test]
+	fn chain_spec_builder_builds_genesis_with_runtime() {
+		let spec = genesis_builder_spec().build();
+		let storage = spec.build_storage().unwrap();
+		assert_eq!(built_config(&storage), json::json!({ "a": 1, "b": 2 }));
+		assert_eq!(storage.top.get(well_known_keys::CODE), Some(&spec_code(&spec)));
+
+		let spec = genesis_builder_spec().with_genesis_config_preset_name("development").build();
+		assert_eq!(built_config(&spec.build_storage().unwrap()), json::json!({ "a": 1, "b": 3 }));
+
+		let spec = genesis_builder_spec()
+			.with_genesis_config_patch(json::json!({ "a": null }))
+			.build();
+		assert_eq!(built_config(&spec.build_storage().unwrap()), json::json!({ "b": 2 }));
+
+		let spec = genesis_builder_spec().with_genesis_config(json::json!({ "c": 3 })).build();
+		assert_eq!(built_config(&spec.build_storage().unwrap()), json::json!({ "c": 3 }));
+
+		let spec = genesis_builder_spec().with_genesis_config_preset_name("unknown").build();
+		assert!(spec.build_storage().is_err());
+	}
+
+	fn spec_code<G, E>(spec: &ChainSpec<G, E>) -> Vec<u8> {
+		match &spec.genesis {
+			GenesisSource::GenesisBuilderApi(inner) => inner.code.clone(),
+			_ => panic!("Expected a genesis built by the runtime"),
+		}
+	}
+
+	#[test]
+	fn chain_spec_builder_json_roundtrip() {
+		let spec = genesis_builder_spec()
+			.with_chain_type(ChainType::Development)
+			.with_protocol_id("test")
+			.with_genesis_config_preset_name("development")
+			.build();
+
+		let json = spec.as_json(false).unwrap();
+		let value: json::Value = json::from_str(&json).unwrap();
+		assert_eq!(value["genesis"]["runtimeGenesis"]["namedPreset"], "development");
+		assert_eq!(
+			value["genesis"]["runtimeGenesis"]["code"],
+			sp_core::bytes::to_hex(&spec_code(&spec), false).as_str()
+		);
+
+		let decoded =
+			ChainSpec::<(), NoExtension>::from_json_bytes(json.clone().into_bytes()).unwrap();
+		assert_eq!(decoded.as_json(false).unwrap(), json);
+		assert_eq!(decoded.chain_type(), ChainType::Development);
+		assert_eq!(decoded.build_storage().unwrap().top, spec.build_storage().unwrap().top);
+
+		// The raw chain spec contains the storage built by the runtime.
+		let raw = spec.as_json(true).unwrap();
+		let raw_spec = ChainSpec::<(), NoExtension>::from_json_bytes(raw.into_bytes()).unwrap();
+		assert_eq!(raw_spec.build_storage().unwrap().top, spec.build_storage().unwrap().top);
+	}
 }
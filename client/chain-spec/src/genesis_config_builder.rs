@@ -0,0 +1,217 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A helper module for calling the GenesisBuilder API from arbitrary runtime wasm blobs.
+
+use codec::{Decode, Encode};
+use sc_executor::{error::Result, sp_wasm_interface::HostFunctions, WasmExecutor};
+use serde_json::{from_slice, Value};
+use sp_core::{
+	storage::Storage,
+	traits::{CallContext, CodeExecutor, Externalities, FetchRuntimeCode, RuntimeCode},
+};
+use sp_genesis_builder::{PresetId, Result as BuildResult};
+use sp_state_machine::BasicExternalities;
+use std::borrow::Cow;
+
+/// A utility that facilitates calling the GenesisBuilder API from the runtime wasm code blob.
+///
+/// `EHF` type allows to specify the extended host function required for building runtime's genesis
+/// config. The type will be combined with default `sp_io::SubstrateHostFunctions`.
+pub struct GenesisConfigBuilderRuntimeCaller<'a, EHF = ()>
+where
+	EHF: HostFunctions,
+{
+	code: Cow<'a, [u8]>,
+	code_hash: Vec<u8>,
+	executor: WasmExecutor<(sp_io::SubstrateHostFunctions, EHF)>,
+}
+
+impl<'a, EHF> FetchRuntimeCode for GenesisConfigBuilderRuntimeCaller<'a, EHF>
+where
+	EHF: HostFunctions,
+{
+	fn fetch_runtime_code(&self) -> Option<Cow<[u8]>> {
+		Some(self.code.as_ref().into())
+	}
+}
+
+impl<'a, EHF> GenesisConfigBuilderRuntimeCaller<'a, EHF>
+where
+	EHF: HostFunctions,
+{
+	/// Creates new instance using the provided code blob.
+	///
+	/// This code is later referred to as `runtime`.
+	pub fn new(code: &'a [u8]) -> Self {
+		GenesisConfigBuilderRuntimeCaller {
+			code: code.into(),
+			code_hash: sp_core::blake2_256(code).to_vec(),
+			executor: WasmExecutor::<(sp_io::SubstrateHostFunctions, EHF)>::builder(
+				Default::default(),
+			)
+			.with_allow_missing_host_functions(true)
+			.build(),
+		}
+	}
+
+	fn call(&self, ext: &mut dyn Externalities, method: &str, data: &[u8]) -> Result<Vec<u8>> {
+		self.executor
+			.call(
+				ext,
+				&RuntimeCode { heap_pages: None, code_fetcher: self, hash: self.code_hash.clone() },
+				method,
+				data,
+				false,
+				CallContext::Offchain,
+			)
+			.0
+	}
+
+	/// Returns the default `GenesisConfig` provided by the `runtime`.
+	///
+	/// Calls [`GenesisBuilder::create_default_config`] in the `runtime`.
+	///
+	/// [`GenesisBuilder::create_default_config`]:
+	/// sp_genesis_builder::GenesisBuilder::create_default_config
+	pub fn get_default_config(&self) -> core::result::Result<Value, String> {
+		let mut t = BasicExternalities::new_empty();
+		let call_result = self
+			.call(&mut t, "GenesisBuilder_create_default_config", &[])
+			.map_err(|e| format!("wasm call error {e}"))?;
+		let default_config = Vec::<u8>::decode(&mut &call_result[..])
+			.map_err(|e| format!("scale codec error: {e}"))?;
+		from_slice(&default_config[..]).map_err(|e| format!("json error: {e}"))
+	}
+
+	/// Returns the JSON patch of the named preset provided by the `runtime`.
+	///
+	/// Calls [`GenesisBuilder::get_preset`] in the `runtime`.
+	///
+	/// [`GenesisBuilder::get_preset`]: sp_genesis_builder::GenesisBuilder::get_preset
+	pub fn get_named_preset(&self, name: &str) -> core::result::Result<Value, String> {
+		let mut t = BasicExternalities::new_empty();
+		let call_result = self
+			.call(&mut t, "GenesisBuilder_get_preset", &PresetId::from(name).encode())
+			.map_err(|e| format!("wasm call error {e}"))?;
+		let preset = Option::<Vec<u8>>::decode(&mut &call_result[..])
+			.map_err(|e| format!("scale codec error: {e}"))?
+			.ok_or_else(|| format!("The runtime doesn't provide the preset `{name}`"))?;
+		from_slice(&preset[..]).map_err(|e| format!("json error: {e}"))
+	}
+
+	/// Builds the genesis storage from the given `GenesisConfig`.
+	///
+	/// Calls [`GenesisBuilder::build_config`] in the `runtime`. The given config needs to be
+	/// complete, see [`Self::get_storage_for_patch`] for partial configs.
+	///
+	/// [`GenesisBuilder::build_config`]: sp_genesis_builder::GenesisBuilder::build_config
+	pub fn get_storage_for_config(&self, config: Value) -> core::result::Result<Storage, String> {
+		let mut ext = BasicExternalities::new_empty();
+
+		let call_result = self
+			.call(&mut ext, "GenesisBuilder_build_config", &config.to_string().encode())
+			.map_err(|e| format!("wasm call error {e}"))?;
+
+		BuildResult::decode(&mut &call_result[..])
+			.map_err(|e| format!("scale codec error: {e}"))?
+			.map_err(|e| format!("{e}"))?;
+
+		Ok(ext.into_storages())
+	}
+
+	/// Builds the genesis storage from the given JSON `patch`.
+	///
+	/// The `patch` is merged into the default `GenesisConfig` of the `runtime` (see
+	/// [`crate::json_merge`]) and the result is passed to [`Self::get_storage_for_config`].
+	pub fn get_storage_for_patch(&self, patch: Value) -> core::result::Result<Storage, String> {
+		let mut config = self.get_default_config()?;
+		crate::json_patch::merge(&mut config, patch);
+		self.get_storage_for_config(config)
+	}
+
+	/// Builds the genesis storage from the named preset of the `runtime`.
+	///
+	/// See [`Self::get_named_preset`] and [`Self::get_storage_for_patch`].
+	pub fn get_storage_for_named_preset(
+		&self,
+		name: &str,
+	) -> core::result::Result<Storage, String> {
+		self.get_storage_for_patch(self.get_named_preset(name)?)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	fn runtime() -> Vec<u8> {
+		wat::parse_str(include_str!("../res/genesis_builder_runtime.wat")).unwrap()
+	}
+
+	/// The `GenesisConfig` the test runtime was built with.
+	fn built_config(storage: &Storage) -> Value {
+		let json = String::decode(&mut &storage.top[&b":genesis"[..]][..]).unwrap();
+		from_slice(json.as_bytes()).unwrap()
+	}
+
+	#[test]
+	fn get_default_config_works() {
+		let code = runtime();
+		let config = GenesisConfigBuilderRuntimeCaller::<()>::new(&code).get_default_config();
+
+		assert_eq!(config, Ok(json!({ "a": 1, "b": 2 })));
+	}
+
+	#[test]
+	fn get_named_preset_works() {
+		let code = runtime();
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(&code);
+
+		assert_eq!(
+			caller.get_named_preset(sp_genesis_builder::DEV_RUNTIME_PRESET),
+			Ok(json!({ "b": 3 }))
+		);
+		assert!(caller
+			.get_named_preset(sp_genesis_builder::LOCAL_TESTNET_RUNTIME_PRESET)
+			.unwrap_err()
+			.contains("doesn't provide the preset `local_testnet`"));
+	}
+
+	#[test]
+	fn get_storage_for_config_works() {
+		let code = runtime();
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(&code);
+
+		let storage = caller.get_storage_for_config(json!({ "a": 5 })).unwrap();
+		assert_eq!(built_config(&storage), json!({ "a": 5 }));
+	}
+
+	#[test]
+	fn get_storage_for_patch_merges_the_default_config() {
+		let code = runtime();
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(&code);
+
+		let storage = caller.get_storage_for_patch(json!({ "a": 5, "c": [1] })).unwrap();
+		assert_eq!(built_config(&storage), json!({ "a": 5, "b": 2, "c": [1] }));
+
+		let storage = caller.get_storage_for_named_preset("development").unwrap();
+		assert_eq!(built_config(&storage), json!({ "a": 1, "b": 3 }));
+	}
+}
@@ -0,0 +1,130 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! A helper module providing json patching functions.
+
+use serde_json::Value;
+
+/// Recursively merges two JSON objects, `a` and `b`, into a single object.
+///
+/// If a key exists in both objects, the value from `b` will override the value from `a`. If a
+/// key exists in `b` with a `null` value, it is removed from `a`. Objects are merged key by key,
+/// all other values (including arrays) are replaced as a whole.
+///
+/// # Examples
+///
+/// ```
+/// use sc_chain_spec::json_merge;
+/// use serde_json::json;
+///
+/// let mut a = json!({ "a": 1, "b": { "c": 2, "d": 3 } });
+/// json_merge(&mut a, json!({ "b": { "c": 4, "d": null }, "e": 5 }));
+///
+/// assert_eq!(a, json!({ "a": 1, "b": { "c": 4 }, "e": 5 }));
+/// ```
+pub fn merge(a: &mut Value, b: Value) {
+	match (a, b) {
+		(Value::Object(a), Value::Object(b)) =>
+			for (k, v) in b {
+				if v.is_null() {
+					a.remove(&k);
+				} else {
+					merge(a.entry(k).or_insert(Value::Null), v);
+				}
+			},
+		(a, b) => *a = b,
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde_json::json;
+
+	#[test]
+	fn test1_simple_merge() {
+		let mut j1 = json!({ "a":123 });
+		merge(&mut j1, json!({ "b":256 }));
+		assert_eq!(j1, json!({ "a":123, "b":256 }));
+	}
+
+	#[test]
+	fn test2_patch_simple_merge_nested() {
+		let mut j1 = json!({
+			"a": {
+				"name": "xxx",
+				"value": 123
+			},
+			"b": { "c" : { "inner_name": "yyy" } }
+		});
+
+		let j2 = json!({
+			"a": {
+				"keys": ["a", "b", "c" ]
+			}
+		});
+
+		merge(&mut j1, j2);
+		assert_eq!(
+			j1,
+			json!({"a":{"keys":["a","b","c"],"name":"xxx","value":123}, "b": { "c" : { "inner_name": "yyy" } }})
+		);
+	}
+
+	#[test]
+	fn test3_patch_overrides_existing_keys() {
+		let mut j1 = json!({
+			"a": {
+				"name": "xxx",
+				"value": 123,
+				"keys": ["d"]
+			}
+		});
+
+		let j2 = json!({
+			"a": {
+				"keys": ["a", "b", "c" ]
+			}
+		});
+
+		merge(&mut j1, j2);
+		assert_eq!(j1, json!({"a":{"keys":["a","b","c"],"name":"xxx","value":123}}));
+	}
+
+	#[test]
+	fn test4_patch_null_removes_keys() {
+		let mut j1 = json!({
+			"a": {
+				"name": "xxx",
+				"value": 123
+			},
+			"b": 1
+		});
+
+		merge(&mut j1, json!({ "a": { "name": null }, "b": null }));
+		assert_eq!(j1, json!({ "a": { "value": 123 } }));
+	}
+
+	#[test]
+	fn test5_patch_replaces_non_objects() {
+		let mut j1 = json!({ "a": [1, 2], "b": { "c": 1 } });
+
+		merge(&mut j1, json!({ "a": { "c": 1 }, "b": 5 }));
+		assert_eq!(j1, json!({ "a": { "c": 1 }, "b": 5 }));
+	}
+}
@@ -148,7 +148,8 @@
 //! "consensusEngine": null,
 //! // The genesis declaration of the chain.
 //! //
-//! // `runtime`, `raw`, `stateRootHash` denote the type of the genesis declaration.
+//! // `runtime`, `raw`, `stateRootHash`, `runtimeGenesis` denote the type of the genesis
+//! // declaration.
 //! //
 //! // These declarations are in the following formats:
 //! // - `runtime` is a `json` object that can be parsed by a compatible `GenesisConfig`. This
@@ -157,6 +158,10 @@
 //! //   fields is a map of `key => value`. These key/value pairs represent the genesis storage.
 //! // - `stateRootHash` is a single hex encoded hash that represents the genesis hash. The hash
 //! //   type depends on the hash used by the chain.
+//! // - `runtimeGenesis` is a `json` object with the hex encoded runtime `code` and one of
+//! //   `config` (a complete `GenesisConfig`), `patch` (a patch of the default `GenesisConfig`)
+//! //   or `namedPreset` (the name of a preset provided by the runtime). The genesis storage is
+//! //   built by the `GenesisBuilder` runtime api of `code`.
 //! //
 //! "genesis": { "runtime": {} },
 //! /// Optional map of `block_number` to `wasm_code`.
@@ -174,18 +179,41 @@
 //!
 //! The chain spec can be extended with other fields that are opaque to the default chain spec.
 //! Specific node implementations will need to be able to deserialize these extensions.
+//!
+//! # Building a chain spec from the runtime
+//!
+//! Runtimes implementing the `sp_genesis_builder::GenesisBuilder` runtime api don't require their
+//! `GenesisConfig` to be available natively. [`GenericChainSpec::builder`] creates a chain spec
+//! from just the wasm blob of the runtime and a named preset, a patch of the default
+//! `GenesisConfig` or a complete `GenesisConfig`:
+//!
+//! ```ignore
+//! let spec = GenericChainSpec::<(), NoExtension>::builder(WASM_BINARY, None)
+//! 	.with_name("Development")
+//! 	.with_id("dev")
+//! 	.with_chain_type(ChainType::Development)
+//! 	.with_genesis_config_preset_name(sp_genesis_builder::DEV_RUNTIME_PRESET)
+//! 	.build();
+//! ```
+//!
+//! [`GenesisConfigBuilderRuntimeCaller`] can be used to call the `GenesisBuilder` runtime api
+//! directly.
 
 mod chain_spec;
 mod extension;
 mod genesis;
+mod genesis_config_builder;
+mod json_patch;
 
 pub use self::{
-	chain_spec::{ChainSpec as GenericChainSpec, NoExtension},
+	chain_spec::{ChainSpec as GenericChainSpec, ChainSpecBuilder, NoExtension},
 	extension::{get_extension, get_extension_mut, Extension, Fork, Forks, GetExtension, Group},
 	genesis::{
 		construct_genesis_block, resolve_state_version_from_wasm, BuildGenesisBlock,
 		GenesisBlockBuilder,
 	},
+	genesis_config_builder::GenesisConfigBuilderRuntimeCaller,
+	json_patch::merge as json_merge,
 };
 pub use sc_chain_spec_derive::{ChainSpecExtension, ChainSpecGroup};
 
@@ -98,6 +98,25 @@ pub fn execution_method_from_cli(
 /// The default [`WasmExecutionMethod`].
 pub const DEFAULT_WASM_EXECUTION_METHOD: WasmExecutionMethod = WasmExecutionMethod::Compiled;
 
+/// Allocator for the runtime heap.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum WasmAllocator {
+	/// Bump allocator with power-of-two size classes that never coalesces freed memory.
+	FreeingBump,
+	/// Segregated fits allocator that splits and coalesces free blocks.
+	SegregatedFits,
+}
+
+impl Into<sc_service::config::AllocatorKind> for WasmAllocator {
+	fn into(self) -> sc_service::config::AllocatorKind {
+		match self {
+			WasmAllocator::FreeingBump => sc_service::config::AllocatorKind::FreeingBump,
+			WasmAllocator::SegregatedFits => sc_service::config::AllocatorKind::SegregatedFits,
+		}
+	}
+}
+
 #[allow(missing_docs)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
 #[value(rename_all = "kebab-case")]
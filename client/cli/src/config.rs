@@ -27,9 +27,10 @@ use names::{Generator, Name};
 use sc_client_api::execution_extensions::ExecutionStrategies;
 use sc_service::{
 	config::{
-		BasePath, Configuration, DatabaseSource, KeystoreConfig, NetworkConfiguration,
-		NodeKeyConfig, OffchainWorkerConfig, PrometheusConfig, PruningMode, Role, RpcMethods,
-		TelemetryEndpoints, TransactionPoolOptions, WasmExecutionMethod,
+		AllocatorKind, BasePath, Configuration, DatabaseSource, DeterministicStackLimit,
+		KeystoreConfig, NetworkConfiguration, NodeKeyConfig, OffchainWorkerConfig,
+		PrometheusConfig, PruningMode, Role, RpcMethods, TelemetryEndpoints,
+		TransactionPoolOptions, WasmExecutionMethod,
 	},
 	BlocksPruning, ChainSpec, TracingReceiver,
 };
@@ -165,7 +166,7 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		node_key: NodeKeyConfig,
 		default_listen_port: u16,
 	) -> Result<NetworkConfiguration> {
-		Ok(if let Some(network_params) = self.network_params() {
+		if let Some(network_params) = self.network_params() {
 			network_params.network_config(
 				chain_spec,
 				is_dev,
@@ -177,8 +178,8 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 				default_listen_port,
 			)
 		} else {
-			NetworkConfiguration::new(node_name, client_id, node_key, Some(net_config_dir))
-		})
+			Ok(NetworkConfiguration::new(node_name, client_id, node_key, Some(net_config_dir)))
+		}
 	}
 
 	/// Get the keystore configuration.
@@ -238,6 +239,20 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		Ok(self.import_params().map(|x| x.trie_cache_maximum_size()).unwrap_or_default())
 	}
 
+	/// Whether the trie cache should be persisted across restarts.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `false`.
+	fn trie_cache_persistence(&self) -> Result<bool> {
+		Ok(self.import_params().map(|x| x.trie_cache_persistence).unwrap_or_default())
+	}
+
+	/// Whether the storage changes of every imported block should be indexed.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its `false`.
+	fn index_storage_changes(&self) -> Result<bool> {
+		Ok(self.import_params().map(|x| x.index_storage_changes).unwrap_or_default())
+	}
+
 	/// Get the state pruning mode.
 	///
 	/// By default this is retrieved from `PruningMode` if it is available. Otherwise its
@@ -287,6 +302,45 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 		self.import_params().map(|x| x.wasm_runtime_overrides()).unwrap_or_default()
 	}
 
+	/// Get the file runtime call profiles are exported to.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise profiling
+	/// is disabled.
+	fn wasm_profile(&self) -> Option<PathBuf> {
+		self.import_params().and_then(|x| x.wasm_profile())
+	}
+
+	/// Whether runtime call profiles are aggregated into Prometheus metrics.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise `false`.
+	fn wasm_profile_metrics(&self) -> bool {
+		self.import_params().map(|x| x.wasm_profile_metrics()).unwrap_or_default()
+	}
+
+	/// Get the allocator for the runtime heap.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise
+	/// `AllocatorKind::default()`.
+	fn wasm_allocator(&self) -> AllocatorKind {
+		self.import_params().map(|x| x.wasm_allocator()).unwrap_or_default()
+	}
+
+	/// Get the deterministic stack limit the runtime code is instrumented with.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise the stack
+	/// depth is only limited by the execution engine.
+	fn wasm_deterministic_stack_limit(&self) -> Option<DeterministicStackLimit> {
+		self.import_params().and_then(|x| x.wasm_deterministic_stack_limit())
+	}
+
+	/// Get the number of bytes the storage changes of a block may hold in memory.
+	///
+	/// By default this is retrieved from `ImportParams` if it is available. Otherwise the
+	/// memory is not limited.
+	fn overlay_memory_limit(&self) -> Option<usize> {
+		self.import_params().and_then(|x| x.overlay_memory_limit())
+	}
+
 	/// Get the execution strategies.
 	///
 	/// By default this is retrieved from `ImportParams` if it is available. Otherwise its
@@ -527,10 +581,17 @@ pub trait CliConfiguration<DCV: DefaultConfigurationValues = ()>: Sized {
 			keystore,
 			database: self.database_config(&config_dir, database_cache_size, database)?,
 			trie_cache_maximum_size: self.trie_cache_maximum_size()?,
+			trie_cache_persistence: self.trie_cache_persistence()?,
+			index_storage_changes: self.index_storage_changes()?,
 			state_pruning: self.state_pruning()?,
 			blocks_pruning: self.blocks_pruning()?,
 			wasm_method: self.wasm_method()?,
 			wasm_runtime_overrides: self.wasm_runtime_overrides(),
+			wasm_profile: self.wasm_profile(),
+			wasm_profile_metrics: self.wasm_profile_metrics(),
+			wasm_deterministic_stack_limit: self.wasm_deterministic_stack_limit(),
+			wasm_allocator: self.wasm_allocator(),
+			overlay_memory_limit: self.overlay_memory_limit(),
 			execution_strategies: self.execution_strategies(is_dev, is_validator)?,
 			rpc_http: self.rpc_http(DCV::rpc_http_listen_port())?,
 			rpc_ws: self.rpc_ws(DCV::rpc_ws_listen_port())?,
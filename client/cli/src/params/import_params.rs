@@ -18,7 +18,7 @@
 
 use crate::{
 	arg_enums::{
-		ExecutionStrategy, WasmExecutionMethod, WasmtimeInstantiationStrategy,
+		ExecutionStrategy, WasmAllocator, WasmExecutionMethod, WasmtimeInstantiationStrategy,
 		DEFAULT_EXECUTION_BLOCK_CONSTRUCTION, DEFAULT_EXECUTION_IMPORT_BLOCK,
 		DEFAULT_EXECUTION_IMPORT_BLOCK_VALIDATOR, DEFAULT_EXECUTION_OFFCHAIN_WORKER,
 		DEFAULT_EXECUTION_OTHER, DEFAULT_EXECUTION_SYNCING,
@@ -72,12 +72,54 @@ pub struct ImportParams {
 	)]
 	pub wasmtime_instantiation_strategy: WasmtimeInstantiationStrategy,
 
+	/// The allocator to use for the runtime heap.
+	///
+	/// The allocator decides when a runtime runs out of heap memory, so all nodes of a network
+	/// must use the same one.
+	#[arg(long, value_name = "ALLOCATOR", value_enum, default_value_t = WasmAllocator::FreeingBump)]
+	pub wasm_allocator: WasmAllocator,
+
 	/// Specify the path where local WASM runtimes are stored.
 	///
 	/// These runtimes will override on-chain runtimes when the version matches.
 	#[arg(long, value_name = "PATH")]
 	pub wasm_runtime_overrides: Option<PathBuf>,
 
+	/// Profile the host functions called by every runtime call executed in Wasm.
+	///
+	/// One JSON object per runtime call is appended to the file at the given path. It contains
+	/// the method, the hash and number of the block the call is executed at, the time spent in
+	/// Wasm and, for every host function, the number of calls, the time spent and the bytes read
+	/// from and written to the Wasm memory.
+	///
+	/// Profiling slows down the execution and should only be enabled for debugging.
+	#[arg(long, value_name = "PATH")]
+	pub wasm_profile: Option<PathBuf>,
+
+	/// Aggregate the host function profiles of the runtime calls executed in Wasm into
+	/// Prometheus metrics.
+	///
+	/// Works with or without `--wasm-profile`, but requires Prometheus to be enabled.
+	///
+	/// Profiling slows down the execution and should only be enabled for debugging.
+	#[arg(long)]
+	pub wasm_profile_metrics: bool,
+
+	/// Instrument the runtime code to limit the stack depth deterministically.
+	///
+	/// Deep recursion then traps at the same point regardless of the execution engine and the
+	/// host machine. This changes which blocks are valid, so it must be enabled by all nodes of
+	/// a network or by none.
+	#[arg(long)]
+	pub wasm_deterministic_stack_limit: bool,
+
+	/// Limit the memory used for the storage changes of a block, in MiB.
+	///
+	/// Once exceeded, committed storage changes are moved to a temporary file while the block
+	/// is imported or authored. Useful for blocks that modify more state than fits into memory.
+	#[arg(long, value_name = "MiB")]
+	pub overlay_memory_limit: Option<usize>,
+
 	#[allow(missing_docs)]
 	#[clap(flatten)]
 	pub execution_strategies: ExecutionStrategiesParams,
@@ -88,6 +130,21 @@ pub struct ImportParams {
 	#[arg(long, value_name = "Bytes", default_value_t = 67108864)]
 	pub trie_cache_size: usize,
 
+	/// Persist the most recently used trie cache entries in the database directory on shutdown.
+	///
+	/// The entries are loaded again on startup, which speeds up block import right after a
+	/// restart. They are only used if they still belong to the best or finalized block.
+	#[arg(long)]
+	pub trie_cache_persistence: bool,
+
+	/// Index the storage changes of every imported block.
+	///
+	/// This allows to query the storage diff between two blocks with `state_getStorageDiff`.
+	/// Only blocks that are executed on import are indexed, e.g. not the ones fetched by warp or
+	/// state sync.
+	#[arg(long)]
+	pub index_storage_changes: bool,
+
 	/// DEPRECATED
 	///
 	/// Switch to `--trie-cache-size`.
@@ -120,6 +177,33 @@ impl ImportParams {
 		self.wasm_runtime_overrides.clone()
 	}
 
+	/// Get the file the runtime call profiles should be exported to, if profiling is enabled.
+	pub fn wasm_profile(&self) -> Option<PathBuf> {
+		self.wasm_profile.clone()
+	}
+
+	/// Whether the runtime call profiles should be aggregated into Prometheus metrics.
+	pub fn wasm_profile_metrics(&self) -> bool {
+		self.wasm_profile_metrics
+	}
+
+	/// Get the allocator for the runtime heap.
+	pub fn wasm_allocator(&self) -> sc_service::config::AllocatorKind {
+		self.wasm_allocator.into()
+	}
+
+	/// Get the deterministic stack limit the runtime code should be instrumented with, if any.
+	pub fn wasm_deterministic_stack_limit(
+		&self,
+	) -> Option<sc_service::config::DeterministicStackLimit> {
+		self.wasm_deterministic_stack_limit.then(Default::default)
+	}
+
+	/// Get the number of bytes the storage changes of a block may hold in memory, if limited.
+	pub fn overlay_memory_limit(&self) -> Option<usize> {
+		self.overlay_memory_limit.map(|limit| limit.saturating_mul(1024 * 1024))
+	}
+
 	/// Get execution strategies for the parameters
 	pub fn execution_strategies(&self, is_dev: bool, is_validator: bool) -> ExecutionStrategies {
 		let exec = &self.execution_strategies;
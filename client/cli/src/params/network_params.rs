@@ -16,11 +16,12 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{arg_enums::SyncMode, params::node_key_params::NodeKeyParams};
-use clap::Args;
+use crate::{arg_enums::SyncMode, error, params::node_key_params::NodeKeyParams};
+use clap::{builder::ArgPredicate, Args};
 use sc_network::{
 	config::{
-		NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig, TransportConfig,
+		IpLimits, NetworkConfiguration, NodeKeyConfig, NonReservedPeerMode, SetConfig,
+		TransportConfig, WarpSyncTarget,
 	},
 	multiaddr::Protocol,
 };
@@ -131,17 +132,57 @@ pub struct NetworkParams {
 	#[arg(long)]
 	pub ipfs_server: bool,
 
+	/// Persist the reputations, bans and addresses of the peers across restarts.
+	///
+	/// The peer store is saved in the network configuration directory and can be inspected
+	/// and edited with the `system_peerReputations`, `system_banPeer` and `system_unbanPeer`
+	/// RPCs.
+	#[arg(long)]
+	pub persistent_peer_store: bool,
+
+	/// Maximum number of peers connected to us from the same IP address.
+	///
+	/// Only the peers that connected to us count towards the limit, and reserved nodes are
+	/// exempt from it.
+	#[arg(long, value_name = "COUNT")]
+	pub max_peers_per_ip: Option<u32>,
+
+	/// Maximum number of peers connected to us from the same /24 (IPv4) or /48 (IPv6) subnet.
+	///
+	/// Only the peers that connected to us count towards the limit, and reserved nodes are
+	/// exempt from it.
+	#[arg(long, value_name = "COUNT")]
+	pub max_peers_per_subnet: Option<u32>,
+
+	/// Maximum number of inbound slots of each peer set taken by peers from the same IP address.
+	#[arg(long, value_name = "COUNT")]
+	pub max_inbound_per_ip: Option<u32>,
+
+	/// Maximum number of inbound slots of each peer set taken by peers from the same /24 (IPv4)
+	/// or /48 (IPv6) subnet.
+	#[arg(long, value_name = "COUNT")]
+	pub max_inbound_per_subnet: Option<u32>,
+
 	/// Blockchain syncing mode.
 	#[arg(
 		long,
 		value_enum,
 		value_name = "SYNC_MODE",
 		default_value_t = SyncMode::Full,
+		default_value_if("warp_sync_target", ArgPredicate::IsPresent, "warp"),
 		ignore_case = true,
 		verbatim_doc_comment
 	)]
 	pub sync: SyncMode,
 
+	/// Warp sync to the given block instead of the latest finalized block.
+	///
+	/// The block is given as `<HASH>:<NUMBER>` and must come from a trusted source, as it is
+	/// not verified against the finality proofs. Implies `--sync warp` and can not be combined
+	/// with any other sync mode.
+	#[arg(long, value_name = "HASH:NUMBER")]
+	pub warp_sync_target: Option<WarpSyncTarget>,
+
 	/// Maximum number of blocks per request.
 	///
 	/// Try reducing this number from the default value if you have a slow network connection
@@ -162,7 +203,7 @@ impl NetworkParams {
 		node_name: &str,
 		node_key: NodeKeyConfig,
 		default_listen_port: u16,
-	) -> NetworkConfiguration {
+	) -> error::Result<NetworkConfiguration> {
 		let port = self.port.unwrap_or(default_listen_port);
 
 		let listen_addresses = if self.listen_addr.is_empty() {
@@ -211,7 +252,7 @@ impl NetworkParams {
 				is_dev || matches!(chain_type, ChainType::Local | ChainType::Development),
 		};
 
-		NetworkConfiguration {
+		Ok(NetworkConfiguration {
 			boot_nodes,
 			net_config_path,
 			default_peers_set: SetConfig {
@@ -243,7 +284,26 @@ impl NetworkParams {
 			kademlia_disjoint_query_paths: self.kademlia_disjoint_query_paths,
 			yamux_window_size: None,
 			ipfs_server: self.ipfs_server,
-			sync_mode: self.sync.into(),
+			persistent_peer_store: self.persistent_peer_store,
+			ip_limits: IpLimits {
+				max_peers_per_ip: self.max_peers_per_ip,
+				max_peers_per_subnet: self.max_peers_per_subnet,
+				max_inbound_per_ip: self.max_inbound_per_ip,
+				max_inbound_per_subnet: self.max_inbound_per_subnet,
+				..IpLimits::default()
+			},
+			sync_mode: self.sync_mode()?,
+			warp_sync_target: self.warp_sync_target.clone(),
+		})
+	}
+
+	/// Get the sync mode from the parameters.
+	pub fn sync_mode(&self) -> error::Result<sc_network::config::SyncMode> {
+		match (self.sync, &self.warp_sync_target) {
+			(SyncMode::Warp, _) | (_, None) => Ok(self.sync.into()),
+			(_, Some(_)) => Err(error::Error::Input(
+				"--warp-sync-target can not be used with a sync mode other than warp".into(),
+			)),
 		}
 	}
 }
@@ -298,4 +358,42 @@ mod tests {
 
 		assert_eq!(SyncMode::Warp, params.network_params.sync);
 	}
+
+	#[test]
+	fn warp_sync_target_parses() {
+		let params = Cli::try_parse_from(["", "--warp-sync-target", "0xabcd:100"])
+			.expect("Parses network params");
+
+		assert_eq!(
+			Some(WarpSyncTarget { hash: vec![0xab, 0xcd], number: 100 }),
+			params.network_params.warp_sync_target,
+		);
+		assert!(Cli::try_parse_from(["", "--warp-sync-target", "0xabcd"]).is_err());
+	}
+
+	#[test]
+	fn warp_sync_target_implies_warp_sync() {
+		let params = Cli::try_parse_from(["", "--warp-sync-target", "0xabcd:100"])
+			.expect("Parses network params");
+		assert_eq!(SyncMode::Warp, params.network_params.sync);
+		assert_eq!(sc_network::config::SyncMode::Warp, params.network_params.sync_mode().unwrap());
+
+		let params =
+			Cli::try_parse_from(["", "--sync", "warp", "--warp-sync-target", "0xabcd:100"])
+				.expect("Parses network params");
+		assert_eq!(sc_network::config::SyncMode::Warp, params.network_params.sync_mode().unwrap());
+
+		let params = Cli::try_parse_from(["", "--sync", "full"]).expect("Parses network params");
+		assert_eq!(sc_network::config::SyncMode::Full, params.network_params.sync_mode().unwrap());
+	}
+
+	#[test]
+	fn warp_sync_target_conflicts_with_other_sync_modes() {
+		for mode in ["full", "fast", "fast-unsafe"] {
+			let params =
+				Cli::try_parse_from(["", "--sync", mode, "--warp-sync-target", "0xabcd:100"])
+					.expect("Parses network params");
+			assert!(params.network_params.sync_mode().is_err());
+		}
+	}
 }
@@ -19,6 +19,7 @@
 use crate::error;
 use clap::Args;
 use sc_service::{BlocksPruning, PruningMode};
+use std::num::NonZeroU32;
 
 /// Parameters to define the pruning mode
 #[derive(Debug, Clone, PartialEq, Args)]
@@ -50,6 +51,15 @@ pub struct PruningParams {
 	#[arg(alias = "pruning", long, value_name = "PRUNING_MODE")]
 	pub state_pruning: Option<DatabasePruningMode>,
 
+	/// Keep the state of every block whose number is a multiple of this interval.
+	///
+	/// The state of the other finalized blocks is pruned according to `--state-pruning`, which
+	/// can't be an archive mode. Requires the ParityDB database backend.
+	///
+	/// Like the state pruning mode, this can only be set on the first creation of the database.
+	#[arg(long, value_name = "BLOCKS")]
+	pub state_checkpoint_interval: Option<NonZeroU32>,
+
 	/// Specify the blocks pruning mode.
 	///
 	/// This mode specifies when the block's body (including justifications)
@@ -79,7 +89,20 @@ pub struct PruningParams {
 impl PruningParams {
 	/// Get the pruning value from the parameters
 	pub fn state_pruning(&self) -> error::Result<Option<PruningMode>> {
-		Ok(self.state_pruning.map(|v| v.into()))
+		match (self.state_pruning, self.state_checkpoint_interval) {
+			(mode, None) => Ok(mode.map(|v| v.into())),
+			(None, Some(interval)) =>
+				Ok(Some(PruningMode::Checkpoints { constraints: Default::default(), interval })),
+			(Some(DatabasePruningMode::Custom(n)), Some(interval)) =>
+				Ok(Some(PruningMode::checkpoints(n, interval))),
+			(
+				Some(DatabasePruningMode::Archive | DatabasePruningMode::ArchiveCanonical),
+				Some(_),
+			) => Err(error::Error::Input(
+				"--state-checkpoint-interval can not be used with an archive state pruning mode"
+					.into(),
+			)),
+		}
 	}
 
 	/// Get the block pruning value from the parameters
@@ -270,6 +270,8 @@ mod tests {
 				keystore: sc_service::config::KeystoreConfig::InMemory,
 				database: sc_client_db::DatabaseSource::ParityDb { path: PathBuf::from("db") },
 				trie_cache_maximum_size: None,
+				trie_cache_persistence: false,
+				index_storage_changes: false,
 				state_pruning: None,
 				blocks_pruning: sc_client_db::BlocksPruning::KeepAll,
 				chain_spec: Box::new(GenericChainSpec::from_genesis(
@@ -286,6 +288,11 @@ mod tests {
 				)),
 				wasm_method: Default::default(),
 				wasm_runtime_overrides: None,
+				wasm_profile: None,
+				wasm_profile_metrics: false,
+				wasm_deterministic_stack_limit: None,
+				wasm_allocator: Default::default(),
+				overlay_memory_limit: None,
 				execution_strategies: Default::default(),
 				rpc_http: None,
 				rpc_ws: None,
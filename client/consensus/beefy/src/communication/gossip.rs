@@ -19,7 +19,9 @@
 use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use sc_network::PeerId;
-use sc_network_gossip::{MessageIntent, ValidationResult, Validator, ValidatorContext};
+use sc_network_gossip::{
+	MessageIntent, RateLimit, RateLimits, ValidationResult, Validator, ValidatorContext,
+};
 use sp_core::hashing::twox_64;
 use sp_runtime::traits::{Block, Hash, Header, NumberFor};
 
@@ -47,6 +49,15 @@ const REBROADCAST_AFTER: Duration = Duration::from_secs(60);
 #[cfg(test)]
 const REBROADCAST_AFTER: Duration = Duration::from_secs(5);
 
+/// Limit on the gossip messages of each kind received from a peer.
+///
+/// A peer relays at most one vote per validator and round, and a round concludes at most once
+/// per block, so this stays well above what a thousand validators produce.
+pub(crate) const GOSSIP_RATE_LIMITS: RateLimits = RateLimits {
+	per_peer: None,
+	per_topic: Some(RateLimit { max_messages: 4096, period: Duration::from_secs(10) }),
+};
+
 /// BEEFY gossip message type that gets encoded and sent on the network.
 #[derive(Debug, Encode, Decode)]
 pub(crate) enum GossipMessage<B: Block> {
@@ -308,6 +319,15 @@ where
 		}
 	}
 
+	fn topic_kind(&self, data: &[u8]) -> Option<&'static str> {
+		// The variant index of the encoded `GossipMessage`.
+		match data.first()? {
+			0 => Some("vote"),
+			1 => Some("finality_proof"),
+			_ => None,
+		}
+	}
+
 	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(B::Hash, &[u8]) -> bool + 'a> {
 		let filter = self.gossip_filter.read();
 		Box::new(move |_topic, mut data| match GossipMessage::<B>::decode(&mut data) {
@@ -554,6 +574,19 @@ pub(crate) mod tests {
 		assert!(matches!(res, ValidationResult::Discard));
 	}
 
+	#[test]
+	fn classifies_messages_by_kind() {
+		let validator_set =
+			ValidatorSet::<AuthorityId>::new(vec![Keyring::Alice.public()], 0).unwrap();
+		let gv = GossipValidator::<Block>::new(Arc::new(Mutex::new(KnownPeers::new())));
+
+		let vote = GossipMessage::<Block>::Vote(dummy_vote(3)).encode();
+		let proof = GossipMessage::<Block>::FinalityProof(dummy_proof(3, &validator_set)).encode();
+		assert_eq!(Validator::<Block>::topic_kind(&gv, &vote), Some("vote"));
+		assert_eq!(Validator::<Block>::topic_kind(&gv, &proof), Some("finality_proof"));
+		assert_eq!(Validator::<Block>::topic_kind(&gv, &[]), None);
+	}
+
 	#[test]
 	fn messages_allowed_and_expired() {
 		let keys = vec![Keyring::Alice.public()];
@@ -258,6 +258,7 @@ pub async fn start_beefy_gadget<B, BE, C, N, P, R, S>(
 		gossip_validator.clone(),
 		None,
 	);
+	gossip_engine.set_rate_limits(communication::gossip::GOSSIP_RATE_LIMITS);
 	let metrics = register_metrics(prometheus_registry.clone());
 
 	// The `GossipValidator` adds and removes known peers based on valid votes and network events.
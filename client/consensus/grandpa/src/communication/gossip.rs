@@ -1512,6 +1512,7 @@ impl<Block: BlockT> sc_network_gossip::Validator<Block> for GossipValidator<Bloc
 		context: &mut dyn ValidatorContext<Block>,
 		who: &PeerId,
 		roles: ObservedRole,
+		_negotiated_version: Option<u32>,
 	) {
 		let packet = {
 			let mut inner = self.inner.write();
@@ -1568,6 +1569,18 @@ impl<Block: BlockT> sc_network_gossip::Validator<Block> for GossipValidator<Bloc
 		}
 	}
 
+	fn topic_kind(&self, data: &[u8]) -> Option<&'static str> {
+		// The variant index of the encoded `GossipMessage`.
+		match data.first()? {
+			0 => Some("vote"),
+			1 => Some("commit"),
+			2 => Some("neighbor"),
+			3 => Some("catch_up_request"),
+			4 => Some("catch_up"),
+			_ => None,
+		}
+	}
+
 	fn message_allowed<'a>(
 		&'a self,
 	) -> Box<dyn FnMut(&PeerId, MessageIntent, &Block::Hash, &[u8]) -> bool + 'a> {
@@ -1910,6 +1923,25 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn classifies_messages_by_kind() {
+		let (val, _) = GossipValidator::<Block>::new(config(), voter_set_state(), None, None);
+
+		let neighbor = GossipMessage::<Block>::from(NeighborPacket {
+			round: Round(1),
+			set_id: SetId(0),
+			commit_finalized_height: 1,
+		});
+		let catch_up_request = GossipMessage::<Block>::CatchUpRequest(CatchUpRequestMessage {
+			set_id: SetId(0),
+			round: Round(1),
+		});
+
+		assert_eq!(val.topic_kind(&neighbor.encode()), Some("neighbor"));
+		assert_eq!(val.topic_kind(&catch_up_request.encode()), Some("catch_up_request"));
+		assert_eq!(val.topic_kind(&[5]), None);
+	}
+
 	#[test]
 	fn message_from_unknown_authority_discarded() {
 		assert!(cost::UNKNOWN_VOTER != cost::BAD_SIGNATURE);
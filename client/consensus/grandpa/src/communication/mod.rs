@@ -47,7 +47,7 @@ use finality_grandpa::{
 };
 use parity_scale_codec::{Decode, Encode};
 use sc_network::{NetworkBlock, NetworkSyncForkRequest, ReputationChange};
-use sc_network_gossip::{GossipEngine, Network as GossipNetwork};
+use sc_network_gossip::{GossipEngine, Network as GossipNetwork, RateLimit, RateLimits};
 use sc_telemetry::{telemetry, TelemetryHandle, CONSENSUS_DEBUG, CONSENSUS_INFO};
 use sp_keystore::KeystorePtr;
 use sp_runtime::traits::{Block as BlockT, Hash as HashT, Header as HeaderT, NumberFor};
@@ -72,6 +72,14 @@ pub(crate) mod tests;
 // How often to rebroadcast neighbor packets, in cases where no new packets are created.
 pub(crate) const NEIGHBOR_REBROADCAST_PERIOD: Duration = Duration::from_secs(2 * 60);
 
+// Limit on the gossip messages of each kind (votes, commits, neighbor packets...) received from a
+// peer. A peer relays at most two votes per voter and round, which stays well below this limit
+// for a thousand voters and rounds completing every couple of seconds.
+const GOSSIP_RATE_LIMITS: RateLimits = RateLimits {
+	per_peer: None,
+	per_topic: Some(RateLimit { max_messages: 16384, period: Duration::from_secs(10) }),
+};
+
 pub mod grandpa_protocol_name {
 	use sc_chain_spec::ChainSpec;
 	use sc_network::types::ProtocolName;
@@ -257,13 +265,15 @@ impl<B: BlockT, N: Network<B>, S: Syncing<B>> NetworkBridge<B, N, S> {
 			GossipValidator::new(config, set_state.clone(), prometheus_registry, telemetry.clone());
 
 		let validator = Arc::new(validator);
-		let gossip_engine = Arc::new(Mutex::new(GossipEngine::new(
+		let mut gossip_engine = GossipEngine::new(
 			service.clone(),
 			sync.clone(),
 			protocol,
 			validator.clone(),
 			prometheus_registry,
-		)));
+		);
+		gossip_engine.set_rate_limits(GOSSIP_RATE_LIMITS);
+		let gossip_engine = Arc::new(Mutex::new(gossip_engine));
 
 		{
 			// register all previous votes with the gossip service so that they're
@@ -374,7 +374,9 @@ fn good_commit_leads_to_relay() {
 		.0
 		.then(move |tester| {
 			// register a peer.
-			tester.gossip_validator.new_peer(&mut NoopContext, &id, ObservedRole::Full);
+			tester
+				.gossip_validator
+				.new_peer(&mut NoopContext, &id, ObservedRole::Full, None);
 			future::ready((tester, id))
 		})
 		.then(move |(tester, id)| {
@@ -404,6 +406,7 @@ fn good_commit_leads_to_relay() {
 						remote: sender_id,
 						protocol: grandpa_protocol_name::NAME.into(),
 						negotiated_fallback: None,
+						negotiated_version: None,
 						role: ObservedRole::Full,
 						received_handshake: vec![],
 					});
@@ -422,6 +425,7 @@ fn good_commit_leads_to_relay() {
 						remote: receiver_id,
 						protocol: grandpa_protocol_name::NAME.into(),
 						negotiated_fallback: None,
+						negotiated_version: None,
 						role: ObservedRole::Full,
 						received_handshake: vec![],
 					});
@@ -526,7 +530,9 @@ fn bad_commit_leads_to_report() {
 		.0
 		.map(move |tester| {
 			// register a peer.
-			tester.gossip_validator.new_peer(&mut NoopContext, &id, ObservedRole::Full);
+			tester
+				.gossip_validator
+				.new_peer(&mut NoopContext, &id, ObservedRole::Full, None);
 			(tester, id)
 		})
 		.then(move |(tester, id)| {
@@ -555,6 +561,7 @@ fn bad_commit_leads_to_report() {
 						remote: sender_id,
 						protocol: grandpa_protocol_name::NAME.into(),
 						negotiated_fallback: None,
+						negotiated_version: None,
 						role: ObservedRole::Full,
 						received_handshake: vec![],
 					});
@@ -607,7 +614,9 @@ fn peer_with_higher_view_leads_to_catch_up_request() {
 	let test = tester
 		.map(move |tester| {
 			// register a peer with authority role.
-			tester.gossip_validator.new_peer(&mut NoopContext, &id, ObservedRole::Authority);
+			tester
+				.gossip_validator
+				.new_peer(&mut NoopContext, &id, ObservedRole::Authority, None);
 			(tester, id)
 		})
 		.then(move |(tester, id)| {
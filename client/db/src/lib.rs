@@ -37,6 +37,7 @@ mod parity_db;
 mod pinned_blocks_cache;
 mod record_stats_state;
 mod stats;
+mod trie_cache_snapshot;
 #[cfg(any(feature = "rocksdb", test))]
 mod upgrade;
 mod utils;
@@ -55,6 +56,7 @@ use crate::{
 	pinned_blocks_cache::PinnedBlocksCache,
 	record_stats_state::RecordStatsState,
 	stats::StateUsageStats,
+	trie_cache_snapshot::TrieCacheSnapshot,
 	utils::{meta_keys, read_db, read_meta, DatabaseType, Meta},
 };
 use codec::{Decode, Encode};
@@ -69,7 +71,7 @@ use sc_state_db::{IsPruned, LastCanonicalized, StateDb};
 use sp_arithmetic::traits::Saturating;
 use sp_blockchain::{
 	Backend as _, CachedHeaderMetadata, Error as ClientError, HeaderBackend, HeaderMetadata,
-	HeaderMetadataCache, Result as ClientResult,
+	HeaderMetadataCache, Result as ClientResult, TransactionHashAlgorithm,
 };
 use sp_core::{
 	offchain::OffchainOverlayedChange,
@@ -291,6 +293,17 @@ pub struct DatabaseSettings {
 	///
 	/// If `None` is given, the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// Persist the most recently used entries of the trie cache on shutdown.
+	///
+	/// The entries are stored in the database directory and loaded again on startup, if they
+	/// still belong to the best or finalized state. Only has an effect if the trie cache is
+	/// enabled and the database is stored on disk.
+	pub trie_cache_persistence: bool,
+	/// Index the storage changes of every imported block.
+	///
+	/// Only the changes of blocks that are executed on import are indexed. The index is required
+	/// to query the storage diff between two blocks.
+	pub index_storage_changes: bool,
 	/// Requested state pruning mode.
 	pub state_pruning: Option<PruningMode>,
 	/// Where to find the database.
@@ -415,6 +428,10 @@ pub(crate) mod columns {
 	/// Transactions
 	pub const TRANSACTION: u32 = 11;
 	pub const BODY_INDEX: u32 = 12;
+	/// Maps alternative digests of indexed transactions to their hash in `TRANSACTION`.
+	pub const TRANSACTION_ALIAS: u32 = 13;
+	/// Storage changes of every block, keyed by the block lookup key.
+	pub const STORAGE_CHANGES: u32 = 14;
 }
 
 struct PendingBlock<Block: BlockT> {
@@ -751,6 +768,27 @@ impl<Block: BlockT> sc_client_api::blockchain::Backend<Block> for BlockchainDb<B
 		Ok(self.db.contains(columns::TRANSACTION, hash.as_ref()))
 	}
 
+	fn indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> ClientResult<Option<Vec<u8>>> {
+		let hash = match algorithm {
+			TransactionHashAlgorithm::Blake2b256 => digest.to_vec(),
+			TransactionHashAlgorithm::Sha2_256 | TransactionHashAlgorithm::Keccak256 =>
+				match self.db.get(columns::TRANSACTION_ALIAS, digest) {
+					Some(hash) => hash,
+					None => return Ok(None),
+				},
+		};
+
+		// Aliases of all algorithms share the same column, make sure the digest is the right one.
+		Ok(self
+			.db
+			.get(columns::TRANSACTION, &hash)
+			.filter(|transaction| transaction_digest(algorithm, transaction).as_ref() == digest))
+	}
+
 	fn block_indexed_body(&self, hash: Block::Hash) -> ClientResult<Option<Vec<Vec<u8>>>> {
 		let body = match read_db(
 			&*self.db,
@@ -827,6 +865,8 @@ pub struct BlockImportOperation<Block: BlockT> {
 	db_updates: PrefixedMemoryDB<HashFor<Block>>,
 	storage_updates: StorageCollection,
 	child_storage_updates: ChildStorageCollection,
+	/// Whether the storage changes of the pending block are known.
+	has_storage_changes: bool,
 	offchain_storage_updates: OffchainChangesCollection,
 	pending_block: Option<PendingBlock<Block>>,
 	aux_ops: Vec<(Vec<u8>, Option<Vec<u8>>)>,
@@ -954,6 +994,7 @@ impl<Block: BlockT> sc_client_api::backend::BlockImportOperation<Block>
 	) -> ClientResult<()> {
 		self.storage_updates = update;
 		self.child_storage_updates = child_update;
+		self.has_storage_changes = true;
 		Ok(())
 	}
 
@@ -1105,6 +1146,8 @@ pub struct Backend<Block: BlockT> {
 	state_usage: Arc<StateUsageStats>,
 	genesis_state: RwLock<Option<Arc<DbGenesisStorage<Block>>>>,
 	shared_trie_cache: Option<sp_trie::cache::SharedTrieCache<HashFor<Block>>>,
+	trie_cache_snapshot: Option<TrieCacheSnapshot>,
+	index_storage_changes: bool,
 }
 
 impl<Block: BlockT> Backend<Block> {
@@ -1151,6 +1194,8 @@ impl<Block: BlockT> Backend<Block> {
 		};
 		let db_setting = DatabaseSettings {
 			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_persistence: false,
+			index_storage_changes: false,
 			state_pruning: Some(state_pruning),
 			source: DatabaseSource::Custom { db, require_create_flag: true },
 			blocks_pruning,
@@ -1159,6 +1204,24 @@ impl<Block: BlockT> Backend<Block> {
 		Self::new(db_setting, canonicalization_delay).expect("failed to create test-db")
 	}
 
+	/// Create new memory-backed client backend for tests that indexes the storage changes of
+	/// every block.
+	#[cfg(any(test, feature = "test-helpers"))]
+	pub fn new_test_with_storage_changes_index(blocks_pruning: u32) -> Self {
+		let db = kvdb_memorydb::create(crate::utils::NUM_COLUMNS);
+		let db = sp_database::as_database(db);
+		let db_setting = DatabaseSettings {
+			trie_cache_maximum_size: Some(16 * 1024 * 1024),
+			trie_cache_persistence: false,
+			index_storage_changes: true,
+			state_pruning: Some(PruningMode::blocks_pruning(blocks_pruning)),
+			source: DatabaseSource::Custom { db, require_create_flag: true },
+			blocks_pruning: BlocksPruning::Some(blocks_pruning),
+		};
+
+		Self::new(db_setting, 0).expect("failed to create test-db")
+	}
+
 	/// Expose the Database that is used by this backend.
 	/// The second argument is the Column that stores the State.
 	///
@@ -1221,6 +1284,12 @@ impl<Block: BlockT> Backend<Block> {
 			shared_trie_cache: config.trie_cache_maximum_size.map(|maximum_size| {
 				SharedTrieCache::new(sp_trie::cache::CacheSize::new(maximum_size))
 			}),
+			trie_cache_snapshot: config
+				.trie_cache_persistence
+				.then(|| config.trie_cache_maximum_size.zip(config.source.path()))
+				.flatten()
+				.map(|(maximum_size, path)| TrieCacheSnapshot::new(path, maximum_size)),
+			index_storage_changes: config.index_storage_changes,
 		};
 
 		// Older DB versions have no last state key. Check if the state is available and set it.
@@ -1243,6 +1312,19 @@ impl<Block: BlockT> Backend<Block> {
 
 		db.commit(db_init_transaction)?;
 
+		if let (Some(cache), Some(snapshot)) =
+			(&backend.shared_trie_cache, &backend.trie_cache_snapshot)
+		{
+			let info = backend.blockchain.info();
+			let storage_roots = [info.best_hash, info.finalized_hash]
+				.into_iter()
+				.filter_map(|hash| backend.blockchain.header(hash).ok().flatten())
+				.map(|header: Block::Header| *header.state_root())
+				.collect::<Vec<_>>();
+
+			snapshot.load::<Block>(cache, &storage_roots);
+		}
+
 		Ok(backend)
 	}
 
@@ -1479,8 +1561,12 @@ impl<Block: BlockT> Backend<Block> {
 				if operation.index_ops.is_empty() {
 					transaction.set_from_vec(columns::BODY, &lookup_key, body.encode());
 				} else {
-					let body =
-						apply_index_ops::<Block>(&mut transaction, body, operation.index_ops);
+					let body = apply_index_ops::<Block>(
+						&mut transaction,
+						&*self.storage.db,
+						body,
+						operation.index_ops,
+					);
 					transaction.set_from_vec(columns::BODY_INDEX, &lookup_key, body);
 				}
 			}
@@ -1494,6 +1580,13 @@ impl<Block: BlockT> Backend<Block> {
 					justifications.encode(),
 				);
 			}
+			if self.index_storage_changes && operation.has_storage_changes {
+				transaction.set_from_vec(
+					columns::STORAGE_CHANGES,
+					&lookup_key,
+					(&operation.storage_updates, &operation.child_storage_updates).encode(),
+				);
+			}
 
 			if number.is_zero() {
 				transaction.set(columns::META, meta_keys::GENESIS_HASH, hash.as_ref());
@@ -1878,6 +1971,13 @@ impl<Block: BlockT> Backend<Block> {
 			columns::JUSTIFICATIONS,
 			id,
 		)?;
+		utils::remove_from_db(
+			transaction,
+			&*self.storage.db,
+			columns::KEY_LOOKUP,
+			columns::STORAGE_CHANGES,
+			id,
+		)?;
 		if let Some(index) =
 			read_db(&*self.storage.db, columns::KEY_LOOKUP, columns::BODY_INDEX, id)?
 		{
@@ -1892,6 +1992,13 @@ impl<Block: BlockT> Backend<Block> {
 				Ok(index) =>
 					for ex in index {
 						if let DbExtrinsic::Indexed { hash, .. } = ex {
+							if let Some(data) =
+								self.storage.db.get(columns::TRANSACTION, hash.as_ref())
+							{
+								for alias in stored_transaction_aliases(&*self.storage.db, &data) {
+									transaction.release(columns::TRANSACTION_ALIAS, alias);
+								}
+							}
 							transaction.release(columns::TRANSACTION, hash);
 						}
 					},
@@ -1933,8 +2040,45 @@ fn apply_state_commit(
 	}
 }
 
+/// Digests of `data` under which it can be looked up in addition to its Blake2b-256 hash.
+fn transaction_aliases(data: &[u8]) -> [DbHash; 2] {
+	[
+		transaction_digest(TransactionHashAlgorithm::Sha2_256, data),
+		transaction_digest(TransactionHashAlgorithm::Keccak256, data),
+	]
+}
+
+/// Aliases of the indexed transaction `data` that are stored in `db`.
+///
+/// Transactions indexed before the aliases were introduced have none, see
+/// `upgrade::migrate_4_to_5`. Their aliases must not be referenced or released, as ref-counted
+/// databases don't expect changes to keys they don't contain.
+fn stored_transaction_aliases(db: &dyn Database<DbHash>, data: &[u8]) -> Vec<DbHash> {
+	transaction_aliases(data)
+		.into_iter()
+		.filter(|alias| db.contains(columns::TRANSACTION_ALIAS, alias.as_ref()))
+		.collect()
+}
+
+fn transaction_digest(algorithm: TransactionHashAlgorithm, data: &[u8]) -> DbHash {
+	match algorithm {
+		TransactionHashAlgorithm::Blake2b256 => sp_core::hashing::blake2_256(data).into(),
+		TransactionHashAlgorithm::Sha2_256 => sp_core::hashing::sha2_256(data).into(),
+		TransactionHashAlgorithm::Keccak256 => sp_core::hashing::keccak_256(data).into(),
+	}
+}
+
+/// Stores an indexed transaction along with its aliases.
+fn store_indexed_transaction(transaction: &mut Transaction<DbHash>, hash: DbHash, data: Vec<u8>) {
+	for alias in transaction_aliases(&data) {
+		transaction.store(columns::TRANSACTION_ALIAS, alias, hash.as_ref().to_vec());
+	}
+	transaction.store(columns::TRANSACTION, hash, data);
+}
+
 fn apply_index_ops<Block: BlockT>(
 	transaction: &mut Transaction<DbHash>,
+	db: &dyn Database<DbHash>,
 	body: Vec<Block::Extrinsic>,
 	ops: Vec<IndexOperation>,
 ) -> Vec<u8> {
@@ -1955,6 +2099,11 @@ fn apply_index_ops<Block: BlockT>(
 		let db_extrinsic = if let Some(hash) = renewed_map.get(&(index as u32)) {
 			// Bump ref counter
 			let extrinsic = extrinsic.encode();
+			if let Some(data) = db.get(columns::TRANSACTION, hash.as_ref()) {
+				for alias in stored_transaction_aliases(db, &data) {
+					transaction.reference(columns::TRANSACTION_ALIAS, alias);
+				}
+			}
 			transaction.reference(columns::TRANSACTION, DbHash::from_slice(hash.as_ref()));
 			DbExtrinsic::Indexed { hash: *hash, header: extrinsic }
 		} else {
@@ -1963,8 +2112,8 @@ fn apply_index_ops<Block: BlockT>(
 					let encoded = extrinsic.encode();
 					if *size as usize <= encoded.len() {
 						let offset = encoded.len() - *size as usize;
-						transaction.store(
-							columns::TRANSACTION,
+						store_indexed_transaction(
+							transaction,
 							DbHash::from_slice(hash.as_ref()),
 							encoded[offset..].to_vec(),
 						);
@@ -1995,7 +2144,19 @@ fn apply_index_ops<Block: BlockT>(
 fn apply_indexed_body<Block: BlockT>(transaction: &mut Transaction<DbHash>, body: Vec<Vec<u8>>) {
 	for extrinsic in body {
 		let hash = sp_runtime::traits::BlakeTwo256::hash(&extrinsic);
-		transaction.store(columns::TRANSACTION, DbHash::from_slice(hash.as_ref()), extrinsic);
+		store_indexed_transaction(transaction, DbHash::from_slice(hash.as_ref()), extrinsic);
+	}
+}
+
+impl<Block: BlockT> Drop for Backend<Block> {
+	fn drop(&mut self) {
+		if let (Some(cache), Some(snapshot)) = (&self.shared_trie_cache, &self.trie_cache_snapshot)
+		{
+			let best_hash = self.blockchain.info().best_hash;
+			if let Ok(Some(header)) = self.blockchain.header(best_hash) {
+				snapshot.store::<Block>(cache, *header.state_root());
+			}
+		}
 	}
 }
 
@@ -2043,6 +2204,7 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 			db_updates: PrefixedMemoryDB::default(),
 			storage_updates: Default::default(),
 			child_storage_updates: Default::default(),
+			has_storage_changes: false,
 			offchain_storage_updates: Default::default(),
 			aux_ops: Vec::new(),
 			finalized_blocks: Vec::new(),
@@ -2395,6 +2557,23 @@ impl<Block: BlockT> sc_client_api::backend::Backend<Block> for Backend<Block> {
 		&self.blockchain
 	}
 
+	fn storage_changes(
+		&self,
+		hash: Block::Hash,
+	) -> ClientResult<Option<(StorageCollection, ChildStorageCollection)>> {
+		match read_db(
+			&*self.storage.db,
+			columns::KEY_LOOKUP,
+			columns::STORAGE_CHANGES,
+			BlockId::<Block>::Hash(hash),
+		)? {
+			Some(changes) => Decode::decode(&mut &changes[..]).map(Some).map_err(|e| {
+				sp_blockchain::Error::Backend(format!("Error decoding storage changes: {}", e))
+			}),
+			None => Ok(None),
+		}
+	}
+
 	fn state_at(&self, hash: Block::Hash) -> ClientResult<Self::State> {
 		if hash == self.blockchain.meta.read().genesis_hash {
 			if let Some(genesis_state) = &*self.genesis_state.read() {
@@ -2669,6 +2848,8 @@ pub(crate) mod tests {
 		let backend = Backend::<Block>::new(
 			DatabaseSettings {
 				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_persistence: false,
+				index_storage_changes: false,
 				state_pruning: Some(PruningMode::blocks_pruning(1)),
 				source: DatabaseSource::Custom { db: backing, require_create_flag: false },
 				blocks_pruning: BlocksPruning::KeepFinalized,
@@ -2764,6 +2945,40 @@ pub(crate) mod tests {
 		}
 	}
 
+	#[test]
+	fn storage_changes_are_indexed() {
+		let backend = Backend::<Block>::new_test_with_storage_changes_index(1);
+
+		let genesis = insert_header(&backend, 0, Default::default(), None, Default::default());
+		assert_eq!(backend.storage_changes(genesis).unwrap(), None);
+
+		let main = vec![(vec![1], Some(vec![2])), (vec![3], None)];
+		let children = vec![(b"child".to_vec(), vec![(vec![4], Some(vec![5]))])];
+
+		let mut op = backend.begin_operation().unwrap();
+		backend.begin_state_operation(&mut op, genesis).unwrap();
+		let header = Header {
+			number: 1,
+			parent_hash: genesis,
+			state_root: Default::default(),
+			digest: Default::default(),
+			extrinsics_root: Default::default(),
+		};
+		op.update_storage(main.clone(), children.clone()).unwrap();
+		op.set_block_data(header.clone(), Some(vec![]), None, None, NewBlockState::Best)
+			.unwrap();
+		backend.commit_operation(op).unwrap();
+
+		let hash = header.hash();
+		assert_eq!(backend.storage_changes(hash).unwrap(), Some((main, children)));
+
+		// The changes are pruned together with the block body.
+		let block2 = insert_header(&backend, 2, hash, None, Default::default());
+		backend.finalize_block(hash, None).unwrap();
+		backend.finalize_block(block2, None).unwrap();
+		assert_eq!(backend.storage_changes(hash).unwrap(), None);
+	}
+
 	#[test]
 	fn delete_only_when_negative_rc() {
 		sp_tracing::try_init_simple();
@@ -3569,6 +3784,27 @@ pub(crate) mod tests {
 		assert_eq!(bc.indexed_transaction(x0_hash).unwrap().unwrap(), &x0[1..]);
 		assert_eq!(bc.indexed_transaction(x1_hash).unwrap().unwrap(), &x1[1..]);
 
+		let x0_sha2 = sp_core::hashing::sha2_256(&x0[1..]);
+		let x1_keccak = sp_core::hashing::keccak_256(&x1[1..]);
+		assert_eq!(
+			bc.indexed_transaction_by(TransactionHashAlgorithm::Sha2_256, &x0_sha2)
+				.unwrap()
+				.unwrap(),
+			&x0[1..]
+		);
+		assert_eq!(
+			bc.indexed_transaction_by(TransactionHashAlgorithm::Keccak256, &x1_keccak)
+				.unwrap()
+				.unwrap(),
+			&x1[1..]
+		);
+		// Digests are only valid for the algorithm that produced them.
+		assert_eq!(
+			bc.indexed_transaction_by(TransactionHashAlgorithm::Keccak256, &x0_sha2)
+				.unwrap(),
+			None
+		);
+
 		let hashof0 = bc.info().genesis_hash;
 		// Push one more blocks and make sure block is pruned and transaction index is cleared.
 		let block1 =
@@ -3577,6 +3813,11 @@ pub(crate) mod tests {
 		assert_eq!(bc.body(hashof0).unwrap(), None);
 		assert_eq!(bc.indexed_transaction(x0_hash).unwrap(), None);
 		assert_eq!(bc.indexed_transaction(x1_hash).unwrap(), None);
+		assert_eq!(
+			bc.indexed_transaction_by(TransactionHashAlgorithm::Sha2_256, &x0_sha2).unwrap(),
+			None
+		);
+		assert!(backend.storage.db.get(columns::TRANSACTION_ALIAS, &x0_sha2).is_none());
 	}
 
 	#[test]
@@ -3661,6 +3902,104 @@ pub(crate) mod tests {
 		}
 	}
 
+	/// Database that rejects referencing or releasing transaction aliases it doesn't contain, like
+	/// ref-counted databases do.
+	struct StrictAliasDb(Arc<dyn Database<DbHash>>);
+
+	impl Database<DbHash> for StrictAliasDb {
+		fn commit(&self, transaction: Transaction<DbHash>) -> sp_database::error::Result<()> {
+			let mut stored = HashSet::new();
+			for change in &transaction.0 {
+				match change {
+					sp_database::Change::Store(columns::TRANSACTION_ALIAS, alias, _) => {
+						stored.insert(*alias);
+					},
+					sp_database::Change::Reference(columns::TRANSACTION_ALIAS, alias) |
+					sp_database::Change::Release(columns::TRANSACTION_ALIAS, alias) => assert!(
+						stored.contains(alias) ||
+							self.0.contains(columns::TRANSACTION_ALIAS, alias.as_ref()),
+						"Missing transaction alias {:?}",
+						alias,
+					),
+					_ => {},
+				}
+			}
+			self.0.commit(transaction)
+		}
+
+		fn get(&self, col: sp_database::ColumnId, key: &[u8]) -> Option<Vec<u8>> {
+			self.0.get(col, key)
+		}
+	}
+
+	#[test]
+	fn renew_and_prune_transaction_without_aliases() {
+		let db = Arc::new(StrictAliasDb(sp_database::as_database(kvdb_memorydb::create(
+			crate::utils::NUM_COLUMNS,
+		))));
+		let backend = Backend::<Block>::new(
+			DatabaseSettings {
+				trie_cache_maximum_size: Some(16 * 1024 * 1024),
+				trie_cache_persistence: false,
+				index_storage_changes: false,
+				state_pruning: Some(PruningMode::blocks_pruning(2)),
+				source: DatabaseSource::Custom { db: db.clone(), require_create_flag: true },
+				blocks_pruning: BlocksPruning::Some(2),
+			},
+			10,
+		)
+		.unwrap();
+		let mut blocks = Vec::new();
+		let mut prev_hash = Default::default();
+		let x1 = ExtrinsicWrapper::from(0u64).encode();
+		let x1_hash = <HashFor<Block> as sp_core::Hasher>::hash(&x1[1..]);
+		for i in 0..10 {
+			let mut index = Vec::new();
+			if i == 0 {
+				index.push(IndexOperation::Insert {
+					extrinsic: 0,
+					hash: x1_hash.as_ref().to_vec(),
+					size: (x1.len() - 1) as u32,
+				});
+			} else if i < 5 {
+				index.push(IndexOperation::Renew { extrinsic: 0, hash: x1_hash.as_ref().to_vec() });
+			}
+			let hash = insert_block(
+				&backend,
+				i,
+				prev_hash,
+				None,
+				Default::default(),
+				vec![i.into()],
+				Some(index),
+			)
+			.unwrap();
+			if i == 0 {
+				// Drop the aliases, as if the transaction was indexed before they were introduced.
+				let mut transaction = Transaction::new();
+				for alias in transaction_aliases(&x1[1..]) {
+					transaction.remove(columns::TRANSACTION_ALIAS, alias.as_ref());
+				}
+				db.commit(transaction).unwrap();
+			}
+			blocks.push(hash);
+			prev_hash = hash;
+		}
+
+		for i in 1..10 {
+			let mut op = backend.begin_operation().unwrap();
+			backend.begin_state_operation(&mut op, blocks[4]).unwrap();
+			op.mark_finalized(blocks[i], None).unwrap();
+			backend.commit_operation(op).unwrap();
+			let bc = backend.blockchain();
+			if i < 6 {
+				assert!(bc.indexed_transaction(x1_hash).unwrap().is_some());
+			} else {
+				assert!(bc.indexed_transaction(x1_hash).unwrap().is_none());
+			}
+		}
+	}
+
 	#[test]
 	fn remove_leaf_block_works() {
 		let backend = Backend::<Block>::new_test_with_tx_storage(BlocksPruning::Some(2), 10);
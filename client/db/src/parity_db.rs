@@ -67,6 +67,10 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 			tx_col.ref_counted = true;
 			tx_col.preimage = true;
 			tx_col.uniform = true;
+
+			let mut tx_alias_col = &mut config.columns[columns::TRANSACTION_ALIAS as usize];
+			tx_alias_col.ref_counted = true;
+			tx_alias_col.uniform = true;
 		},
 	}
 
@@ -87,7 +91,7 @@ pub fn open<H: Clone + AsRef<[u8]>>(
 }
 
 fn ref_counted_column(col: u32) -> bool {
-	col == columns::TRANSACTION || col == columns::STATE
+	col == columns::TRANSACTION || col == columns::TRANSACTION_ALIAS || col == columns::STATE
 }
 
 impl<H: Clone + AsRef<[u8]>> Database<H> for DbAdapter {
@@ -0,0 +1,150 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Persistence of the shared trie cache across restarts.
+
+use log::{debug, info, warn};
+use sp_runtime::traits::{Block as BlockT, HashFor};
+use sp_trie::cache::SharedTrieCache;
+use std::{
+	io,
+	path::{Path, PathBuf},
+};
+
+const LOG_TARGET: &str = "db::trie-cache";
+
+/// Name of the file in the database directory the snapshot is stored in.
+const SNAPSHOT_FILE_NAME: &str = "trie_cache.snapshot";
+
+/// A snapshot of the shared trie cache that is stored in the database directory.
+pub(crate) struct TrieCacheSnapshot {
+	/// Path of the snapshot file.
+	path: PathBuf,
+	/// Maximum size of the snapshot in bytes.
+	max_size: usize,
+}
+
+impl TrieCacheSnapshot {
+	/// Create a new instance for the database stored in `db_path`.
+	pub fn new(db_path: &Path, max_size: usize) -> Self {
+		Self { path: db_path.join(SNAPSHOT_FILE_NAME), max_size }
+	}
+
+	/// Load the snapshot into `cache`, if it was taken at one of the given `storage_roots`.
+	///
+	/// The snapshot file is removed afterwards, so that it is never loaded twice.
+	pub fn load<Block: BlockT>(
+		&self,
+		cache: &SharedTrieCache<HashFor<Block>>,
+		storage_roots: &[Block::Hash],
+	) {
+		let snapshot = match std::fs::read(&self.path) {
+			Ok(snapshot) => snapshot,
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+			Err(e) => {
+				warn!(target: LOG_TARGET, "Failed to read trie cache snapshot: {}", e);
+				return
+			},
+		};
+
+		if let Err(e) = std::fs::remove_file(&self.path) {
+			warn!(target: LOG_TARGET, "Failed to remove trie cache snapshot: {}", e);
+		}
+
+		match cache.import(&snapshot, self.max_size, |root| storage_roots.contains(root)) {
+			Ok(()) => info!(
+				target: LOG_TARGET,
+				"📦 Loaded trie cache snapshot ({} bytes)",
+				snapshot.len(),
+			),
+			Err(e) => debug!(target: LOG_TARGET, "Ignoring trie cache snapshot: {}", e),
+		}
+	}
+
+	/// Store the entries of `cache` that are relevant for the state at `storage_root`.
+	///
+	/// The snapshot is written to a temporary file first, so that an interrupted write never
+	/// leaves a partial snapshot behind.
+	pub fn store<Block: BlockT>(
+		&self,
+		cache: &SharedTrieCache<HashFor<Block>>,
+		storage_root: Block::Hash,
+	) {
+		let snapshot = cache.export(storage_root, self.max_size);
+		let tmp_path = self.path.with_extension("tmp");
+
+		let res = std::fs::write(&tmp_path, &snapshot)
+			.and_then(|()| std::fs::rename(&tmp_path, &self.path));
+
+		match res {
+			Ok(()) => debug!(
+				target: LOG_TARGET,
+				"Stored trie cache snapshot ({} bytes) at {:?}",
+				snapshot.len(),
+				storage_root,
+			),
+			Err(e) => warn!(target: LOG_TARGET, "Failed to store trie cache snapshot: {}", e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_trie::{cache::CacheSize, LayoutV1, TrieDBBuilder, TrieDBMutBuilder, TrieMut};
+	use substrate_test_runtime_client::runtime::Block;
+
+	type Hasher = HashFor<Block>;
+
+	#[test]
+	fn snapshot_is_stored_and_loaded() {
+		let tmp = tempfile::tempdir().unwrap();
+		let snapshot = TrieCacheSnapshot::new(tmp.path(), 1024 * 1024);
+
+		let mut db = sp_trie::MemoryDB::<Hasher>::default();
+		let mut root = Default::default();
+		{
+			let mut trie = TrieDBMutBuilder::<LayoutV1<Hasher>>::new(&mut db, &mut root).build();
+			trie.insert(b"key", b"value").unwrap();
+		}
+
+		let cache = SharedTrieCache::<Hasher>::new(CacheSize::new(1024 * 1024));
+		{
+			let local_cache = cache.local_cache();
+			let mut trie_cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<LayoutV1<Hasher>>::new(&db, &root)
+				.with_cache(&mut trie_cache)
+				.build();
+			sp_trie::Trie::get(&trie, b"key").unwrap().unwrap();
+		}
+
+		snapshot.store::<Block>(&cache, root);
+		assert!(tmp.path().join(SNAPSHOT_FILE_NAME).exists());
+
+		// A snapshot taken at an unknown root is not loaded, but removed.
+		let new_cache = SharedTrieCache::<Hasher>::new(CacheSize::new(1024 * 1024));
+		snapshot.load::<Block>(&new_cache, &[Default::default()]);
+		assert!(new_cache.peek_node(&root).is_none());
+		assert!(!tmp.path().join(SNAPSHOT_FILE_NAME).exists());
+
+		snapshot.store::<Block>(&cache, root);
+		snapshot.load::<Block>(&new_cache, &[root]);
+		assert!(new_cache.peek_node(&root).is_some());
+		assert!(!tmp.path().join(SNAPSHOT_FILE_NAME).exists());
+	}
+}
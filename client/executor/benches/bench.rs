@@ -33,7 +33,7 @@ use std::sync::{
 
 #[derive(Clone)]
 enum Method {
-	Interpreted,
+	Interpreted { instantiation_strategy: sc_executor_wasmi::InstantiationStrategy },
 	Compiled { instantiation_strategy: InstantiationStrategy, precompile: bool },
 }
 
@@ -55,9 +55,12 @@ fn initialize(
 	let allow_missing_func_imports = true;
 
 	match method {
-		Method::Interpreted => sc_executor_wasmi::create_runtime(
+		Method::Interpreted { instantiation_strategy } => sc_executor_wasmi::create_runtime(
 			blob,
 			HeapAllocStrategy::Static { extra_pages },
+			Default::default(),
+			instantiation_strategy,
+			None,
 			host_functions,
 			allow_missing_func_imports,
 		)
@@ -68,6 +71,7 @@ fn initialize(
 				cache_path: None,
 				semantics: sc_executor_wasmtime::Semantics {
 					heap_alloc_strategy: HeapAllocStrategy::Static { extra_pages },
+					allocator: Default::default(),
 					instantiation_strategy,
 					deterministic_stack_limit: None,
 					canonicalize_nans: false,
@@ -209,7 +213,13 @@ fn bench_call_instance(c: &mut Criterion) {
 				precompile: true,
 			},
 		),
-		("interpreted", Method::Interpreted),
+		(
+			"interpreted",
+			Method::Interpreted {
+				instantiation_strategy:
+					sc_executor_wasmi::InstantiationStrategy::PoolingCopyOnWrite,
+			},
+		),
 	];
 
 	let runtimes = [("kusama_runtime", kusama_runtime()), ("test_runtime", test_runtime())];
@@ -255,9 +265,35 @@ fn bench_call_instance(c: &mut Criterion) {
 	}
 }
 
+fn bench_new_instance_interpreted(c: &mut Criterion) {
+	let _ = env_logger::try_init();
+
+	let strategies = [
+		("recreate_instance", sc_executor_wasmi::InstantiationStrategy::RecreateInstance),
+		("pooling_cow", sc_executor_wasmi::InstantiationStrategy::PoolingCopyOnWrite),
+	];
+
+	let mut tmpdir = None;
+
+	for (strategy_name, instantiation_strategy) in strategies {
+		let runtime =
+			initialize(&mut tmpdir, test_runtime(), Method::Interpreted { instantiation_strategy });
+
+		c.bench_function(
+			&format!("new_instance_and_call_empty_function_with_interpreted_{}", strategy_name),
+			|b| {
+				b.iter(|| {
+					let mut instance = runtime.new_instance().unwrap();
+					instance.call_export("test_empty_return", &[0]).unwrap();
+				})
+			},
+		);
+	}
+}
+
 criterion_group! {
 	name = benches;
 	config = Criterion::default();
-	targets = bench_call_instance
+	targets = bench_call_instance, bench_new_instance_interpreted
 }
 criterion_main!(benches);
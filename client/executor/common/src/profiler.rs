@@ -0,0 +1,145 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Profiling of the host functions called during a runtime call.
+
+use std::{
+	cell::Cell,
+	collections::BTreeMap,
+	time::{Duration, Instant},
+};
+
+/// Statistics about the calls into a single host function.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HostFunctionStats {
+	/// How often the host function was called.
+	pub calls: u64,
+	/// Cumulative time spent executing the host function.
+	pub time: Duration,
+	/// Number of bytes the host function read from the linear memory.
+	pub bytes_read: u64,
+	/// Number of bytes the host function wrote to the linear memory.
+	pub bytes_written: u64,
+}
+
+/// The host function statistics of a single runtime call.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostFunctionProfile {
+	/// Time spent in the runtime call, including the host functions.
+	pub total_time: Duration,
+	/// Statistics of every host function called, by name.
+	pub host_functions: BTreeMap<&'static str, HostFunctionStats>,
+}
+
+impl HostFunctionProfile {
+	/// Time spent executing host functions.
+	pub fn host_time(&self) -> Duration {
+		self.host_functions.values().map(|stats| stats.time).sum()
+	}
+
+	/// Time spent executing wasm code, i.e. outside of host functions.
+	pub fn wasm_time(&self) -> Duration {
+		self.total_time.saturating_sub(self.host_time())
+	}
+}
+
+/// Records the host function calls of a single runtime call.
+///
+/// The executor calls [`Self::enter`] and [`Self::exit`] around every host function call and
+/// reports the linear memory accessed by the host function in between.
+#[derive(Debug, Default)]
+pub struct HostFunctionProfiler {
+	host_functions: BTreeMap<&'static str, HostFunctionStats>,
+	/// Bytes read by the host function that is currently executed.
+	bytes_read: Cell<u64>,
+	/// Bytes written by the host function that is currently executed.
+	bytes_written: Cell<u64>,
+}
+
+impl HostFunctionProfiler {
+	/// Note that the current host function read `len` bytes from the linear memory.
+	pub fn note_read(&self, len: usize) {
+		self.bytes_read.set(self.bytes_read.get().saturating_add(len as u64));
+	}
+
+	/// Note that the current host function wrote `len` bytes to the linear memory.
+	pub fn note_written(&self, len: usize) {
+		self.bytes_written.set(self.bytes_written.get().saturating_add(len as u64));
+	}
+
+	/// Start recording a host function call.
+	///
+	/// Returns the instant that needs to be passed to [`Self::exit`].
+	pub fn enter(&self) -> Instant {
+		self.bytes_read.set(0);
+		self.bytes_written.set(0);
+		Instant::now()
+	}
+
+	/// Finish recording a call of the host function `name` that started at `started`.
+	pub fn exit(&mut self, name: &'static str, started: Instant) {
+		let elapsed = started.elapsed();
+		let stats = self.host_functions.entry(name).or_default();
+		stats.calls += 1;
+		stats.time += elapsed;
+		stats.bytes_read += self.bytes_read.take();
+		stats.bytes_written += self.bytes_written.take();
+	}
+
+	/// Finish profiling a runtime call that took `total_time`.
+	pub fn into_profile(self, total_time: Duration) -> HostFunctionProfile {
+		HostFunctionProfile { total_time, host_functions: self.host_functions }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn profiler_aggregates_host_function_calls() {
+		let mut profiler = HostFunctionProfiler::default();
+
+		// Accesses outside of host functions are not attributed to any of them.
+		profiler.note_written(100);
+
+		let started = profiler.enter();
+		profiler.note_read(32);
+		profiler.note_written(8);
+		profiler.exit("ext_storage_get_version_1", started);
+
+		let started = profiler.enter();
+		profiler.note_read(32);
+		profiler.exit("ext_storage_get_version_1", started);
+
+		let started = profiler.enter();
+		profiler.note_read(64);
+		profiler.note_read(64);
+		profiler.exit("ext_crypto_sr25519_verify_version_2", started);
+
+		let profile = profiler.into_profile(Duration::from_secs(1));
+		let storage_get = profile.host_functions["ext_storage_get_version_1"];
+		assert_eq!(
+			(storage_get.calls, storage_get.bytes_read, storage_get.bytes_written),
+			(2, 64, 8)
+		);
+		let verify = profile.host_functions["ext_crypto_sr25519_verify_version_2"];
+		assert_eq!((verify.calls, verify.bytes_read, verify.bytes_written), (1, 128, 0));
+		assert_eq!(profile.wasm_time() + profile.host_time(), Duration::from_secs(1));
+	}
+}
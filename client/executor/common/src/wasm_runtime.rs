@@ -18,10 +18,10 @@
 
 //! Definitions for a wasm runtime.
 
-use crate::error::Error;
+use crate::{error::Error, profiler::HostFunctionProfile};
 use sp_wasm_interface::Value;
 
-pub use sc_allocator::AllocationStats;
+pub use sc_allocator::{AllocationStats, AllocatorKind};
 
 /// A method to be used to find the entrypoint when calling into the runtime
 ///
@@ -95,6 +95,20 @@ pub trait WasmInstance: Send {
 		data: &[u8],
 	) -> (Result<Vec<u8>, Error>, Option<AllocationStats>);
 
+	/// Call a method on this WASM instance while profiling the host functions it calls.
+	///
+	/// Before execution, instance is reset.
+	///
+	/// Returns the encoded result on success. The profile is `None` if the execution engine
+	/// doesn't support profiling.
+	fn call_with_host_function_profile(
+		&mut self,
+		method: InvokeMethod,
+		data: &[u8],
+	) -> (Result<Vec<u8>, Error>, Option<HostFunctionProfile>) {
+		(self.call(method, data), None)
+	}
+
 	/// Call an exported method on this WASM instance.
 	///
 	/// Before execution, instance is reset.
@@ -120,6 +134,63 @@ pub trait WasmInstance: Send {
 	}
 }
 
+/// Knobs for deterministic stack height limiting.
+///
+/// The WebAssembly standard defines a call/value stack but it doesn't say anything about its
+/// size except that it has to be finite. The implementations are free to choose their own notion
+/// of limit: some may count the number of calls or values, others would rely on the host machine
+/// stack and trap on reaching a guard page.
+///
+/// This obviously is a source of non-determinism during execution. This feature can be used
+/// to instrument the code so that it will count the number of items that may be on the stack
+/// (the machine stack limit should be so high that the deterministic limit always triggers
+/// first). The same instrumentation is applied by all execution engines, so deep recursion traps
+/// at exactly the same point regardless of the engine, the host machine and the compiler.
+///
+/// For wasmtime this counting will only act as an rough estimate of the actual stack limit. This
+/// is because wasmtime measures it's stack usage in bytes. The actual number of bytes consumed by
+/// a function is not trivial to compute without going through full compilation. Therefore, it's
+/// expected that `native_stack_max` is greatly overestimated and thus never reached in practice.
+/// The stack overflow check introduced by the instrumentation and that relies on the logical item
+/// count should be reached first.
+///
+/// See [here][stack_height] for more details of the instrumentation
+///
+/// [stack_height]: https://github.com/paritytech/wasm-utils/blob/d9432baf/src/stack_height/mod.rs#L1-L50
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct DeterministicStackLimit {
+	/// A number of logical "values" that can be pushed on the wasm stack. A trap will be triggered
+	/// if exceeded.
+	///
+	/// A logical value is a local, an argument or a value pushed on operand stack.
+	pub logical_max: u32,
+	/// The maximum number of bytes for stack used by wasmtime JITed code.
+	///
+	/// It's not specified how much bytes will be consumed by a stack frame for a given wasm
+	/// function after translation into machine code. It is also not quite trivial.
+	///
+	/// Therefore, this number should be chosen conservatively. It must be so large so that it can
+	/// fit the [`logical_max`](Self::logical_max) logical values on the stack, according to the
+	/// current instrumentation algorithm.
+	///
+	/// This value cannot be 0.
+	///
+	/// It is ignored by wasmi, which only runs out of its own value and call stacks before the
+	/// instrumentation traps if [`logical_max`](Self::logical_max) exceeds `131072`.
+	pub native_stack_max: u32,
+}
+
+impl Default for DeterministicStackLimit {
+	/// The limit nodes use when opting into the deterministic stack limit.
+	///
+	/// The native stack limit is the one wasmtime uses without instrumentation, which fits into
+	/// the stack of the threads calling into the runtime. It leaves at least 32 bytes per logical
+	/// value, so the logical limit is reached first.
+	fn default() -> Self {
+		Self { logical_max: 32 * 1024, native_stack_max: 1024 * 1024 }
+	}
+}
+
 /// Defines the heap pages allocation strategy the wasm runtime should use.
 ///
 /// A heap page is defined as 64KiB of memory.
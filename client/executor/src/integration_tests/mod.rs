@@ -24,7 +24,7 @@ use codec::{Decode, Encode};
 use sc_executor_common::{
 	error::Error,
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{HeapAllocStrategy, WasmModule},
+	wasm_runtime::{AllocatorKind, DeterministicStackLimit, HeapAllocStrategy, WasmModule},
 };
 use sc_runtime_test::wasm_binary_unwrap;
 use sp_core::{
@@ -490,6 +490,8 @@ fn mk_test_runtime(
 	crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
 		wasm_method,
 		pages,
+		AllocatorKind::default(),
+		None,
 		blob,
 		true,
 		None,
@@ -558,6 +560,181 @@ fn restoration_of_globals(wasm_method: WasmExecutionMethod) {
 	assert!(res.is_ok());
 }
 
+test_wasm_execution!(new_instances_start_from_initial_state);
+fn new_instances_start_from_initial_state(wasm_method: WasmExecutionMethod) {
+	// Increments a static initialized by a data segment as well as a mutable global and returns
+	// their sum, i.e. `(32 + 1) + (0 + 1)` if the instance starts from its initial state.
+	let binary = wat::parse_str(r#"
+	(module
+	 (import "env" "ext_allocator_malloc_version_1" (func $ext_allocator_malloc_version_1 (param i32) (result i32)))
+	 (global $__stack_pointer (mut i32) (i32.const 1048576))
+	 (global $counter (mut i32) (i32.const 0))
+	 (global $global$1 i32 (i32.const 1048580))
+	 (global $global$2 i32 (i32.const 1048592))
+	 (memory $0 17)
+	 (data (i32.const 1048576) "\20\00\00\00")
+	 (export "memory" (memory $0))
+	 (export "increments_static_and_global" (func $increments_static_and_global))
+	 (export "__data_end" (global $global$1))
+	 (export "__heap_base" (global $global$2))
+	 (func $increments_static_and_global (param $0 i32) (param $1 i32) (result i64)
+	  (local $2 i32)
+	  (i32.store offset=1048576
+	   (i32.const 0)
+	   (i32.add (i32.load offset=1048576 (i32.const 0)) (i32.const 1))
+	  )
+	  (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+	  (i64.store
+	   (local.tee $2
+	    (call $ext_allocator_malloc_version_1 (i32.const 8))
+	   )
+	   (i64.extend_i32_u
+	    (i32.add (i32.load offset=1048576 (i32.const 0)) (global.get $counter))
+	   )
+	  )
+	  (i64.or
+	   (i64.extend_i32_u (local.get $2))
+	   (i64.const 34359738368)
+	  )
+	 )
+	)"#).unwrap();
+
+	let runtime = crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
+		wasm_method,
+		HeapAllocStrategy::Dynamic { maximum_pages: Some(1024) },
+		AllocatorKind::default(),
+		None,
+		RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
+		true,
+		None,
+	)
+	.unwrap();
+
+	// Pooling strategies hand out the state of previously dropped instances again, which must
+	// not be observable.
+	for _ in 0..3 {
+		let mut instance = runtime.new_instance().unwrap();
+		let res = instance.call_export("increments_static_and_global", &[0]).unwrap();
+		assert_eq!(34, u64::decode(&mut &res[..]).unwrap());
+
+		let res = instance.call_export("increments_static_and_global", &[0]).unwrap();
+		assert_eq!(34, u64::decode(&mut &res[..]).unwrap());
+	}
+}
+
+test_wasm_execution!(runtime_uses_selected_allocator);
+fn runtime_uses_selected_allocator(wasm_method: WasmExecutionMethod) {
+	// Frees a 100 byte allocation and returns whether a following 50 byte allocation reuses it.
+	// The freeing-bump allocator only reuses memory of the same size class, while the
+	// segregated-fits allocator reuses any free chunk that is large enough.
+	let binary = wat::parse_str(r#"
+	(module
+	 (import "env" "ext_allocator_malloc_version_1" (func $ext_allocator_malloc_version_1 (param i32) (result i32)))
+	 (import "env" "ext_allocator_free_version_1" (func $ext_allocator_free_version_1 (param i32)))
+	 (global $__stack_pointer (mut i32) (i32.const 1048576))
+	 (global $global$1 i32 (i32.const 1048576))
+	 (global $global$2 i32 (i32.const 1048576))
+	 (memory $0 17)
+	 (export "memory" (memory $0))
+	 (export "reuses_freed_memory" (func $reuses_freed_memory))
+	 (export "__data_end" (global $global$1))
+	 (export "__heap_base" (global $global$2))
+	 (func $reuses_freed_memory (param $0 i32) (param $1 i32) (result i64)
+	  (local $2 i32)
+	  (local $3 i32)
+	  (call $ext_allocator_free_version_1
+	   (local.tee $2 (call $ext_allocator_malloc_version_1 (i32.const 100)))
+	  )
+	  (local.set $2
+	   (i32.eq (local.get $2) (call $ext_allocator_malloc_version_1 (i32.const 50)))
+	  )
+	  (i64.store
+	   (local.tee $3 (call $ext_allocator_malloc_version_1 (i32.const 8)))
+	   (i64.extend_i32_u (local.get $2))
+	  )
+	  (i64.or
+	   (i64.extend_i32_u (local.get $3))
+	   (i64.const 34359738368)
+	  )
+	 )
+	)"#).unwrap();
+
+	let reuses_freed_memory = |allocator| {
+		let runtime = crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
+			wasm_method,
+			HeapAllocStrategy::Dynamic { maximum_pages: Some(1024) },
+			allocator,
+			None,
+			RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
+			true,
+			None,
+		)
+		.unwrap();
+		let res = runtime
+			.new_instance()
+			.unwrap()
+			.call_export("reuses_freed_memory", &[0])
+			.unwrap();
+		u64::decode(&mut &res[..]).unwrap() == 1
+	};
+
+	assert!(!reuses_freed_memory(AllocatorKind::FreeingBump));
+	assert!(reuses_freed_memory(AllocatorKind::SegregatedFits));
+}
+
+test_wasm_execution!(deep_recursion_traps_at_deterministic_depth);
+fn deep_recursion_traps_at_deterministic_depth(wasm_method: WasmExecutionMethod) {
+	// Recurses as deep as requested by the little endian `u32` passed as input.
+	let binary = wat::parse_str(
+		r#"
+	(module
+	 (global $__stack_pointer (mut i32) (i32.const 1048576))
+	 (global $global$1 i32 (i32.const 1048576))
+	 (global $global$2 i32 (i32.const 1048576))
+	 (memory $0 17)
+	 (export "memory" (memory $0))
+	 (export "recurse" (func $recurse_export))
+	 (export "__data_end" (global $global$1))
+	 (export "__heap_base" (global $global$2))
+	 (func $recurse (param $depth i32)
+	  (if (local.get $depth)
+	   (then (call $recurse (i32.sub (local.get $depth) (i32.const 1))))
+	  )
+	 )
+	 (func $recurse_export (param $0 i32) (param $1 i32) (result i64)
+	  (call $recurse (i32.load (local.get $0)))
+	  (i64.const 0)
+	 )
+	)"#,
+	)
+	.unwrap();
+
+	// The executor is configured like the one of a node that opted into the default limit.
+	let executor = crate::WasmExecutor::<HostFunctions>::builder(wasm_method)
+		.with_deterministic_stack_limit(Some(DeterministicStackLimit::default()))
+		.build();
+	let mut ext = TestExternalities::default();
+	let mut recurse = |depth: u32| {
+		executor
+			.uncached_call(
+				RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
+				&mut ext.ext(),
+				true,
+				"recurse",
+				&depth.to_le_bytes(),
+			)
+			.is_ok()
+	};
+
+	// The exported function costs three logical values and every call of `$recurse` costs four,
+	// two for the activation frame and two for the operand stack. So `8191` calls of `$recurse`
+	// fit into the default limit of `32768` values, regardless of the execution engine.
+	assert_eq!(DeterministicStackLimit::default().logical_max, 32768);
+	assert!(recurse(8190));
+	assert!(!recurse(8191));
+	assert!(!recurse(u32::MAX));
+}
+
 test_wasm_execution!(interpreted_only heap_is_reset_between_calls);
 fn heap_is_reset_between_calls(wasm_method: WasmExecutionMethod) {
 	let runtime = mk_test_runtime(wasm_method, HeapAllocStrategy::Static { extra_pages: 1024 });
@@ -737,6 +914,8 @@ fn memory_is_cleared_between_invocations(wasm_method: WasmExecutionMethod) {
 	let runtime = crate::wasm_runtime::create_wasm_runtime_with_code::<HostFunctions>(
 		wasm_method,
 		HeapAllocStrategy::Dynamic { maximum_pages: Some(1024) },
+		AllocatorKind::default(),
+		None,
 		RuntimeBlob::uncompress_if_needed(&binary[..]).unwrap(),
 		true,
 		None,
@@ -35,21 +35,26 @@
 mod native_executor;
 #[cfg(test)]
 mod integration_tests;
+mod profiler;
 mod wasm_runtime;
 
 pub use codec::Codec;
 pub use native_executor::{
 	with_externalities_safe, NativeElseWasmExecutor, NativeExecutionDispatch, WasmExecutor,
 };
+pub use profiler::{RuntimeCallBlock, RuntimeCallBlockExt, RuntimeCallProfiler};
 #[doc(hidden)]
 pub use sp_core::traits::Externalities;
 pub use sp_version::{NativeVersion, RuntimeVersion};
 #[doc(hidden)]
 pub use sp_wasm_interface;
-pub use wasm_runtime::{read_embedded_version, WasmExecutionMethod};
+pub use wasm_runtime::{read_embedded_build_info, read_embedded_version, WasmExecutionMethod};
 pub use wasmi;
 
-pub use sc_executor_common::error;
+pub use sc_executor_common::{
+	error,
+	wasm_runtime::{AllocatorKind, DeterministicStackLimit},
+};
 pub use sc_executor_wasmtime::InstantiationStrategy as WasmtimeInstantiationStrategy;
 
 /// Extracts the runtime version of a given runtime code.
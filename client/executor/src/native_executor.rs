@@ -19,7 +19,7 @@
 use crate::{
 	error::{Error, Result},
 	wasm_runtime::{RuntimeCache, WasmExecutionMethod},
-	RuntimeVersionOf,
+	RuntimeCallBlockExt, RuntimeCallProfiler, RuntimeVersionOf,
 };
 
 use std::{
@@ -32,7 +32,10 @@ use std::{
 use codec::Encode;
 use sc_executor_common::{
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{AllocationStats, HeapAllocStrategy, WasmInstance, WasmModule},
+	wasm_runtime::{
+		AllocationStats, AllocatorKind, DeterministicStackLimit, HeapAllocStrategy, WasmInstance,
+		WasmModule,
+	},
 };
 use sp_core::traits::{CallContext, CodeExecutor, Externalities, RuntimeCode};
 use sp_version::{GetNativeVersion, NativeVersion, RuntimeVersion};
@@ -94,6 +97,9 @@ pub struct WasmExecutorBuilder<H> {
 	cache_path: Option<PathBuf>,
 	allow_missing_host_functions: bool,
 	runtime_cache_size: u8,
+	profiler: Option<RuntimeCallProfiler>,
+	deterministic_stack_limit: Option<DeterministicStackLimit>,
+	allocator: AllocatorKind,
 }
 
 impl<H> WasmExecutorBuilder<H> {
@@ -110,6 +116,9 @@ impl<H> WasmExecutorBuilder<H> {
 			runtime_cache_size: 4,
 			allow_missing_host_functions: false,
 			cache_path: None,
+			profiler: None,
+			deterministic_stack_limit: None,
+			allocator: AllocatorKind::default(),
 		}
 	}
 
@@ -179,6 +188,43 @@ impl<H> WasmExecutorBuilder<H> {
 		self
 	}
 
+	/// Create the wasm executor with the given host function `profiler`.
+	///
+	/// Every runtime call executed in wasm is profiled and reported to the `profiler`.
+	///
+	/// By default runtime calls are not profiled.
+	pub fn with_profiler(mut self, profiler: RuntimeCallProfiler) -> Self {
+		self.profiler = Some(profiler);
+		self
+	}
+
+	/// Create the wasm executor with the given deterministic stack `limit`.
+	///
+	/// The runtime code is instrumented to count the stack depth in a way that doesn't depend on
+	/// the execution engine or the host machine, so deep recursion traps at the same point on all
+	/// nodes. The same `limit` needs to be used by all nodes of a network for this to hold, so it
+	/// must only be enabled network-wide.
+	///
+	/// By default the stack depth is only limited by the execution engine.
+	pub fn with_deterministic_stack_limit(
+		mut self,
+		limit: Option<DeterministicStackLimit>,
+	) -> Self {
+		self.deterministic_stack_limit = limit;
+		self
+	}
+
+	/// Create the wasm executor with the given runtime heap `allocator`.
+	///
+	/// The allocator is part of the consensus critical execution environment, all nodes of a
+	/// network need to use the same one.
+	///
+	/// By default [`AllocatorKind::FreeingBump`] is used.
+	pub fn with_allocator(mut self, allocator: AllocatorKind) -> Self {
+		self.allocator = allocator;
+		self
+	}
+
 	/// Build the configured [`WasmExecutor`].
 	pub fn build(self) -> WasmExecutor<H> {
 		WasmExecutor {
@@ -196,6 +242,9 @@ impl<H> WasmExecutorBuilder<H> {
 			)),
 			cache_path: self.cache_path,
 			allow_missing_host_functions: self.allow_missing_host_functions,
+			profiler: self.profiler.map(Arc::new),
+			deterministic_stack_limit: self.deterministic_stack_limit,
+			allocator: self.allocator,
 			phantom: PhantomData,
 		}
 	}
@@ -217,6 +266,12 @@ pub struct WasmExecutor<H> {
 	cache_path: Option<PathBuf>,
 	/// Ignore missing function imports.
 	allow_missing_host_functions: bool,
+	/// Receives the host function profiles of the runtime calls, if profiling is enabled.
+	profiler: Option<Arc<RuntimeCallProfiler>>,
+	/// The deterministic stack limit the runtime code is instrumented with, if any.
+	deterministic_stack_limit: Option<DeterministicStackLimit>,
+	/// The allocator used for the runtime heap.
+	allocator: AllocatorKind,
 	phantom: PhantomData<H>,
 }
 
@@ -229,6 +284,9 @@ impl<H> Clone for WasmExecutor<H> {
 			cache: self.cache.clone(),
 			cache_path: self.cache_path.clone(),
 			allow_missing_host_functions: self.allow_missing_host_functions,
+			profiler: self.profiler.clone(),
+			deterministic_stack_limit: self.deterministic_stack_limit,
+			allocator: self.allocator,
 			phantom: self.phantom,
 		}
 	}
@@ -278,6 +336,9 @@ where
 			)),
 			cache_path,
 			allow_missing_host_functions: false,
+			profiler: None,
+			deterministic_stack_limit: None,
+			allocator: AllocatorKind::default(),
 			phantom: PhantomData,
 		}
 	}
@@ -292,6 +353,71 @@ where
 		self.allow_missing_host_functions = allow_missing_host_functions
 	}
 
+	/// Profile every runtime call executed in wasm and report it to the given `profiler`.
+	pub fn set_profiler(&mut self, profiler: RuntimeCallProfiler) {
+		self.profiler = Some(Arc::new(profiler));
+	}
+
+	/// Instrument the runtime code with the given deterministic stack `limit`.
+	///
+	/// See [`WasmExecutorBuilder::with_deterministic_stack_limit`]. Only affects runtimes that
+	/// are not yet cached, so it should be set before the first runtime call.
+	pub fn set_deterministic_stack_limit(&mut self, limit: Option<DeterministicStackLimit>) {
+		self.deterministic_stack_limit = limit;
+	}
+
+	/// Use the given runtime heap `allocator`.
+	///
+	/// See [`WasmExecutorBuilder::with_allocator`]. Only affects runtimes that are not yet
+	/// cached, so it should be set before the first runtime call.
+	pub fn set_allocator(&mut self, allocator: AllocatorKind) {
+		self.allocator = allocator;
+	}
+
+	/// Call `method` on the given `instance`, profiling the call if a profiler is set.
+	///
+	/// Must be called with the externalities of the call set, as the profile is tagged with the
+	/// block registered as [`RuntimeCallBlockExt`], if any.
+	///
+	/// Otherwise the heap allocation stats of the call are collected and traced, but only when
+	/// debug logging is enabled for the `wasm-heap` target.
+	fn call_export(
+		&self,
+		instance: &mut dyn WasmInstance,
+		method: &str,
+		data: &[u8],
+	) -> Result<Vec<u8>> {
+		match &self.profiler {
+			Some(profiler) => {
+				let (result, profile) =
+					instance.call_with_host_function_profile(method.into(), data);
+				if let Some(profile) = profile {
+					let block = sp_externalities::with_externalities(|ext| {
+						ext.extension::<RuntimeCallBlockExt>().map(|block| block.0.clone())
+					})
+					.flatten();
+					profiler.report(method, block.as_ref(), &profile);
+				}
+				result
+			},
+			None if tracing::enabled!(target: "wasm-heap", tracing::Level::DEBUG) => {
+				let (result, stats) = instance.call_with_allocation_stats(method.into(), data);
+				if let Some(stats) = stats {
+					tracing::debug!(
+						target: "wasm-heap",
+						%method,
+						bytes_allocated_peak = stats.bytes_allocated_peak,
+						address_space_used = stats.address_space_used,
+						size_classes = ?stats.size_classes,
+						"Heap allocation stats of runtime call",
+					);
+				}
+				result
+			},
+			None => instance.call_export(method, data),
+		}
+	}
+
 	/// Execute the given closure `f` with the latest runtime (based on `runtime_code`).
 	///
 	/// The closure `f` is expected to return `Err(_)` when there happened a `panic!` in native code
@@ -325,6 +451,8 @@ where
 			ext,
 			self.method,
 			heap_alloc_strategy,
+			self.allocator,
+			self.deterministic_stack_limit,
 			self.allow_missing_host_functions,
 			|module, instance, version, ext| {
 				let module = AssertUnwindSafe(module);
@@ -398,6 +526,8 @@ where
 		let module = crate::wasm_runtime::create_wasm_runtime_with_code::<H>(
 			self.method,
 			self.default_onchain_heap_alloc_strategy,
+			self.allocator,
+			self.deterministic_stack_limit,
 			runtime_blob,
 			allow_missing_host_functions,
 			self.cache_path.as_deref(),
@@ -495,7 +625,10 @@ where
 			ext,
 			heap_alloc_strategy,
 			|_, mut instance, _onchain_version, mut ext| {
-				with_externalities_safe(&mut **ext, move || instance.call_export(method, data))
+				let this = AssertUnwindSafe(self);
+				with_externalities_safe(&mut **ext, move || {
+					this.call_export(&mut **instance, method, data)
+				})
 			},
 		);
 
@@ -583,6 +716,21 @@ impl<D: NativeExecutionDispatch> NativeElseWasmExecutor<D> {
 	pub fn allow_missing_host_functions(&mut self, allow_missing_host_functions: bool) {
 		self.wasm.allow_missing_host_functions = allow_missing_host_functions
 	}
+
+	/// Profile every runtime call executed in wasm and report it to the given `profiler`.
+	pub fn set_profiler(&mut self, profiler: RuntimeCallProfiler) {
+		self.wasm.set_profiler(profiler)
+	}
+
+	/// Instrument the runtime code with the given deterministic stack `limit`.
+	pub fn set_deterministic_stack_limit(&mut self, limit: Option<DeterministicStackLimit>) {
+		self.wasm.set_deterministic_stack_limit(limit)
+	}
+
+	/// Use the given runtime heap `allocator`.
+	pub fn set_allocator(&mut self, allocator: AllocatorKind) {
+		self.wasm.set_allocator(allocator)
+	}
 }
 
 impl<D: NativeExecutionDispatch> RuntimeVersionOf for NativeElseWasmExecutor<D> {
@@ -662,7 +810,10 @@ impl<D: NativeExecutionDispatch + 'static> CodeExecutor for NativeElseWasmExecut
 						);
 					}
 
-					with_externalities_safe(&mut **ext, move || instance.call_export(method, data))
+					let wasm = AssertUnwindSafe(&self.wasm);
+					with_externalities_safe(&mut **ext, move || {
+						wasm.call_export(&mut **instance, method, data)
+					})
 				}
 			},
 		);
@@ -0,0 +1,259 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Reporting of the host function profiles collected for runtime calls.
+
+use parking_lot::Mutex;
+use prometheus_endpoint::{register, CounterVec, Opts, PrometheusError, Registry, F64, U64};
+use sc_executor_common::profiler::HostFunctionProfile;
+use sp_core::hexdisplay::HexDisplay;
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+};
+
+/// The block a runtime call is executed at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeCallBlock {
+	/// Hash of the block.
+	pub hash: Vec<u8>,
+	/// Number of the block.
+	pub number: u64,
+}
+
+sp_externalities::decl_extension! {
+	/// Tells the [`RuntimeCallProfiler`] which block a runtime call is executed at.
+	pub struct RuntimeCallBlockExt(RuntimeCallBlock);
+}
+
+/// Prometheus metrics aggregating the profiles of all runtime calls.
+struct Metrics {
+	host_function_calls: CounterVec<U64>,
+	host_function_time: CounterVec<F64>,
+	host_function_bytes: CounterVec<U64>,
+	runtime_call_time: CounterVec<F64>,
+}
+
+impl Metrics {
+	fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			host_function_calls: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_executor_host_function_calls_total",
+						"Number of calls into a host function",
+					),
+					&["function"],
+				)?,
+				registry,
+			)?,
+			host_function_time: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_executor_host_function_time_seconds_total",
+						"Time spent executing a host function",
+					),
+					&["function"],
+				)?,
+				registry,
+			)?,
+			host_function_bytes: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_executor_host_function_bytes_total",
+						"Bytes read from or written to the linear memory by a host function",
+					),
+					&["function", "direction"],
+				)?,
+				registry,
+			)?,
+			runtime_call_time: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_executor_runtime_call_time_seconds_total",
+						"Time spent in profiled runtime calls, split into wasm and host functions",
+					),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	fn report(&self, profile: &HostFunctionProfile) {
+		for (name, stats) in &profile.host_functions {
+			self.host_function_calls.with_label_values(&[name]).inc_by(stats.calls);
+			self.host_function_time
+				.with_label_values(&[name])
+				.inc_by(stats.time.as_secs_f64());
+			self.host_function_bytes
+				.with_label_values(&[name, "read"])
+				.inc_by(stats.bytes_read);
+			self.host_function_bytes
+				.with_label_values(&[name, "written"])
+				.inc_by(stats.bytes_written);
+		}
+		self.runtime_call_time
+			.with_label_values(&["wasm"])
+			.inc_by(profile.wasm_time().as_secs_f64());
+		self.runtime_call_time
+			.with_label_values(&["host"])
+			.inc_by(profile.host_time().as_secs_f64());
+	}
+}
+
+/// Receives the host function profile of every runtime call executed in wasm.
+///
+/// The profiles are aggregated into Prometheus metrics and/or exported to a file, one JSON object
+/// per line and runtime call. Profiling adds some overhead to every host function call, so it
+/// should only be enabled when investigating performance issues.
+#[derive(Default)]
+pub struct RuntimeCallProfiler {
+	metrics: Option<Metrics>,
+	export: Option<Mutex<BufWriter<File>>>,
+}
+
+impl RuntimeCallProfiler {
+	/// Create a new profiler that doesn't report the profiles anywhere yet.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Aggregate the profiles into metrics registered at the given Prometheus `registry`.
+	pub fn with_prometheus(mut self, registry: &Registry) -> Result<Self, PrometheusError> {
+		self.metrics = Some(Metrics::register(registry)?);
+		Ok(self)
+	}
+
+	/// Export the profile of every runtime call to the file at `path`.
+	///
+	/// An existing file is truncated.
+	pub fn with_export(mut self, path: &Path) -> io::Result<Self> {
+		self.export = Some(Mutex::new(BufWriter::new(File::create(path)?)));
+		Ok(self)
+	}
+
+	/// Report the `profile` of a call to the runtime function `method` at the given `block`.
+	pub fn report(
+		&self,
+		method: &str,
+		block: Option<&RuntimeCallBlock>,
+		profile: &HostFunctionProfile,
+	) {
+		if let Some(metrics) = &self.metrics {
+			metrics.report(profile);
+		}
+
+		if let Some(export) = &self.export {
+			let host_functions = profile
+				.host_functions
+				.iter()
+				.map(|(name, stats)| {
+					let stats = serde_json::json!({
+						"calls": stats.calls,
+						"time_ns": stats.time.as_nanos() as u64,
+						"bytes_read": stats.bytes_read,
+						"bytes_written": stats.bytes_written,
+					});
+					(name.to_string(), stats)
+				})
+				.collect::<serde_json::Map<_, _>>();
+			let entry = serde_json::json!({
+				"method": method,
+				"block_hash": block.map(|block| format!("0x{}", HexDisplay::from(&block.hash))),
+				"block_number": block.map(|block| block.number),
+				"total_time_ns": profile.total_time.as_nanos() as u64,
+				"wasm_time_ns": profile.wasm_time().as_nanos() as u64,
+				"host_functions": host_functions,
+			});
+
+			let mut export = export.lock();
+			if let Err(e) = writeln!(export, "{}", entry).and_then(|()| export.flush()) {
+				tracing::warn!(target: "executor", "Failed to export runtime call profile: {}", e);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sc_executor_common::profiler::HostFunctionStats;
+	use std::time::Duration;
+
+	#[test]
+	fn profiles_are_exported_as_json_lines() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("profile.json");
+		let registry = Registry::new();
+		let profiler = RuntimeCallProfiler::new()
+			.with_prometheus(&registry)
+			.unwrap()
+			.with_export(&path)
+			.unwrap();
+
+		let mut profile = HostFunctionProfile {
+			total_time: Duration::from_millis(10),
+			host_functions: Default::default(),
+		};
+		profile.host_functions.insert(
+			"ext_storage_get_version_1",
+			HostFunctionStats {
+				calls: 3,
+				time: Duration::from_millis(4),
+				bytes_read: 96,
+				bytes_written: 12,
+			},
+		);
+		let block = RuntimeCallBlock { hash: vec![0xab; 4], number: 42 };
+		profiler.report("Core_execute_block", Some(&block), &profile);
+		profiler.report("Core_version", None, &HostFunctionProfile::default());
+
+		let export = std::fs::read_to_string(&path).unwrap();
+		let lines = export
+			.lines()
+			.map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+			.collect::<Vec<_>>();
+		assert_eq!(lines.len(), 2);
+		assert_eq!(lines[0]["method"], "Core_execute_block");
+		assert_eq!(lines[0]["block_hash"], "0xabababab");
+		assert_eq!(lines[0]["block_number"], 42);
+		assert_eq!(lines[0]["wasm_time_ns"], 6_000_000);
+		assert_eq!(lines[0]["host_functions"]["ext_storage_get_version_1"]["calls"], 3);
+		assert_eq!(lines[0]["host_functions"]["ext_storage_get_version_1"]["bytes_read"], 96);
+		assert_eq!(lines[1]["method"], "Core_version");
+		assert!(lines[1]["block_number"].is_null());
+
+		let metrics = profiler.metrics.as_ref().unwrap();
+		assert_eq!(
+			metrics
+				.host_function_calls
+				.with_label_values(&["ext_storage_get_version_1"])
+				.get(),
+			3
+		);
+		assert_eq!(
+			metrics
+				.host_function_bytes
+				.with_label_values(&["ext_storage_get_version_1", "written"])
+				.get(),
+			12
+		);
+	}
+}
@@ -27,10 +27,12 @@ use lru::LruCache;
 use parking_lot::Mutex;
 use sc_executor_common::{
 	runtime_blob::RuntimeBlob,
-	wasm_runtime::{HeapAllocStrategy, WasmInstance, WasmModule},
+	wasm_runtime::{
+		AllocatorKind, DeterministicStackLimit, HeapAllocStrategy, WasmInstance, WasmModule,
+	},
 };
 use sp_core::traits::{Externalities, FetchRuntimeCode, RuntimeCode};
-use sp_version::RuntimeVersion;
+use sp_version::{embed::BuildInfo, RuntimeVersion};
 use std::{
 	num::NonZeroUsize,
 	panic::AssertUnwindSafe,
@@ -66,6 +68,8 @@ struct VersionedRuntimeId {
 	wasm_method: WasmExecutionMethod,
 	/// The heap allocation strategy this runtime was created with.
 	heap_alloc_strategy: HeapAllocStrategy,
+	/// The allocator this runtime was created with.
+	allocator: AllocatorKind,
 }
 
 /// A Wasm runtime object along with its cached runtime version.
@@ -203,6 +207,10 @@ impl RuntimeCache {
 	///
 	/// `heap_alloc_strategy` - The heap allocation strategy to use.
 	///
+	/// `allocator` - The allocator that manages the heap.
+	///
+	/// `deterministic_stack_limit` - The deterministic stack limit to instrument the code with.
+	///
 	/// `allow_missing_func_imports` - Ignore missing function imports.
 	///
 	/// `f` - Function to execute.
@@ -222,6 +230,8 @@ impl RuntimeCache {
 		ext: &mut dyn Externalities,
 		wasm_method: WasmExecutionMethod,
 		heap_alloc_strategy: HeapAllocStrategy,
+		allocator: AllocatorKind,
+		deterministic_stack_limit: Option<DeterministicStackLimit>,
 		allow_missing_func_imports: bool,
 		f: F,
 	) -> Result<Result<R, Error>, Error>
@@ -236,8 +246,12 @@ impl RuntimeCache {
 	{
 		let code_hash = &runtime_code.hash;
 
-		let versioned_runtime_id =
-			VersionedRuntimeId { code_hash: code_hash.clone(), heap_alloc_strategy, wasm_method };
+		let versioned_runtime_id = VersionedRuntimeId {
+			code_hash: code_hash.clone(),
+			heap_alloc_strategy,
+			allocator,
+			wasm_method,
+		};
 
 		let mut runtimes = self.runtimes.lock(); // this must be released prior to calling f
 		let versioned_runtime = if let Some(versioned_runtime) = runtimes.get(&versioned_runtime_id)
@@ -253,6 +267,8 @@ impl RuntimeCache {
 				ext,
 				wasm_method,
 				heap_alloc_strategy,
+				allocator,
+				deterministic_stack_limit,
 				allow_missing_func_imports,
 				self.max_runtime_instances,
 				self.cache_path.as_deref(),
@@ -291,6 +307,8 @@ impl RuntimeCache {
 pub fn create_wasm_runtime_with_code<H>(
 	wasm_method: WasmExecutionMethod,
 	heap_alloc_strategy: HeapAllocStrategy,
+	allocator: AllocatorKind,
+	deterministic_stack_limit: Option<DeterministicStackLimit>,
 	blob: RuntimeBlob,
 	allow_missing_func_imports: bool,
 	cache_path: Option<&Path>,
@@ -309,6 +327,9 @@ where
 			sc_executor_wasmi::create_runtime(
 				blob,
 				heap_alloc_strategy,
+				allocator,
+				sc_executor_wasmi::InstantiationStrategy::PoolingCopyOnWrite,
+				deterministic_stack_limit,
 				H::host_functions(),
 				allow_missing_func_imports,
 			)
@@ -322,8 +343,9 @@ where
 					cache_path: cache_path.map(ToOwned::to_owned),
 					semantics: sc_executor_wasmtime::Semantics {
 						heap_alloc_strategy,
+						allocator,
 						instantiation_strategy,
-						deterministic_stack_limit: None,
+						deterministic_stack_limit,
 						canonicalize_nans: false,
 						parallel_compilation: true,
 						wasm_multi_value: false,
@@ -393,11 +415,26 @@ pub fn read_embedded_version(blob: &RuntimeBlob) -> Result<Option<RuntimeVersion
 	}
 }
 
+/// Take the runtime blob and scan it for the custom wasm section containing the [`BuildInfo`].
+///
+/// If there is no such section, it returns `None`. If there is an error during decoding the
+/// section, `Err` will be returned.
+pub fn read_embedded_build_info(blob: &RuntimeBlob) -> Result<Option<BuildInfo>, WasmError> {
+	blob.custom_section_contents(sp_version::embed::BUILD_INFO_SECTION)
+		.map(|mut section| {
+			BuildInfo::decode(&mut section)
+				.map_err(|_| WasmError::Other("failed to decode build info section".into()))
+		})
+		.transpose()
+}
+
 fn create_versioned_wasm_runtime<H>(
 	code: &[u8],
 	ext: &mut dyn Externalities,
 	wasm_method: WasmExecutionMethod,
 	heap_alloc_strategy: HeapAllocStrategy,
+	allocator: AllocatorKind,
+	deterministic_stack_limit: Option<DeterministicStackLimit>,
 	allow_missing_func_imports: bool,
 	max_instances: usize,
 	cache_path: Option<&Path>,
@@ -417,6 +454,8 @@ where
 	let runtime = create_wasm_runtime_with_code::<H>(
 		wasm_method,
 		heap_alloc_strategy,
+		allocator,
+		deterministic_stack_limit,
 		blob,
 		allow_missing_func_imports,
 		cache_path,
@@ -570,4 +609,29 @@ mod tests {
 
 		assert_eq!(runtime_version, read_version);
 	}
+
+	#[test]
+	fn embed_build_info_works() {
+		let wasm = wat::parse_str("(module)").expect("Parsing wat works");
+		let blob = RuntimeBlob::new(&wasm).expect("Blob is valid");
+		assert_eq!(read_embedded_build_info(&blob).expect("No section is fine"), None);
+
+		let build_info = BuildInfo {
+			metadata_hash: [7; 32],
+			git_commit: Some("0123456789abcdef".into()),
+			features: vec!["std".into()],
+			profile: "production".into(),
+		};
+
+		let embedded =
+			sp_version::embed::embed_build_info(&wasm, &build_info).expect("Embedding works");
+
+		let blob = RuntimeBlob::new(&embedded).expect("Embedded blob is valid");
+		let read_build_info = read_embedded_build_info(&blob)
+			.ok()
+			.flatten()
+			.expect("Reading embedded build info works");
+
+		assert_eq!(build_info, read_build_info);
+	}
 }
@@ -18,21 +18,28 @@
 
 //! This crate provides an implementation of `WasmModule` that is baked by wasmi.
 
-use std::{cell::RefCell, str, sync::Arc};
+use std::{cell::RefCell, str, sync::Arc, time::Instant};
 
 use log::{error, trace};
+use parking_lot::Mutex;
 use wasmi::{
 	memory_units::Pages,
-	FuncInstance, ImportsBuilder, MemoryRef, Module, ModuleInstance, ModuleRef,
-	RuntimeValue::{self, I32, I64},
+	FuncInstance, GlobalRef, ImportsBuilder, MemoryRef, Module, ModuleInstance, ModuleRef,
+	RuntimeValue::{I32, I64},
 	TableRef,
 };
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, AllocatorKind, HeapAllocator};
 use sc_executor_common::{
 	error::{Error, MessageWithBacktrace, WasmError},
-	runtime_blob::{DataSegmentsSnapshot, RuntimeBlob},
-	wasm_runtime::{HeapAllocStrategy, InvokeMethod, WasmInstance, WasmModule},
+	profiler::{HostFunctionProfile, HostFunctionProfiler},
+	runtime_blob::{
+		DataSegmentsSnapshot, ExposedMutableGlobalsSet, GlobalsSnapshot, InstanceGlobals,
+		RuntimeBlob,
+	},
+	wasm_runtime::{
+		DeterministicStackLimit, HeapAllocStrategy, InvokeMethod, WasmInstance, WasmModule,
+	},
 };
 use sp_runtime_interface::unpack_ptr_and_len;
 use sp_wasm_interface::{Function, FunctionContext, Pointer, Result as WResult, WordSize};
@@ -73,39 +80,50 @@ impl sc_allocator::Memory for MemoryWrapper<'_> {
 }
 
 struct FunctionExecutor {
-	heap: RefCell<sc_allocator::FreeingBumpHeapAllocator>,
+	heap: RefCell<HeapAllocator>,
 	memory: MemoryRef,
 	host_functions: Arc<Vec<&'static dyn Function>>,
 	allow_missing_func_imports: bool,
 	missing_functions: Arc<Vec<String>>,
 	panic_message: Option<String>,
+	/// Only set when the host function calls are profiled.
+	profiler: Option<HostFunctionProfiler>,
 }
 
 impl FunctionExecutor {
 	fn new(
 		m: MemoryRef,
+		allocator: AllocatorKind,
 		heap_base: u32,
 		host_functions: Arc<Vec<&'static dyn Function>>,
 		allow_missing_func_imports: bool,
 		missing_functions: Arc<Vec<String>>,
+		profiler: Option<HostFunctionProfiler>,
 	) -> Result<Self, Error> {
 		Ok(FunctionExecutor {
-			heap: RefCell::new(FreeingBumpHeapAllocator::new(heap_base)),
+			heap: RefCell::new(HeapAllocator::new(allocator, heap_base)),
 			memory: m,
 			host_functions,
 			allow_missing_func_imports,
 			missing_functions,
 			panic_message: None,
+			profiler,
 		})
 	}
 }
 
 impl FunctionContext for FunctionExecutor {
 	fn read_memory_into(&self, address: Pointer<u8>, dest: &mut [u8]) -> WResult<()> {
+		if let Some(profiler) = &self.profiler {
+			profiler.note_read(dest.len());
+		}
 		self.memory.get_into(address.into(), dest).map_err(|e| e.to_string())
 	}
 
 	fn write_memory(&mut self, address: Pointer<u8>, data: &[u8]) -> WResult<()> {
+		if let Some(profiler) = &self.profiler {
+			profiler.note_written(data.len());
+		}
 		self.memory.set(address.into(), data).map_err(|e| e.to_string())
 	}
 
@@ -209,9 +227,14 @@ impl wasmi::Externals for FunctionExecutor {
 	) -> Result<Option<wasmi::RuntimeValue>, wasmi::Trap> {
 		let mut args = args.as_ref().iter().copied().map(Into::into);
 
-		if let Some(function) = self.host_functions.clone().get(index) {
-			function
-				.execute(self, &mut args)
+		if let Some(&function) = self.host_functions.clone().get(index) {
+			let started = self.profiler.as_ref().map(HostFunctionProfiler::enter);
+			let result = function.execute(self, &mut args);
+			if let (Some(profiler), Some(started)) = (&mut self.profiler, started) {
+				profiler.exit(function.name(), started);
+			}
+
+			result
 				.map_err(|msg| Error::FunctionExecution(function.name().to_string(), msg))
 				.map_err(wasmi::Trap::from)
 				.map(|v| v.map(Into::into))
@@ -259,12 +282,14 @@ fn get_heap_base(module: &ModuleRef) -> Result<u32, Error> {
 fn call_in_wasm_module(
 	module_instance: &ModuleRef,
 	memory: &MemoryRef,
+	allocator: AllocatorKind,
 	method: InvokeMethod,
 	data: &[u8],
 	host_functions: Arc<Vec<&'static dyn Function>>,
 	allow_missing_func_imports: bool,
 	missing_functions: Arc<Vec<String>>,
 	allocation_stats: &mut Option<AllocationStats>,
+	profile: Option<&mut Option<HostFunctionProfile>>,
 ) -> Result<Vec<u8>, Error> {
 	// Initialize FunctionExecutor.
 	let table: Option<TableRef> = module_instance
@@ -274,16 +299,20 @@ fn call_in_wasm_module(
 
 	let mut function_executor = FunctionExecutor::new(
 		memory.clone(),
+		allocator,
 		heap_base,
 		host_functions,
 		allow_missing_func_imports,
 		missing_functions,
+		profile.is_some().then(HostFunctionProfiler::default),
 	)?;
 
 	// Write the call data
 	let offset = function_executor.allocate_memory(data.len() as u32)?;
 	function_executor.write_memory(offset, data)?;
 
+	let started = Instant::now();
+
 	fn convert_trap(executor: &mut FunctionExecutor, trap: wasmi::Trap) -> Error {
 		if let Some(message) = executor.panic_message.take() {
 			Error::AbortedDueToPanic(MessageWithBacktrace { message, backtrace: None })
@@ -337,6 +366,9 @@ fn call_in_wasm_module(
 	};
 
 	*allocation_stats = Some(function_executor.heap.borrow().stats());
+	if let (Some(profile), Some(profiler)) = (profile, function_executor.profiler.take()) {
+		*profile = Some(profiler.into_profile(started.elapsed()));
+	}
 
 	match result {
 		Ok(Some(I64(r))) => {
@@ -387,45 +419,74 @@ fn instantiate_module(
 	}
 }
 
-/// A state snapshot of an instance taken just after instantiation.
-///
-/// It is used for restoring the state of the module after execution.
-#[derive(Clone)]
-struct GlobalValsSnapshot {
-	/// The list of all global mutable variables of the module in their sequential order.
-	global_mut_values: Vec<RuntimeValue>,
+/// Adapter to access the exported mutable globals of a module instance.
+struct InstanceGlobalsAdapter<'a>(&'a ModuleRef);
+
+impl InstanceGlobals for InstanceGlobalsAdapter<'_> {
+	type Global = GlobalRef;
+
+	fn get_global(&mut self, export_name: &str) -> Self::Global {
+		self.0
+			.export_by_name(export_name)
+			.and_then(|export| export.as_global().cloned())
+			.expect("get_global is guaranteed to be called with an export name of a global; qed")
+	}
+
+	fn get_global_value(&mut self, global: &Self::Global) -> sp_wasm_interface::Value {
+		global.get().into()
+	}
+
+	fn set_global_value(&mut self, global: &Self::Global, value: sp_wasm_interface::Value) {
+		global.set(value.into()).expect(
+			"the value is guaranteed to be of the same value; the global is guaranteed to be mutable; qed",
+		);
+	}
 }
 
-impl GlobalValsSnapshot {
-	// Returns `None` if instance is not valid.
-	fn take(module_instance: &ModuleRef) -> Self {
-		// Collect all values of mutable globals.
-		let global_mut_values = module_instance
-			.globals()
-			.iter()
-			.filter(|g| g.is_mutable())
-			.map(|g| g.get())
-			.collect();
-		Self { global_mut_values }
-	}
-
-	/// Reset the runtime instance to the initial version by restoring
-	/// the preserved memory and globals.
+/// The instantiation strategy to use for the wasmi runtime.
+#[non_exhaustive]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum InstantiationStrategy {
+	/// Pool the instances to avoid instantiating the module from scratch on each instantiation.
 	///
-	/// Returns `Err` if applying the snapshot is failed.
-	fn apply(&self, instance: &ModuleRef) -> Result<(), WasmError> {
-		for (global_ref, global_val) in instance
-			.globals()
-			.iter()
-			.filter(|g| g.is_mutable())
-			.zip(self.global_mut_values.iter())
-		{
-			// the instance should be the same as used for preserving and
-			// we iterate the same way it as we do it for preserving values that means that the
-			// types should be the same and all the values are mutable. So no error is expected/
-			global_ref.set(*global_val).map_err(|_| WasmError::ApplySnapshotFailed)?;
-		}
-		Ok(())
+	/// Instances are returned to the pool when they are dropped. The linear memory is reset by
+	/// mapping fresh copy-on-write zero pages, so only the pages touched by the data segments are
+	/// written again.
+	///
+	/// This is the fastest instantiation strategy.
+	PoolingCopyOnWrite,
+
+	/// Instantiate the module from scratch on every instantiation.
+	RecreateInstance,
+}
+
+/// The maximum number of idle instances kept in the pool of a [`WasmiRuntime`].
+const MAX_POOLED_INSTANCES: usize = 32;
+
+/// An instantiated module together with everything needed to reset it to its initial state.
+struct InstanceState {
+	/// A wasm module instance.
+	instance: ModuleRef,
+	/// The memory instance of used by the wasm module.
+	memory: MemoryRef,
+	/// Is the memory zeroed?
+	memory_zeroed: bool,
+	/// The snapshot of the mutable global values just after instantiation.
+	globals_snapshot: GlobalsSnapshot<GlobalRef>,
+	/// List of missing functions detected during function resolution
+	missing_functions: Arc<Vec<String>>,
+}
+
+// This is safe because the state is only ever moved between threads as a whole, i.e. together
+// with all references to the `Rc`s inside of `instance` and `memory`.
+unsafe impl Send for InstanceState {}
+
+impl InstanceState {
+	/// Whether the instance can be reset to its initial state by [`WasmiInstance::call_impl`].
+	///
+	/// Wasmi can't shrink the linear memory again, so instances whose memory grew are not reused.
+	fn is_reusable(&self) -> bool {
+		self.memory_zeroed && self.memory.current_size() == self.memory.initial()
 	}
 }
 
@@ -438,39 +499,72 @@ pub struct WasmiRuntime {
 	/// Enable stub generation for functions that are not available in `host_functions`.
 	/// These stubs will error when the wasm blob tries to call them.
 	allow_missing_func_imports: bool,
-
-	global_vals_snapshot: GlobalValsSnapshot,
-	data_segments_snapshot: DataSegmentsSnapshot,
+	/// The allocator that manages the heap of the instances.
+	allocator: AllocatorKind,
+	/// The mutable globals that need to be restored between calls.
+	mutable_globals: ExposedMutableGlobalsSet,
+	/// The snapshot of data segments, shared by all instances.
+	data_segments_snapshot: Arc<DataSegmentsSnapshot>,
+	/// Idle instances ready to be handed out again, if pooling is enabled.
+	pool: Option<Arc<Mutex<Vec<InstanceState>>>>,
 }
 
-impl WasmModule for WasmiRuntime {
-	fn new_instance(&self) -> Result<Box<dyn WasmInstance>, Error> {
-		// Instantiate this module.
+impl WasmiRuntime {
+	/// Instantiate the module from scratch.
+	fn instantiate(&self) -> Result<InstanceState, Error> {
 		let (instance, missing_functions, memory) =
 			instantiate_module(&self.module, &self.host_functions, self.allow_missing_func_imports)
 				.map_err(|e| WasmError::Instantiation(e.to_string()))?;
+		let globals_snapshot =
+			GlobalsSnapshot::take(&self.mutable_globals, &mut InstanceGlobalsAdapter(&instance));
 
-		Ok(Box::new(WasmiInstance {
+		Ok(InstanceState {
 			instance,
 			memory,
-			global_vals_snapshot: self.global_vals_snapshot.clone(),
+			memory_zeroed: true,
+			globals_snapshot,
+			missing_functions: Arc::new(missing_functions),
+		})
+	}
+}
+
+impl WasmModule for WasmiRuntime {
+	fn new_instance(&self) -> Result<Box<dyn WasmInstance>, Error> {
+		let pooled = self.pool.as_ref().and_then(|pool| pool.lock().pop());
+		let state = match pooled {
+			Some(state) => state,
+			None => self.instantiate()?,
+		};
+
+		Ok(Box::new(WasmiInstance {
+			state: Some(state),
 			data_segments_snapshot: self.data_segments_snapshot.clone(),
 			host_functions: self.host_functions.clone(),
 			allow_missing_func_imports: self.allow_missing_func_imports,
-			missing_functions: Arc::new(missing_functions),
-			memory_zeroed: true,
+			allocator: self.allocator,
+			pool: self.pool.clone(),
 		}))
 	}
 }
 
 /// Create a new `WasmiRuntime` given the code. This function loads the module and
 /// stores it in the instance.
+///
+/// When a `deterministic_stack_limit` is given, the code is instrumented in the same way as for
+/// wasmtime, so that deep recursion traps at the same point with both execution engines.
 pub fn create_runtime(
 	mut blob: RuntimeBlob,
 	heap_alloc_strategy: HeapAllocStrategy,
+	allocator: AllocatorKind,
+	instantiation_strategy: InstantiationStrategy,
+	deterministic_stack_limit: Option<DeterministicStackLimit>,
 	host_functions: Vec<&'static dyn Function>,
 	allow_missing_func_imports: bool,
 ) -> Result<WasmiRuntime, WasmError> {
+	if let Some(DeterministicStackLimit { logical_max, .. }) = deterministic_stack_limit {
+		blob = blob.inject_stack_depth_metering(logical_max)?;
+	}
+
 	let data_segments_snapshot =
 		DataSegmentsSnapshot::take(&blob).map_err(|e| WasmError::Other(e.to_string()))?;
 
@@ -478,64 +572,74 @@ pub fn create_runtime(
 	blob.convert_memory_import_into_export()?;
 	// Ensure that the memory uses the correct heap pages.
 	blob.setup_memory_according_to_heap_alloc_strategy(heap_alloc_strategy)?;
+	// Export the mutable globals, so that they can be restored between calls.
+	blob.expose_mutable_globals();
+	let mutable_globals = ExposedMutableGlobalsSet::collect(&blob);
 
 	let module =
 		Module::from_parity_wasm_module(blob.into_inner()).map_err(|_| WasmError::InvalidModule)?;
 
-	let global_vals_snapshot = {
-		let (instance, _, _) =
-			instantiate_module(&module, &host_functions, allow_missing_func_imports)
-				.map_err(|e| WasmError::Instantiation(e.to_string()))?;
-		GlobalValsSnapshot::take(&instance)
+	let pool = match instantiation_strategy {
+		InstantiationStrategy::PoolingCopyOnWrite => Some(Default::default()),
+		InstantiationStrategy::RecreateInstance => None,
 	};
 
-	Ok(WasmiRuntime {
+	let runtime = WasmiRuntime {
 		module,
-		data_segments_snapshot,
-		global_vals_snapshot,
+		data_segments_snapshot: Arc::new(data_segments_snapshot),
+		mutable_globals,
 		host_functions: Arc::new(host_functions),
 		allow_missing_func_imports,
-	})
+		allocator,
+		pool,
+	};
+
+	// Instantiate the module once to make sure that it is valid.
+	let state = runtime.instantiate().map_err(|e| WasmError::Instantiation(e.to_string()))?;
+	if let Some(pool) = &runtime.pool {
+		pool.lock().push(state);
+	}
+
+	Ok(runtime)
 }
 
 /// Wasmi instance wrapper along with the state snapshot.
 pub struct WasmiInstance {
-	/// A wasm module instance.
-	instance: ModuleRef,
-	/// The memory instance of used by the wasm module.
-	memory: MemoryRef,
-	/// Is the memory zeroed?
-	memory_zeroed: bool,
-	/// The snapshot of global variable values just after instantiation.
-	global_vals_snapshot: GlobalValsSnapshot,
+	/// The instance state, only `None` while the instance is being dropped.
+	state: Option<InstanceState>,
 	/// The snapshot of data segments.
-	data_segments_snapshot: DataSegmentsSnapshot,
+	data_segments_snapshot: Arc<DataSegmentsSnapshot>,
 	/// The host functions registered for this instance.
 	host_functions: Arc<Vec<&'static dyn Function>>,
 	/// Enable stub generation for functions that are not available in `host_functions`.
 	/// These stubs will error when the wasm blob trie to call them.
 	allow_missing_func_imports: bool,
-	/// List of missing functions detected during function resolution
-	missing_functions: Arc<Vec<String>>,
+	/// The allocator that manages the heap.
+	allocator: AllocatorKind,
+	/// The pool the instance state is returned to on drop, if pooling is enabled.
+	pool: Option<Arc<Mutex<Vec<InstanceState>>>>,
 }
 
-// This is safe because `WasmiInstance` does not leak any references to `self.memory` and
-// `self.instance`
-unsafe impl Send for WasmiInstance {}
-
 impl WasmiInstance {
+	fn state(&self) -> &InstanceState {
+		self.state.as_ref().expect("the state is only taken on drop; qed")
+	}
+
 	fn call_impl(
 		&mut self,
 		method: InvokeMethod,
 		data: &[u8],
 		allocation_stats: &mut Option<AllocationStats>,
+		profile: Option<&mut Option<HostFunctionProfile>>,
 	) -> Result<Vec<u8>, Error> {
+		let state = self.state.as_mut().expect("the state is only taken on drop; qed");
+
 		// We reuse a single wasm instance for multiple calls and a previous call (if any)
 		// altered the state. Therefore, we need to restore the instance to original state.
 
-		if !self.memory_zeroed {
+		if !state.memory_zeroed {
 			// First, zero initialize the linear memory.
-			self.memory.erase().map_err(|e| {
+			state.memory.erase().map_err(|e| {
 				// Snapshot restoration failed. This is pretty unexpected since this can happen
 				// if some invariant is broken or if the system is under extreme memory pressure
 				// (so erasing fails).
@@ -546,29 +650,44 @@ impl WasmiInstance {
 
 		// Second, reapply data segments into the linear memory.
 		self.data_segments_snapshot
-			.apply(|offset, contents| self.memory.set(offset, contents))?;
+			.apply(|offset, contents| state.memory.set(offset, contents))?;
 
 		// Third, restore the global variables to their initial values.
-		self.global_vals_snapshot.apply(&self.instance)?;
+		state.globals_snapshot.apply(&mut InstanceGlobalsAdapter(&state.instance));
 
 		let res = call_in_wasm_module(
-			&self.instance,
-			&self.memory,
+			&state.instance,
+			&state.memory,
+			self.allocator,
 			method,
 			data,
 			self.host_functions.clone(),
 			self.allow_missing_func_imports,
-			self.missing_functions.clone(),
+			state.missing_functions.clone(),
 			allocation_stats,
+			profile,
 		);
 
 		// If we couldn't unmap it, erase the memory.
-		self.memory_zeroed = self.memory.erase().is_ok();
+		state.memory_zeroed = state.memory.erase().is_ok();
 
 		res
 	}
 }
 
+impl Drop for WasmiInstance {
+	fn drop(&mut self) {
+		let (Some(pool), Some(state)) = (&self.pool, self.state.take()) else { return };
+
+		if state.is_reusable() {
+			let mut pool = pool.lock();
+			if pool.len() < MAX_POOLED_INSTANCES {
+				pool.push(state);
+			}
+		}
+	}
+}
+
 impl WasmInstance for WasmiInstance {
 	fn call_with_allocation_stats(
 		&mut self,
@@ -576,12 +695,22 @@ impl WasmInstance for WasmiInstance {
 		data: &[u8],
 	) -> (Result<Vec<u8>, Error>, Option<AllocationStats>) {
 		let mut allocation_stats = None;
-		let result = self.call_impl(method, data, &mut allocation_stats);
+		let result = self.call_impl(method, data, &mut allocation_stats, None);
 		(result, allocation_stats)
 	}
 
+	fn call_with_host_function_profile(
+		&mut self,
+		method: InvokeMethod,
+		data: &[u8],
+	) -> (Result<Vec<u8>, Error>, Option<HostFunctionProfile>) {
+		let mut profile = None;
+		let result = self.call_impl(method, data, &mut None, Some(&mut profile));
+		(result, profile)
+	}
+
 	fn get_global_const(&mut self, name: &str) -> Result<Option<sp_wasm_interface::Value>, Error> {
-		match self.instance.export_by_name(name) {
+		match self.state().instance.export_by_name(name) {
 			Some(global) => Ok(Some(
 				global
 					.as_global()
@@ -594,6 +723,6 @@ impl WasmInstance for WasmiInstance {
 	}
 
 	fn linear_memory_base_ptr(&self) -> Option<*const u8> {
-		Some(self.memory.direct_access().as_ref().as_ptr())
+		Some(self.state().memory.direct_access().as_ref().as_ptr())
 	}
 }
@@ -21,7 +21,8 @@
 
 use wasmtime::Caller;
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, HeapAllocator};
+use sc_executor_common::profiler::HostFunctionProfiler;
 use sp_wasm_interface::{Pointer, WordSize};
 
 use crate::{instance_wrapper::MemoryWrapper, runtime::StoreData, util};
@@ -35,14 +36,16 @@ pub struct HostState {
 	/// This is stored as an `Option` as we need to temporarly set this to `None` when we are
 	/// allocating/deallocating memory. The problem being that we can only mutable access `caller`
 	/// once.
-	allocator: Option<FreeingBumpHeapAllocator>,
+	allocator: Option<HeapAllocator>,
 	panic_message: Option<String>,
+	/// Only set when the host function calls are profiled.
+	pub(crate) profiler: Option<HostFunctionProfiler>,
 }
 
 impl HostState {
 	/// Constructs a new `HostState`.
-	pub fn new(allocator: FreeingBumpHeapAllocator) -> Self {
-		HostState { allocator: Some(allocator), panic_message: None }
+	pub fn new(allocator: HeapAllocator, profiler: Option<HostFunctionProfiler>) -> Self {
+		HostState { allocator: Some(allocator), panic_message: None, profiler }
 	}
 
 	/// Takes the error message out of the host state, leaving a `None` in its place.
@@ -71,6 +74,10 @@ impl<'a> HostContext<'a> {
 			.host_state_mut()
 			.expect("host state is not empty when calling a function in wasm; qed")
 	}
+
+	pub(crate) fn profiler(&self) -> Option<&HostFunctionProfiler> {
+		self.caller.data().host_state.as_ref().and_then(|state| state.profiler.as_ref())
+	}
 }
 
 impl<'a> sp_wasm_interface::FunctionContext for HostContext<'a> {
@@ -79,10 +86,16 @@ impl<'a> sp_wasm_interface::FunctionContext for HostContext<'a> {
 		address: Pointer<u8>,
 		dest: &mut [u8],
 	) -> sp_wasm_interface::Result<()> {
+		if let Some(profiler) = self.profiler() {
+			profiler.note_read(dest.len());
+		}
 		util::read_memory_into(&self.caller, address, dest).map_err(|e| e.to_string())
 	}
 
 	fn write_memory(&mut self, address: Pointer<u8>, data: &[u8]) -> sp_wasm_interface::Result<()> {
+		if let Some(profiler) = self.profiler() {
+			profiler.note_written(data.len());
+		}
 		util::write_memory_from(&mut self.caller, address, data).map_err(|e| e.to_string())
 	}
 
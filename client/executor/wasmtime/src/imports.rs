@@ -17,7 +17,7 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{host::HostContext, runtime::StoreData};
-use sc_executor_common::error::WasmError;
+use sc_executor_common::{error::WasmError, profiler::HostFunctionProfiler};
 use sp_wasm_interface::{FunctionContext, HostFunctions};
 use std::collections::HashMap;
 use wasmtime::{ExternType, FuncType, ImportType, Linker, Module};
@@ -104,6 +104,23 @@ impl<'a, 'b> sp_wasm_interface::HostFunctionRegistry for Registry<'a, 'b> {
 		callback(&mut HostContext { caller })
 	}
 
+	fn with_host_function_context<R>(
+		name: &'static str,
+		caller: wasmtime::Caller<Self::State>,
+		callback: impl FnOnce(&mut dyn FunctionContext) -> R,
+	) -> R {
+		let mut context = HostContext { caller };
+		let started = context.profiler().map(HostFunctionProfiler::enter);
+		let result = callback(&mut context);
+		if let Some(started) = started {
+			let host_state = context.caller.data_mut().host_state_mut();
+			if let Some(profiler) = host_state.and_then(|state| state.profiler.as_mut()) {
+				profiler.exit(name, started);
+			}
+		}
+		result
+	}
+
 	fn register_static<Params, Results>(
 		&mut self,
 		fn_name: &str,
@@ -38,5 +38,6 @@ mod tests;
 
 pub use runtime::{
 	create_runtime, create_runtime_from_artifact, prepare_runtime_artifact, Config,
-	DeterministicStackLimit, InstantiationStrategy, Semantics,
+	InstantiationStrategy, Semantics,
 };
+pub use sc_executor_common::wasm_runtime::DeterministicStackLimit;
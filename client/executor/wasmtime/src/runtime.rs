@@ -24,14 +24,17 @@ use crate::{
 	util::{self, replace_strategy_if_broken},
 };
 
-use sc_allocator::{AllocationStats, FreeingBumpHeapAllocator};
+use sc_allocator::{AllocationStats, AllocatorKind, HeapAllocator};
 use sc_executor_common::{
 	error::{Error, Result, WasmError},
+	profiler::{HostFunctionProfile, HostFunctionProfiler},
 	runtime_blob::{
 		self, DataSegmentsSnapshot, ExposedMutableGlobalsSet, GlobalsSnapshot, RuntimeBlob,
 	},
 	util::checked_range,
-	wasm_runtime::{HeapAllocStrategy, InvokeMethod, WasmInstance, WasmModule},
+	wasm_runtime::{
+		DeterministicStackLimit, HeapAllocStrategy, InvokeMethod, WasmInstance, WasmModule,
+	},
 };
 use sp_runtime_interface::unpack_ptr_and_len;
 use sp_wasm_interface::{HostFunctions, Pointer, Value, WordSize};
@@ -41,6 +44,7 @@ use std::{
 		atomic::{AtomicBool, Ordering},
 		Arc,
 	},
+	time::Instant,
 };
 use wasmtime::{AsContext, Engine, Memory, Table};
 
@@ -125,6 +129,7 @@ pub struct WasmtimeRuntime {
 	engine: wasmtime::Engine,
 	instance_pre: Arc<wasmtime::InstancePre<StoreData>>,
 	instantiation_strategy: InternalInstantiationStrategy,
+	allocator: AllocatorKind,
 }
 
 impl WasmModule for WasmtimeRuntime {
@@ -156,7 +161,7 @@ impl WasmModule for WasmtimeRuntime {
 			}),
 		};
 
-		Ok(Box::new(WasmtimeInstance { strategy }))
+		Ok(Box::new(WasmtimeInstance { strategy, allocator: self.allocator }))
 	}
 }
 
@@ -164,6 +169,7 @@ impl WasmModule for WasmtimeRuntime {
 /// to execute the compiled code.
 pub struct WasmtimeInstance {
 	strategy: Strategy,
+	allocator: AllocatorKind,
 }
 
 impl WasmtimeInstance {
@@ -172,6 +178,7 @@ impl WasmtimeInstance {
 		method: InvokeMethod,
 		data: &[u8],
 		allocation_stats: &mut Option<AllocationStats>,
+		profile: Option<&mut Option<HostFunctionProfile>>,
 	) -> Result<Vec<u8>> {
 		match &mut self.strategy {
 			Strategy::LegacyInstanceReuse {
@@ -190,10 +197,16 @@ impl WasmtimeInstance {
 					)
 				})?;
 				globals_snapshot.apply(&mut InstanceGlobals { instance: instance_wrapper });
-				let allocator = FreeingBumpHeapAllocator::new(*heap_base);
+				let allocator = HeapAllocator::new(self.allocator, *heap_base);
 
-				let result =
-					perform_call(data, instance_wrapper, entrypoint, allocator, allocation_stats);
+				let result = perform_call(
+					data,
+					instance_wrapper,
+					entrypoint,
+					allocator,
+					allocation_stats,
+					profile,
+				);
 
 				// Signal to the OS that we are done with the linear memory and that it can be
 				// reclaimed.
@@ -206,8 +219,15 @@ impl WasmtimeInstance {
 				let heap_base = instance_wrapper.extract_heap_base()?;
 				let entrypoint = instance_wrapper.resolve_entrypoint(method)?;
 
-				let allocator = FreeingBumpHeapAllocator::new(heap_base);
-				perform_call(data, &mut instance_wrapper, entrypoint, allocator, allocation_stats)
+				let allocator = HeapAllocator::new(self.allocator, heap_base);
+				perform_call(
+					data,
+					&mut instance_wrapper,
+					entrypoint,
+					allocator,
+					allocation_stats,
+					profile,
+				)
 			},
 		}
 	}
@@ -220,10 +240,20 @@ impl WasmInstance for WasmtimeInstance {
 		data: &[u8],
 	) -> (Result<Vec<u8>>, Option<AllocationStats>) {
 		let mut allocation_stats = None;
-		let result = self.call_impl(method, data, &mut allocation_stats);
+		let result = self.call_impl(method, data, &mut allocation_stats, None);
 		(result, allocation_stats)
 	}
 
+	fn call_with_host_function_profile(
+		&mut self,
+		method: InvokeMethod,
+		data: &[u8],
+	) -> (Result<Vec<u8>>, Option<HostFunctionProfile>) {
+		let mut profile = None;
+		let result = self.call_impl(method, data, &mut None, Some(&mut profile));
+		(result, profile)
+	}
+
 	fn get_global_const(&mut self, name: &str) -> Result<Option<Value>> {
 		match &mut self.strategy {
 			Strategy::LegacyInstanceReuse { instance_wrapper, .. } =>
@@ -345,7 +375,7 @@ fn common_config(semantics: &Semantics) -> std::result::Result<wasmtime::Config,
 
 	config.memory_init_cow(use_cow);
 	config.memory_guaranteed_dense_image_size(match semantics.heap_alloc_strategy {
-		HeapAllocStrategy::Dynamic { maximum_pages } =>
+		HeapAllocStrategy::Dynamic { maximum_pages, .. } =>
 			maximum_pages.map(|p| p as u64 * WASM_PAGE_SIZE).unwrap_or(u64::MAX),
 		HeapAllocStrategy::Static { .. } => u64::MAX,
 	});
@@ -354,7 +384,7 @@ fn common_config(semantics: &Semantics) -> std::result::Result<wasmtime::Config,
 		const MAX_WASM_PAGES: u64 = 0x10000;
 
 		let memory_pages = match semantics.heap_alloc_strategy {
-			HeapAllocStrategy::Dynamic { maximum_pages } =>
+			HeapAllocStrategy::Dynamic { maximum_pages, .. } =>
 				maximum_pages.map(|p| p as u64).unwrap_or(MAX_WASM_PAGES),
 			HeapAllocStrategy::Static { .. } => MAX_WASM_PAGES,
 		};
@@ -385,51 +415,6 @@ fn common_config(semantics: &Semantics) -> std::result::Result<wasmtime::Config,
 	Ok(config)
 }
 
-/// Knobs for deterministic stack height limiting.
-///
-/// The WebAssembly standard defines a call/value stack but it doesn't say anything about its
-/// size except that it has to be finite. The implementations are free to choose their own notion
-/// of limit: some may count the number of calls or values, others would rely on the host machine
-/// stack and trap on reaching a guard page.
-///
-/// This obviously is a source of non-determinism during execution. This feature can be used
-/// to instrument the code so that it will count the depth of execution in some deterministic
-/// way (the machine stack limit should be so high that the deterministic limit always triggers
-/// first).
-///
-/// The deterministic stack height limiting feature allows to instrument the code so that it will
-/// count the number of items that may be on the stack. This counting will only act as an rough
-/// estimate of the actual stack limit in wasmtime. This is because wasmtime measures it's stack
-/// usage in bytes.
-///
-/// The actual number of bytes consumed by a function is not trivial to compute  without going
-/// through full compilation. Therefore, it's expected that `native_stack_max` is greatly
-/// overestimated and thus never reached in practice. The stack overflow check introduced by the
-/// instrumentation and that relies on the logical item count should be reached first.
-///
-/// See [here][stack_height] for more details of the instrumentation
-///
-/// [stack_height]: https://github.com/paritytech/wasm-utils/blob/d9432baf/src/stack_height/mod.rs#L1-L50
-#[derive(Clone)]
-pub struct DeterministicStackLimit {
-	/// A number of logical "values" that can be pushed on the wasm stack. A trap will be triggered
-	/// if exceeded.
-	///
-	/// A logical value is a local, an argument or a value pushed on operand stack.
-	pub logical_max: u32,
-	/// The maximum number of bytes for stack used by wasmtime JITed code.
-	///
-	/// It's not specified how much bytes will be consumed by a stack frame for a given wasm
-	/// function after translation into machine code. It is also not quite trivial.
-	///
-	/// Therefore, this number should be chosen conservatively. It must be so large so that it can
-	/// fit the [`logical_max`](Self::logical_max) logical values on the stack, according to the
-	/// current instrumentation algorithm.
-	///
-	/// This value cannot be 0.
-	pub native_stack_max: u32,
-}
-
 /// The instantiation strategy to use for the WASM executor.
 ///
 /// All of the CoW strategies (with `CopyOnWrite` suffix) are only supported when either:
@@ -505,6 +490,12 @@ pub struct Semantics {
 	/// The heap allocation strategy to use.
 	pub heap_alloc_strategy: HeapAllocStrategy,
 
+	/// The allocator that manages the heap.
+	///
+	/// The allocator decides whether an allocation fits into the heap, so all nodes executing a
+	/// runtime call as part of consensus need to use the same allocator.
+	pub allocator: AllocatorKind,
+
 	/// Enables WASM Multi-Value proposal
 	pub wasm_multi_value: bool,
 
@@ -672,7 +663,12 @@ where
 		.instantiate_pre(&module)
 		.map_err(|e| WasmError::Other(format!("cannot preinstantiate module: {:#}", e)))?;
 
-	Ok(WasmtimeRuntime { engine, instance_pre: Arc::new(instance_pre), instantiation_strategy })
+	Ok(WasmtimeRuntime {
+		engine,
+		instance_pre: Arc::new(instance_pre),
+		instantiation_strategy,
+		allocator: config.semantics.allocator,
+	})
 }
 
 fn prepare_blob_for_compilation(
@@ -723,25 +719,32 @@ fn perform_call(
 	data: &[u8],
 	instance_wrapper: &mut InstanceWrapper,
 	entrypoint: EntryPoint,
-	mut allocator: FreeingBumpHeapAllocator,
+	mut allocator: HeapAllocator,
 	allocation_stats: &mut Option<AllocationStats>,
+	profile: Option<&mut Option<HostFunctionProfile>>,
 ) -> Result<Vec<u8>> {
 	let (data_ptr, data_len) = inject_input_data(instance_wrapper, &mut allocator, data)?;
 
-	let host_state = HostState::new(allocator);
+	let host_state =
+		HostState::new(allocator, profile.is_some().then(HostFunctionProfiler::default));
 
 	// Set the host state before calling into wasm.
 	instance_wrapper.store_mut().data_mut().host_state = Some(host_state);
 
+	let started = Instant::now();
 	let ret = entrypoint
 		.call(instance_wrapper.store_mut(), data_ptr, data_len)
 		.map(unpack_ptr_and_len);
 
 	// Reset the host state
-	let host_state = instance_wrapper.store_mut().data_mut().host_state.take().expect(
+	let total_time = started.elapsed();
+	let mut host_state = instance_wrapper.store_mut().data_mut().host_state.take().expect(
 		"the host state is always set before calling into WASM so it can't be None here; qed",
 	);
 	*allocation_stats = Some(host_state.allocation_stats());
+	if let (Some(profile), Some(profiler)) = (profile, host_state.profiler.take()) {
+		*profile = Some(profiler.into_profile(total_time));
+	}
 
 	let (output_ptr, output_len) = ret?;
 	let output = extract_output_data(instance_wrapper, output_ptr, output_len)?;
@@ -751,7 +754,7 @@ fn perform_call(
 
 fn inject_input_data(
 	instance: &mut InstanceWrapper,
-	allocator: &mut FreeingBumpHeapAllocator,
+	allocator: &mut HeapAllocator,
 	data: &[u8],
 ) -> Result<(Pointer<u8>, WordSize)> {
 	let mut ctx = instance.store_mut();
@@ -155,6 +155,7 @@ impl RuntimeBuilder {
 				canonicalize_nans: self.canonicalize_nans,
 				parallel_compilation: true,
 				heap_alloc_strategy: self.heap_pages,
+				allocator: Default::default(),
 				wasm_multi_value: false,
 				wasm_bulk_memory: false,
 				wasm_reference_types: false,
@@ -478,6 +479,7 @@ fn test_instances_without_reuse_are_not_leaked() {
 				canonicalize_nans: false,
 				parallel_compilation: true,
 				heap_alloc_strategy: HeapAllocStrategy::Static { extra_pages: 2048 },
+				allocator: Default::default(),
 				wasm_multi_value: false,
 				wasm_bulk_memory: false,
 				wasm_reference_types: false,
@@ -0,0 +1,195 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Rolling Bloom filter remembering the most recently seen gossip messages.
+
+use ahash::RandomState;
+use std::{
+	f64::consts::LN_2,
+	hash::{BuildHasher, Hash, Hasher},
+	mem,
+};
+
+/// Memory-bounded probabilistic set of the most recently inserted items.
+///
+/// Items are inserted in the current generation of the filter. Once `capacity` items have been
+/// inserted in it, the oldest generation is dropped and a new one is started. The filter thus
+/// remembers at least the last `capacity` inserted items, and at most twice as many.
+///
+/// Lookups never return false negatives for remembered items, but can return false positives with
+/// a probability of roughly twice the configured false positive rate. The hashers are randomly
+/// seeded for each filter, so that remotes can't craft colliding items.
+pub(crate) struct RollingBloomFilter {
+	current: Generation,
+	previous: Generation,
+	capacity: usize,
+	num_bits: usize,
+	num_hashes: u32,
+	hashers: (RandomState, RandomState),
+}
+
+/// Bits of a single generation of a [`RollingBloomFilter`].
+struct Generation {
+	bits: Vec<u64>,
+	len: usize,
+}
+
+impl Generation {
+	fn new(num_bits: usize) -> Self {
+		Generation { bits: vec![0; (num_bits + 63) / 64], len: 0 }
+	}
+
+	fn contains(&self, indices: &[usize]) -> bool {
+		indices.iter().all(|i| self.bits[i / 64] & (1 << (i % 64)) != 0)
+	}
+
+	fn insert(&mut self, indices: &[usize]) {
+		for i in indices {
+			self.bits[i / 64] |= 1 << (i % 64);
+		}
+		self.len += 1;
+	}
+
+	fn clear(&mut self) {
+		self.bits.iter_mut().for_each(|word| *word = 0);
+		self.len = 0;
+	}
+}
+
+impl RollingBloomFilter {
+	/// Create a new filter remembering at least the last `capacity` items, with the given false
+	/// positive rate per generation.
+	pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+		let capacity = capacity.max(1);
+		let num_bits = (-(capacity as f64) * false_positive_rate.ln() / (LN_2 * LN_2)).ceil();
+		let num_bits = (num_bits as usize).max(64);
+		let num_hashes = (num_bits as f64 / capacity as f64 * LN_2).round().clamp(1.0, 32.0);
+
+		RollingBloomFilter {
+			current: Generation::new(num_bits),
+			previous: Generation::new(num_bits),
+			capacity,
+			num_bits,
+			num_hashes: num_hashes as u32,
+			hashers: (RandomState::new(), RandomState::new()),
+		}
+	}
+
+	/// Returns `true` if the item has (probably) been inserted recently.
+	pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+		let indices = self.indices(item);
+		self.current.contains(&indices) || self.previous.contains(&indices)
+	}
+
+	/// Insert an item, returning `true` if it wasn't (probably) known already.
+	///
+	/// An item only known to the oldest generation is moved to the current one, so that it is
+	/// remembered for longer.
+	pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) -> bool {
+		let indices = self.indices(item);
+		if self.current.contains(&indices) {
+			return false
+		}
+
+		let known = self.previous.contains(&indices);
+		if self.current.len >= self.capacity {
+			mem::swap(&mut self.current, &mut self.previous);
+			self.current.clear();
+		}
+		self.current.insert(&indices);
+
+		!known
+	}
+
+	/// Number of items remembered by the filter.
+	pub fn len(&self) -> usize {
+		self.current.len + self.previous.len
+	}
+
+	/// Indices of the bits of the item, using double hashing.
+	fn indices<T: Hash + ?Sized>(&self, item: &T) -> Vec<usize> {
+		let hash = |state: &RandomState| {
+			let mut hasher = state.build_hasher();
+			item.hash(&mut hasher);
+			hasher.finish()
+		};
+		let h1 = hash(&self.hashers.0);
+		// Make sure the step is never zero, as all indices would be the same.
+		let h2 = hash(&self.hashers.1) | 1;
+
+		(0..self.num_hashes as u64)
+			.map(|i| (h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits as u64) as usize)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn remembers_inserted_items() {
+		let mut filter = RollingBloomFilter::new(100, 0.000_001);
+
+		for i in 0u32..100 {
+			assert!(filter.insert(&i));
+		}
+		for i in 0u32..100 {
+			assert!(filter.contains(&i));
+			assert!(!filter.insert(&i));
+		}
+		assert_eq!(filter.len(), 100);
+		assert!((100u32..200).all(|i| !filter.contains(&i)));
+	}
+
+	#[test]
+	fn forgets_oldest_generation() {
+		let mut filter = RollingBloomFilter::new(10, 0.000_001);
+
+		for i in 0u32..30 {
+			filter.insert(&i);
+		}
+
+		// At least the last `capacity` items are remembered.
+		assert!((20u32..30).all(|i| filter.contains(&i)));
+		// The generation holding the first items has been dropped.
+		assert!((0u32..10).all(|i| !filter.contains(&i)));
+		assert_eq!(filter.len(), 20);
+	}
+
+	#[test]
+	fn refreshes_items_of_oldest_generation() {
+		let mut filter = RollingBloomFilter::new(10, 0.000_001);
+
+		for i in 0u32..10 {
+			filter.insert(&i);
+		}
+		// Starts a new generation.
+		filter.insert(&10u32);
+		assert!(!filter.insert(&0u32));
+
+		// Drop the generation holding the first items.
+		for i in 11u32..20 {
+			filter.insert(&i);
+		}
+		filter.insert(&20u32);
+
+		assert!(filter.contains(&0u32));
+		assert!((1u32..10).all(|i| !filter.contains(&i)));
+	}
+}
@@ -17,12 +17,17 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-	state_machine::{ConsensusGossip, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL},
+	state_machine::{
+		ConsensusGossip, RateLimits, TopicNotification, PERIODIC_MAINTENANCE_INTERVAL,
+	},
 	Network, Syncing, Validator,
 };
 
 use sc_network::{event::Event, types::ProtocolName};
-use sc_network_common::sync::SyncEvent;
+use sc_network_common::{
+	clock::{Clock, SystemClock},
+	sync::SyncEvent,
+};
 use sc_peerset::ReputationChange;
 
 use futures::{
@@ -46,7 +51,8 @@ pub struct GossipEngine<B: BlockT> {
 	state_machine: ConsensusGossip<B>,
 	network: Box<dyn Network<B> + Send>,
 	sync: Box<dyn Syncing<B>>,
-	periodic_maintenance_interval: futures_timer::Delay,
+	periodic_maintenance_interval: future::BoxFuture<'static, ()>,
+	clock: Arc<dyn Clock>,
 	protocol: ProtocolName,
 
 	/// Incoming events from the network.
@@ -94,12 +100,14 @@ impl<B: BlockT> GossipEngine<B> {
 		let protocol = protocol.into();
 		let network_event_stream = network.event_stream("network-gossip");
 		let sync_event_stream = sync.event_stream("network-gossip");
+		let clock: Arc<dyn Clock> = Arc::new(SystemClock);
 
 		GossipEngine {
 			state_machine: ConsensusGossip::new(validator, protocol.clone(), metrics_registry),
 			network: Box::new(network),
 			sync: Box::new(sync),
-			periodic_maintenance_interval: futures_timer::Delay::new(PERIODIC_MAINTENANCE_INTERVAL),
+			periodic_maintenance_interval: clock.delay(PERIODIC_MAINTENANCE_INTERVAL),
+			clock,
 			protocol,
 
 			network_event_stream,
@@ -115,6 +123,22 @@ impl<B: BlockT> GossipEngine<B> {
 		self.network.report_peer(who, reputation);
 	}
 
+	/// Set the rate limits applied to the messages received from peers.
+	///
+	/// No rate limits are applied by default.
+	pub fn set_rate_limits(&mut self, rate_limits: RateLimits) {
+		self.state_machine.set_rate_limits(rate_limits);
+	}
+
+	/// Set the clock driving the periodic maintenance and the rate limiting periods.
+	///
+	/// The system clock is used by default.
+	pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+		self.periodic_maintenance_interval = clock.delay(PERIODIC_MAINTENANCE_INTERVAL);
+		self.state_machine.set_clock(clock.clone());
+		self.clock = clock;
+	}
+
 	/// Registers a message without propagating it to any peers. The message
 	/// becomes available to new peers or when the service is asked to gossip
 	/// the message's topic. No validation is performed on the message, if the
@@ -194,9 +218,20 @@ impl<B: BlockT> Future for GossipEngine<B> {
 
 					match net_event_stream {
 						Poll::Ready(Some(event)) => match event {
-							Event::NotificationStreamOpened { remote, protocol, role, .. } =>
+							Event::NotificationStreamOpened {
+								remote,
+								protocol,
+								role,
+								negotiated_version,
+								..
+							} =>
 								if protocol == this.protocol {
-									this.state_machine.new_peer(&mut *this.network, remote, role);
+									this.state_machine.new_peer(
+										&mut *this.network,
+										remote,
+										role,
+										negotiated_version,
+									);
 								},
 							Event::NotificationStreamClosed { remote, protocol } => {
 								if protocol == this.protocol {
@@ -309,7 +344,7 @@ impl<B: BlockT> Future for GossipEngine<B> {
 		}
 
 		while let Poll::Ready(()) = this.periodic_maintenance_interval.poll_unpin(cx) {
-			this.periodic_maintenance_interval.reset(PERIODIC_MAINTENANCE_INTERVAL);
+			this.periodic_maintenance_interval = this.clock.delay(PERIODIC_MAINTENANCE_INTERVAL);
 			this.state_machine.tick(&mut *this.network);
 
 			this.message_sinks.retain(|_, sinks| {
@@ -576,6 +611,7 @@ mod tests {
 				remote: remote_peer,
 				protocol: protocol.clone(),
 				negotiated_fallback: None,
+				negotiated_version: None,
 				role: ObservedRole::Authority,
 				received_handshake: vec![],
 			})
@@ -624,6 +660,67 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn validator_is_told_the_negotiated_version() {
+		#[derive(Default)]
+		struct RecordVersions(Mutex<Vec<(PeerId, Option<u32>)>>);
+
+		impl Validator<Block> for RecordVersions {
+			fn new_peer(
+				&self,
+				_context: &mut dyn ValidatorContext<Block>,
+				who: &PeerId,
+				_role: ObservedRole,
+				negotiated_version: Option<u32>,
+			) {
+				self.0.lock().unwrap().push((*who, negotiated_version));
+			}
+
+			fn validate(
+				&self,
+				_context: &mut dyn ValidatorContext<Block>,
+				_sender: &PeerId,
+				_data: &[u8],
+			) -> ValidationResult<H256> {
+				ValidationResult::Discard
+			}
+		}
+
+		let protocol = ProtocolName::from("/my_protocol/2");
+		let (old_peer, new_peer) = (PeerId::random(), PeerId::random());
+		let network = TestNetwork::default();
+		let validator = Arc::new(RecordVersions::default());
+		let mut gossip_engine = GossipEngine::<Block>::new(
+			network.clone(),
+			Arc::new(TestSync::default()),
+			protocol.clone(),
+			validator.clone(),
+			None,
+		);
+
+		let mut event_sender = network.inner.lock().unwrap().event_senders.pop().unwrap();
+		for (remote, negotiated_version) in [(old_peer, 1), (new_peer, 2)] {
+			event_sender
+				.start_send(Event::NotificationStreamOpened {
+					remote,
+					protocol: protocol.clone(),
+					negotiated_fallback: (negotiated_version == 1)
+						.then(|| ProtocolName::from("/my_protocol/1")),
+					negotiated_version: Some(negotiated_version),
+					role: ObservedRole::Authority,
+					received_handshake: vec![],
+				})
+				.expect("Event stream is unbounded; qed.");
+		}
+
+		block_on(poll_fn(|cx| {
+			let _ = gossip_engine.poll_unpin(cx);
+			Poll::Ready(())
+		}));
+
+		assert_eq!(*validator.0.lock().unwrap(), vec![(old_peer, Some(1)), (new_peer, Some(2))]);
+	}
+
 	#[test]
 	fn forwarding_to_different_size_and_topic_channels() {
 		#[derive(Clone, Debug)]
@@ -741,6 +838,7 @@ mod tests {
 					remote: remote_peer,
 					protocol: protocol.clone(),
 					negotiated_fallback: None,
+					negotiated_version: None,
 					role: ObservedRole::Authority,
 					received_handshake: vec![],
 				})
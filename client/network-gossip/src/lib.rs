@@ -63,7 +63,7 @@
 
 pub use self::{
 	bridge::GossipEngine,
-	state_machine::TopicNotification,
+	state_machine::{RateLimit, RateLimits, TopicNotification},
 	validator::{DiscardAll, MessageIntent, ValidationResult, Validator, ValidatorContext},
 };
 
@@ -75,6 +75,7 @@ use sc_network_common::sync::SyncEventStream;
 use sp_runtime::traits::{Block as BlockT, NumberFor};
 use std::iter;
 
+mod bloom;
 mod bridge;
 mod state_machine;
 mod validator;
@@ -16,16 +16,28 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{MessageIntent, Network, ValidationResult, Validator, ValidatorContext};
+use crate::{
+	bloom::RollingBloomFilter, MessageIntent, Network, ValidationResult, Validator,
+	ValidatorContext,
+};
 
-use ahash::AHashSet;
 use libp2p::PeerId;
-use lru::LruCache;
-use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
+use prometheus_endpoint::{
+	register, Counter, CounterVec, GaugeVec, Opts, PrometheusError, Registry, U64,
+};
 use sc_network::types::ProtocolName;
-use sc_network_common::role::ObservedRole;
+use sc_network_common::{
+	clock::{Clock, SystemClock},
+	role::ObservedRole,
+};
 use sp_runtime::traits::{Block as BlockT, Hash, HashFor};
-use std::{collections::HashMap, iter, num::NonZeroUsize, sync::Arc, time, time::Instant};
+use std::{
+	collections::{HashMap, HashSet},
+	iter,
+	sync::Arc,
+	time,
+	time::{Duration, Instant},
+};
 
 // FIXME: Add additional spam/DoS attack protection: https://github.com/paritytech/substrate/issues/1115
 // NOTE: The current value is adjusted based on largest production network deployment (Kusama) and
@@ -34,10 +46,14 @@ use std::{collections::HashMap, iter, num::NonZeroUsize, sync::Arc, time, time::
 // completed rounds and the current live one. That makes it so that at any point we will be holding
 // ~4800 live messages.
 //
-// Assuming that each known message is tracked with a 32 byte hash (common for `Block::Hash`), then
-// this cache should take about 256 KB of memory.
+// Known messages are tracked with a rolling Bloom filter, which takes about 29 bits per message and
+// generation with the false positive rate below. With two generations, this cache should take
+// about 60 KB of memory, globally and for each peer.
 const KNOWN_MESSAGES_CACHE_SIZE: usize = 8192;
 
+// The probability that a message is wrongly considered as known, and is thus not propagated.
+const KNOWN_MESSAGES_FALSE_POSITIVE_RATE: f64 = 0.000_001;
+
 const REBROADCAST_INTERVAL: time::Duration = time::Duration::from_millis(750);
 
 pub(crate) const PERIODIC_MAINTENANCE_INTERVAL: time::Duration = time::Duration::from_millis(1100);
@@ -48,10 +64,82 @@ mod rep {
 	pub const GOSSIP_SUCCESS: Rep = Rep::new(1 << 4, "Successful gossip");
 	/// Reputation change when a peer sends us a gossip message that we already knew about.
 	pub const DUPLICATE_GOSSIP: Rep = Rep::new(-(1 << 2), "Duplicate gossip");
+	/// Reputation change when a peer sends us more gossip messages than allowed by the rate limits.
+	pub const RATE_LIMITED_GOSSIP: Rep = Rep::new(-(1 << 12), "Gossip rate limit exceeded");
+}
+
+/// Limit on the number of new messages accepted within a period of time.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RateLimit {
+	/// Maximum number of messages accepted within a period.
+	pub max_messages: u32,
+	/// Length of a period.
+	pub period: Duration,
+}
+
+/// Rate limits applied to the new messages received from a peer.
+///
+/// The limits are checked before validating the messages. Messages exceeding a limit are dropped
+/// without being validated or forwarded, and the peer is reported. The dropped messages are only
+/// remembered as known by the peer that sent them, so that they are still accepted when received
+/// from another peer.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RateLimits {
+	/// Limit on the messages received from a single peer, whatever their topic.
+	pub per_peer: Option<RateLimit>,
+	/// Limit on the messages received from a single peer for each kind of topic, as classified by
+	/// [`Validator::topic_kind`]. Messages without a topic kind aren't subject to this limit.
+	pub per_topic: Option<RateLimit>,
 }
 
-struct PeerConsensus<H> {
-	known_messages: AHashSet<H>,
+/// Number of messages accepted within the current period of a [`RateLimit`].
+struct RateCounter {
+	period_start: Instant,
+	count: u32,
+}
+
+impl RateCounter {
+	fn new(now: Instant) -> Self {
+		RateCounter { period_start: now, count: 0 }
+	}
+
+	/// Note a new message, returning `false` if it exceeds the limit.
+	fn note(&mut self, limit: &RateLimit, now: Instant) -> bool {
+		if self.is_expired(limit, now) {
+			self.period_start = now;
+			self.count = 0;
+		}
+
+		if self.count >= limit.max_messages {
+			return false
+		}
+
+		self.count += 1;
+		true
+	}
+
+	fn is_expired(&self, limit: &RateLimit, now: Instant) -> bool {
+		now.saturating_duration_since(self.period_start) >= limit.period
+	}
+}
+
+struct PeerConsensus {
+	known_messages: RollingBloomFilter,
+	rate: RateCounter,
+	topic_rates: HashMap<&'static str, RateCounter>,
+}
+
+impl PeerConsensus {
+	fn new(now: Instant) -> Self {
+		PeerConsensus {
+			known_messages: RollingBloomFilter::new(
+				KNOWN_MESSAGES_CACHE_SIZE,
+				KNOWN_MESSAGES_FALSE_POSITIVE_RATE,
+			),
+			rate: RateCounter::new(now),
+			topic_rates: HashMap::new(),
+		}
+	}
 }
 
 /// Topic stream message with sender.
@@ -66,6 +154,7 @@ pub struct TopicNotification {
 struct MessageEntry<B: BlockT> {
 	message_hash: B::Hash,
 	topic: B::Hash,
+	kind: Option<&'static str>,
 	message: Vec<u8>,
 	sender: Option<PeerId>,
 }
@@ -103,7 +192,7 @@ fn propagate<'a, B: BlockT, I>(
 	protocol: ProtocolName,
 	messages: I,
 	intent: MessageIntent,
-	peers: &mut HashMap<PeerId, PeerConsensus<B::Hash>>,
+	peers: &mut HashMap<PeerId, PeerConsensus>,
 	validator: &Arc<dyn Validator<B>>,
 )
 // (msg_hash, topic, message)
@@ -137,7 +226,7 @@ where
 				continue
 			}
 
-			peer.known_messages.insert(*message_hash);
+			peer.known_messages.insert(message_hash);
 
 			tracing::trace!(
 				target: "gossip",
@@ -153,12 +242,14 @@ where
 
 /// Consensus network protocol handler. Manages statements and candidate requests.
 pub struct ConsensusGossip<B: BlockT> {
-	peers: HashMap<PeerId, PeerConsensus<B::Hash>>,
+	peers: HashMap<PeerId, PeerConsensus>,
 	messages: Vec<MessageEntry<B>>,
-	known_messages: LruCache<B::Hash, ()>,
+	known_messages: RollingBloomFilter,
 	protocol: ProtocolName,
 	validator: Arc<dyn Validator<B>>,
 	next_broadcast: Instant,
+	rate_limits: RateLimits,
+	clock: Arc<dyn Clock>,
 	metrics: Option<Metrics>,
 }
 
@@ -181,32 +272,53 @@ impl<B: BlockT> ConsensusGossip<B> {
 		ConsensusGossip {
 			peers: HashMap::new(),
 			messages: Default::default(),
-			known_messages: {
-				let cap = NonZeroUsize::new(KNOWN_MESSAGES_CACHE_SIZE)
-					.expect("cache capacity is not zero");
-				LruCache::new(cap)
-			},
+			known_messages: RollingBloomFilter::new(
+				KNOWN_MESSAGES_CACHE_SIZE,
+				KNOWN_MESSAGES_FALSE_POSITIVE_RATE,
+			),
 			protocol,
 			validator,
 			next_broadcast: Instant::now() + REBROADCAST_INTERVAL,
+			rate_limits: RateLimits::default(),
+			clock: Arc::new(SystemClock),
 			metrics,
 		}
 	}
 
+	/// Set the rate limits applied to the messages received from peers.
+	pub fn set_rate_limits(&mut self, rate_limits: RateLimits) {
+		self.rate_limits = rate_limits;
+	}
+
+	/// Set the clock used to schedule rebroadcasts and rate limiting periods.
+	pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+		self.next_broadcast = clock.now() + REBROADCAST_INTERVAL;
+		self.clock = clock;
+	}
+
 	/// Handle new connected peer.
-	pub fn new_peer(&mut self, network: &mut dyn Network<B>, who: PeerId, role: ObservedRole) {
+	///
+	/// `negotiated_version` is passed on to [`Validator::new_peer`].
+	pub fn new_peer(
+		&mut self,
+		network: &mut dyn Network<B>,
+		who: PeerId,
+		role: ObservedRole,
+		negotiated_version: Option<u32>,
+	) {
 		tracing::trace!(
 			target:"gossip",
 			%who,
 			protocol = %self.protocol,
 			?role,
+			?negotiated_version,
 			"Registering peer",
 		);
-		self.peers.insert(who, PeerConsensus { known_messages: Default::default() });
+		self.peers.insert(who, PeerConsensus::new(self.clock.now()));
 
 		let validator = self.validator.clone();
 		let mut context = NetworkContext { gossip: self, network };
-		validator.new_peer(&mut context, &who, role);
+		validator.new_peer(&mut context, &who, role, negotiated_version);
 	}
 
 	fn register_message_hashed(
@@ -216,11 +328,13 @@ impl<B: BlockT> ConsensusGossip<B> {
 		message: Vec<u8>,
 		sender: Option<PeerId>,
 	) {
-		if self.known_messages.put(message_hash, ()).is_none() {
-			self.messages.push(MessageEntry { message_hash, topic, message, sender });
+		if self.known_messages.insert(&message_hash) {
+			let kind = self.validator.topic_kind(&message);
+			self.messages.push(MessageEntry { message_hash, topic, kind, message, sender });
 
-			if let Some(ref metrics) = self.metrics {
+			if let Some(ref mut metrics) = self.metrics {
 				metrics.registered_messages.inc();
+				metrics.kept_messages.with_label_values(&[kind_label(kind)]).inc();
 			}
 		}
 	}
@@ -246,9 +360,10 @@ impl<B: BlockT> ConsensusGossip<B> {
 	/// Perform periodic maintenance
 	pub fn tick(&mut self, network: &mut dyn Network<B>) {
 		self.collect_garbage();
-		if Instant::now() >= self.next_broadcast {
+		let now = self.clock.now();
+		if now >= self.next_broadcast {
 			self.rebroadcast(network);
-			self.next_broadcast = Instant::now() + REBROADCAST_INTERVAL;
+			self.next_broadcast = now + REBROADCAST_INTERVAL;
 		}
 	}
 
@@ -291,7 +406,6 @@ impl<B: BlockT> ConsensusGossip<B> {
 	/// Prune old or no longer relevant consensus messages. Provide a predicate
 	/// for pruning, which returns `false` when the items with a given topic should be pruned.
 	pub fn collect_garbage(&mut self) {
-		let known_messages = &mut self.known_messages;
 		let before = self.messages.len();
 
 		let mut message_expired = self.validator.message_expired();
@@ -299,8 +413,14 @@ impl<B: BlockT> ConsensusGossip<B> {
 
 		let expired_messages = before - self.messages.len();
 
-		if let Some(ref metrics) = self.metrics {
-			metrics.expired_messages.inc_by(expired_messages as u64)
+		if let Some(ref mut metrics) = self.metrics {
+			metrics.expired_messages.inc_by(expired_messages as u64);
+
+			let mut kinds = HashMap::<_, u64>::new();
+			for entry in &self.messages {
+				*kinds.entry(kind_label(entry.kind)).or_default() += 1;
+			}
+			metrics.update_kept_messages(kinds);
 		}
 
 		tracing::trace!(
@@ -309,11 +429,16 @@ impl<B: BlockT> ConsensusGossip<B> {
 			"Cleaned up {} stale messages, {} left ({} known)",
 			expired_messages,
 			self.messages.len(),
-			known_messages.len(),
+			self.known_messages.len(),
 		);
 
+		let now = self.clock.now();
+		let per_topic = self.rate_limits.per_topic;
 		for (_, ref mut peer) in self.peers.iter_mut() {
-			peer.known_messages.retain(|h| known_messages.contains(h));
+			match per_topic {
+				Some(limit) => peer.topic_rates.retain(|_, rate| !rate.is_expired(&limit, now)),
+				None => peer.topic_rates.clear(),
+			}
 		}
 	}
 
@@ -360,13 +485,34 @@ impl<B: BlockT> ConsensusGossip<B> {
 				if self
 					.peers
 					.get_mut(&who)
-					.map_or(false, |p| !p.known_messages.insert(message_hash))
+					.map_or(false, |p| !p.known_messages.insert(&message_hash))
 				{
 					network.report_peer(who, rep::DUPLICATE_GOSSIP);
 				}
 				continue
 			}
 
+			let kind = self.validator.topic_kind(&message);
+			if let Some(limit) = self.exceeded_rate_limit(&who, kind) {
+				tracing::trace!(
+					target: "gossip",
+					%who,
+					protocol = %self.protocol,
+					?kind,
+					"Dropped message exceeding the {} rate limit",
+					limit,
+				);
+
+				network.report_peer(who, rep::RATE_LIMITED_GOSSIP);
+				if let Some(peer) = self.peers.get_mut(&who) {
+					peer.known_messages.insert(&message_hash);
+				}
+				if let Some(ref metrics) = self.metrics {
+					metrics.rate_limited_messages.with_label_values(&[limit]).inc();
+				}
+				continue
+			}
+
 			// validate the message
 			let validation = {
 				let validator = self.validator.clone();
@@ -401,8 +547,12 @@ impl<B: BlockT> ConsensusGossip<B> {
 				},
 			};
 
+			if let Some(ref metrics) = self.metrics {
+				metrics.received_messages.with_label_values(&[kind_label(kind)]).inc();
+			}
+
 			network.report_peer(who, rep::GOSSIP_SUCCESS);
-			peer.known_messages.insert(message_hash);
+			peer.known_messages.insert(&message_hash);
 			to_forward
 				.push((topic, TopicNotification { message: message.clone(), sender: Some(who) }));
 
@@ -414,6 +564,33 @@ impl<B: BlockT> ConsensusGossip<B> {
 		to_forward
 	}
 
+	/// Note a new message of the given topic `kind` from `who` against the rate limits.
+	///
+	/// Returns the label of the exceeded limit, if any.
+	fn exceeded_rate_limit(
+		&mut self,
+		who: &PeerId,
+		kind: Option<&'static str>,
+	) -> Option<&'static str> {
+		let peer = self.peers.get_mut(who)?;
+		let now = self.clock.now();
+
+		if let Some(limit) = self.rate_limits.per_peer {
+			if !peer.rate.note(&limit, now) {
+				return Some("peer")
+			}
+		}
+
+		if let (Some(limit), Some(kind)) = (self.rate_limits.per_topic, kind) {
+			let rate = peer.topic_rates.entry(kind).or_insert_with(|| RateCounter::new(now));
+			if !rate.note(&limit, now) {
+				return Some("topic")
+			}
+		}
+
+		None
+	}
+
 	/// Send all messages with given topic to a peer.
 	pub fn send_topic(
 		&mut self,
@@ -437,7 +614,7 @@ impl<B: BlockT> ConsensusGossip<B> {
 					continue
 				}
 
-				peer.known_messages.insert(entry.message_hash);
+				peer.known_messages.insert(&entry.message_hash);
 
 				tracing::trace!(
 					target: "gossip",
@@ -490,14 +667,24 @@ impl<B: BlockT> ConsensusGossip<B> {
 			"Sending direct message",
 		);
 
-		peer.known_messages.insert(message_hash);
+		peer.known_messages.insert(&message_hash);
 		network.write_notification(*who, self.protocol.clone(), message);
 	}
 }
 
+/// Label of the given topic kind in the metrics.
+fn kind_label(kind: Option<&'static str>) -> &'static str {
+	kind.unwrap_or("unknown")
+}
+
 struct Metrics {
 	registered_messages: Counter<U64>,
 	expired_messages: Counter<U64>,
+	rate_limited_messages: CounterVec<U64>,
+	kept_messages: GaugeVec<U64>,
+	received_messages: CounterVec<U64>,
+	/// Labels of the topic kinds that have been kept so far.
+	kinds: HashSet<&'static str>,
 }
 
 impl Metrics {
@@ -517,8 +704,49 @@ impl Metrics {
 				)?,
 				registry,
 			)?,
+			rate_limited_messages: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_network_gossip_rate_limited_messages_total",
+						"Number of messages dropped by the gossip service for exceeding a rate limit.",
+					),
+					&["limit"],
+				)?,
+				registry,
+			)?,
+			kept_messages: register(
+				GaugeVec::new(
+					Opts::new(
+						"substrate_network_gossip_kept_messages",
+						"Number of messages kept by the gossip service for a kind of topic.",
+					),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+			received_messages: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_network_gossip_received_messages_total",
+						"Number of valid messages received by the gossip service for a kind of topic.",
+					),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+			kinds: HashSet::new(),
 		})
 	}
+
+	/// Update the number of messages kept for each topic kind.
+	fn update_kept_messages(&mut self, kinds: HashMap<&'static str, u64>) {
+		self.kinds.extend(kinds.keys());
+		for kind in &self.kinds {
+			self.kept_messages
+				.with_label_values(&[kind])
+				.set(kinds.get(kind).copied().unwrap_or_default());
+		}
+	}
 }
 
 #[cfg(test)]
@@ -546,7 +774,7 @@ mod tests {
 
 	macro_rules! push_msg {
 		($consensus:expr, $topic:expr, $hash: expr, $m:expr) => {
-			if $consensus.known_messages.put($hash, ()).is_none() {
+			if $consensus.known_messages.insert(&$hash) {
 				$consensus.messages.push(MessageEntry {
 					message_hash: $hash,
 					topic: $topic,
@@ -733,8 +961,8 @@ mod tests {
 
 		push_msg!(consensus, prev_hash, m1_hash, m1);
 		push_msg!(consensus, best_hash, m2_hash, m2);
-		consensus.known_messages.put(m1_hash, ());
-		consensus.known_messages.put(m2_hash, ());
+		consensus.known_messages.insert(&m1_hash);
+		consensus.known_messages.insert(&m2_hash);
 
 		consensus.collect_garbage();
 		assert_eq!(consensus.messages.len(), 2);
@@ -786,7 +1014,7 @@ mod tests {
 		let mut network = NoOpNetwork::default();
 
 		let peer_id = PeerId::random();
-		consensus.new_peer(&mut network, peer_id, ObservedRole::Full);
+		consensus.new_peer(&mut network, peer_id, ObservedRole::Full, None);
 		assert!(consensus.peers.contains_key(&peer_id));
 
 		consensus.peer_disconnected(&mut network, peer_id);
@@ -834,11 +1062,11 @@ mod tests {
 		let mut network = NoOpNetwork::default();
 
 		let peer_id = PeerId::random();
-		consensus.new_peer(&mut network, peer_id, ObservedRole::Full);
+		consensus.new_peer(&mut network, peer_id, ObservedRole::Full, None);
 		assert!(consensus.peers.contains_key(&peer_id));
 
 		let peer_id2 = PeerId::random();
-		consensus.new_peer(&mut network, peer_id2, ObservedRole::Full);
+		consensus.new_peer(&mut network, peer_id2, ObservedRole::Full, None);
 		assert!(consensus.peers.contains_key(&peer_id2));
 
 		let message = vec![vec![1, 2, 3]];
@@ -850,4 +1078,173 @@ mod tests {
 			network.inner.lock().unwrap().peer_reports
 		);
 	}
+
+	#[test]
+	fn drops_messages_exceeding_peer_rate_limit() {
+		let mut consensus = ConsensusGossip::<Block>::new(Arc::new(AllowAll), "/foo".into(), None);
+		consensus.set_rate_limits(RateLimits {
+			per_peer: Some(RateLimit { max_messages: 2, period: Duration::from_secs(60) }),
+			per_topic: None,
+		});
+
+		let mut network = NoOpNetwork::default();
+
+		let peer_id = PeerId::random();
+		consensus.new_peer(&mut network, peer_id, ObservedRole::Full, None);
+
+		let to_forward =
+			consensus.on_incoming(&mut network, peer_id, vec![vec![1], vec![2], vec![3]]);
+
+		assert_eq!(to_forward.len(), 2);
+		assert_eq!(
+			vec![
+				(peer_id, rep::GOSSIP_SUCCESS),
+				(peer_id, rep::GOSSIP_SUCCESS),
+				(peer_id, rep::RATE_LIMITED_GOSSIP),
+			],
+			network.inner.lock().unwrap().peer_reports
+		);
+
+		// The dropped message isn't known, so that it can be accepted later on.
+		assert!(!consensus.known_messages.contains(&HashFor::<Block>::hash(&[3])));
+	}
+
+	/// Uses the first byte of a message as its topic, and classifies topics by their parity. Counts
+	/// the validated messages.
+	#[derive(Default)]
+	struct TopicFromData {
+		validated: Mutex<usize>,
+	}
+
+	impl Validator<Block> for TopicFromData {
+		fn validate(
+			&self,
+			_context: &mut dyn ValidatorContext<Block>,
+			_sender: &PeerId,
+			data: &[u8],
+		) -> ValidationResult<H256> {
+			*self.validated.lock().unwrap() += 1;
+			ValidationResult::ProcessAndKeep(H256::repeat_byte(data[0]))
+		}
+
+		fn topic_kind(&self, data: &[u8]) -> Option<&'static str> {
+			if data[0] % 2 == 0 {
+				Some("even")
+			} else {
+				Some("odd")
+			}
+		}
+	}
+
+	#[test]
+	fn drops_messages_exceeding_topic_rate_limit() {
+		let validator = Arc::new(TopicFromData::default());
+		let mut consensus = ConsensusGossip::<Block>::new(validator.clone(), "/foo".into(), None);
+		consensus.set_rate_limits(RateLimits {
+			per_peer: None,
+			per_topic: Some(RateLimit { max_messages: 1, period: Duration::from_secs(60) }),
+		});
+
+		let mut network = NoOpNetwork::default();
+
+		let peer_id = PeerId::random();
+		let peer_id2 = PeerId::random();
+		consensus.new_peer(&mut network, peer_id, ObservedRole::Full, None);
+		consensus.new_peer(&mut network, peer_id2, ObservedRole::Full, None);
+
+		let to_forward =
+			consensus.on_incoming(&mut network, peer_id, vec![vec![1, 0], vec![3, 0], vec![2, 0]]);
+		assert_eq!(
+			to_forward.iter().map(|(topic, _)| *topic).collect::<Vec<_>>(),
+			vec![H256::repeat_byte(1), H256::repeat_byte(2)],
+		);
+
+		// The dropped message wasn't validated, and is only known by the peer that sent it.
+		assert_eq!(*validator.validated.lock().unwrap(), 2);
+		let dropped = HashFor::<Block>::hash(&[3, 0]);
+		assert!(!consensus.known_messages.contains(&dropped));
+		assert!(consensus.peers[&peer_id].known_messages.contains(&dropped));
+
+		// Limits are tracked separately for each peer.
+		let to_forward = consensus.on_incoming(&mut network, peer_id2, vec![vec![3, 0]]);
+		assert_eq!(to_forward.len(), 1);
+
+		assert_eq!(
+			vec![
+				(peer_id, rep::GOSSIP_SUCCESS),
+				(peer_id, rep::RATE_LIMITED_GOSSIP),
+				(peer_id, rep::GOSSIP_SUCCESS),
+				(peer_id2, rep::GOSSIP_SUCCESS),
+			],
+			network.inner.lock().unwrap().peer_reports
+		);
+	}
+
+	#[test]
+	fn rate_counter_resets_after_period() {
+		let limit = RateLimit { max_messages: 1, period: Duration::from_secs(10) };
+		let now = Instant::now();
+		let mut rate = RateCounter::new(now);
+
+		assert!(rate.note(&limit, now));
+		assert!(!rate.note(&limit, now + Duration::from_secs(9)));
+		assert!(rate.note(&limit, now + Duration::from_secs(10)));
+		assert!(!rate.is_expired(&limit, now + Duration::from_secs(19)));
+		assert!(rate.is_expired(&limit, now + Duration::from_secs(20)));
+	}
+
+	#[test]
+	fn reports_kept_messages_by_topic_kind() {
+		/// Classifies messages by the parity of their first byte, and expires the odd ones.
+		struct ExpireOdd;
+		impl Validator<Block> for ExpireOdd {
+			fn validate(
+				&self,
+				_context: &mut dyn ValidatorContext<Block>,
+				_sender: &PeerId,
+				_data: &[u8],
+			) -> ValidationResult<H256> {
+				ValidationResult::Discard
+			}
+
+			fn topic_kind(&self, data: &[u8]) -> Option<&'static str> {
+				if data[0] % 2 == 0 {
+					Some("even")
+				} else {
+					Some("odd")
+				}
+			}
+
+			fn message_expired<'a>(&'a self) -> Box<dyn FnMut(H256, &[u8]) -> bool + 'a> {
+				Box::new(move |_topic, data| data[0] % 2 == 1)
+			}
+		}
+
+		let registry = Registry::new();
+		let mut consensus =
+			ConsensusGossip::<Block>::new(Arc::new(ExpireOdd), "/foo".into(), Some(&registry));
+
+		consensus.register_message(H256::repeat_byte(1), vec![1]);
+		consensus.register_message(H256::repeat_byte(2), vec![2]);
+		consensus.register_message(H256::repeat_byte(4), vec![4]);
+		consensus.register_message(H256::repeat_byte(5), vec![5, 0]);
+
+		let kept_messages = |consensus: &ConsensusGossip<Block>, kind| {
+			consensus
+				.metrics
+				.as_ref()
+				.unwrap()
+				.kept_messages
+				.with_label_values(&[kind])
+				.get()
+		};
+		assert_eq!(kept_messages(&consensus, "odd"), 2);
+		assert_eq!(kept_messages(&consensus, "even"), 2);
+
+		// The kinds of topics remain reported once all their messages have expired.
+		consensus.collect_garbage();
+		assert_eq!(kept_messages(&consensus, "odd"), 0);
+		assert_eq!(kept_messages(&consensus, "even"), 2);
+		assert_eq!(consensus.metrics.as_ref().unwrap().expired_messages.get(), 2);
+	}
 }
@@ -23,7 +23,16 @@ use sp_runtime::traits::Block as BlockT;
 /// Validates consensus messages.
 pub trait Validator<B: BlockT>: Send + Sync {
 	/// New peer is connected.
-	fn new_peer(&self, _context: &mut dyn ValidatorContext<B>, _who: &PeerId, _role: ObservedRole) {
+	///
+	/// `negotiated_version` is the version of the protocol negotiated with the peer, if the
+	/// protocol is versioned.
+	fn new_peer(
+		&self,
+		_context: &mut dyn ValidatorContext<B>,
+		_who: &PeerId,
+		_role: ObservedRole,
+		_negotiated_version: Option<u32>,
+	) {
 	}
 
 	/// New connection is dropped.
@@ -37,6 +46,16 @@ pub trait Validator<B: BlockT>: Send + Sync {
 		data: &[u8],
 	) -> ValidationResult<B::Hash>;
 
+	/// Classify a message by the kind of its topic, before it is validated.
+	///
+	/// Topic kinds are a small set of labels, such as the message types of the protocol, that can
+	/// be determined cheaply from the encoded message. They are used to apply the per-topic
+	/// [`RateLimits`](crate::RateLimits) and to label the gossip metrics. By default messages have
+	/// no topic kind.
+	fn topic_kind(&self, _data: &[u8]) -> Option<&'static str> {
+		None
+	}
+
 	/// Produce a closure for validating messages on a given topic.
 	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(B::Hash, &[u8]) -> bool + 'a> {
 		Box::new(move |_topic, _data| false)
@@ -18,7 +18,8 @@
 //!
 //! Allows querying transactions by hash over standard bitswap protocol
 //! Only supports bitswap 1.2.0.
-//! CID is expected to reference 256-bit Blake2b transaction hash.
+//! CID (v0 or v1) is expected to reference the 256-bit Blake2b, SHA2 or Keccak hash of an indexed
+//! transaction.
 
 use cid::{self, Version};
 use futures::{channel::mpsc, StreamExt};
@@ -34,6 +35,7 @@ use schema::bitswap::{
 	message::{wantlist::WantType, Block as MessageBlock, BlockPresence, BlockPresenceType},
 	Message as BitswapMessage,
 };
+use sp_blockchain::TransactionHashAlgorithm;
 use sp_runtime::traits::Block as BlockT;
 use std::{io, sync::Arc, time::Duration};
 use unsigned_varint::encode as varint_encode;
@@ -57,6 +59,31 @@ const MAX_WANTED_BLOCKS: usize = 16;
 /// Bitswap protocol name
 const PROTOCOL_NAME: &'static str = "/ipfs/bitswap/1.2.0";
 
+/// Multihash code of SHA2-256.
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+/// Multihash code of Keccak-256.
+const MULTIHASH_KECCAK_256: u64 = 0x1b;
+
+/// Multihash code of Blake2b-256.
+const MULTIHASH_BLAKE2B_256: u64 = 0xb220;
+
+/// Returns the algorithm under which the transaction referenced by `cid` can be looked up, if
+/// supported.
+fn hash_algorithm(cid: &cid::Cid) -> Option<TransactionHashAlgorithm> {
+	if cid.hash().size() != 32 {
+		return None
+	}
+
+	// CIDv0 are always SHA2-256 multihashes, which is checked when decoding them.
+	match cid.hash().code() {
+		MULTIHASH_SHA2_256 => Some(TransactionHashAlgorithm::Sha2_256),
+		MULTIHASH_KECCAK_256 => Some(TransactionHashAlgorithm::Keccak256),
+		MULTIHASH_BLAKE2B_256 => Some(TransactionHashAlgorithm::Blake2b256),
+		_ => None,
+	}
+}
+
 /// Prefix represents all metadata of a CID, without the actual content.
 #[derive(PartialEq, Eq, Clone, Debug)]
 struct Prefix {
@@ -186,6 +213,11 @@ impl<B: BlockT> BitswapRequestHandler<B> {
 		}
 
 		for entry in wantlist.entries {
+			// Wanted blocks are answered right away, there is never anything to cancel.
+			if entry.cancel {
+				continue
+			}
+
 			let cid = match cid::Cid::read_bytes(entry.block.as_slice()) {
 				Ok(cid) => cid,
 				Err(e) => {
@@ -194,29 +226,35 @@ impl<B: BlockT> BitswapRequestHandler<B> {
 				},
 			};
 
-			if cid.version() != cid::Version::V1 ||
-				cid.hash().code() != u64::from(cid::multihash::Code::Blake2b256) ||
-				cid.hash().size() != 32
-			{
+			let dont_have = |response: &mut BitswapMessage| {
+				if entry.send_dont_have {
+					response.block_presences.push(BlockPresence {
+						r#type: BlockPresenceType::DontHave as i32,
+						cid: cid.to_bytes(),
+					});
+				}
+			};
+
+			let Some(algorithm) = hash_algorithm(&cid) else {
 				debug!(target: LOG_TARGET, "Ignoring unsupported CID {}: {}", peer, cid);
+				dont_have(&mut response);
 				continue
-			}
-
-			let mut hash = B::Hash::default();
-			hash.as_mut().copy_from_slice(&cid.hash().digest()[0..32]);
-			let transaction = match self.client.indexed_transaction(hash) {
-				Ok(ex) => ex,
-				Err(e) => {
-					error!(target: LOG_TARGET, "Error retrieving transaction {}: {}", hash, e);
-					None
-				},
 			};
+			let digest = cid.hash().digest();
+
+			if entry.want_type == WantType::Block as i32 {
+				let transaction = match self.client.indexed_transaction_by(algorithm, digest) {
+					Ok(ex) => ex,
+					Err(e) => {
+						error!(target: LOG_TARGET, "Error retrieving transaction {}: {}", cid, e);
+						None
+					},
+				};
 
-			match transaction {
-				Some(transaction) => {
-					trace!(target: LOG_TARGET, "Found CID {:?}, hash {:?}", cid, hash);
+				match transaction {
+					Some(transaction) => {
+						trace!(target: LOG_TARGET, "Found CID {:?}", cid);
 
-					if entry.want_type == WantType::Block as i32 {
 						let prefix = Prefix {
 							version: cid.version(),
 							codec: cid.codec(),
@@ -226,23 +264,35 @@ impl<B: BlockT> BitswapRequestHandler<B> {
 						response
 							.payload
 							.push(MessageBlock { prefix: prefix.to_bytes(), data: transaction });
-					} else {
-						response.block_presences.push(BlockPresence {
-							r#type: BlockPresenceType::Have as i32,
-							cid: cid.to_bytes(),
-						});
-					}
-				},
-				None => {
-					trace!(target: LOG_TARGET, "Missing CID {:?}, hash {:?}", cid, hash);
-
-					if entry.send_dont_have {
-						response.block_presences.push(BlockPresence {
-							r#type: BlockPresenceType::DontHave as i32,
-							cid: cid.to_bytes(),
-						});
-					}
-				},
+					},
+					None => {
+						trace!(target: LOG_TARGET, "Missing CID {:?}", cid);
+						dont_have(&mut response);
+					},
+				}
+			} else {
+				let has_transaction =
+					match self.client.has_indexed_transaction_by(algorithm, digest) {
+						Ok(has_transaction) => has_transaction,
+						Err(e) => {
+							error!(
+								target: LOG_TARGET,
+								"Error checking transaction {}: {}", cid, e,
+							);
+							false
+						},
+					};
+
+				if has_transaction {
+					trace!(target: LOG_TARGET, "Have CID {:?}", cid);
+					response.block_presences.push(BlockPresence {
+						r#type: BlockPresenceType::Have as i32,
+						cid: cid.to_bytes(),
+					});
+				} else {
+					trace!(target: LOG_TARGET, "Missing CID {:?}", cid);
+					dont_have(&mut response);
+				}
 			}
 		}
 
@@ -522,4 +572,97 @@ mod tests {
 			panic!("invalid event received");
 		}
 	}
+
+	#[tokio::test]
+	async fn transaction_found_by_other_hashes() {
+		let mut client = TestClientBuilder::with_tx_storage(u32::MAX).build();
+		let mut block_builder = client.new_block(Default::default()).unwrap();
+
+		let ext = Extrinsic::Store(vec![0x13, 0x37, 0x13, 0x38]);
+
+		block_builder.push(ext.clone()).unwrap();
+		let block = block_builder.build().unwrap().block;
+
+		client.import(BlockOrigin::File, block).await.unwrap();
+
+		let (bitswap, config) = BitswapRequestHandler::new(Arc::new(client));
+
+		tokio::spawn(async move { bitswap.run().await });
+
+		let data = &ext.encode()[2..];
+		let sha2_cid = cid::Cid::new_v0(
+			cid::multihash::Multihash::wrap(MULTIHASH_SHA2_256, &sp_core::hashing::sha2_256(data))
+				.unwrap(),
+		)
+		.unwrap();
+		let keccak_cid = cid::Cid::new_v1(
+			0x55,
+			cid::multihash::Multihash::wrap(
+				MULTIHASH_KECCAK_256,
+				&sp_core::hashing::keccak_256(data),
+			)
+			.unwrap(),
+		);
+		let missing_cid = cid::Cid::new_v1(
+			0x55,
+			cid::multihash::Multihash::wrap(MULTIHASH_KECCAK_256, &[0u8; 32]).unwrap(),
+		);
+
+		let (tx, rx) = oneshot::channel();
+		config
+			.inbound_queue
+			.unwrap()
+			.send(IncomingRequest {
+				peer: PeerId::random(),
+				payload: BitswapMessage {
+					wantlist: Some(Wantlist {
+						entries: vec![
+							Entry { block: sha2_cid.to_bytes(), ..Default::default() },
+							Entry {
+								block: keccak_cid.to_bytes(),
+								want_type: WantType::Have as i32,
+								..Default::default()
+							},
+							Entry {
+								block: missing_cid.to_bytes(),
+								want_type: WantType::Have as i32,
+								send_dont_have: true,
+								..Default::default()
+							},
+						],
+						full: false,
+					}),
+					..Default::default()
+				}
+				.encode_to_vec(),
+				pending_response: tx,
+			})
+			.await
+			.unwrap();
+
+		if let Ok(OutgoingResponse { result, reputation_changes, sent_feedback }) = rx.await {
+			assert_eq!(reputation_changes, Vec::new());
+			assert!(sent_feedback.is_none());
+
+			let response =
+				schema::bitswap::Message::decode(&result.expect("fetch to succeed")[..]).unwrap();
+			assert_eq!(response.payload.len(), 1);
+			assert_eq!(response.payload[0].data, vec![0x13, 0x37, 0x13, 0x38]);
+			assert_eq!(
+				response.block_presences,
+				vec![
+					BlockPresence {
+						cid: keccak_cid.to_bytes(),
+						r#type: BlockPresenceType::Have as i32,
+					},
+					BlockPresence {
+						cid: missing_cid.to_bytes(),
+						r#type: BlockPresenceType::DontHave as i32,
+					},
+				],
+			);
+		} else {
+			panic!("invalid event received");
+		}
+	}
 }
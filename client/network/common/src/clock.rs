@@ -0,0 +1,48 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Source of time of the networking protocols.
+
+use futures::{future::BoxFuture, FutureExt};
+use std::time::{Duration, Instant};
+
+/// Source of time used by the networking protocols for their timers.
+///
+/// The protocols use the [`SystemClock`] unless told otherwise. Tests can substitute a virtual
+/// clock, so that the behaviour of the protocols doesn't depend on the wall-clock time.
+pub trait Clock: Send + Sync {
+	/// Returns the current time.
+	fn now(&self) -> Instant;
+
+	/// Returns a future that resolves once `duration` has elapsed.
+	fn delay(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// [`Clock`] following the time of the system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn delay(&self, duration: Duration) -> BoxFuture<'static, ()> {
+		futures_timer::Delay::new(duration).boxed()
+	}
+}
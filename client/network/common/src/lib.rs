@@ -18,6 +18,7 @@
 
 //! Common data structures of the networking layer.
 
+pub mod clock;
 pub mod message;
 pub mod role;
 pub mod sync;
@@ -38,6 +38,16 @@ pub enum WarpSyncParams<Block: BlockT> {
 	///
 	/// It is expected that the header provider ensures that the header is trusted.
 	WaitForTarget(oneshot::Receiver<<Block as BlockT>::Header>),
+	/// Skip downloading proofs and warp sync to the block with the given hash and number.
+	///
+	/// The header of the block is downloaded from the peers and checked against the hash. Like
+	/// with [`Self::WaitForTarget`], the block is expected to be trusted.
+	WithTargetBlock {
+		/// Hash of the target block.
+		hash: Block::Hash,
+		/// Number of the target block.
+		number: NumberFor<Block>,
+	},
 }
 
 /// Proof verification result.
@@ -43,6 +43,14 @@ use std::{marker::PhantomData, sync::Arc};
 
 const LOG_TARGET: &str = "light-client-request-handler";
 
+/// Maximum number of keys that are read for a single key range read request.
+const MAX_RANGE_KEYS: u32 = 1024;
+
+/// Size of the proof after which no more keys are read for a key range read request.
+///
+/// Well below the maximum response size, as the proof may still grow by a value after it.
+const MAX_RANGE_PROOF_SIZE: usize = 2 * 1024 * 1024;
+
 /// Handler for incoming light client requests from a remote peer.
 pub struct LightClientRequestHandler<B, Client> {
 	request_receiver: mpsc::Receiver<IncomingRequest>,
@@ -153,6 +161,8 @@ where
 				self.on_remote_read_request(&peer, r)?,
 			Some(schema::v1::light::request::Request::RemoteReadChildRequest(r)) =>
 				self.on_remote_read_child_request(&peer, r)?,
+			Some(schema::v1::light::request::Request::RemoteReadRangeRequest(r)) =>
+				self.on_remote_read_range_request(&peer, r)?,
 			None =>
 				return Err(HandleRequestError::BadRequest("Remote request without request data.")),
 		};
@@ -280,6 +290,73 @@ where
 			response: Some(schema::v1::light::response::Response::RemoteReadResponse(response)),
 		})
 	}
+
+	fn on_remote_read_range_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::v1::light::RemoteReadRangeRequest,
+	) -> Result<schema::v1::light::Response, HandleRequestError> {
+		if request.limit == 0 {
+			debug!("Invalid remote read range request sent by {}.", peer);
+			return Err(HandleRequestError::BadRequest("Remote read range request without limit."))
+		}
+
+		trace!(
+			"Remote read range request from {} ({} {} {} at {:?}).",
+			peer,
+			request
+				.storage_key
+				.as_ref()
+				.map_or(String::from("top"), |k| HexDisplay::from(k).to_string()),
+			request
+				.prefix
+				.as_ref()
+				.map_or(String::from("n/a"), |p| HexDisplay::from(p).to_string()),
+			fmt_keys(request.start_key.as_ref(), request.end_key.as_ref()),
+			request.block,
+		);
+
+		let block = Decode::decode(&mut request.block.as_ref())?;
+
+		let child_info = match request.storage_key.as_ref() {
+			Some(storage_key) =>
+				match ChildType::from_prefixed_key(PrefixedStorageKey::new_ref(storage_key)) {
+					Some((ChildType::ParentKeyId, storage_key)) =>
+						Ok(Some(ChildInfo::new_default(storage_key))),
+					None => Err(sp_blockchain::Error::InvalidChildStorageKey),
+				},
+			None => Ok(None),
+		};
+		let response = match child_info.and_then(|child_info| {
+			self.client.read_range_proof(
+				block,
+				child_info.as_ref(),
+				request.prefix.as_deref(),
+				request.start_key.as_deref(),
+				request.end_key.as_deref(),
+				request.limit.min(MAX_RANGE_KEYS),
+				MAX_RANGE_PROOF_SIZE,
+			)
+		}) {
+			Ok(proof) => schema::v1::light::RemoteReadRangeResponse { proof: Some(proof.encode()) },
+			Err(error) => {
+				trace!(
+					"remote read range request from {} ({} at {:?}) failed with: {}",
+					peer,
+					fmt_keys(request.start_key.as_ref(), request.end_key.as_ref()),
+					request.block,
+					error,
+				);
+				schema::v1::light::RemoteReadRangeResponse { proof: None }
+			},
+		};
+
+		Ok(schema::v1::light::Response {
+			response: Some(schema::v1::light::response::Response::RemoteReadRangeResponse(
+				response,
+			)),
+		})
+	}
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -309,3 +386,86 @@ fn fmt_keys(first: Option<&Vec<u8>>, last: Option<&Vec<u8>>) -> String {
 		String::from("n/a")
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_blockchain::HeaderBackend;
+	use sp_runtime::traits::{BlakeTwo256, Header as _};
+	use sp_state_machine::{read_range_proof_check_with_limit, CompactProof};
+	use substrate_test_runtime_client::{
+		DefaultTestClientBuilderExt, TestClientBuilder, TestClientBuilderExt,
+	};
+
+	#[test]
+	fn range_read_of_large_values_is_split_into_responses() {
+		const VALUE_SIZE: usize = 1536 * 1024;
+		const PREFIX: &[u8] = b"big";
+
+		let client = Arc::new(
+			(0..4u8)
+				.fold(TestClientBuilder::new(), |builder, i| {
+					builder.add_extra_storage([PREFIX, &[i]].concat(), vec![i; VALUE_SIZE])
+				})
+				.build(),
+		);
+		let genesis_hash = client.info().genesis_hash;
+		let root = *client.header(genesis_hash).unwrap().unwrap().state_root();
+		let (mut handler, protocol_config) =
+			LightClientRequestHandler::new(&ProtocolId::from("test"), None, client);
+
+		let mut start_key = None;
+		let mut keys = Vec::new();
+		let mut responses = 0;
+		loop {
+			assert!(responses < 10, "The range read doesn't make progress");
+			responses += 1;
+
+			let request = schema::v1::light::Request {
+				request: Some(schema::v1::light::request::Request::RemoteReadRangeRequest(
+					schema::v1::light::RemoteReadRangeRequest {
+						block: genesis_hash.encode(),
+						storage_key: None,
+						prefix: Some(PREFIX.to_vec()),
+						start_key: start_key.clone(),
+						end_key: None,
+						limit: MAX_RANGE_KEYS,
+					},
+				)),
+			};
+			let response =
+				handler.handle_request(PeerId::random(), request.encode_to_vec()).unwrap();
+			assert!(response.len() as u64 <= protocol_config.max_response_size);
+
+			let proof = match schema::v1::light::Response::decode(&response[..]).unwrap().response {
+				Some(schema::v1::light::response::Response::RemoteReadRangeResponse(response)) =>
+					response.proof.expect("The block is known"),
+				response => panic!("Unexpected response: {:?}", response),
+			};
+			let (proof, _) = CompactProof::decode(&mut &proof[..])
+				.unwrap()
+				.to_storage_proof::<BlakeTwo256>(Some(&root))
+				.unwrap();
+			let (values, completed) = read_range_proof_check_with_limit::<BlakeTwo256>(
+				root,
+				proof,
+				None,
+				Some(PREFIX),
+				start_key.as_deref(),
+				None,
+				MAX_RANGE_KEYS,
+			)
+			.unwrap();
+
+			assert!(!values.is_empty() || completed);
+			start_key = values.last().map(|(key, _)| key.clone()).or(start_key);
+			keys.extend(values.into_iter().map(|(key, _)| key));
+			if completed {
+				break
+			}
+		}
+
+		assert!(responses > 1);
+		assert_eq!(keys, (0..4u8).map(|i| [PREFIX, &[i]].concat()).collect::<Vec<_>>());
+	}
+}
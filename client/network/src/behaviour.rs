@@ -104,6 +104,8 @@ pub enum BehaviourOut {
 		/// used.
 		/// See also [`crate::Event::NotificationStreamOpened`].
 		negotiated_fallback: Option<ProtocolName>,
+		/// See [`crate::Event::NotificationStreamOpened::negotiated_version`].
+		negotiated_version: Option<u32>,
 		/// Object that permits sending notifications to the peer.
 		notifications_sink: NotificationsSink,
 		/// Role of the remote.
@@ -284,6 +286,7 @@ impl From<CustomMessageOutcome> for BehaviourOut {
 				remote,
 				protocol,
 				negotiated_fallback,
+				negotiated_version,
 				roles,
 				received_handshake,
 				notifications_sink,
@@ -291,6 +294,7 @@ impl From<CustomMessageOutcome> for BehaviourOut {
 				remote,
 				protocol,
 				negotiated_fallback,
+				negotiated_version,
 				role: reported_roles_to_observed_role(roles),
 				received_handshake,
 				notifications_sink,
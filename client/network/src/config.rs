@@ -57,6 +57,7 @@ pub use libp2p::{
 	build_multiaddr,
 	identity::{self, ed25519},
 };
+pub use sc_peerset::IpLimits;
 
 /// Protocol name prefix, transmitted on the wire for legacy protocol names.
 /// I.e., `dot` in `/dot/sync/2`. Should be unique for each chain. Always UTF-8.
@@ -307,6 +308,34 @@ impl Default for SyncMode {
 	}
 }
 
+/// Block that warp sync should download the state of, instead of the block proven by the warp
+/// sync proofs.
+///
+/// Parsed from `<HASH>:<NUMBER>`, with the hash hex encoded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WarpSyncTarget {
+	/// SCALE encoded hash of the block.
+	pub hash: Vec<u8>,
+	/// Number of the block.
+	pub number: u64,
+}
+
+impl FromStr for WarpSyncTarget {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (hash, number) =
+			s.split_once(':').ok_or_else(|| "Expected `<HASH>:<NUMBER>`".to_string())?;
+		let hash = array_bytes::hex2bytes(hash)
+			.map_err(|e| format!("Invalid block hash `{}`: {:?}", hash, e))?;
+		let number = number
+			.parse()
+			.map_err(|e| format!("Invalid block number `{}`: {}", number, e))?;
+
+		Ok(Self { hash, number })
+	}
+}
+
 /// The configuration of a node's secret key, describing the type of key
 /// and how it is obtained. A node's identity keypair is the result of
 /// the evaluation of the node key configuration.
@@ -495,6 +524,14 @@ pub struct NonDefaultSetConfig {
 	/// `sc_network::protocol::event::Event::NotificationStreamOpened::negotiated_fallback`
 	pub fallback_names: Vec<ProtocolName>,
 
+	/// Version of the protocol each of the names in `notifications_protocol` and
+	/// `fallback_names` corresponds to.
+	///
+	/// The version of the negotiated name is reported in
+	/// `sc_network::protocol::event::Event::NotificationStreamOpened::negotiated_version`.
+	/// Empty if the protocol isn't versioned, see [`NonDefaultSetConfig::new_versioned`].
+	pub versions: Vec<(ProtocolName, u32)>,
+
 	/// Handshake of the protocol
 	///
 	/// NOTE: Currently custom handshakes are not fully supported. See issue #5685 for more
@@ -516,6 +553,7 @@ impl NonDefaultSetConfig {
 			notifications_protocol,
 			max_notification_size,
 			fallback_names: Vec::new(),
+			versions: Vec::new(),
 			handshake: None,
 			set_config: SetConfig {
 				in_peers: 0,
@@ -526,6 +564,40 @@ impl NonDefaultSetConfig {
 		}
 	}
 
+	/// Creates a new [`NonDefaultSetConfig`] for a protocol that exists in multiple versions.
+	/// Zero slots and accepts only reserved nodes.
+	///
+	/// Version `v` of the protocol is negotiated under the name `{base_name}/{v}`. The highest
+	/// version is the main name of the protocol and the other ones are its fallback names, from
+	/// the highest to the lowest, so that the highest version supported by both sides is used.
+	///
+	/// Returns an error if `versions` is empty.
+	pub fn new_versioned(
+		base_name: &str,
+		versions: impl IntoIterator<Item = u32>,
+		max_notification_size: u64,
+	) -> Result<Self, crate::error::Error> {
+		let mut versions = versions.into_iter().collect::<Vec<_>>();
+		versions.sort_unstable_by(|a, b| b.cmp(a));
+		versions.dedup();
+
+		let names = versions
+			.iter()
+			.map(|version| ProtocolName::from(format!("{}/{}", base_name, version)))
+			.collect::<Vec<_>>();
+
+		let main_name = names
+			.first()
+			.ok_or_else(|| crate::error::Error::NoProtocolVersion {
+				base_name: base_name.to_owned(),
+			})?
+			.clone();
+		let mut config = Self::new(main_name, max_notification_size);
+		config.fallback_names = names[1..].to_vec();
+		config.versions = names.into_iter().zip(versions).collect();
+		Ok(config)
+	}
+
 	/// Modifies the configuration to allow non-reserved nodes.
 	pub fn allow_non_reserved(&mut self, in_peers: u32, out_peers: u32) {
 		self.set_config.in_peers = in_peers;
@@ -597,6 +669,13 @@ pub struct NetworkConfiguration {
 	/// Initial syncing mode.
 	pub sync_mode: SyncMode,
 
+	/// Trusted block to warp sync to.
+	///
+	/// Only used if [`Self::sync_mode`] is [`SyncMode::Warp`]. The warp sync proofs are not
+	/// downloaded, the header of the block is requested from the peers and its state is
+	/// downloaded directly.
+	pub warp_sync_target: Option<WarpSyncTarget>,
+
 	/// True if Kademlia random discovery should be enabled.
 	///
 	/// If true, the node will automatically randomly walk the DHT in order to find new peers.
@@ -612,6 +691,17 @@ pub struct NetworkConfiguration {
 	/// Enable serving block data over IPFS bitswap.
 	pub ipfs_server: bool,
 
+	/// Persist the reputations, manual bans and last seen addresses of the peers in
+	/// [`Self::net_config_path`], so that they survive restarts.
+	///
+	/// Has no effect if [`Self::net_config_path`] is `None`.
+	pub persistent_peer_store: bool,
+
+	/// Limits on the number of peers connecting to us from the same IP address or subnet.
+	///
+	/// Reserved nodes are exempt from the limits.
+	pub ip_limits: IpLimits,
+
 	/// Size of Yamux receive window of all substreams. `None` for the default (256kiB).
 	/// Any value less than 256kiB is invalid.
 	///
@@ -659,11 +749,14 @@ impl NetworkConfiguration {
 			max_parallel_downloads: 5,
 			max_blocks_per_request: 64,
 			sync_mode: SyncMode::Full,
+			warp_sync_target: None,
 			enable_dht_random_walk: true,
 			allow_non_globals_in_dht: false,
 			kademlia_disjoint_query_paths: false,
 			yamux_window_size: None,
 			ipfs_server: false,
+			persistent_peer_store: false,
+			ip_limits: IpLimits::default(),
 		}
 	}
 
@@ -770,4 +863,31 @@ mod tests {
 		let kp2 = NodeKeyConfig::Ed25519(Secret::New).into_keypair().unwrap();
 		assert!(secret_bytes(&kp1) != secret_bytes(&kp2));
 	}
+
+	#[test]
+	fn test_warp_sync_target_parsing() {
+		let target: WarpSyncTarget = "0x0102:42".parse().unwrap();
+		assert_eq!(target, WarpSyncTarget { hash: vec![1, 2], number: 42 });
+
+		assert!("0x0102".parse::<WarpSyncTarget>().is_err());
+		assert!("0x01g2:42".parse::<WarpSyncTarget>().is_err());
+		assert!("0x0102:-1".parse::<WarpSyncTarget>().is_err());
+	}
+
+	#[test]
+	fn versioned_set_config() {
+		let config = NonDefaultSetConfig::new_versioned("/foo", [1, 3, 2], 1024).unwrap();
+
+		assert_eq!(config.notifications_protocol, ProtocolName::from("/foo/3"));
+		assert_eq!(
+			config.fallback_names,
+			vec![ProtocolName::from("/foo/2"), ProtocolName::from("/foo/1")],
+		);
+		assert_eq!(
+			config.versions,
+			vec![("/foo/3".into(), 3), ("/foo/2".into(), 2), ("/foo/1".into(), 1)],
+		);
+
+		assert!(NonDefaultSetConfig::new_versioned("/foo", [], 1024).is_err());
+	}
 }
@@ -68,6 +68,12 @@ pub enum Error {
 		/// Name of the protocol registered multiple times.
 		protocol: ProtocolName,
 	},
+	/// A versioned notification protocol has been configured without any version.
+	#[error("Notification protocol configured without any version: {base_name}")]
+	NoProtocolVersion {
+		/// Base name of the protocol.
+		base_name: String,
+	},
 }
 
 // Make `Debug` use the `Display` implementation.
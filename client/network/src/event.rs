@@ -69,6 +69,11 @@ pub enum Event {
 		/// Always contains a value equal to the value in
 		/// `sc_network::config::NonDefaultSetConfig::fallback_names`.
 		negotiated_fallback: Option<ProtocolName>,
+		/// Version of the protocol that has been negotiated, as declared in
+		/// `sc_network::config::NonDefaultSetConfig::versions`.
+		///
+		/// `None` if the protocol isn't versioned.
+		negotiated_version: Option<u32>,
 		/// Role of the remote.
 		role: ObservedRole,
 		/// Received handshake.
@@ -66,6 +66,9 @@ const HARDCODED_PEERSETS_SYNC: sc_peerset::SetId = sc_peerset::SetId::from(0);
 /// superior to this value corresponds to a user-defined protocol.
 const NUM_HARDCODED_PEERSETS: usize = 1;
 
+/// Name of the peer store file in [`config::NetworkConfiguration::net_config_path`].
+const PEER_STORE_FILE: &str = "peers.json";
+
 mod rep {
 	use sc_peerset::ReputationChange as Rep;
 	/// We received a message that failed to decode.
@@ -85,6 +88,9 @@ pub struct Protocol<B: BlockT> {
 	behaviour: Notifications,
 	/// List of notifications protocols that have been registered.
 	notification_protocols: Vec<ProtocolName>,
+	/// For each of the `notification_protocols`, the version each of its names corresponds to.
+	/// See [`config::NonDefaultSetConfig::versions`].
+	notification_protocol_versions: Vec<Vec<(ProtocolName, u32)>>,
 	/// If we receive a new "substream open" event that contains an invalid handshake, we ask the
 	/// inner layer to force-close the substream. Force-closing the substream will generate a
 	/// "substream closed" event. This is a problem: since we can't propagate the "substream open"
@@ -156,7 +162,17 @@ impl<B: BlockT> Protocol<B> {
 				});
 			}
 
-			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig { sets })
+			let peer_store = network_config
+				.net_config_path
+				.as_ref()
+				.filter(|_| network_config.persistent_peer_store)
+				.map(|path| path.join(PEER_STORE_FILE));
+
+			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig {
+				sets,
+				peer_store,
+				ip_limits: network_config.ip_limits.clone(),
+			})
 		};
 
 		let behaviour = {
@@ -184,6 +200,9 @@ impl<B: BlockT> Protocol<B> {
 			pending_messages: VecDeque::new(),
 			peerset_handle: peerset_handle.clone(),
 			behaviour,
+			notification_protocol_versions: iter::once(block_announces_protocol.versions)
+				.chain(network_config.extra_sets.iter().map(|s| s.versions.clone()))
+				.collect(),
 			notification_protocols: iter::once(block_announces_protocol.notifications_protocol)
 				.chain(network_config.extra_sets.iter().map(|s| s.notifications_protocol.clone()))
 				.collect(),
@@ -219,11 +238,37 @@ impl<B: BlockT> Protocol<B> {
 		}
 	}
 
+	/// Returns the version of the protocol of the given set that has been negotiated, if the
+	/// protocol is versioned.
+	fn negotiated_version(
+		&self,
+		set_id: sc_peerset::SetId,
+		negotiated_fallback: Option<&ProtocolName>,
+	) -> Option<u32> {
+		let set_id = usize::from(set_id);
+		let name = negotiated_fallback.unwrap_or(&self.notification_protocols[set_id]);
+		self.notification_protocol_versions[set_id]
+			.iter()
+			.find(|(version_name, _)| version_name == name)
+			.map(|(_, version)| *version)
+	}
+
 	/// Returns the state of the peerset manager, for debugging purposes.
 	pub fn peerset_debug_info(&mut self) -> serde_json::Value {
 		self.behaviour.peerset_debug_info()
 	}
 
+	/// Returns the number of incoming connections the peerset rejected because of the IP limits.
+	pub fn peerset_ip_limit_rejections(&self) -> sc_peerset::IpLimitRejections {
+		self.behaviour.peerset_ip_limit_rejections()
+	}
+
+	/// Returns the addresses the peerset has last seen the peers at, including the ones loaded
+	/// from the peer store.
+	pub fn peerset_known_addresses(&self) -> impl Iterator<Item = (PeerId, Multiaddr)> + '_ {
+		self.behaviour.peerset_known_addresses()
+	}
+
 	/// Returns the number of peers we're connected to.
 	pub fn num_connected_peers(&self) -> usize {
 		self.peers.len()
@@ -358,6 +403,8 @@ pub enum CustomMessageOutcome {
 		protocol: ProtocolName,
 		/// See [`crate::Event::NotificationStreamOpened::negotiated_fallback`].
 		negotiated_fallback: Option<ProtocolName>,
+		/// See [`crate::Event::NotificationStreamOpened::negotiated_version`].
+		negotiated_version: Option<u32>,
 		roles: Roles,
 		received_handshake: Vec<u8>,
 		notifications_sink: NotificationsSink,
@@ -556,6 +603,8 @@ impl<B: BlockT> NetworkBehaviour for Protocol<B> {
 						(Ok(roles), _) => CustomMessageOutcome::NotificationStreamOpened {
 							remote: peer_id,
 							protocol: self.notification_protocols[usize::from(set_id)].clone(),
+							negotiated_version: self
+								.negotiated_version(set_id, negotiated_fallback.as_ref()),
 							negotiated_fallback,
 							roles,
 							received_handshake,
@@ -569,6 +618,8 @@ impl<B: BlockT> NetworkBehaviour for Protocol<B> {
 							CustomMessageOutcome::NotificationStreamOpened {
 								remote: peer_id,
 								protocol: self.notification_protocols[usize::from(set_id)].clone(),
+								negotiated_version: self
+									.negotiated_version(set_id, negotiated_fallback.as_ref()),
 								negotiated_fallback,
 								roles: *roles,
 								received_handshake,
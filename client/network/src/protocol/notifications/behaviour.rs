@@ -27,7 +27,7 @@ use bytes::BytesMut;
 use fnv::FnvHashMap;
 use futures::prelude::*;
 use libp2p::{
-	core::{connection::ConnectionId, Multiaddr, PeerId},
+	core::{connection::ConnectionId, multiaddr::Protocol, Multiaddr, PeerId},
 	swarm::{
 		behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm},
 		handler::ConnectionHandler,
@@ -44,6 +44,7 @@ use std::{
 	cmp,
 	collections::{hash_map::Entry, VecDeque},
 	mem,
+	net::IpAddr,
 	pin::Pin,
 	sync::Arc,
 	task::{Context, Poll},
@@ -135,6 +136,10 @@ pub struct Notifications {
 	/// to use when a connection is incoming.
 	next_incoming_index: sc_peerset::IncomingIndex,
 
+	/// IP address of the remote of each connection, if known. Passed to the peerset with the
+	/// incoming connections, to enforce the per-IP limits.
+	connection_ips: FnvHashMap<ConnectionId, IpAddr>,
+
 	/// Events to produce from `poll()`.
 	events: VecDeque<NetworkBehaviourAction<NotificationsOut, NotifsHandlerProto>>,
 }
@@ -381,6 +386,7 @@ impl Notifications {
 			next_delay_id: DelayId(0),
 			incoming: SmallVec::new(),
 			next_incoming_index: sc_peerset::IncomingIndex(0),
+			connection_ips: FnvHashMap::default(),
 			events: VecDeque::new(),
 		}
 	}
@@ -545,6 +551,17 @@ impl Notifications {
 		self.peerset.debug_info()
 	}
 
+	/// Returns the number of incoming connections the peerset rejected because of the IP limits.
+	pub fn peerset_ip_limit_rejections(&self) -> sc_peerset::IpLimitRejections {
+		self.peerset.ip_limit_rejections()
+	}
+
+	/// Returns the addresses the peerset has last seen the peers at, including the ones loaded
+	/// from the peer store.
+	pub fn peerset_known_addresses(&self) -> impl Iterator<Item = (PeerId, Multiaddr)> + '_ {
+		self.peerset.known_addresses()
+	}
+
 	/// Function that is called when the peerset wants us to connect to a peer.
 	fn peerset_report_connect(&mut self, peer_id: PeerId, set_id: sc_peerset::SetId) {
 		// If `PeerId` is unknown to us, insert an entry, start dialing, and return early.
@@ -992,6 +1009,15 @@ impl Notifications {
 	}
 }
 
+/// Returns the IP address contained in `address`, if any.
+fn ip_address(address: &Multiaddr) -> Option<IpAddr> {
+	address.iter().find_map(|protocol| match protocol {
+		Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+		Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+		_ => None,
+	})
+}
+
 impl NetworkBehaviour for Notifications {
 	type ConnectionHandler = NotifsHandlerProto;
 	type OutEvent = NotificationsOut;
@@ -1012,6 +1038,10 @@ impl NetworkBehaviour for Notifications {
 				connection_id,
 				..
 			}) => {
+				if let Some(ip) = ip_address(endpoint.get_remote_address()) {
+					self.connection_ips.insert(connection_id, ip);
+				}
+
 				for set_id in (0..self.notif_protocols.len()).map(sc_peerset::SetId::from) {
 					match self.peers.entry((peer_id, set_id)).or_insert(PeerState::Poisoned) {
 						// Requested | PendingRequest => Enabled
@@ -1066,6 +1096,8 @@ impl NetworkBehaviour for Notifications {
 				}
 			},
 			FromSwarm::ConnectionClosed(ConnectionClosed { peer_id, connection_id, .. }) => {
+				self.connection_ips.remove(&connection_id);
+
 				for set_id in (0..self.notif_protocols.len()).map(sc_peerset::SetId::from) {
 					let mut entry = if let Entry::Occupied(entry) =
 						self.peers.entry((peer_id, set_id))
@@ -1544,7 +1576,8 @@ impl NetworkBehaviour for Notifications {
 
 								trace!(target: "sub-libp2p", "PSM <= Incoming({}, {:?}).",
 									peer_id, incoming_id);
-								self.peerset.incoming(set_id, peer_id, incoming_id);
+								let ip = self.connection_ips.get(&connection_id).copied();
+								self.peerset.incoming(set_id, peer_id, ip, incoming_id);
 								self.incoming.push(IncomingPeer {
 									peer_id,
 									set_id,
@@ -2119,7 +2152,11 @@ mod tests {
 				reserved_only: false,
 			});
 
-			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig { sets })
+			sc_peerset::Peerset::from_config(sc_peerset::PeersetConfig {
+				sets,
+				peer_store: None,
+				ip_limits: Default::default(),
+			})
 		};
 
 		(
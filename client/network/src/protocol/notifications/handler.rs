@@ -384,6 +384,29 @@ enum NotificationsSinkMessage {
 }
 
 impl NotificationsSink {
+	/// Creates a sink that isn't attached to any connection, along with the stream of the
+	/// notifications sent through it.
+	///
+	/// This allows driving the protocols that write to a sink without a libp2p connection, for
+	/// instance over a simulated network.
+	pub fn new_detached(peer_id: PeerId) -> (Self, impl Stream<Item = Vec<u8>> + Send + Unpin) {
+		let (async_tx, async_rx) = mpsc::channel(ASYNC_NOTIFICATIONS_BUFFER_SIZE);
+		let (sync_tx, sync_rx) = mpsc::channel(SYNC_NOTIFICATIONS_BUFFER_SIZE);
+		let sink = NotificationsSink {
+			inner: Arc::new(NotificationsSinkInner {
+				peer_id,
+				async_channel: FuturesMutex::new(async_tx),
+				sync_channel: Mutex::new(Some(sync_tx)),
+			}),
+		};
+		let notifications = stream::select(async_rx, sync_rx).filter_map(|message| match message {
+			NotificationsSinkMessage::Notification { message } => future::ready(Some(message)),
+			NotificationsSinkMessage::ForceClose => future::ready(None),
+		});
+
+		(sink, notifications)
+	}
+
 	/// Returns the [`PeerId`] the sink is connected to.
 	pub fn peer_id(&self) -> &PeerId {
 		&self.inner.peer_id
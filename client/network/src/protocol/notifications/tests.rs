@@ -79,6 +79,8 @@ fn build_nodes() -> (Swarm<CustomProtoWithAddr>, Swarm<CustomProtoWithAddr>) {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		});
 
 		let behaviour = CustomProtoWithAddr {
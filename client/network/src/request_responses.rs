@@ -1089,6 +1089,8 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
@@ -87,6 +87,7 @@ use std::{
 		atomic::{AtomicUsize, Ordering},
 		Arc,
 	},
+	time::Duration,
 };
 
 pub use behaviour::{InboundFailure, OutboundFailure, ResponseFailure};
@@ -431,6 +432,14 @@ where
 			);
 		}
 
+		// Make the addresses of the peers we were connected to before the restart known again,
+		// so that the peerset can reconnect to them.
+		let persisted_addresses =
+			swarm.behaviour().user_protocol().peerset_known_addresses().collect::<Vec<_>>();
+		for (peer_id, addr) in persisted_addresses {
+			swarm.behaviour_mut().add_known_address(peer_id, addr);
+		}
+
 		let external_addresses = Arc::new(Mutex::new(Vec::new()));
 		let listen_addresses = Arc::new(Mutex::new(Vec::new()));
 		let peers_notifications_sinks = Arc::new(Mutex::new(HashMap::new()));
@@ -657,6 +666,27 @@ impl<B: BlockT + 'static, H: ExHashT> NetworkService<B, H> {
 		rx.await.map_err(|_| ())
 	}
 
+	/// Bans the peer for the given `duration`, or until it is unbanned if `None`.
+	///
+	/// See [`sc_peerset::PeersetHandle::ban_peer`].
+	pub fn ban_peer(&self, peer_id: PeerId, reason: String, duration: Option<Duration>) {
+		self.peerset.ban_peer(peer_id, reason, duration);
+	}
+
+	/// Lifts the ban of the peer.
+	///
+	/// See [`sc_peerset::PeersetHandle::unban_peer`].
+	pub fn unban_peer(&self, peer_id: PeerId) {
+		self.peerset.unban_peer(peer_id);
+	}
+
+	/// Get the reputations, bans and addresses of the peers known to the peerset.
+	///
+	/// Returns an error if the `NetworkWorker` is no longer running.
+	pub async fn peer_records(&self) -> Result<Vec<sc_peerset::PeerRecord>, ()> {
+		self.peerset.clone().peer_records().await
+	}
+
 	/// Utility function to extract `PeerId` from each `Multiaddr` for peer set updates.
 	///
 	/// Returns an `Err` if one of the given addresses is invalid or contains an
@@ -1250,6 +1280,20 @@ where
 				.peerset_num_discovered
 				.set(self.network_service.behaviour_mut().user_protocol().num_discovered_peers()
 					as u64);
+			let rejections = self
+				.network_service
+				.behaviour_mut()
+				.user_protocol()
+				.peerset_ip_limit_rejections();
+			for (limit, total) in [
+				("peers-per-ip", rejections.peers_per_ip),
+				("peers-per-subnet", rejections.peers_per_subnet),
+				("inbound-per-ip", rejections.inbound_per_ip),
+				("inbound-per-subnet", rejections.inbound_per_subnet),
+			] {
+				let counter = metrics.peerset_ip_limit_rejections_total.with_label_values(&[limit]);
+				counter.inc_by(total.saturating_sub(counter.get()));
+			}
 			metrics.pending_connections.set(
 				Swarm::network_info(&self.network_service).connection_counters().num_pending()
 					as u64,
@@ -1474,6 +1518,7 @@ where
 				remote,
 				protocol,
 				negotiated_fallback,
+				negotiated_version,
 				notifications_sink,
 				role,
 				received_handshake,
@@ -1494,6 +1539,7 @@ where
 					remote,
 					protocol,
 					negotiated_fallback,
+					negotiated_version,
 					role,
 					received_handshake,
 				});
@@ -1592,6 +1638,13 @@ where
 					debug!(target: "sub-libp2p", "Libp2p => Connected({:?})", peer_id);
 				}
 
+				// Only the address of an outgoing connection is known to be dialable.
+				let address = match &endpoint {
+					ConnectedPoint::Dialer { address, .. } => Some(address.clone()),
+					ConnectedPoint::Listener { .. } => None,
+				};
+				self.service.peerset.note_peer_seen(peer_id, address);
+
 				if let Some(metrics) = self.metrics.as_ref() {
 					let direction = match endpoint {
 						ConnectedPoint::Dialer { .. } => "out",
@@ -64,6 +64,7 @@ pub struct Metrics {
 	pub notifications_sizes: HistogramVec,
 	pub notifications_streams_closed_total: CounterVec<U64>,
 	pub notifications_streams_opened_total: CounterVec<U64>,
+	pub peerset_ip_limit_rejections_total: CounterVec<U64>,
 	pub peerset_num_discovered: Gauge<U64>,
 	pub pending_connections: Gauge<U64>,
 	pub pending_connections_errors_total: CounterVec<U64>,
@@ -178,6 +179,13 @@ impl Metrics {
 				),
 				&["protocol"]
 			)?, registry)?,
+			peerset_ip_limit_rejections_total: prometheus::register(CounterVec::new(
+				Opts::new(
+					"substrate_sub_libp2p_peerset_ip_limit_rejections_total",
+					"Total number of incoming connections rejected because of the IP limits"
+				),
+				&["limit"]
+			)?, registry)?,
 			peerset_num_discovered: prometheus::register(Gauge::new(
 				"substrate_sub_libp2p_peerset_num_discovered",
 				"Number of nodes stored in the peerset manager",
@@ -25,15 +25,14 @@ use crate::{
 };
 
 use codec::{Decode, Encode};
-use futures::{FutureExt, StreamExt};
-use futures_timer::Delay;
+use futures::{future::BoxFuture, FutureExt, StreamExt};
 use libp2p::PeerId;
 use lru::LruCache;
 use prometheus_endpoint::{
 	register, Gauge, GaugeVec, MetricSource, Opts, PrometheusError, Registry, SourcedGauge, U64,
 };
 
-use sc_client_api::{BlockBackend, HeaderBackend, ProofProvider};
+use sc_client_api::{AuxStore, BlockBackend, HeaderBackend, ProofProvider};
 use sc_consensus::import_queue::ImportQueueService;
 use sc_network::{
 	config::{
@@ -43,6 +42,7 @@ use sc_network::{
 	NotificationsSink, ProtocolName,
 };
 use sc_network_common::{
+	clock::{Clock, SystemClock},
 	role::Roles,
 	sync::{
 		message::{
@@ -195,7 +195,10 @@ pub struct SyncingEngine<B: BlockT, Client> {
 	event_streams: Vec<TracingUnboundedSender<SyncEvent>>,
 
 	/// Interval at which we call `tick`.
-	tick_timeout: Delay,
+	tick_timeout: BoxFuture<'static, ()>,
+
+	/// Clock scheduling `tick_timeout`.
+	clock: Arc<dyn Clock>,
 
 	/// All connected peers. Contains both full and light node peers.
 	peers: HashMap<PeerId, Peer<B>>,
@@ -237,6 +240,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -367,7 +371,8 @@ where
 				default_peers_set_num_full,
 				default_peers_set_num_light,
 				event_streams: Vec::new(),
-				tick_timeout: Delay::new(TICK_TIMEOUT),
+				tick_timeout: SystemClock.delay(TICK_TIMEOUT),
+				clock: Arc::new(SystemClock),
 				metrics: if let Some(r) = metrics_registry {
 					match Metrics::register(r, is_major_syncing.clone()) {
 						Ok(metrics) => Some(metrics),
@@ -385,6 +390,14 @@ where
 		))
 	}
 
+	/// Set the clock driving the periodic maintenance of the engine.
+	///
+	/// The system clock is used by default.
+	pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+		self.tick_timeout = clock.delay(TICK_TIMEOUT);
+		self.clock = clock;
+	}
+
 	/// Report Prometheus metrics.
 	pub fn report_metrics(&self) {
 		if let Some(metrics) = &self.metrics {
@@ -596,7 +609,7 @@ where
 
 		while let Poll::Ready(()) = self.tick_timeout.poll_unpin(cx) {
 			self.report_metrics();
-			self.tick_timeout.reset(TICK_TIMEOUT);
+			self.tick_timeout = self.clock.delay(TICK_TIMEOUT);
 		}
 
 		while let Poll::Ready(Some(event)) = self.service_rx.poll_next_unpin(cx) {
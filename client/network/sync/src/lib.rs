@@ -45,7 +45,7 @@ use log::{debug, error, info, trace, warn};
 use prost::Message;
 
 use prometheus_endpoint::{register, Counter, PrometheusError, Registry, U64};
-use sc_client_api::{BlockBackend, ProofProvider};
+use sc_client_api::{AuxStore, BlockBackend, ProofProvider};
 use sc_consensus::{
 	import_queue::ImportQueueService, BlockImportError, BlockImportStatus, IncomingBlock,
 };
@@ -131,6 +131,10 @@ const MAX_CONCURRENT_BLOCK_ANNOUNCE_VALIDATIONS_PER_PEER: usize = 4;
 /// Pick the state to sync as the latest finalized number minus this.
 const STATE_SYNC_FINALITY_THRESHOLD: u32 = 8;
 
+/// An interrupted state sync is only resumed if its target is at most this many blocks behind the
+/// median of the peers. Older states are likely pruned by the peers already.
+const STATE_SYNC_RESUME_THRESHOLD: u32 = 128;
+
 /// We use a heuristic that with a high likelihood, by the time
 /// `MAJOR_SYNC_BLOCKS` have been imported we'll be on the same
 /// chain as (or at least closer to) the peer so we want to delay
@@ -476,6 +480,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -643,7 +648,11 @@ where
 					{
 						log::debug!(target: "sync", "Starting warp state sync.");
 						if let Some(params) = self.warp_sync_params.take() {
-							self.warp_sync = Some(WarpSync::new(self.client.clone(), params));
+							self.warp_sync = Some(match self.resume_state_sync(false) {
+								Some(state_sync) =>
+									WarpSync::resume(self.client.clone(), state_sync),
+								None => WarpSync::new(self.client.clone(), params),
+							});
 						}
 					}
 				}
@@ -1094,13 +1103,16 @@ where
 							number,
 							hash,
 						);
-						self.state_sync = Some(StateSync::new(
-							self.client.clone(),
-							header,
-							None,
-							None,
-							*skip_proofs,
-						));
+						self.state_sync =
+							Some(self.resume_state_sync(*skip_proofs).unwrap_or_else(|| {
+								StateSync::new(
+									self.client.clone(),
+									header,
+									None,
+									None,
+									*skip_proofs,
+								)
+							}));
 						self.allowed_requests.set_all();
 					}
 				}
@@ -1238,6 +1250,12 @@ where
 			gap_sync.blocks.clear_peer_download(who)
 		}
 		self.peers.remove(who);
+		if let Some(state_sync) = &mut self.state_sync {
+			state_sync.peer_disconnected(who);
+		}
+		if let Some(warp_sync) = &mut self.warp_sync {
+			warp_sync.peer_disconnected(who);
+		}
 		self.extra_justifications.peer_disconnected(who);
 		self.allowed_requests.set_all();
 		self.fork_targets.retain(|_, target| {
@@ -1392,6 +1410,7 @@ where
 		+ BlockBackend<B>
 		+ HeaderMetadata<B, Error = sp_blockchain::Error>
 		+ ProofProvider<B>
+		+ AuxStore
 		+ Send
 		+ Sync
 		+ 'static,
@@ -1476,6 +1495,29 @@ where
 		Ok((sync, block_announce_config))
 	}
 
+	/// Restore an interrupted state sync from its checkpoint.
+	///
+	/// Returns `None` if there is no checkpoint or its target is too old to still be available
+	/// from the peers.
+	fn resume_state_sync(&self, skip_proofs: bool) -> Option<StateSync<B, Client>> {
+		// Only the progress record is read here, the stored state is replayed once it is resumed.
+		let (hash, number) = state::checkpoint_target::<B, _>(&*self.client)?;
+		let median = self.median_seen()?;
+		if number + STATE_SYNC_RESUME_THRESHOLD.saturated_into() < median {
+			debug!(
+				target: "sync",
+				"Not resuming state sync for #{}, peers are at #{}",
+				number,
+				median,
+			);
+			return None
+		}
+
+		let sync = StateSync::resume(self.client.clone(), skip_proofs)?;
+		info!(target: "sync", "Resuming state sync for #{} ({})", number, hash);
+		Some(sync)
+	}
+
 	/// Returns the median seen block number.
 	fn median_seen(&self) -> Option<NumberFor<B>> {
 		let mut best_seens = self.peers.values().map(|p| p.best_number).collect::<Vec<_>>();
@@ -1972,6 +2014,7 @@ where
 				format!("/{}/block-announces/1", protocol_id.as_ref()).into(),
 			)
 			.collect(),
+			versions: Vec::new(),
 			max_notification_size: MAX_BLOCK_ANNOUNCE_SIZE,
 			handshake: Some(NotificationHandshake::new(BlockAnnouncesHandshake::<B>::build(
 				roles,
@@ -2161,7 +2204,7 @@ where
 			self.send_block_request(id, request);
 		}
 
-		if let Some((id, request)) = self.state_request() {
+		for (id, request) in self.state_requests() {
 			self.send_state_request(id, request);
 		}
 
@@ -2470,49 +2513,47 @@ where
 		// Box::new(iter)
 	}
 
-	fn state_request(&mut self) -> Option<(PeerId, OpaqueStateRequest)> {
+	fn state_requests(&mut self) -> Vec<(PeerId, OpaqueStateRequest)> {
 		if self.allowed_requests.is_empty() {
-			return None
-		}
-		if (self.state_sync.is_some() || self.warp_sync.is_some()) &&
-			self.peers.iter().any(|(_, peer)| peer.state == PeerSyncState::DownloadingState)
-		{
-			// Only one pending state request is allowed.
-			return None
+			return Vec::new()
 		}
-		if let Some(sync) = &self.state_sync {
+		// Every available peer that has the target state downloads a different key range.
+		let mut requests = Vec::new();
+		if let Some(sync) = &mut self.state_sync {
 			if sync.is_complete() {
-				return None
+				return Vec::new()
 			}
 
 			for (id, peer) in self.peers.iter_mut() {
 				if peer.state.is_available() && peer.common_number >= sync.target_block_num() {
-					peer.state = PeerSyncState::DownloadingState;
-					let request = sync.next_request();
-					trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
-					self.allowed_requests.clear();
-					return Some((*id, OpaqueStateRequest(Box::new(request))))
+					if let Some(request) = sync.next_request(id) {
+						trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
+						peer.state = PeerSyncState::DownloadingState;
+						requests.push((*id, OpaqueStateRequest(Box::new(request))));
+					}
 				}
 			}
 		}
-		if let Some(sync) = &self.warp_sync {
+		if let Some(sync) = &mut self.warp_sync {
 			if sync.is_complete() {
-				return None
+				return Vec::new()
 			}
-			if let (Some(request), Some(target)) =
-				(sync.next_state_request(), sync.target_block_number())
-			{
+			if let Some(target) = sync.target_block_number() {
 				for (id, peer) in self.peers.iter_mut() {
 					if peer.state.is_available() && peer.best_number >= target {
-						trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
-						peer.state = PeerSyncState::DownloadingState;
-						self.allowed_requests.clear();
-						return Some((*id, OpaqueStateRequest(Box::new(request))))
+						if let Some(request) = sync.next_state_request(id) {
+							trace!(target: "sync", "New StateRequest for {}: {:?}", id, request);
+							peer.state = PeerSyncState::DownloadingState;
+							requests.push((*id, OpaqueStateRequest(Box::new(request))));
+						}
 					}
 				}
 			}
 		}
-		None
+		if !requests.is_empty() {
+			self.allowed_requests.clear();
+		}
+		requests
 	}
 
 	fn warp_sync_request(&mut self) -> Option<(PeerId, WarpProofRequest<B>)> {
@@ -2574,7 +2615,7 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import(*response)
+			sync.import(who, *response)
 		} else if let Some(sync) = &mut self.warp_sync {
 			debug!(
 				target: "sync",
@@ -2583,7 +2624,7 @@ where
 				response.entries.len(),
 				response.proof.len(),
 			);
-			sync.import_state(*response)
+			sync.import_state(who, *response)
 		} else {
 			debug!(target: "sync", "Ignored obsolete state response from {}", who);
 			return Err(BadPeer(*who, rep::NOT_REQUESTED))
@@ -2743,6 +2784,7 @@ where
 						);
 						self.state_sync = None;
 						self.mode = SyncMode::Full;
+						state::clear_checkpoint::<B, _>(&*self.client);
 						output.extend(self.restart());
 					}
 					let warp_sync_complete = self
@@ -2757,6 +2799,7 @@ where
 						);
 						self.warp_sync = None;
 						self.mode = SyncMode::Full;
+						state::clear_checkpoint::<B, _>(&*self.client);
 						output.extend(self.restart());
 					}
 					let gap_sync_complete =
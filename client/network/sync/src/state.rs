@@ -17,11 +17,17 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 //! State sync support.
+//!
+//! The keys of the top trie are split into [`STATE_RANGES`] disjoint ranges, which are downloaded
+//! concurrently from different peers. Every range has its own cursor and every response is
+//! verified against the target state root on its own, so a peer sending an invalid proof or
+//! disconnecting only affects its range, which is then requested from another peer.
 
 use crate::schema::v1::{StateEntry, StateRequest, StateResponse};
 use codec::{Decode, Encode};
-use log::debug;
-use sc_client_api::{CompactProof, ProofProvider};
+use libp2p::PeerId;
+use log::{debug, warn};
+use sc_client_api::{AuxStore, CompactProof, ProofProvider};
 use sc_consensus::ImportedState;
 use sc_network_common::sync::StateDownloadProgress;
 use smallvec::SmallVec;
@@ -30,22 +36,158 @@ use sp_runtime::{
 	traits::{Block as BlockT, Header, NumberFor},
 	Justifications,
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+/// Number of key ranges the state is split into.
+///
+/// The ranges are split on the first byte of the keys, so all child trie roots, which are stored
+/// under the `:child_storage:` prefix, belong to the same range.
+const STATE_RANGES: usize = 16;
+
+/// Aux storage key of the state sync checkpoint.
+const CHECKPOINT_KEY: &[u8] = b"state_sync_checkpoint";
+
+/// Prefix of the aux storage keys of the state chunks of the checkpoint.
+const CHECKPOINT_CHUNK_PREFIX: &[u8] = b"state_sync_checkpoint_chunk";
+
+/// Verified key values of a state response, grouped by the state root of the trie they belong to.
+/// The top trie has an empty state root.
+type StateChunk = Vec<(Vec<u8>, Vec<(Vec<u8>, Vec<u8>)>)>;
+
+/// Progress of a state sync, persisted in the aux storage after every imported response.
+///
+/// The imported state itself is stored separately, one [`StateChunk`] per response, under
+/// [`checkpoint_chunk_key`]. Replaying the chunks in order restores the accumulated state.
+#[derive(Encode, Decode)]
+struct Checkpoint<B: BlockT> {
+	target_header: B::Header,
+	target_body: Option<Vec<B::Extrinsic>>,
+	target_justifications: Option<Justifications>,
+	skip_proof: bool,
+	/// Cursor and completeness of every range.
+	ranges: Vec<(Vec<Vec<u8>>, bool)>,
+	imported_bytes: u64,
+	chunks: u32,
+}
+
+fn checkpoint_chunk_key(index: u32) -> Vec<u8> {
+	(CHECKPOINT_CHUNK_PREFIX, index).encode()
+}
+
+/// Removes the state sync checkpoint from the aux storage, if there is any.
+///
+/// Called once the synced state was imported, the checkpoint is kept until then so that an
+/// interruption before the import doesn't lose the downloaded state.
+pub fn clear_checkpoint<B: BlockT, Client: AuxStore>(client: &Client) {
+	let chunks = match load_checkpoint::<B, _>(client) {
+		Some(checkpoint) => checkpoint.chunks,
+		None => return,
+	};
+	let chunk_keys: Vec<_> = (0..chunks).map(checkpoint_chunk_key).collect();
+	let delete = std::iter::once(CHECKPOINT_KEY).chain(chunk_keys.iter().map(|k| &k[..]));
+	if let Err(e) = client.insert_aux(&[], &delete.collect::<Vec<_>>()) {
+		warn!(target: "sync", "Failed to remove the state sync checkpoint: {}", e);
+	}
+}
+
+/// Returns the hash and number of the target block of the checkpoint in the aux storage.
+///
+/// Unlike [`StateSync::resume`], this only reads the progress record, not the stored state.
+pub fn checkpoint_target<B: BlockT, Client: AuxStore>(
+	client: &Client,
+) -> Option<(B::Hash, NumberFor<B>)> {
+	load_checkpoint::<B, _>(client)
+		.map(|checkpoint| (checkpoint.target_header.hash(), *checkpoint.target_header.number()))
+}
+
+fn load_checkpoint<B: BlockT, Client: AuxStore>(client: &Client) -> Option<Checkpoint<B>> {
+	match client.get_aux(CHECKPOINT_KEY) {
+		Ok(Some(encoded)) => match Checkpoint::decode(&mut encoded.as_slice()) {
+			Ok(checkpoint) => Some(checkpoint),
+			Err(e) => {
+				warn!(target: "sync", "Ignoring invalid state sync checkpoint: {}", e);
+				None
+			},
+		},
+		Ok(None) => None,
+		Err(e) => {
+			warn!(target: "sync", "Failed to read the state sync checkpoint: {}", e);
+			None
+		},
+	}
+}
+
+/// A range of the top trie keys, downloaded independently of the other ranges.
+///
+/// The range contains the keys after `start` up to and including `end`.
+struct StateRange {
+	/// First byte of the keys of the range.
+	start: u8,
+	/// Last key of the range, `None` for the last range.
+	end: Option<Vec<u8>>,
+	/// Position of the last imported key, the start of the next request.
+	last_key: SmallVec<[Vec<u8>; 2]>,
+	/// Whether all keys of the range were imported.
+	complete: bool,
+	/// The peer the range is currently requested from.
+	peer: Option<PeerId>,
+}
+
+impl StateRange {
+	/// Split the key space into `count` ranges of equal width.
+	fn split(count: usize) -> Vec<Self> {
+		let boundary = |index: usize| (index * 256 / count) as u8;
+		(0..count)
+			.map(|index| StateRange {
+				start: boundary(index),
+				end: (index + 1 < count).then(|| vec![boundary(index + 1)]),
+				// Requests start after the given key, the first range starts at the beginning.
+				last_key: if index == 0 {
+					SmallVec::new()
+				} else {
+					SmallVec::from_elem(vec![boundary(index)], 1)
+				},
+				complete: false,
+				peer: None,
+			})
+			.collect()
+	}
+
+	/// Estimated number of downloaded key prefixes out of the 256 possible first key bytes.
+	fn progress(&self) -> u32 {
+		let end = self.end.as_ref().map_or(256, |end| end[0] as u32);
+		if self.complete {
+			return end - self.start as u32
+		}
+		let cursor = self.last_key.first().and_then(|last| last.first()).copied();
+		cursor.map_or(0, |cursor| (cursor as u32).saturating_sub(self.start as u32))
+	}
+}
 
 /// State sync state machine. Accumulates partial state data until it
 /// is ready to be imported.
+///
+/// The verified state is checkpointed in the aux storage as it arrives, so that an interrupted
+/// state sync can resume from the last verified keys, see [`StateSync::resume`].
 pub struct StateSync<B: BlockT, Client> {
 	target_block: B::Hash,
 	target_header: B::Header,
 	target_root: B::Hash,
 	target_body: Option<Vec<B::Extrinsic>>,
 	target_justifications: Option<Justifications>,
-	last_key: SmallVec<[Vec<u8>; 2]>,
+	ranges: Vec<StateRange>,
+	/// Peers that sent a bad response, they are not asked again until they reconnect.
+	failed_peers: HashSet<PeerId>,
 	state: HashMap<Vec<u8>, (Vec<(Vec<u8>, Vec<u8>)>, Vec<Vec<u8>>)>,
 	complete: bool,
 	client: Arc<Client>,
 	imported_bytes: u64,
 	skip_proof: bool,
+	/// Number of chunks stored in the checkpoint, `None` if checkpointing failed.
+	checkpoint_chunks: Option<u32>,
 }
 
 /// Import state chunk result.
@@ -61,9 +203,12 @@ pub enum ImportResult<B: BlockT> {
 impl<B, Client> StateSync<B, Client>
 where
 	B: BlockT,
-	Client: ProofProvider<B> + Send + Sync + 'static,
+	Client: ProofProvider<B> + AuxStore + Send + Sync + 'static,
 {
 	///  Create a new instance.
+	///
+	/// Continues from the checkpoint in the aux storage if it has the same target, any other
+	/// checkpoint is removed.
 	pub fn new(
 		client: Arc<Client>,
 		target_header: B::Header,
@@ -71,6 +216,15 @@ where
 		target_justifications: Option<Justifications>,
 		skip_proof: bool,
 	) -> Self {
+		if let Some(checkpoint) = load_checkpoint::<B, _>(&*client) {
+			if checkpoint.target_header.hash() == target_header.hash() {
+				if let Some(sync) = Self::resume(client.clone(), skip_proof) {
+					return sync
+				}
+			}
+			clear_checkpoint::<B, _>(&*client);
+		}
+
 		Self {
 			client,
 			target_block: target_header.hash(),
@@ -78,38 +232,147 @@ where
 			target_header,
 			target_body,
 			target_justifications,
-			last_key: SmallVec::default(),
+			ranges: StateRange::split(STATE_RANGES),
+			failed_peers: HashSet::new(),
 			state: HashMap::default(),
 			complete: false,
 			imported_bytes: 0,
 			skip_proof,
+			checkpoint_chunks: Some(0),
+		}
+	}
+
+	/// Restore the state sync from the checkpoint in the aux storage.
+	///
+	/// Returns `None` if there is no checkpoint, it can't be read or it was made with a different
+	/// `skip_proof` setting.
+	pub fn resume(client: Arc<Client>, skip_proof: bool) -> Option<Self> {
+		let checkpoint = load_checkpoint::<B, _>(&*client)?;
+		if checkpoint.skip_proof != skip_proof || checkpoint.ranges.len() != STATE_RANGES {
+			return None
+		}
+
+		let mut ranges = StateRange::split(STATE_RANGES);
+		for (range, (last_key, complete)) in ranges.iter_mut().zip(checkpoint.ranges) {
+			range.last_key = last_key.into();
+			range.complete = complete;
+		}
+		let mut sync = Self {
+			target_block: checkpoint.target_header.hash(),
+			target_root: *checkpoint.target_header.state_root(),
+			target_header: checkpoint.target_header,
+			target_body: checkpoint.target_body,
+			target_justifications: checkpoint.target_justifications,
+			ranges,
+			failed_peers: HashSet::new(),
+			state: HashMap::default(),
+			complete: false,
+			imported_bytes: 0,
+			skip_proof,
+			checkpoint_chunks: Some(checkpoint.chunks),
+			client,
+		};
+		for index in 0..checkpoint.chunks {
+			let chunk = match sync.client.get_aux(&checkpoint_chunk_key(index)) {
+				Ok(Some(encoded)) => StateChunk::decode(&mut encoded.as_slice()).ok(),
+				_ => None,
+			};
+			match chunk {
+				Some(chunk) => sync.import_chunk(chunk),
+				None => {
+					warn!(target: "sync", "Ignoring state sync checkpoint with missing state");
+					return None
+				},
+			}
+		}
+		sync.imported_bytes = checkpoint.imported_bytes;
+
+		debug!(
+			target: "sync",
+			"Resuming state sync for #{} ({}) from {} stored chunks",
+			sync.target_header.number(),
+			sync.target_block,
+			checkpoint.chunks,
+		);
+		Some(sync)
+	}
+
+	///  Validate and import a state response of the given peer.
+	pub fn import(&mut self, who: &PeerId, response: StateResponse) -> ImportResult<B> {
+		let index = match self.ranges.iter().position(|range| range.peer == Some(*who)) {
+			Some(index) => index,
+			None => {
+				debug!(target: "sync", "Unexpected state response from {}", who);
+				return ImportResult::BadResponse
+			},
+		};
+		self.ranges[index].peer = None;
+
+		let (mut chunk, mut complete) = match self.verify_response(index, response) {
+			Some(verified) => verified,
+			None => {
+				self.failed_peers.insert(*who);
+				return ImportResult::BadResponse
+			},
+		};
+		if let Some(end) = self.ranges[index].end.clone() {
+			complete |= self.truncate_chunk(&end, &mut chunk);
+		}
+		self.ranges[index].complete = complete;
+
+		if self.ranges.iter().all(|range| range.complete) {
+			// The checkpoint is cleared after the state was imported, see `clear_checkpoint`.
+			self.import_chunk(chunk);
+			self.complete = true;
+			ImportResult::Import(
+				self.target_block,
+				self.target_header.clone(),
+				ImportedState {
+					block: self.target_block,
+					state: std::mem::take(&mut self.state).into(),
+				},
+				self.target_body.clone(),
+				self.target_justifications.clone(),
+			)
+		} else {
+			let encoded_chunk = chunk.encode();
+			self.import_chunk(chunk);
+			self.store_checkpoint(&encoded_chunk);
+			ImportResult::Continue
 		}
 	}
 
-	///  Validate and import a state response.
-	pub fn import(&mut self, response: StateResponse) -> ImportResult<B> {
+	/// Verify the response for the range at `index` and advance the cursor of the range.
+	///
+	/// Returns the verified key values and whether the response reached the end of the state.
+	fn verify_response(
+		&mut self,
+		index: usize,
+		response: StateResponse,
+	) -> Option<(StateChunk, bool)> {
 		if response.entries.is_empty() && response.proof.is_empty() {
 			debug!(target: "sync", "Bad state response");
-			return ImportResult::BadResponse
+			return None
 		}
 		if !self.skip_proof && response.proof.is_empty() {
 			debug!(target: "sync", "Missing proof");
-			return ImportResult::BadResponse
+			return None
 		}
-		let complete = if !self.skip_proof {
+		let last_key = &mut self.ranges[index].last_key;
+		if !self.skip_proof {
 			debug!(target: "sync", "Importing state from {} trie nodes", response.proof.len());
 			let proof_size = response.proof.len() as u64;
 			let proof = match CompactProof::decode(&mut response.proof.as_ref()) {
 				Ok(proof) => proof,
 				Err(e) => {
 					debug!(target: "sync", "Error decoding proof: {:?}", e);
-					return ImportResult::BadResponse
+					return None
 				},
 			};
 			let (values, completed) = match self.client.verify_range_proof(
 				self.target_root,
 				proof,
-				self.last_key.as_slice(),
+				last_key.as_slice(),
 			) {
 				Err(e) => {
 					debug!(
@@ -117,70 +380,33 @@ where
 						"StateResponse failed proof verification: {}",
 						e,
 					);
-					return ImportResult::BadResponse
+					return None
 				},
 				Ok(values) => values,
 			};
 			debug!(target: "sync", "Imported with {} keys", values.len());
 
 			let complete = completed == 0;
-			if !complete && !values.update_last_key(completed, &mut self.last_key) {
+			if !complete && !values.update_last_key(completed, last_key) {
 				debug!(target: "sync", "Error updating key cursor, depth: {}", completed);
 			};
 
-			for values in values.0 {
-				let key_values = if values.state_root.is_empty() {
-					// Read child trie roots.
-					values
-						.key_values
-						.into_iter()
-						.filter(|key_value| {
-							if well_known_keys::is_child_storage_key(key_value.0.as_slice()) {
-								self.state
-									.entry(key_value.1.clone())
-									.or_default()
-									.1
-									.push(key_value.0.clone());
-								false
-							} else {
-								true
-							}
-						})
-						.collect()
-				} else {
-					values.key_values
-				};
-				let mut entry = self.state.entry(values.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child_trie with same root.
-					// Warning this will not work with parallel download.
-				} else if entry.0.is_empty() {
-					for (key, _value) in key_values.iter() {
-						self.imported_bytes += key.len() as u64;
-					}
-
-					entry.0 = key_values;
-				} else {
-					for (key, value) in key_values {
-						self.imported_bytes += key.len() as u64;
-						entry.0.push((key, value))
-					}
-				}
-			}
 			self.imported_bytes += proof_size;
-			complete
+			let chunk = values.0.into_iter().map(|values| (values.state_root, values.key_values));
+			Some((chunk.collect(), complete))
 		} else {
 			let mut complete = true;
 			// if the trie is a child trie and one of its parent trie is empty,
 			// the parent cursor stays valid.
 			// Empty parent trie content only happens when all the response content
 			// is part of a single child trie.
-			if self.last_key.len() == 2 && response.entries[0].entries.is_empty() {
+			if last_key.len() == 2 && response.entries[0].entries.is_empty() {
 				// Do not remove the parent trie position.
-				self.last_key.pop();
+				last_key.pop();
 			} else {
-				self.last_key.clear();
+				last_key.clear();
 			}
+			let mut chunk = StateChunk::new();
 			for state in response.entries {
 				debug!(
 					target: "sync",
@@ -191,56 +417,150 @@ where
 
 				if !state.complete {
 					if let Some(e) = state.entries.last() {
-						self.last_key.push(e.key.clone());
+						last_key.push(e.key.clone());
 					}
 					complete = false;
 				}
-				let is_top = state.state_root.is_empty();
-				let entry = self.state.entry(state.state_root).or_default();
-				if entry.0.len() > 0 && entry.1.len() > 1 {
-					// Already imported child trie with same root.
-				} else {
-					let mut child_roots = Vec::new();
-					for StateEntry { key, value } in state.entries {
-						// Skip all child key root (will be recalculated on import).
-						if is_top && well_known_keys::is_child_storage_key(key.as_slice()) {
-							child_roots.push((value, key));
+				let key_values = state
+					.entries
+					.into_iter()
+					.map(|StateEntry { key, value }| (key, value))
+					.collect();
+				chunk.push((state.state_root, key_values));
+			}
+			Some((chunk, complete))
+		}
+	}
+
+	/// Remove the top trie keys after `end` from the chunk, together with their child tries.
+	///
+	/// Returns `true` if any keys were removed, i.e. the range ending at `end` is complete.
+	fn truncate_chunk(&self, end: &[u8], chunk: &mut StateChunk) -> bool {
+		let mut truncated = false;
+		let mut child_roots = HashSet::new();
+		for (state_root, key_values) in chunk.iter_mut() {
+			if !state_root.is_empty() {
+				continue
+			}
+			if let Some(pos) = key_values.iter().position(|(key, _)| key.as_slice() > end) {
+				key_values.truncate(pos);
+				truncated = true;
+			}
+			child_roots.extend(
+				key_values
+					.iter()
+					.filter(|(key, _)| well_known_keys::is_child_storage_key(key))
+					.map(|(_, root)| root.clone()),
+			);
+		}
+		if truncated {
+			// Child tries of the removed keys are downloaded by the range they belong to. The
+			// child trie the cursor is in was already announced by a previous response.
+			chunk.retain(|(state_root, _)| {
+				state_root.is_empty() ||
+					child_roots.contains(state_root) ||
+					matches!(self.state.get(state_root), Some(entry) if !entry.1.is_empty())
+			});
+		}
+		truncated
+	}
+
+	/// Add the verified key values to the accumulated state.
+	fn import_chunk(&mut self, chunk: StateChunk) {
+		for (state_root, key_values) in chunk {
+			let key_values: Vec<_> = if state_root.is_empty() {
+				// Read child trie roots, they will be recalculated on import.
+				key_values
+					.into_iter()
+					.filter(|key_value| {
+						if well_known_keys::is_child_storage_key(key_value.0.as_slice()) {
+							self.state
+								.entry(key_value.1.clone())
+								.or_default()
+								.1
+								.push(key_value.0.clone());
+							false
 						} else {
-							self.imported_bytes += key.len() as u64;
-							entry.0.push((key, value))
+							true
 						}
-					}
-					for (root, storage_key) in child_roots {
-						self.state.entry(root).or_default().1.push(storage_key);
-					}
+					})
+					.collect()
+			} else {
+				key_values
+			};
+			let entry = self.state.entry(state_root).or_default();
+			if entry.0.len() > 0 && entry.1.len() > 1 {
+				// Already imported child_trie with same root.
+				// This relies on all child tries being downloaded by the same range, see
+				// `STATE_RANGES`.
+			} else {
+				for (key, value) in key_values {
+					self.imported_bytes += key.len() as u64;
+					entry.0.push((key, value))
 				}
 			}
-			complete
+		}
+	}
+
+	/// Store the progress and the given verified chunk in the aux storage.
+	fn store_checkpoint(&mut self, encoded_chunk: &[u8]) {
+		let chunks = match self.checkpoint_chunks {
+			Some(chunks) => chunks,
+			None => return,
 		};
-		if complete {
-			self.complete = true;
-			ImportResult::Import(
-				self.target_block,
-				self.target_header.clone(),
-				ImportedState {
-					block: self.target_block,
-					state: std::mem::take(&mut self.state).into(),
-				},
-				self.target_body.clone(),
-				self.target_justifications.clone(),
-			)
-		} else {
-			ImportResult::Continue
+		let chunk_key = checkpoint_chunk_key(chunks);
+		let checkpoint = Checkpoint::<B> {
+			target_header: self.target_header.clone(),
+			target_body: self.target_body.clone(),
+			target_justifications: self.target_justifications.clone(),
+			skip_proof: self.skip_proof,
+			ranges: self
+				.ranges
+				.iter()
+				.map(|range| (range.last_key.to_vec(), range.complete))
+				.collect(),
+			imported_bytes: self.imported_bytes,
+			chunks: chunks + 1,
+		}
+		.encode();
+
+		match self
+			.client
+			.insert_aux(&[(&chunk_key[..], encoded_chunk), (CHECKPOINT_KEY, &checkpoint[..])], &[])
+		{
+			Ok(()) => self.checkpoint_chunks = Some(chunks + 1),
+			Err(e) => {
+				// The stored state would be missing this chunk, don't resume from it.
+				warn!(target: "sync", "Failed to store the state sync checkpoint: {}", e);
+				clear_checkpoint::<B, _>(&*self.client);
+				self.checkpoint_chunks = None;
+			},
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_request(&self) -> StateRequest {
-		StateRequest {
+	/// Produce the next state request for the given peer.
+	///
+	/// Returns `None` if there is no range left to request, if the peer is already downloading a
+	/// range or if it sent a bad response before.
+	pub fn next_request(&mut self, who: &PeerId) -> Option<StateRequest> {
+		if self.failed_peers.contains(who) || self.ranges.iter().any(|r| r.peer == Some(*who)) {
+			return None
+		}
+		let range = self.ranges.iter_mut().find(|range| !range.complete && range.peer.is_none())?;
+		range.peer = Some(*who);
+		Some(StateRequest {
 			block: self.target_block.encode(),
-			start: self.last_key.clone().into_vec(),
+			start: range.last_key.clone().into_vec(),
 			no_proof: self.skip_proof,
+		})
+	}
+
+	/// Handle a disconnected peer. The range it was downloading is requested from another peer.
+	pub fn peer_disconnected(&mut self, who: &PeerId) {
+		for range in self.ranges.iter_mut().filter(|range| range.peer == Some(*who)) {
+			range.peer = None;
 		}
+		self.failed_peers.remove(who);
 	}
 
 	/// Check if the state is complete.
@@ -260,8 +580,399 @@ where
 
 	/// Returns state sync estimated progress.
 	pub fn progress(&self) -> StateDownloadProgress {
-		let cursor = *self.last_key.get(0).and_then(|last| last.get(0)).unwrap_or(&0u8);
-		let percent_done = cursor as u32 * 100 / 256;
+		let done: u32 = self.ranges.iter().map(StateRange::progress).sum();
+		let percent_done = done * 100 / 256;
 		StateDownloadProgress { percentage: percent_done, size: self.imported_bytes }
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use sp_runtime::traits::Header as _;
+	use sp_test_primitives::{Block, Header};
+	use std::sync::Mutex;
+
+	/// Client that only provides the aux storage, the tests sync without proofs.
+	#[derive(Default)]
+	struct TestClient {
+		aux: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+	}
+
+	impl AuxStore for TestClient {
+		fn insert_aux<
+			'a,
+			'b: 'a,
+			'c: 'a,
+			I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+			D: IntoIterator<Item = &'a &'b [u8]>,
+		>(
+			&self,
+			insert: I,
+			delete: D,
+		) -> sp_blockchain::Result<()> {
+			let mut aux = self.aux.lock().unwrap();
+			for (key, value) in insert {
+				aux.insert(key.to_vec(), value.to_vec());
+			}
+			for key in delete {
+				aux.remove(*key);
+			}
+			Ok(())
+		}
+
+		fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+			Ok(self.aux.lock().unwrap().get(key).cloned())
+		}
+	}
+
+	impl ProofProvider<Block> for TestClient {
+		fn read_proof(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: &mut dyn Iterator<Item = &[u8]>,
+		) -> sp_blockchain::Result<sc_client_api::StorageProof> {
+			unimplemented!()
+		}
+
+		fn read_child_proof(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: &sp_core::storage::ChildInfo,
+			_: &mut dyn Iterator<Item = &[u8]>,
+		) -> sp_blockchain::Result<sc_client_api::StorageProof> {
+			unimplemented!()
+		}
+
+		fn execution_proof(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: &str,
+			_: &[u8],
+		) -> sp_blockchain::Result<(Vec<u8>, sc_client_api::StorageProof)> {
+			unimplemented!()
+		}
+
+		fn read_proof_collection(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: &[Vec<u8>],
+			_: usize,
+		) -> sp_blockchain::Result<(CompactProof, u32)> {
+			unimplemented!()
+		}
+
+		fn read_range_proof(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: Option<&sp_core::storage::ChildInfo>,
+			_: Option<&[u8]>,
+			_: Option<&[u8]>,
+			_: Option<&[u8]>,
+			_: u32,
+			_: usize,
+		) -> sp_blockchain::Result<CompactProof> {
+			unimplemented!()
+		}
+
+		fn storage_collection(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: &[Vec<u8>],
+			_: usize,
+		) -> sp_blockchain::Result<Vec<(sp_state_machine::KeyValueStorageLevel, bool)>> {
+			unimplemented!()
+		}
+
+		fn verify_range_proof(
+			&self,
+			_: <Block as BlockT>::Hash,
+			_: CompactProof,
+			_: &[Vec<u8>],
+		) -> sp_blockchain::Result<(sp_state_machine::KeyValueStates, usize)> {
+			unimplemented!()
+		}
+	}
+
+	fn header(number: u64) -> Header {
+		Header::new(
+			number,
+			Default::default(),
+			Default::default(),
+			Default::default(),
+			Default::default(),
+		)
+	}
+
+	/// Answers the request with the next 10 entries of the top trie holding the keys `0..100`.
+	fn state_response(request: &StateRequest) -> StateResponse {
+		let start = request.start.first().map_or(0, |key| key[0] + 1);
+		let end = (start + 10).min(100);
+		StateResponse {
+			entries: vec![crate::schema::v1::KeyValueStateEntry {
+				state_root: Vec::new(),
+				entries: (start..end)
+					.map(|i| StateEntry { key: vec![i], value: vec![i; 4] })
+					.collect(),
+				complete: end == 100,
+			}],
+			proof: Vec::new(),
+		}
+	}
+
+	fn expected_state() -> Vec<(Vec<u8>, Vec<u8>)> {
+		(0..100).map(|i| (vec![i], vec![i; 4])).collect()
+	}
+
+	fn imported_state(state: ImportedState<Block>) -> Vec<(Vec<u8>, Vec<u8>)> {
+		let mut key_values = state.state.0[0].key_values.clone();
+		key_values.sort();
+		key_values
+	}
+
+	/// Request and import until the state is complete, answering for `peers` in turn.
+	fn sync_to_end(
+		sync: &mut StateSync<Block, TestClient>,
+		peers: &[PeerId],
+	) -> Vec<(Vec<u8>, Vec<u8>)> {
+		loop {
+			let requests: Vec<_> = peers
+				.iter()
+				.filter_map(|peer| sync.next_request(peer).map(|request| (*peer, request)))
+				.collect();
+			assert!(!requests.is_empty());
+			for (peer, request) in requests {
+				match sync.import(&peer, state_response(&request)) {
+					ImportResult::Import(_, _, state, _, _) => return imported_state(state),
+					ImportResult::Continue => {},
+					ImportResult::BadResponse => panic!("Unexpected bad response"),
+				}
+			}
+		}
+	}
+
+	/// Import a single response of `peer`.
+	fn import_next(sync: &mut StateSync<Block, TestClient>, peer: &PeerId) {
+		let request = sync.next_request(peer).unwrap();
+		assert!(matches!(sync.import(peer, state_response(&request)), ImportResult::Continue));
+	}
+
+	#[test]
+	fn state_sync_downloads_ranges_in_parallel() {
+		let client = Arc::new(TestClient::default());
+		let mut sync = StateSync::new(client, header(10), None, None, true);
+		let peers: Vec<_> = (0..4).map(|_| PeerId::random()).collect();
+
+		let starts: Vec<_> =
+			peers.iter().map(|peer| sync.next_request(peer).unwrap().start).collect();
+		assert_eq!(starts, vec![vec![], vec![vec![16]], vec![vec![32]], vec![vec![48]]]);
+		// Peers only download one range at a time.
+		assert!(sync.next_request(&peers[0]).is_none());
+
+		// Keys after the end of a range are dropped, the range is complete then.
+		let request = StateRequest { start: starts[1].clone(), ..Default::default() };
+		assert!(matches!(sync.import(&peers[1], state_response(&request)), ImportResult::Continue));
+		assert!(!sync.ranges[1].complete);
+		import_next(&mut sync, &peers[1]);
+		assert!(sync.ranges[1].complete);
+		assert_eq!(
+			sync.state[&Vec::new()].0,
+			(17..=32).map(|i| (vec![i], vec![i; 4])).collect::<Vec<_>>()
+		);
+
+		let mut sync =
+			StateSync::new(Arc::new(TestClient::default()), header(10), None, None, true);
+		assert_eq!(sync_to_end(&mut sync, &peers), expected_state());
+	}
+
+	#[test]
+	fn state_sync_verifies_range_proofs_against_target_root() {
+		use sc_client_api::StorageProvider;
+		use sp_blockchain::HeaderBackend;
+		use sp_core::storage::ChildInfo;
+		use substrate_test_runtime_client::{
+			runtime::Block, DefaultTestClientBuilderExt, TestClient as RuntimeClient,
+			TestClientBuilder, TestClientBuilderExt,
+		};
+
+		// Top trie keys in all ranges and one child trie on each side of `boundary`.
+		let boundary = b":child_storage:default:m".to_vec();
+		let child_tries = [ChildInfo::new_default(b"alpha"), ChildInfo::new_default(b"zulu")];
+		let child_key_values: Vec<_> = (0..16u8).map(|i| (vec![i], vec![i; 256])).collect();
+		let mut builder = TestClientBuilder::new();
+		for first in (0..=255u8).step_by(8) {
+			for i in 0..8u8 {
+				builder = builder.add_extra_storage(vec![first, i], vec![i; 256]);
+			}
+		}
+		for child_info in &child_tries {
+			for (key, value) in &child_key_values {
+				builder = builder.add_extra_child_storage(child_info, key.clone(), value.clone());
+			}
+		}
+		let server = builder.build();
+		let hash = server.chain_info().genesis_hash;
+		let target = server.header(hash).unwrap().unwrap();
+		// A node with a different state, its proofs don't match the target state root.
+		let other = substrate_test_runtime_client::new();
+
+		let client = Arc::new(substrate_test_runtime_client::new());
+		let mut sync = StateSync::<Block, _>::new(client, target, None, None, false);
+		// Split the range holding the `:child_storage:` prefix between the two child tries.
+		let index = sync.ranges.iter().position(|range| range.start == b':' & 0xf0).unwrap();
+		let end = sync.ranges[index].end.replace(boundary.clone());
+		sync.ranges.insert(
+			index + 1,
+			StateRange {
+				start: b':',
+				end,
+				last_key: SmallVec::from_elem(boundary, 1),
+				complete: false,
+				peer: None,
+			},
+		);
+
+		let response = |client: &RuntimeClient, request: &StateRequest| {
+			let (proof, _) = client.read_proof_collection(hash, &request.start, 1024).unwrap();
+			StateResponse { entries: Vec::new(), proof: proof.encode() }
+		};
+
+		// A proof of another state is rejected.
+		let bad = PeerId::random();
+		let request = sync.next_request(&bad).unwrap();
+		assert!(!request.no_proof);
+		assert!(matches!(sync.import(&bad, response(&other, &request)), ImportResult::BadResponse));
+
+		let peers: Vec<_> = (0..4).map(|_| PeerId::random()).collect();
+		let mut responses = 0;
+		let state = 'sync: loop {
+			let requests: Vec<_> = peers
+				.iter()
+				.filter_map(|peer| sync.next_request(peer).map(|request| (*peer, request)))
+				.collect();
+			assert!(!requests.is_empty());
+			for (peer, request) in requests {
+				responses += 1;
+				assert!(responses < 1000, "State sync makes no progress");
+				match sync.import(&peer, response(&server, &request)) {
+					ImportResult::Import(_, _, state, _, _) => break 'sync state.state,
+					ImportResult::Continue => {},
+					ImportResult::BadResponse => panic!("Unexpected bad response"),
+				}
+			}
+		};
+		// The ranges were downloaded in several responses each.
+		assert!(responses > 2 * sync.ranges.len());
+
+		let mut top = state
+			.0
+			.iter()
+			.find(|level| level.state_root.is_empty())
+			.unwrap()
+			.key_values
+			.clone();
+		top.sort();
+		let expected_top: Vec<_> = server
+			.storage_pairs(hash, None, None)
+			.unwrap()
+			.map(|(key, value)| (key.0, value.0))
+			.filter(|(key, _)| !well_known_keys::is_child_storage_key(key))
+			.collect();
+		assert_eq!(top, expected_top);
+
+		for child_info in &child_tries {
+			let parent_key = child_info.prefixed_storage_key().into_inner();
+			let level =
+				state.0.iter().find(|level| level.parent_storage_keys == [parent_key.clone()]);
+			let mut key_values = level.unwrap().key_values.clone();
+			key_values.sort();
+			assert_eq!(key_values, child_key_values);
+		}
+	}
+
+	#[test]
+	fn state_sync_retries_range_on_other_peer() {
+		let client = Arc::new(TestClient::default());
+		let mut sync = StateSync::new(client, header(10), None, None, true);
+		let (bad, slow, good) = (PeerId::random(), PeerId::random(), PeerId::random());
+
+		// A bad response fails the range of the peer, which is not asked again.
+		let request = sync.next_request(&bad).unwrap();
+		assert!(request.start.is_empty());
+		let response = StateResponse { entries: Vec::new(), proof: Vec::new() };
+		assert!(matches!(sync.import(&bad, response), ImportResult::BadResponse));
+		assert!(sync.next_request(&bad).is_none());
+		assert_eq!(sync.next_request(&good).unwrap(), request);
+
+		// The range of a disconnected peer is requested from another peer.
+		let request = sync.next_request(&slow).unwrap();
+		sync.peer_disconnected(&slow);
+		assert!(matches!(sync.import(&slow, state_response(&request)), ImportResult::BadResponse));
+		assert!(matches!(sync.import(&good, state_response(&request)), ImportResult::Continue));
+		assert_eq!(sync.next_request(&good).unwrap(), request);
+
+		// Reconnected peers are asked again.
+		sync.peer_disconnected(&bad);
+		assert!(sync.next_request(&bad).is_some());
+	}
+
+	#[test]
+	fn state_sync_resumes_from_checkpoint() {
+		let client = Arc::new(TestClient::default());
+		let mut sync = StateSync::new(client.clone(), header(10), None, None, true);
+		let peers: Vec<_> = (0..3).map(|_| PeerId::random()).collect();
+		for peer in &peers {
+			import_next(&mut sync, peer);
+		}
+		import_next(&mut sync, &peers[0]);
+		let progress = sync.progress();
+		let requests: Vec<_> = peers.iter().map(|peer| sync.next_request(peer)).collect();
+		drop(sync);
+
+		// A checkpoint made without proofs can't be used to sync with them.
+		assert!(StateSync::<Block, _>::resume(client.clone(), false).is_none());
+
+		let mut sync = StateSync::<Block, _>::resume(client.clone(), true).unwrap();
+		assert_eq!(sync.target(), header(10).hash());
+		assert_eq!(sync.progress(), progress);
+		assert_eq!(peers.iter().map(|peer| sync.next_request(peer)).collect::<Vec<_>>(), requests);
+		for peer in &peers {
+			sync.peer_disconnected(peer);
+		}
+		assert_eq!(sync_to_end(&mut sync, &peers), expected_state());
+
+		// The checkpoint is kept until the state was imported.
+		assert_eq!(checkpoint_target::<Block, _>(&*client), Some((header(10).hash(), 10)));
+		clear_checkpoint::<Block, _>(&*client);
+		assert_eq!(checkpoint_target::<Block, _>(&*client), None);
+		assert!(client.aux.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn state_sync_continues_checkpoint_of_same_target() {
+		let client = Arc::new(TestClient::default());
+		let peer = PeerId::random();
+		let mut sync = StateSync::new(client.clone(), header(10), None, None, true);
+		import_next(&mut sync, &peer);
+		let next_request = sync.next_request(&peer);
+		drop(sync);
+
+		let mut sync = StateSync::new(client, header(10), None, None, true);
+		assert_eq!(sync.next_request(&peer), next_request);
+	}
+
+	#[test]
+	fn state_sync_discards_checkpoint_of_other_target() {
+		let client = Arc::new(TestClient::default());
+		let peer = PeerId::random();
+		let mut sync = StateSync::new(client.clone(), header(10), None, None, true);
+		import_next(&mut sync, &peer);
+		drop(sync);
+
+		let mut sync = StateSync::new(client.clone(), header(11), None, None, true);
+		assert_eq!(sync.target(), header(11).hash());
+		assert!(sync.next_request(&peer).unwrap().start.is_empty());
+		assert!(StateSync::<Block, _>::resume(client.clone(), true).is_none());
+		assert!(client.aux.lock().unwrap().is_empty());
+	}
+}
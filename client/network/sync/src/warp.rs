@@ -24,8 +24,9 @@ use crate::{
 	state::{ImportResult, StateSync},
 };
 use futures::FutureExt;
+use libp2p::PeerId;
 use log::error;
-use sc_client_api::ProofProvider;
+use sc_client_api::{AuxStore, ProofProvider};
 use sc_network_common::sync::{
 	message::{BlockAttributes, BlockData, BlockRequest, Direction, FromBlock},
 	warp::{
@@ -48,7 +49,10 @@ enum Phase<B: BlockT, Client> {
 	PendingTargetBlock {
 		target_block: Option<oneshot::Receiver<B::Header>>,
 	},
-	TargetBlock(B::Header),
+	TargetBlock {
+		hash: B::Hash,
+		number: NumberFor<B>,
+	},
 	State(StateSync<B, Client>),
 }
 
@@ -78,11 +82,12 @@ pub struct WarpSync<B: BlockT, Client> {
 impl<B, Client> WarpSync<B, Client>
 where
 	B: BlockT,
-	Client: HeaderBackend<B> + ProofProvider<B> + 'static,
+	Client: HeaderBackend<B> + ProofProvider<B> + AuxStore + 'static,
 {
 	/// Create a new instance. When passing a warp sync provider we will be checking for proof and
 	/// authorities. Alternatively we can pass a target block when we want to skip downloading
-	/// proofs, in this case we will continue polling until the target block is known.
+	/// proofs, in this case we will continue polling until the target block is known. A target
+	/// block given by hash and number is downloaded right away.
 	pub fn new(client: Arc<Client>, warp_sync_params: WarpSyncParams<B>) -> Self {
 		let last_hash = client.hash(Zero::zero()).unwrap().expect("Genesis header always exists");
 		match warp_sync_params {
@@ -100,9 +105,18 @@ where
 				phase: Phase::PendingTargetBlock { target_block: Some(block) },
 				total_proof_bytes: 0,
 			},
+			WarpSyncParams::WithTargetBlock { hash, number } =>
+				Self { client, phase: Phase::TargetBlock { hash, number }, total_proof_bytes: 0 },
 		}
 	}
 
+	/// Create a new instance that continues the given state sync, skipping the warp proofs.
+	///
+	/// Used to resume an interrupted warp sync, see [`StateSync::resume`].
+	pub fn resume(client: Arc<Client>, state_sync: StateSync<B, Client>) -> Self {
+		Self { client, phase: Phase::State(state_sync), total_proof_bytes: 0 }
+	}
+
 	/// Poll to make progress.
 	///
 	/// This only makes progress when `phase = Phase::PendingTargetBlock` and the pending block was
@@ -112,7 +126,8 @@ where
 			&mut self.phase
 		{
 			match target_block.poll_unpin(cx) {
-				Poll::Ready(Ok(target)) => Phase::TargetBlock(target),
+				Poll::Ready(Ok(target)) =>
+					Phase::TargetBlock { hash: target.hash(), number: *target.number() },
 				Poll::Ready(Err(e)) => {
 					error!(target: "sync", "Failed to get target block. Error: {:?}",e);
 					Phase::PendingTargetBlock { target_block: None }
@@ -126,21 +141,23 @@ where
 		self.phase = new_phase;
 	}
 
-	///  Validate and import a state response.
-	pub fn import_state(&mut self, response: StateResponse) -> ImportResult<B> {
+	///  Validate and import a state response of the given peer.
+	pub fn import_state(&mut self, who: &PeerId, response: StateResponse) -> ImportResult<B> {
 		match &mut self.phase {
-			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } => {
+			Phase::WarpProof { .. } |
+			Phase::TargetBlock { .. } |
+			Phase::PendingTargetBlock { .. } => {
 				log::debug!(target: "sync", "Unexpected state response");
 				ImportResult::BadResponse
 			},
-			Phase::State(sync) => sync.import(response),
+			Phase::State(sync) => sync.import(who, response),
 		}
 	}
 
 	///  Validate and import a warp proof response.
 	pub fn import_warp_proof(&mut self, response: EncodedProof) -> WarpProofImportResult {
 		match &mut self.phase {
-			Phase::State(_) | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } => {
+			Phase::State(_) | Phase::TargetBlock { .. } | Phase::PendingTargetBlock { .. } => {
 				log::debug!(target: "sync", "Unexpected warp proof response");
 				WarpProofImportResult::BadResponse
 			},
@@ -161,7 +178,8 @@ where
 					Ok(VerificationResult::Complete(new_set_id, _, header)) => {
 						log::debug!(target: "sync", "Verified complete proof, set_id={:?}", new_set_id);
 						self.total_proof_bytes += response.0.len() as u64;
-						self.phase = Phase::TargetBlock(header);
+						self.phase =
+							Phase::TargetBlock { hash: header.hash(), number: *header.number() };
 						WarpProofImportResult::Success
 					},
 				},
@@ -175,13 +193,13 @@ where
 				log::debug!(target: "sync", "Unexpected target block response");
 				TargetBlockImportResult::BadResponse
 			},
-			Phase::TargetBlock(header) =>
+			Phase::TargetBlock { hash, number } =>
 				if let Some(block_header) = &block.header {
-					if block_header == header {
+					if block_header.hash() == *hash && block_header.number() == number {
 						if block.body.is_some() {
 							let state_sync = StateSync::new(
 								self.client.clone(),
-								header.clone(),
+								block_header.clone(),
 								block.body,
 								block.justifications,
 								false,
@@ -209,12 +227,20 @@ where
 		}
 	}
 
-	/// Produce next state request.
-	pub fn next_state_request(&self) -> Option<StateRequest> {
-		match &self.phase {
-			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } =>
-				None,
-			Phase::State(sync) => Some(sync.next_request()),
+	/// Produce next state request for the given peer.
+	pub fn next_state_request(&mut self, who: &PeerId) -> Option<StateRequest> {
+		match &mut self.phase {
+			Phase::WarpProof { .. } |
+			Phase::TargetBlock { .. } |
+			Phase::PendingTargetBlock { .. } => None,
+			Phase::State(sync) => sync.next_request(who),
+		}
+	}
+
+	/// Handle a disconnected peer.
+	pub fn peer_disconnected(&mut self, who: &PeerId) {
+		if let Phase::State(sync) = &mut self.phase {
+			sync.peer_disconnected(who);
 		}
 	}
 
@@ -222,7 +248,7 @@ where
 	pub fn next_warp_proof_request(&self) -> Option<WarpProofRequest<B>> {
 		match &self.phase {
 			Phase::WarpProof { last_hash, .. } => Some(WarpProofRequest { begin: *last_hash }),
-			Phase::TargetBlock(_) | Phase::State(_) | Phase::PendingTargetBlock { .. } => None,
+			Phase::TargetBlock { .. } | Phase::State(_) | Phase::PendingTargetBlock { .. } => None,
 		}
 	}
 
@@ -230,16 +256,16 @@ where
 	pub fn next_target_block_request(&self) -> Option<(NumberFor<B>, BlockRequest<B>)> {
 		match &self.phase {
 			Phase::WarpProof { .. } | Phase::State(_) | Phase::PendingTargetBlock { .. } => None,
-			Phase::TargetBlock(header) => {
+			Phase::TargetBlock { hash, number } => {
 				let request = BlockRequest::<B> {
 					id: 0,
 					fields: BlockAttributes::HEADER |
 						BlockAttributes::BODY | BlockAttributes::JUSTIFICATION,
-					from: FromBlock::Hash(header.hash()),
+					from: FromBlock::Hash(*hash),
 					direction: Direction::Ascending,
 					max: Some(1),
 				};
-				Some((*header.number(), request))
+				Some((*number, request))
 			},
 		}
 	}
@@ -247,8 +273,9 @@ where
 	/// Return target block hash if it is known.
 	pub fn target_block_hash(&self) -> Option<B::Hash> {
 		match &self.phase {
-			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } =>
-				None,
+			Phase::WarpProof { .. } |
+			Phase::TargetBlock { .. } |
+			Phase::PendingTargetBlock { .. } => None,
 			Phase::State(s) => Some(s.target()),
 		}
 	}
@@ -257,7 +284,7 @@ where
 	pub fn target_block_number(&self) -> Option<NumberFor<B>> {
 		match &self.phase {
 			Phase::WarpProof { .. } | Phase::PendingTargetBlock { .. } => None,
-			Phase::TargetBlock(header) => Some(*header.number()),
+			Phase::TargetBlock { number, .. } => Some(*number),
 			Phase::State(s) => Some(s.target_block_num()),
 		}
 	}
@@ -265,8 +292,9 @@ where
 	/// Check if the state is complete.
 	pub fn is_complete(&self) -> bool {
 		match &self.phase {
-			Phase::WarpProof { .. } | Phase::TargetBlock(_) | Phase::PendingTargetBlock { .. } =>
-				false,
+			Phase::WarpProof { .. } |
+			Phase::TargetBlock { .. } |
+			Phase::PendingTargetBlock { .. } => false,
 			Phase::State(sync) => sync.is_complete(),
 		}
 	}
@@ -278,7 +306,7 @@ where
 				phase: WarpSyncPhase::DownloadingWarpProofs,
 				total_bytes: self.total_proof_bytes,
 			},
-			Phase::TargetBlock(_) => WarpSyncProgress {
+			Phase::TargetBlock { .. } => WarpSyncProgress {
 				phase: WarpSyncPhase::DownloadingTargetBlock,
 				total_bytes: self.total_proof_bytes,
 			},
@@ -22,8 +22,12 @@ mod block_import;
 #[cfg(test)]
 mod service;
 #[cfg(test)]
+mod simulated;
+#[cfg(test)]
 mod sync;
 
+pub mod simulator;
+
 use std::{
 	collections::HashMap,
 	marker::PhantomData,
@@ -720,6 +724,8 @@ pub struct FullPeerConfig {
 	pub storage_chain: bool,
 	/// Optional target block header to sync to
 	pub target_block: Option<<Block as BlockT>::Header>,
+	/// Optional hash and number of the target block to sync to
+	pub target_block_hash_number: Option<(<Block as BlockT>::Hash, NumberFor<Block>)>,
 }
 
 #[async_trait::async_trait]
@@ -821,6 +827,7 @@ where
 			.map(|p| NonDefaultSetConfig {
 				notifications_protocol: p,
 				fallback_names: Vec::new(),
+				versions: Vec::new(),
 				max_notification_size: 1024 * 1024,
 				handshake: None,
 				set_config: Default::default(),
@@ -866,12 +873,13 @@ where
 
 		let warp_sync = Arc::new(TestWarpSyncProvider(client.clone()));
 
-		let warp_sync_params = match config.target_block {
-			Some(target_block) => {
+		let warp_sync_params = match (config.target_block, config.target_block_hash_number) {
+			(Some(target_block), _) => {
 				let (sender, receiver) = oneshot::channel::<<Block as BlockT>::Header>();
 				let _ = sender.send(target_block);
 				WarpSyncParams::WaitForTarget(receiver)
 			},
+			(None, Some((hash, number))) => WarpSyncParams::WithTargetBlock { hash, number },
 			_ => WarpSyncParams::WithProvider(warp_sync.clone()),
 		};
 
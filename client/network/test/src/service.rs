@@ -118,6 +118,7 @@ impl TestNetworkBuilder {
 			extra_sets: vec![config::NonDefaultSetConfig {
 				notifications_protocol: PROTOCOL_NAME.into(),
 				fallback_names: Vec::new(),
+				versions: Vec::new(),
 				max_notification_size: 1024 * 1024,
 				handshake: None,
 				set_config: self.set_config.unwrap_or_default(),
@@ -557,6 +558,7 @@ async fn fallback_name_working() {
 			extra_sets: vec![config::NonDefaultSetConfig {
 				notifications_protocol: NEW_PROTOCOL_NAME.into(),
 				fallback_names: vec![PROTOCOL_NAME.into()],
+				versions: Vec::new(),
 				max_notification_size: 1024 * 1024,
 				handshake: None,
 				set_config: Default::default(),
@@ -609,6 +611,84 @@ async fn fallback_name_working() {
 	receiver.await.unwrap();
 }
 
+#[tokio::test]
+async fn versioned_protocol_negotiation() {
+	// Node 1 supports the versions 1 and 2 of the protocol, node 2 only supports the version 1
+	// and node 3 supports the versions 2 and 3. Checks that nodes 2 and 3 can both connect to
+	// node 1, using the highest version both sides support.
+	const BASE_NAME: &str = "/versioned";
+
+	fn network_config(
+		versions: &[u32],
+		listen_addresses: Vec<Multiaddr>,
+		reserved_nodes: Vec<MultiaddrWithPeerId>,
+	) -> config::NetworkConfiguration {
+		let mut set_config =
+			config::NonDefaultSetConfig::new_versioned(BASE_NAME, versions.to_vec(), 1024 * 1024)
+				.unwrap();
+		set_config.allow_non_reserved(25, 25);
+		set_config.set_config.reserved_nodes = reserved_nodes;
+
+		config::NetworkConfiguration {
+			extra_sets: vec![set_config],
+			listen_addresses,
+			transport: TransportConfig::MemoryOnly,
+			..config::NetworkConfiguration::new_local()
+		}
+	}
+
+	async fn wait_stream_opened(
+		events: &mut (impl Stream<Item = Event> + std::marker::Unpin),
+	) -> (PeerId, sc_network::ProtocolName, Option<u32>) {
+		loop {
+			match events.next().await.unwrap() {
+				Event::NotificationStreamOpened {
+					remote, protocol, negotiated_version, ..
+				} if protocol.starts_with(BASE_NAME) => return (remote, protocol, negotiated_version),
+				_ => {},
+			}
+		}
+	}
+
+	let listen_addr = config::build_multiaddr![Memory(rand::random::<u64>())];
+	let (node1, mut events_stream1) = TestNetworkBuilder::new()
+		.with_config(network_config(&[1, 2], vec![listen_addr.clone()], vec![]))
+		.build()
+		.start_network();
+	let node1_addr = MultiaddrWithPeerId { multiaddr: listen_addr, peer_id: node1.local_peer_id() };
+
+	let (node2, mut events_stream2) = TestNetworkBuilder::new()
+		.with_config(network_config(&[1], vec![], vec![node1_addr.clone()]))
+		.build()
+		.start_network();
+	let (node3, mut events_stream3) = TestNetworkBuilder::new()
+		.with_config(network_config(&[2, 3], vec![], vec![node1_addr]))
+		.build()
+		.start_network();
+
+	assert_eq!(
+		wait_stream_opened(&mut events_stream2).await,
+		(node1.local_peer_id(), "/versioned/1".into(), Some(1)),
+	);
+	assert_eq!(
+		wait_stream_opened(&mut events_stream3).await,
+		(node1.local_peer_id(), "/versioned/3".into(), Some(2)),
+	);
+
+	let mut opened = vec![
+		wait_stream_opened(&mut events_stream1).await,
+		wait_stream_opened(&mut events_stream1).await,
+	];
+	opened.sort_by_key(|(_, _, version)| *version);
+	assert_eq!(
+		opened,
+		vec![
+			(node2.local_peer_id(), "/versioned/2".into(), Some(1)),
+			(node3.local_peer_id(), "/versioned/2".into(), Some(2)),
+		],
+	);
+}
+
 #[tokio::test]
 #[should_panic(expected = "don't match the transport")]
 async fn ensure_listen_addresses_consistent_with_transport_memory() {
@@ -0,0 +1,358 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Syncing engine and gossip engine scenarios running on top of the [`Simulator`].
+
+use super::*;
+use crate::simulator::{
+	LinkConfig, SimulatedNetwork, SimulatedNodeConfig, Simulator, SimulatorConfig, SimulatorStats,
+};
+use futures::{executor::block_on, task::ArcWake};
+use sc_network_common::sync::{SyncEvent as PeerSyncEvent, SyncEventStream};
+use sc_network_gossip::{GossipEngine, ValidationResult, Validator, ValidatorContext};
+use sc_network_sync::engine::SyncingEngine;
+use std::{
+	collections::HashSet,
+	sync::atomic::{AtomicBool, Ordering},
+};
+use substrate_test_runtime_client::ClientBlockImportExt;
+
+/// Tasks of the simulated nodes, polled one after the other on the test thread.
+#[derive(Clone, Default)]
+struct Tasks(Arc<Mutex<Vec<BoxFuture<'static, ()>>>>);
+
+struct Woken(AtomicBool);
+
+impl ArcWake for Woken {
+	fn wake_by_ref(arc_self: &Arc<Self>) {
+		arc_self.0.store(true, Ordering::SeqCst);
+	}
+}
+
+impl Tasks {
+	fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+		self.0.lock().push(task.boxed());
+	}
+
+	/// Polls the tasks until none of them is woken up anymore.
+	fn run_until_stalled(&self) {
+		let woken = Arc::new(Woken(AtomicBool::new(true)));
+		let waker = futures::task::waker(woken.clone());
+		let mut cx = FutureContext::from_waker(&waker);
+
+		// Bounded, in case a task keeps waking itself up.
+		for _ in 0..1000 {
+			if !woken.0.swap(false, Ordering::SeqCst) {
+				return
+			}
+
+			let mut tasks = std::mem::take(&mut *self.0.lock());
+			tasks.retain_mut(|task| task.poll_unpin(&mut cx).is_pending());
+
+			let mut spawned = std::mem::take(&mut *self.0.lock());
+			if !spawned.is_empty() {
+				woken.0.store(true, Ordering::SeqCst);
+			}
+			tasks.append(&mut spawned);
+			*self.0.lock() = tasks;
+		}
+	}
+}
+
+impl sp_core::traits::SpawnEssentialNamed for Tasks {
+	fn spawn_essential_blocking(
+		&self,
+		_name: &'static str,
+		_group: Option<&'static str>,
+		future: BoxFuture<'static, ()>,
+	) {
+		self.spawn(future)
+	}
+
+	fn spawn_essential(
+		&self,
+		_name: &'static str,
+		_group: Option<&'static str>,
+		future: BoxFuture<'static, ()>,
+	) {
+		self.spawn(future)
+	}
+}
+
+const GOSSIP_PROTOCOL: &str = "/simulated/gossip/1";
+const RING_SIZE: usize = 20;
+
+fn gossip_topic() -> Hash {
+	Hash::repeat_byte(1)
+}
+
+/// Keeps every message, all of them under the same topic.
+struct KeepAll;
+
+impl Validator<Block> for KeepAll {
+	fn validate(
+		&self,
+		_context: &mut dyn ValidatorContext<Block>,
+		_sender: &PeerId,
+		_data: &[u8],
+	) -> ValidationResult<Hash> {
+		ValidationResult::ProcessAndKeep(gossip_topic())
+	}
+}
+
+/// Syncing service of the gossip nodes, whose peers are connected by the test itself.
+#[derive(Clone)]
+struct NoSync;
+
+impl SyncEventStream for NoSync {
+	fn event_stream(
+		&self,
+		_name: &'static str,
+	) -> Pin<Box<dyn Stream<Item = PeerSyncEvent> + Send>> {
+		Box::pin(stream::pending())
+	}
+}
+
+impl NetworkBlock<Hash, NumberFor<Block>> for NoSync {
+	fn announce_block(&self, _hash: Hash, _data: Option<Vec<u8>>) {}
+
+	fn new_best_block_imported(&self, _hash: Hash, _number: NumberFor<Block>) {}
+}
+
+/// Every node of a ring gossips one message, and returns the time at which each node has
+/// received the messages of all the others.
+///
+/// Messages only go further than the direct neighbours when the engines rebroadcast them, which
+/// happens on their periodic maintenance.
+fn gossip_around_ring(seed: u64) -> Vec<Duration> {
+	let simulator = Simulator::new(SimulatorConfig {
+		seed,
+		default_link: LinkConfig { latency: Duration::from_millis(50), ..Default::default() },
+		..Default::default()
+	});
+	let tasks = Tasks::default();
+
+	let nodes = (0..RING_SIZE)
+		.map(|_| {
+			simulator.add_node(SimulatedNodeConfig {
+				notification_protocols: vec![(GOSSIP_PROTOCOL.into(), Vec::new())],
+				..Default::default()
+			})
+		})
+		.collect::<Vec<_>>();
+
+	let mut engines = Vec::new();
+	let mut received = Vec::new();
+	for network in &nodes {
+		let mut engine = GossipEngine::<Block>::new(
+			network.clone(),
+			NoSync,
+			GOSSIP_PROTOCOL,
+			Arc::new(KeepAll),
+			None,
+		);
+		engine.set_clock(simulator.clock());
+
+		let messages = Arc::new(Mutex::new(HashSet::new()));
+		let sink = messages.clone();
+		tasks.spawn(engine.messages_for(gossip_topic()).for_each(move |notification| {
+			sink.lock().insert(notification.message);
+			future::ready(())
+		}));
+		received.push(messages);
+
+		let engine = Arc::new(Mutex::new(engine));
+		let polled = engine.clone();
+		tasks.spawn(future::poll_fn(move |cx| polled.lock().poll_unpin(cx)));
+		engines.push(engine);
+	}
+
+	for (i, node) in nodes.iter().enumerate() {
+		let next = &nodes[(i + 1) % RING_SIZE];
+		assert!(simulator.connect(node.local_peer_id(), next.local_peer_id()));
+	}
+	simulator.advance(Duration::from_millis(100));
+	tasks.run_until_stalled();
+
+	for (i, engine) in engines.iter().enumerate() {
+		engine.lock().gossip_message(gossip_topic(), vec![i as u8], false);
+	}
+
+	let mut completed = vec![None; RING_SIZE];
+	while completed.iter().any(Option::is_none) {
+		assert!(simulator.now() < Duration::from_secs(60), "Gossip didn't reach every node");
+
+		simulator.advance(Duration::from_millis(100));
+		tasks.run_until_stalled();
+
+		for (completed, received) in completed.iter_mut().zip(&received) {
+			if completed.is_none() && received.lock().len() == RING_SIZE - 1 {
+				*completed = Some(simulator.now());
+			}
+		}
+	}
+
+	completed.into_iter().map(Option::unwrap).collect()
+}
+
+#[test]
+fn gossip_reaches_the_whole_ring_on_virtual_time() {
+	sp_tracing::try_init_simple();
+
+	let completed = gossip_around_ring(7);
+
+	// The farthest nodes are half a ring away, and each hop past the first one waits for a
+	// rebroadcast, which only a virtual clock makes affordable.
+	let last = completed.iter().max().unwrap();
+	assert!(*last >= Duration::from_secs(5), "{:?}", last);
+	assert_eq!(gossip_around_ring(7), completed);
+}
+
+const SYNC_BLOCKS: usize = 64;
+const SYNCING_NODES: usize = 4;
+
+/// Adds a node running a syncing engine on top of `client` to the simulation.
+fn add_sync_node(
+	simulator: &Simulator,
+	tasks: &Tasks,
+	client: Arc<PeersFullClient>,
+	backend: Arc<substrate_test_runtime_client::Backend>,
+) -> SimulatedNetwork {
+	let protocol_id = ProtocolId::from("simulated");
+	let network_config =
+		NetworkConfiguration::new("simulated-node", "simulated-client", Default::default(), None);
+
+	let (block_request_handler, block_request_config) =
+		BlockRequestHandler::new(&protocol_id, None, client.clone(), 50);
+	tasks.spawn(block_request_handler.run());
+	let (state_request_handler, state_request_config) =
+		StateRequestHandler::new(&protocol_id, None, client.clone(), 50);
+	tasks.spawn(state_request_handler.run());
+
+	let import_queue = BasicQueue::new(
+		PassThroughVerifier::new(false),
+		Box::new(PeersClient { client: client.clone(), backend }.as_block_import()),
+		None,
+		tasks,
+		None,
+	);
+
+	let (chain_sync_network_provider, chain_sync_network_handle) = NetworkServiceProvider::new();
+	let (tx, rx) = sc_utils::mpsc::tracing_unbounded("mpsc_syncing_engine_protocol", 100_000);
+	let (mut engine, sync_service, block_announce_config) = SyncingEngine::new(
+		Roles::FULL,
+		client,
+		None,
+		&network_config,
+		protocol_id,
+		&None,
+		Box::new(DefaultBlockAnnounceValidator),
+		None,
+		chain_sync_network_handle,
+		import_queue.service(),
+		block_request_config.name.clone(),
+		state_request_config.name.clone(),
+		None,
+		rx,
+	)
+	.unwrap();
+	engine.set_clock(simulator.clock());
+
+	let block_announces = block_announce_config.notifications_protocol.clone();
+	let handshake = block_announce_config
+		.handshake
+		.as_ref()
+		.map(|handshake| handshake.to_vec())
+		.unwrap_or_default();
+	let network = simulator.add_node(SimulatedNodeConfig {
+		notification_protocols: vec![(block_announces.clone(), handshake)],
+		request_protocols: vec![block_request_config, state_request_config],
+		..Default::default()
+	});
+
+	tasks.spawn(network.forward_sync_events(block_announces, tx));
+	tasks.spawn(chain_sync_network_provider.run(Arc::new(network.clone())));
+	tasks.spawn(import_queue.run(Box::new(sync_service)));
+	tasks.spawn(engine.run());
+
+	network
+}
+
+/// Nodes connected to a node that has authored [`SYNC_BLOCKS`] blocks download them over lossless
+/// but jittery links. Returns the time at which each of them has imported the last block, along
+/// with the network statistics.
+fn sync_from_one_node(seed: u64) -> (Vec<Duration>, SimulatorStats) {
+	let simulator = Simulator::new(SimulatorConfig {
+		seed,
+		default_link: LinkConfig {
+			latency: Duration::from_millis(20),
+			jitter: Duration::from_millis(10),
+			bandwidth: Some(1_000_000),
+			packet_loss: 0.0,
+		},
+		..Default::default()
+	});
+	let tasks = Tasks::default();
+
+	let builder = TestClientBuilder::with_default_backend();
+	let backend = builder.backend();
+	let mut client = Arc::new(builder.build());
+	for _ in 0..SYNC_BLOCKS {
+		let block = client.new_block(Default::default()).unwrap().build().unwrap().block;
+		block_on(client.import(BlockOrigin::Own, block)).unwrap();
+	}
+	let source = add_sync_node(&simulator, &tasks, client, backend);
+
+	let clients = (0..SYNCING_NODES)
+		.map(|_| {
+			let builder = TestClientBuilder::with_default_backend();
+			let backend = builder.backend();
+			let client = Arc::new(builder.build());
+			let network = add_sync_node(&simulator, &tasks, client.clone(), backend);
+			assert!(simulator.connect(source.local_peer_id(), network.local_peer_id()));
+			client
+		})
+		.collect::<Vec<_>>();
+
+	let mut completed = vec![None; SYNCING_NODES];
+	while completed.iter().any(Option::is_none) {
+		assert!(simulator.now() < Duration::from_secs(60), "Nodes didn't sync");
+
+		simulator.advance(Duration::from_millis(10));
+		tasks.run_until_stalled();
+
+		for (completed, client) in completed.iter_mut().zip(&clients) {
+			if completed.is_none() && client.info().best_number == SYNC_BLOCKS as u64 {
+				*completed = Some(simulator.now());
+			}
+		}
+	}
+
+	(completed.into_iter().map(Option::unwrap).collect(), simulator.stats())
+}
+
+#[test]
+fn nodes_sync_over_simulated_network() {
+	sp_tracing::try_init_simple();
+
+	let (completed, stats) = sync_from_one_node(3);
+
+	assert!(stats.delivered > 0);
+	assert_eq!(stats.sent, stats.delivered + stats.dropped);
+	assert_eq!(sync_from_one_node(3), (completed, stats));
+}
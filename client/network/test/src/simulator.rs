@@ -0,0 +1,1457 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Deterministic in-process network simulator.
+//!
+//! The [`Simulator`] replaces the libp2p transports with a virtual network whose behaviour only
+//! depends on the seed it has been created with. Every node is represented by a
+//! [`SimulatedNetwork`] handle implementing the `sc-network` service traits, which means that it
+//! can be handed to the syncing engine or to `sc-network-gossip` in place of a `NetworkService`.
+//!
+//! Time doesn't flow on its own. Messages are only delivered when the virtual clock is moved
+//! forward with [`Simulator::advance`], [`Simulator::step`] or [`Simulator::run_until_idle`].
+//! Messages that are due at the same instant are delivered in an order derived from the seed.
+//! The components under test follow the same clock when given [`Simulator::clock`].
+
+use std::{
+	cmp::{Ordering, Reverse},
+	collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
+	pin::Pin,
+	sync::Arc,
+	task::Poll,
+	time::{Duration, Instant},
+};
+
+use futures::{
+	channel::{mpsc, oneshot},
+	future::BoxFuture,
+	prelude::*,
+	stream::{FuturesUnordered, SelectAll},
+};
+use libp2p::{
+	identity::{ed25519, Keypair},
+	multiaddr::Protocol,
+	Multiaddr, PeerId,
+};
+use log::trace;
+use parking_lot::Mutex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use sc_network::{
+	config::MultiaddrWithPeerId,
+	event::Event,
+	request_responses::{
+		IncomingRequest, OutgoingResponse, ProtocolConfig as RequestResponseConfig,
+	},
+	types::ProtocolName,
+	IfDisconnected, NetworkEventStream, NetworkNotification, NetworkPeers, NetworkRequest,
+	NetworkStateInfo, NotificationSenderError, NotificationSenderReady,
+	NotificationSenderT as NotificationSender, NotificationsSink, ObservedRole, OutboundFailure,
+	ReputationChange, RequestFailure, SyncEvent,
+};
+use sc_network_common::{clock::Clock, sync::message::BlockAnnouncesHandshake};
+use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedSender};
+use sp_runtime::{codec::Decode, traits::Block as BlockT};
+
+const LOG_TARGET: &str = "simulator";
+
+/// Characteristics of a simulated link between two nodes.
+#[derive(Debug, Clone)]
+pub struct LinkConfig {
+	/// Time it takes for a message to travel through the link.
+	pub latency: Duration,
+	/// Maximum random delay added on top of `latency` for each message.
+	pub jitter: Duration,
+	/// Number of bytes per second that the link can carry, or `None` if unlimited.
+	pub bandwidth: Option<u64>,
+	/// Probability, between `0.0` and `1.0`, that a notification, request or response is lost.
+	pub packet_loss: f64,
+}
+
+impl Default for LinkConfig {
+	fn default() -> Self {
+		Self {
+			latency: Duration::from_millis(10),
+			jitter: Duration::ZERO,
+			bandwidth: None,
+			packet_loss: 0.0,
+		}
+	}
+}
+
+/// Configuration of a [`Simulator`].
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+	/// Seed of the random number generator driving the simulation.
+	///
+	/// Two simulations started with the same seed and fed with the same inputs behave
+	/// identically.
+	pub seed: u64,
+	/// Link characteristics used between nodes that haven't been configured with
+	/// [`Simulator::set_link`].
+	pub default_link: LinkConfig,
+	/// Time after which a request whose request or response has been lost fails with
+	/// [`OutboundFailure::Timeout`].
+	pub request_timeout: Duration,
+}
+
+impl Default for SimulatorConfig {
+	fn default() -> Self {
+		Self {
+			seed: 0,
+			default_link: LinkConfig::default(),
+			request_timeout: Duration::from_secs(20),
+		}
+	}
+}
+
+/// Configuration of a node added to the simulation.
+#[derive(Debug)]
+pub struct SimulatedNodeConfig {
+	/// Role reported to the other nodes when a notification stream opens.
+	pub role: ObservedRole,
+	/// Notification protocols supported by the node, along with their handshake.
+	pub notification_protocols: Vec<(ProtocolName, Vec<u8>)>,
+	/// Request-response protocols supported by the node.
+	///
+	/// Incoming requests are pushed on the `inbound_queue` of the corresponding configuration,
+	/// exactly like the real network does.
+	pub request_protocols: Vec<RequestResponseConfig>,
+}
+
+impl Default for SimulatedNodeConfig {
+	fn default() -> Self {
+		Self {
+			role: ObservedRole::Full,
+			notification_protocols: Vec::new(),
+			request_protocols: Vec::new(),
+		}
+	}
+}
+
+/// Counters of what happened in a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimulatorStats {
+	/// Number of notifications, requests and responses put on a link.
+	pub sent: u64,
+	/// Number of notifications, requests and responses that reached their destination.
+	pub delivered: u64,
+	/// Number of notifications, requests and responses lost or dropped because the link went
+	/// down while they were in flight.
+	pub dropped: u64,
+	/// Total number of payload bytes put on a link.
+	pub bytes_sent: u64,
+}
+
+/// Something happening at a given point in time of the simulation.
+enum Action {
+	/// `remote` opened a notification stream with `local`.
+	Open { local: PeerId, remote: PeerId, protocol: ProtocolName },
+	/// A notification reaches `to`.
+	Notification { from: PeerId, to: PeerId, protocol: ProtocolName, message: Vec<u8> },
+	/// A request reaches `to`.
+	Request {
+		from: PeerId,
+		to: PeerId,
+		protocol: ProtocolName,
+		payload: Vec<u8>,
+		response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+	},
+	/// The outcome of a request reaches the requester.
+	Response {
+		response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+		result: Result<Vec<u8>, RequestFailure>,
+	},
+	/// A request whose request or response has been lost times out.
+	Timeout(oneshot::Sender<Result<Vec<u8>, RequestFailure>>),
+	/// A timer created by [`Simulator::timer`] fires.
+	Timer(oneshot::Sender<()>),
+}
+
+/// [`Action`] in the queue of the simulator.
+struct Scheduled {
+	at: Duration,
+	/// Random value breaking the ties between actions scheduled at the same time.
+	tie: u64,
+	/// Insertion counter, making the ordering total.
+	seq: u64,
+	action: Action,
+}
+
+impl Scheduled {
+	fn key(&self) -> (Duration, u64, u64) {
+		(self.at, self.tie, self.seq)
+	}
+}
+
+impl PartialEq for Scheduled {
+	fn eq(&self, other: &Self) -> bool {
+		self.key() == other.key()
+	}
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Scheduled {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.key().cmp(&other.key())
+	}
+}
+
+/// Request delivered to a node, waiting for its handler to answer.
+struct PendingResponse {
+	responder: PeerId,
+	requester: PeerId,
+	receiver: oneshot::Receiver<OutgoingResponse>,
+	response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+}
+
+struct Node {
+	role: ObservedRole,
+	notification_protocols: Vec<(ProtocolName, Vec<u8>)>,
+	request_protocols: Vec<(ProtocolName, Option<mpsc::Sender<IncomingRequest>>)>,
+	/// Notification streams currently open, in the order they have been opened.
+	open: Vec<(PeerId, ProtocolName)>,
+	event_streams: Vec<TracingUnboundedSender<Event>>,
+	/// Reputation changes reported by this node.
+	reports: Vec<(PeerId, ReputationChange)>,
+}
+
+impl Node {
+	fn handshake(&self, protocol: &ProtocolName) -> Option<&Vec<u8>> {
+		self.notification_protocols
+			.iter()
+			.find_map(|(name, handshake)| (name == protocol).then_some(handshake))
+	}
+
+	fn is_open(&self, remote: &PeerId, protocol: &ProtocolName) -> bool {
+		self.open.iter().any(|(peer, name)| peer == remote && name == protocol)
+	}
+
+	fn emit(&mut self, event: Event) {
+		self.event_streams.retain(|stream| stream.unbounded_send(event.clone()).is_ok());
+	}
+}
+
+struct Inner {
+	/// Instant reported by [`SimulatedClock`] at the start of the simulation.
+	origin: Instant,
+	now: Duration,
+	rng: StdRng,
+	next_seq: u64,
+	queue: BinaryHeap<Reverse<Scheduled>>,
+	pending_responses: Vec<PendingResponse>,
+	nodes: BTreeMap<PeerId, Node>,
+	default_link: LinkConfig,
+	links: HashMap<(PeerId, PeerId), LinkConfig>,
+	/// Time at which each link is done transmitting the messages already put on it.
+	busy_until: HashMap<(PeerId, PeerId), Duration>,
+	/// Delivery time of the last message put on each link, keeping links in order.
+	last_delivery: HashMap<(PeerId, PeerId), Duration>,
+	/// Group of each node while the network is partitioned.
+	partition: Option<HashMap<PeerId, usize>>,
+	request_timeout: Duration,
+	stats: SimulatorStats,
+}
+
+impl Inner {
+	fn schedule(&mut self, at: Duration, action: Action) {
+		let tie = self.rng.gen();
+		let seq = self.next_seq;
+		self.next_seq += 1;
+		self.queue.push(Reverse(Scheduled { at, tie, seq, action }));
+	}
+
+	fn link(&self, from: PeerId, to: PeerId) -> &LinkConfig {
+		self.links.get(&(from, to)).unwrap_or(&self.default_link)
+	}
+
+	fn reachable(&self, a: PeerId, b: PeerId) -> bool {
+		match &self.partition {
+			Some(groups) => groups.get(&a) == groups.get(&b),
+			None => true,
+		}
+	}
+
+	/// Returns the time at which a message of `size` bytes sent now from `from` reaches `to`,
+	/// or `None` if it is lost on the way.
+	fn transmit(&mut self, from: PeerId, to: PeerId, size: usize, lossy: bool) -> Option<Duration> {
+		let link = self.link(from, to).clone();
+
+		if lossy {
+			self.stats.sent += 1;
+			self.stats.bytes_sent += size as u64;
+
+			if link.packet_loss > 0.0 && self.rng.gen_bool(link.packet_loss.min(1.0)) {
+				trace!(target: LOG_TARGET, "Message from {} to {} lost", from, to);
+				self.stats.dropped += 1;
+				return None
+			}
+		}
+
+		let mut at = self.now;
+		if let Some(bandwidth) = link.bandwidth {
+			let busy_until = self.busy_until.entry((from, to)).or_default();
+			let start = (*busy_until).max(self.now);
+			*busy_until = start + Duration::from_secs_f64(size as f64 / bandwidth.max(1) as f64);
+			at = *busy_until;
+		}
+
+		at += link.latency;
+		if !link.jitter.is_zero() {
+			at += self.rng.gen_range(Duration::ZERO..=link.jitter);
+		}
+
+		// Substreams don't reorder messages, jitter or not.
+		let last_delivery = self.last_delivery.entry((from, to)).or_default();
+		at = at.max(*last_delivery + Duration::from_nanos(1));
+		*last_delivery = at;
+
+		Some(at)
+	}
+
+	/// Starts opening a notification stream between `a` and `b`.
+	///
+	/// Returns `false` if the nodes can't reach each other or don't both support `protocol`.
+	fn open(&mut self, a: PeerId, b: PeerId, protocol: &ProtocolName) -> bool {
+		if a == b || !self.reachable(a, b) {
+			return false
+		}
+
+		let supported =
+			|peer| self.nodes.get(&peer).map_or(false, |node| node.handshake(protocol).is_some());
+		if !supported(a) || !supported(b) {
+			return false
+		}
+
+		for (local, remote) in [(a, b), (b, a)] {
+			let at =
+				self.transmit(remote, local, 0, false).expect("handshakes are never lost; qed");
+			self.schedule(at, Action::Open { local, remote, protocol: protocol.clone() });
+		}
+
+		true
+	}
+
+	/// Closes the notification stream between `a` and `b`, if any.
+	fn close(&mut self, a: PeerId, b: PeerId, protocol: &ProtocolName) {
+		for (local, remote) in [(a, b), (b, a)] {
+			let Some(node) = self.nodes.get_mut(&local) else { continue };
+			let len = node.open.len();
+			node.open.retain(|(peer, name)| !(peer == &remote && name == protocol));
+
+			if node.open.len() != len {
+				node.emit(Event::NotificationStreamClosed { remote, protocol: protocol.clone() });
+			}
+		}
+	}
+
+	/// Closes all the notification streams between `a` and `b`.
+	fn close_all(&mut self, a: PeerId, b: PeerId) {
+		let protocols = self.nodes.get(&a).map_or_else(Vec::new, |node| {
+			node.open
+				.iter()
+				.filter(|(peer, _)| peer == &b)
+				.map(|(_, protocol)| protocol.clone())
+				.collect()
+		});
+
+		for protocol in protocols {
+			self.close(a, b, &protocol);
+		}
+	}
+
+	fn send_notification(
+		&mut self,
+		from: PeerId,
+		to: PeerId,
+		protocol: ProtocolName,
+		message: Vec<u8>,
+	) -> bool {
+		if !self.nodes.get(&from).map_or(false, |node| node.is_open(&to, &protocol)) {
+			return false
+		}
+
+		if let Some(at) = self.transmit(from, to, message.len(), true) {
+			self.schedule(at, Action::Notification { from, to, protocol, message });
+		}
+
+		true
+	}
+
+	fn start_request(
+		&mut self,
+		from: PeerId,
+		to: PeerId,
+		protocol: ProtocolName,
+		payload: Vec<u8>,
+		response: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+		connect: IfDisconnected,
+	) {
+		let Some(node) = self.nodes.get(&from) else {
+			let _ = response.send(Err(RequestFailure::Obsolete));
+			return
+		};
+
+		if !node.request_protocols.iter().any(|(name, _)| name == &protocol) {
+			let _ = response.send(Err(RequestFailure::UnknownProtocol));
+			return
+		}
+
+		let connected = node.open.iter().any(|(peer, _)| peer == &to);
+		if !connected && !connect.should_connect() {
+			let _ = response.send(Err(RequestFailure::NotConnected));
+			return
+		}
+
+		if !self.nodes.contains_key(&to) || !self.reachable(from, to) {
+			let _ = response.send(Err(RequestFailure::Network(OutboundFailure::DialFailure)));
+			return
+		}
+
+		match self.transmit(from, to, payload.len(), true) {
+			Some(at) =>
+				self.schedule(at, Action::Request { from, to, protocol, payload, response }),
+			None => self.schedule(self.now + self.request_timeout, Action::Timeout(response)),
+		}
+	}
+
+	/// Puts the responses that the request handlers have produced so far on their link.
+	fn poll_pending_responses(&mut self) {
+		for mut pending in std::mem::take(&mut self.pending_responses) {
+			let result = match pending.receiver.try_recv() {
+				Ok(None) => {
+					self.pending_responses.push(pending);
+					continue
+				},
+				Ok(Some(OutgoingResponse { result, reputation_changes, sent_feedback })) => {
+					if let Some(node) = self.nodes.get_mut(&pending.responder) {
+						node.reports.extend(
+							reputation_changes
+								.into_iter()
+								.map(|change| (pending.requester, change)),
+						);
+					}
+					if let Some(sent_feedback) = sent_feedback {
+						let _ = sent_feedback.send(());
+					}
+
+					result.map_err(|()| RequestFailure::Refused)
+				},
+				Err(oneshot::Canceled) => Err(RequestFailure::Refused),
+			};
+
+			let size = result.as_ref().map_or(0, |response| response.len());
+			let at = if self.reachable(pending.responder, pending.requester) {
+				self.transmit(pending.responder, pending.requester, size, true)
+			} else {
+				self.stats.sent += 1;
+				self.stats.bytes_sent += size as u64;
+				self.stats.dropped += 1;
+				None
+			};
+
+			match at {
+				Some(at) =>
+					self.schedule(at, Action::Response { response: pending.response, result }),
+				None => self
+					.schedule(self.now + self.request_timeout, Action::Timeout(pending.response)),
+			}
+		}
+	}
+
+	fn deliver(&mut self, action: Action) {
+		match action {
+			Action::Open { local, remote, protocol } => {
+				if !self.reachable(local, remote) {
+					return
+				}
+				let Some(remote_node) = self.nodes.get(&remote) else { return };
+				let role = remote_node.role.clone();
+				let Some(handshake) = remote_node.handshake(&protocol).cloned() else { return };
+				let Some(node) = self.nodes.get_mut(&local) else { return };
+				if node.is_open(&remote, &protocol) {
+					return
+				}
+
+				node.open.push((remote, protocol.clone()));
+				node.emit(Event::NotificationStreamOpened {
+					remote,
+					protocol,
+					negotiated_fallback: None,
+					negotiated_version: None,
+					role,
+					received_handshake: handshake,
+				});
+			},
+			Action::Notification { from, to, protocol, message } => {
+				let reachable = self.reachable(from, to);
+				match self.nodes.get_mut(&to) {
+					Some(node) if reachable && node.is_open(&from, &protocol) => {
+						self.stats.delivered += 1;
+						node.emit(Event::NotificationsReceived {
+							remote: from,
+							messages: vec![(protocol, message.into())],
+						});
+					},
+					_ => self.stats.dropped += 1,
+				}
+			},
+			Action::Request { from, to, protocol, payload, response } => {
+				let inbound_queue = self.nodes.get(&to).and_then(|node| {
+					node.request_protocols
+						.iter()
+						.find_map(|(name, queue)| (name == &protocol).then(|| queue.clone()))
+				});
+				let Some(Some(mut inbound_queue)) = inbound_queue else {
+					self.stats.dropped += 1;
+					let result =
+						Err(RequestFailure::Network(OutboundFailure::UnsupportedProtocols));
+					let _ = response.send(result);
+					return
+				};
+
+				self.stats.delivered += 1;
+				let (tx, receiver) = oneshot::channel();
+				let request = IncomingRequest { peer: from, payload, pending_response: tx };
+				if inbound_queue.try_send(request).is_err() {
+					let _ = response.send(Err(RequestFailure::Refused));
+					return
+				}
+
+				self.pending_responses.push(PendingResponse {
+					responder: to,
+					requester: from,
+					receiver,
+					response,
+				});
+			},
+			Action::Response { response, result } => {
+				self.stats.delivered += 1;
+				let _ = response.send(result);
+			},
+			Action::Timeout(response) => {
+				let _ = response.send(Err(RequestFailure::Network(OutboundFailure::Timeout)));
+			},
+			Action::Timer(timer) => {
+				let _ = timer.send(());
+			},
+		}
+	}
+
+	/// Delivers the next action if it is due at or before `until`.
+	fn step(&mut self, until: Option<Duration>) -> bool {
+		self.poll_pending_responses();
+
+		match self.queue.peek() {
+			Some(Reverse(next)) if until.map_or(true, |until| next.at <= until) => {},
+			_ => return false,
+		}
+
+		let Reverse(Scheduled { at, action, .. }) =
+			self.queue.pop().expect("queue has just been peeked; qed");
+		self.now = self.now.max(at);
+		self.deliver(action);
+
+		true
+	}
+}
+
+/// Deterministic simulated network.
+///
+/// Cloning a [`Simulator`] returns a handle to the same simulation.
+#[derive(Clone)]
+pub struct Simulator {
+	inner: Arc<Mutex<Inner>>,
+}
+
+impl Simulator {
+	/// Create a new simulation without any node.
+	pub fn new(config: SimulatorConfig) -> Self {
+		let inner = Inner {
+			origin: Instant::now(),
+			now: Duration::ZERO,
+			rng: StdRng::seed_from_u64(config.seed),
+			next_seq: 0,
+			queue: BinaryHeap::new(),
+			pending_responses: Vec::new(),
+			nodes: BTreeMap::new(),
+			default_link: config.default_link,
+			links: HashMap::new(),
+			busy_until: HashMap::new(),
+			last_delivery: HashMap::new(),
+			partition: None,
+			request_timeout: config.request_timeout,
+			stats: SimulatorStats::default(),
+		};
+
+		Self { inner: Arc::new(Mutex::new(inner)) }
+	}
+
+	/// Add a node to the simulation and return its network handle.
+	///
+	/// The identity of the node is derived from the seed of the simulation.
+	pub fn add_node(&self, config: SimulatedNodeConfig) -> SimulatedNetwork {
+		let mut inner = self.inner.lock();
+
+		let mut secret = [0u8; 32];
+		inner.rng.fill(&mut secret);
+		let secret = ed25519::SecretKey::from_bytes(&mut secret)
+			.expect("any 32 bytes form a valid ed25519 secret key; qed");
+		let keypair = Keypair::Ed25519(secret.into());
+		let local_peer_id = PeerId::from_public_key(&keypair.public());
+
+		let request_protocols = config
+			.request_protocols
+			.into_iter()
+			.map(|protocol| (protocol.name, protocol.inbound_queue))
+			.collect();
+
+		inner.nodes.insert(
+			local_peer_id,
+			Node {
+				role: config.role,
+				notification_protocols: config.notification_protocols,
+				request_protocols,
+				open: Vec::new(),
+				event_streams: Vec::new(),
+				reports: Vec::new(),
+			},
+		);
+
+		SimulatedNetwork { simulator: self.clone(), local_peer_id }
+	}
+
+	/// Current time of the virtual clock, since the start of the simulation.
+	pub fn now(&self) -> Duration {
+		self.inner.lock().now
+	}
+
+	/// Returns the virtual clock of the simulation, to be handed to the components under test.
+	///
+	/// The delays created through the clock are scheduled like messages. They keep the queue busy,
+	/// so simulations involving components that re-arm their timers should be driven with
+	/// [`Simulator::advance`] rather than [`Simulator::run_until_idle`].
+	pub fn clock(&self) -> Arc<dyn Clock> {
+		Arc::new(SimulatedClock { simulator: self.clone() })
+	}
+
+	/// Set the characteristics of the link between `a` and `b`, in both directions.
+	pub fn set_link(&self, a: PeerId, b: PeerId, link: LinkConfig) {
+		let mut inner = self.inner.lock();
+		inner.links.insert((a, b), link.clone());
+		inner.links.insert((b, a), link);
+	}
+
+	/// Open notification streams between `a` and `b` for all the protocols they both support.
+	///
+	/// The streams are reported as open once the handshakes have travelled through the link.
+	/// Returns `false` if no stream could be opened.
+	pub fn connect(&self, a: PeerId, b: PeerId) -> bool {
+		let mut inner = self.inner.lock();
+		let protocols = inner.nodes.get(&a).map_or_else(Vec::new, |node| {
+			node.notification_protocols.iter().map(|(name, _)| name.clone()).collect()
+		});
+
+		let mut opened = false;
+		for protocol in protocols {
+			opened |= inner.open(a, b, &protocol);
+		}
+		opened
+	}
+
+	/// Close all the notification streams between `a` and `b`.
+	pub fn disconnect(&self, a: PeerId, b: PeerId) {
+		self.inner.lock().close_all(a, b);
+	}
+
+	/// Split the network into groups of nodes that can only reach nodes of their own group.
+	///
+	/// Nodes that aren't part of any group form one additional group together. Streams between
+	/// nodes of different groups are closed and messages in flight between them are lost.
+	pub fn partition(&self, groups: Vec<Vec<PeerId>>) {
+		let mut inner = self.inner.lock();
+		inner.partition = Some(
+			groups
+				.into_iter()
+				.enumerate()
+				.flat_map(|(index, group)| group.into_iter().map(move |peer| (peer, index)))
+				.collect(),
+		);
+
+		let streams = inner
+			.nodes
+			.iter()
+			.flat_map(|(local, node)| node.open.iter().map(|(remote, _)| (*local, *remote)))
+			.collect::<Vec<_>>();
+		for (local, remote) in streams {
+			if !inner.reachable(local, remote) {
+				inner.close_all(local, remote);
+			}
+		}
+	}
+
+	/// Remove the partition put in place by [`Simulator::partition`].
+	///
+	/// Streams closed by the partition are not reopened automatically.
+	pub fn heal(&self) {
+		self.inner.lock().partition = None;
+	}
+
+	/// Returns a future that resolves once the virtual clock has moved forward by `after`.
+	pub fn timer(&self, after: Duration) -> impl Future<Output = ()> {
+		let (tx, rx) = oneshot::channel();
+		let mut inner = self.inner.lock();
+		let at = inner.now + after;
+		inner.schedule(at, Action::Timer(tx));
+		rx.map(|_| ())
+	}
+
+	/// Deliver the next scheduled message, moving the clock forward to its delivery time.
+	///
+	/// Returns `false` if there is nothing to deliver.
+	pub fn step(&self) -> bool {
+		self.inner.lock().step(None)
+	}
+
+	/// Move the virtual clock forward by `duration`, delivering all the messages due meanwhile.
+	///
+	/// The responses of request handlers are only picked up while the clock moves, so the tasks
+	/// answering requests should be driven in between calls.
+	pub fn advance(&self, duration: Duration) {
+		let mut inner = self.inner.lock();
+		let until = inner.now + duration;
+		while inner.step(Some(until)) {}
+		inner.now = until;
+	}
+
+	/// Deliver messages until there is nothing scheduled anymore.
+	pub fn run_until_idle(&self) {
+		while self.step() {}
+	}
+
+	/// Reputation changes reported by `peer` so far.
+	pub fn reports(&self, peer: &PeerId) -> Vec<(PeerId, ReputationChange)> {
+		self.inner
+			.lock()
+			.nodes
+			.get(peer)
+			.map_or_else(Vec::new, |node| node.reports.clone())
+	}
+
+	/// Counters of the messages sent so far.
+	pub fn stats(&self) -> SimulatorStats {
+		self.inner.lock().stats
+	}
+}
+
+/// [`Clock`] following the virtual time of a [`Simulator`].
+struct SimulatedClock {
+	simulator: Simulator,
+}
+
+impl Clock for SimulatedClock {
+	fn now(&self) -> Instant {
+		let inner = self.simulator.inner.lock();
+		inner.origin + inner.now
+	}
+
+	fn delay(&self, duration: Duration) -> BoxFuture<'static, ()> {
+		self.simulator.timer(duration).boxed()
+	}
+}
+
+/// Extracts the `PeerId` of a `/p2p/` address.
+fn peer_id_of(addr: &Multiaddr) -> Result<PeerId, String> {
+	addr.iter()
+		.find_map(|protocol| match protocol {
+			Protocol::P2p(hash) => PeerId::from_multihash(hash).ok(),
+			_ => None,
+		})
+		.ok_or_else(|| format!("Address has no valid /p2p/ component: {}", addr))
+}
+
+/// Network service of a node of a [`Simulator`].
+#[derive(Clone)]
+pub struct SimulatedNetwork {
+	simulator: Simulator,
+	local_peer_id: PeerId,
+}
+
+impl SimulatedNetwork {
+	/// The simulation this node is part of.
+	pub fn simulator(&self) -> &Simulator {
+		&self.simulator
+	}
+
+	/// Peers that this node has an open notification stream with for `protocol`.
+	pub fn connected_peers(&self, protocol: &ProtocolName) -> Vec<PeerId> {
+		self.simulator
+			.inner
+			.lock()
+			.nodes
+			.get(&self.local_peer_id)
+			.map_or_else(Vec::new, |node| {
+				node.open
+					.iter()
+					.filter(|(_, name)| name == protocol)
+					.map(|(peer, _)| *peer)
+					.collect()
+			})
+	}
+
+	/// Returns a future sending on `tx` the events expected by the syncing engine for the block
+	/// announces `protocol`.
+	///
+	/// The real network decodes the block announces handshake and hands a [`NotificationsSink`]
+	/// to the syncing engine. The future does the same on top of the simulated streams, and must
+	/// be driven alongside the engine.
+	pub fn forward_sync_events<B: BlockT>(
+		&self,
+		protocol: ProtocolName,
+		tx: TracingUnboundedSender<SyncEvent<B>>,
+	) -> BoxFuture<'static, ()> {
+		let network = self.clone();
+		let mut events = self.event_stream("simulated-sync");
+		let mut sinks = SelectAll::new();
+		let mut validations = FuturesUnordered::new();
+
+		future::poll_fn(move |cx| {
+			loop {
+				let event = match events.poll_next_unpin(cx) {
+					Poll::Ready(Some(event)) => event,
+					Poll::Ready(None) => return Poll::Ready(()),
+					Poll::Pending => break,
+				};
+
+				match event {
+					Event::NotificationStreamOpened {
+						remote,
+						protocol: name,
+						received_handshake,
+						..
+					} if name == protocol => {
+						let Ok(handshake) =
+							BlockAnnouncesHandshake::<B>::decode(&mut &received_handshake[..])
+						else {
+							network.disconnect_peer(remote, protocol.clone());
+							continue
+						};
+
+						let (sink, notifications) = NotificationsSink::new_detached(remote);
+						sinks.push(notifications.map(move |message| (remote, message)));
+
+						let (accepted_tx, accepted_rx) = oneshot::channel();
+						validations.push(accepted_rx.map(move |accepted| (remote, accepted)));
+
+						let _ = tx.unbounded_send(SyncEvent::NotificationStreamOpened {
+							remote,
+							received_handshake: handshake,
+							sink,
+							tx: accepted_tx,
+						});
+					},
+					Event::NotificationStreamClosed { remote, protocol: name }
+						if name == protocol =>
+					{
+						let _ = tx.unbounded_send(SyncEvent::NotificationStreamClosed { remote });
+					},
+					Event::NotificationsReceived { remote, messages } => {
+						let messages = messages
+							.into_iter()
+							.filter_map(|(name, message)| (name == protocol).then_some(message))
+							.collect::<Vec<_>>();
+
+						if !messages.is_empty() {
+							let _ = tx.unbounded_send(SyncEvent::NotificationsReceived {
+								remote,
+								messages,
+							});
+						}
+					},
+					_ => {},
+				}
+			}
+
+			while let Poll::Ready(Some((remote, accepted))) = validations.poll_next_unpin(cx) {
+				if !matches!(accepted, Ok(true)) {
+					network.disconnect_peer(remote, protocol.clone());
+				}
+			}
+
+			while let Poll::Ready(Some((remote, message))) = sinks.poll_next_unpin(cx) {
+				network.write_notification(remote, protocol.clone(), message);
+			}
+
+			Poll::Pending
+		})
+		.boxed()
+	}
+
+	fn open_with(&self, protocol: ProtocolName, peers: HashSet<Multiaddr>) -> Result<(), String> {
+		let mut peers = peers.iter().map(peer_id_of).collect::<Result<Vec<_>, _>>()?;
+		// `HashSet` iteration order isn't deterministic.
+		peers.sort();
+
+		let mut inner = self.simulator.inner.lock();
+		for peer in peers {
+			inner.open(self.local_peer_id, peer, &protocol);
+		}
+		Ok(())
+	}
+}
+
+impl NetworkPeers for SimulatedNetwork {
+	fn set_authorized_peers(&self, _peers: HashSet<PeerId>) {}
+
+	fn set_authorized_only(&self, _reserved_only: bool) {}
+
+	fn add_known_address(&self, _peer_id: PeerId, _addr: Multiaddr) {}
+
+	fn report_peer(&self, who: PeerId, cost_benefit: ReputationChange) {
+		if let Some(node) = self.simulator.inner.lock().nodes.get_mut(&self.local_peer_id) {
+			node.reports.push((who, cost_benefit));
+		}
+	}
+
+	fn disconnect_peer(&self, who: PeerId, protocol: ProtocolName) {
+		self.simulator.inner.lock().close(self.local_peer_id, who, &protocol);
+	}
+
+	fn accept_unreserved_peers(&self) {}
+
+	fn deny_unreserved_peers(&self) {}
+
+	fn add_reserved_peer(&self, peer: MultiaddrWithPeerId) -> Result<(), String> {
+		self.simulator.connect(self.local_peer_id, peer.peer_id);
+		Ok(())
+	}
+
+	fn remove_reserved_peer(&self, peer_id: PeerId) {
+		self.simulator.disconnect(self.local_peer_id, peer_id);
+	}
+
+	fn set_reserved_peers(
+		&self,
+		protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		self.open_with(protocol, peers)
+	}
+
+	fn add_peers_to_reserved_set(
+		&self,
+		protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		self.open_with(protocol, peers)
+	}
+
+	fn remove_peers_from_reserved_set(&self, protocol: ProtocolName, peers: Vec<PeerId>) {
+		self.remove_from_peers_set(protocol, peers)
+	}
+
+	fn add_to_peers_set(
+		&self,
+		protocol: ProtocolName,
+		peers: HashSet<Multiaddr>,
+	) -> Result<(), String> {
+		self.open_with(protocol, peers)
+	}
+
+	fn remove_from_peers_set(&self, protocol: ProtocolName, peers: Vec<PeerId>) {
+		let mut inner = self.simulator.inner.lock();
+		for peer in peers {
+			inner.close(self.local_peer_id, peer, &protocol);
+		}
+	}
+
+	fn sync_num_connected(&self) -> usize {
+		let inner = self.simulator.inner.lock();
+		let mut peers = inner.nodes.get(&self.local_peer_id).map_or_else(Vec::new, |node| {
+			node.open.iter().map(|(peer, _)| *peer).collect::<Vec<_>>()
+		});
+		peers.sort();
+		peers.dedup();
+		peers.len()
+	}
+}
+
+impl NetworkEventStream for SimulatedNetwork {
+	fn event_stream(&self, name: &'static str) -> Pin<Box<dyn Stream<Item = Event> + Send>> {
+		let (tx, rx) = tracing_unbounded(name, 100_000);
+		if let Some(node) = self.simulator.inner.lock().nodes.get_mut(&self.local_peer_id) {
+			node.event_streams.push(tx);
+		}
+		Box::pin(rx)
+	}
+}
+
+impl NetworkStateInfo for SimulatedNetwork {
+	fn external_addresses(&self) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn listen_addresses(&self) -> Vec<Multiaddr> {
+		Vec::new()
+	}
+
+	fn local_peer_id(&self) -> PeerId {
+		self.local_peer_id
+	}
+}
+
+/// [`NotificationSender`] of a [`SimulatedNetwork`].
+struct SimulatedNotificationSender {
+	simulator: Simulator,
+	local: PeerId,
+	remote: PeerId,
+	protocol: ProtocolName,
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for SimulatedNotificationSender {
+	async fn ready(
+		&self,
+	) -> Result<Box<dyn NotificationSenderReady + '_>, NotificationSenderError> {
+		let is_open = self
+			.simulator
+			.inner
+			.lock()
+			.nodes
+			.get(&self.local)
+			.map_or(false, |node| node.is_open(&self.remote, &self.protocol));
+
+		if is_open {
+			Ok(Box::new(SimulatedNotificationSenderReady { sender: self, sent: false }))
+		} else {
+			Err(NotificationSenderError::Closed)
+		}
+	}
+}
+
+/// [`NotificationSenderReady`] of a [`SimulatedNetwork`].
+struct SimulatedNotificationSenderReady<'a> {
+	sender: &'a SimulatedNotificationSender,
+	sent: bool,
+}
+
+impl NotificationSenderReady for SimulatedNotificationSenderReady<'_> {
+	fn send(&mut self, notification: Vec<u8>) -> Result<(), NotificationSenderError> {
+		if std::mem::replace(&mut self.sent, true) {
+			return Err(NotificationSenderError::Closed)
+		}
+
+		let sender = self.sender;
+		let sent = sender.simulator.inner.lock().send_notification(
+			sender.local,
+			sender.remote,
+			sender.protocol.clone(),
+			notification,
+		);
+
+		if sent {
+			Ok(())
+		} else {
+			Err(NotificationSenderError::Closed)
+		}
+	}
+}
+
+impl NetworkNotification for SimulatedNetwork {
+	fn write_notification(&self, target: PeerId, protocol: ProtocolName, message: Vec<u8>) {
+		self.simulator.inner.lock().send_notification(
+			self.local_peer_id,
+			target,
+			protocol,
+			message,
+		);
+	}
+
+	fn notification_sender(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+	) -> Result<Box<dyn NotificationSender>, NotificationSenderError> {
+		{
+			let inner = self.simulator.inner.lock();
+			let node = inner
+				.nodes
+				.get(&self.local_peer_id)
+				.ok_or(NotificationSenderError::BadProtocol)?;
+			if node.handshake(&protocol).is_none() {
+				return Err(NotificationSenderError::BadProtocol)
+			}
+			if !node.is_open(&target, &protocol) {
+				return Err(NotificationSenderError::Closed)
+			}
+		}
+
+		Ok(Box::new(SimulatedNotificationSender {
+			simulator: self.simulator.clone(),
+			local: self.local_peer_id,
+			remote: target,
+			protocol,
+		}))
+	}
+
+	fn set_notification_handshake(&self, protocol: ProtocolName, handshake: Vec<u8>) {
+		let mut inner = self.simulator.inner.lock();
+		let Some(node) = inner.nodes.get_mut(&self.local_peer_id) else { return };
+		if let Some((_, current)) =
+			node.notification_protocols.iter_mut().find(|(name, _)| name == &protocol)
+		{
+			*current = handshake;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl NetworkRequest for SimulatedNetwork {
+	async fn request(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+		request: Vec<u8>,
+		connect: IfDisconnected,
+	) -> Result<Vec<u8>, RequestFailure> {
+		let (tx, rx) = oneshot::channel();
+
+		self.start_request(target, protocol, request, tx, connect);
+
+		match rx.await {
+			Ok(v) => v,
+			Err(_) => Err(RequestFailure::Network(OutboundFailure::ConnectionClosed)),
+		}
+	}
+
+	fn start_request(
+		&self,
+		target: PeerId,
+		protocol: ProtocolName,
+		request: Vec<u8>,
+		tx: oneshot::Sender<Result<Vec<u8>, RequestFailure>>,
+		connect: IfDisconnected,
+	) {
+		self.simulator.inner.lock().start_request(
+			self.local_peer_id,
+			target,
+			protocol,
+			request,
+			tx,
+			connect,
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use futures::FutureExt;
+
+	const PROTOCOL: &str = "/simulated/1";
+
+	fn node_config() -> SimulatedNodeConfig {
+		SimulatedNodeConfig {
+			notification_protocols: vec![(PROTOCOL.into(), b"hello".to_vec())],
+			..Default::default()
+		}
+	}
+
+	fn drain(events: &mut Pin<Box<dyn Stream<Item = Event> + Send>>) -> Vec<Event> {
+		std::iter::from_fn(|| events.next().now_or_never().flatten()).collect()
+	}
+
+	fn received(events: Vec<Event>) -> Vec<(PeerId, Vec<u8>)> {
+		events
+			.into_iter()
+			.flat_map(|event| match event {
+				Event::NotificationsReceived { remote, messages } =>
+					messages.into_iter().map(|(_, message)| (remote, message.to_vec())).collect(),
+				_ => Vec::new(),
+			})
+			.collect()
+	}
+
+	/// Runs a small lossy gossip scenario and returns what each node received and when.
+	fn run_scenario(seed: u64) -> (Vec<(Duration, PeerId, PeerId, Vec<u8>)>, SimulatorStats) {
+		let simulator = Simulator::new(SimulatorConfig {
+			seed,
+			default_link: LinkConfig {
+				latency: Duration::from_millis(50),
+				jitter: Duration::from_millis(30),
+				bandwidth: Some(10_000),
+				packet_loss: 0.2,
+			},
+			..Default::default()
+		});
+
+		let nodes = (0..5).map(|_| simulator.add_node(node_config())).collect::<Vec<_>>();
+		let mut events = nodes.iter().map(|node| node.event_stream("test")).collect::<Vec<_>>();
+		for (i, a) in nodes.iter().enumerate() {
+			for b in &nodes[i + 1..] {
+				assert!(simulator.connect(a.local_peer_id(), b.local_peer_id()));
+			}
+		}
+		simulator.run_until_idle();
+
+		let mut trace = Vec::new();
+		for round in 0..20u8 {
+			for (i, node) in nodes.iter().enumerate() {
+				for peer in node.connected_peers(&PROTOCOL.into()) {
+					node.write_notification(peer, PROTOCOL.into(), vec![round, i as u8]);
+				}
+			}
+
+			while simulator.step() {
+				let now = simulator.now();
+				for (node, events) in nodes.iter().zip(events.iter_mut()) {
+					for (remote, message) in received(drain(events)) {
+						trace.push((now, node.local_peer_id(), remote, message));
+					}
+				}
+			}
+		}
+
+		(trace, simulator.stats())
+	}
+
+	#[test]
+	fn same_seed_same_outcome() {
+		let (trace, stats) = run_scenario(42);
+		assert!(!trace.is_empty());
+		assert!(stats.dropped > 0);
+		assert_eq!(stats.sent, stats.delivered + stats.dropped);
+
+		assert_eq!(run_scenario(42), (trace.clone(), stats));
+		assert_ne!(run_scenario(43).0, trace);
+	}
+
+	#[test]
+	fn notifications_are_delayed_by_latency_and_bandwidth() {
+		let simulator = Simulator::new(Default::default());
+		let a = simulator.add_node(node_config());
+		let b = simulator.add_node(node_config());
+		let mut a_events = a.event_stream("test");
+		let mut b_events = b.event_stream("test");
+
+		simulator.set_link(
+			a.local_peer_id(),
+			b.local_peer_id(),
+			LinkConfig {
+				latency: Duration::from_millis(100),
+				bandwidth: Some(1_000),
+				..Default::default()
+			},
+		);
+		assert!(simulator.connect(a.local_peer_id(), b.local_peer_id()));
+
+		// Nothing can be sent before the handshakes went through.
+		a.write_notification(b.local_peer_id(), PROTOCOL.into(), vec![1]);
+		simulator.advance(Duration::from_millis(99));
+		assert!(drain(&mut a_events).is_empty());
+		simulator.advance(Duration::from_millis(1));
+		assert!(matches!(
+			&drain(&mut a_events)[..],
+			[Event::NotificationStreamOpened { remote, received_handshake, .. }]
+				if *remote == b.local_peer_id() && received_handshake == b"hello"
+		));
+		drain(&mut b_events);
+
+		// 500 bytes take 500ms to go through the link, on top of the latency.
+		a.write_notification(b.local_peer_id(), PROTOCOL.into(), vec![0; 500]);
+		simulator.advance(Duration::from_millis(599));
+		assert!(drain(&mut b_events).is_empty());
+		simulator.advance(Duration::from_millis(1));
+		assert_eq!(received(drain(&mut b_events)), vec![(a.local_peer_id(), vec![0; 500])]);
+		assert_eq!(simulator.now(), Duration::from_millis(700));
+	}
+
+	#[test]
+	fn partition_closes_streams_and_drops_messages() {
+		let simulator = Simulator::new(Default::default());
+		let a = simulator.add_node(node_config());
+		let b = simulator.add_node(node_config());
+		let mut b_events = b.event_stream("test");
+
+		assert!(simulator.connect(a.local_peer_id(), b.local_peer_id()));
+		simulator.run_until_idle();
+		drain(&mut b_events);
+
+		a.write_notification(b.local_peer_id(), PROTOCOL.into(), vec![1]);
+		simulator.partition(vec![vec![a.local_peer_id()]]);
+		simulator.run_until_idle();
+
+		assert!(matches!(
+			&drain(&mut b_events)[..],
+			[Event::NotificationStreamClosed { remote, .. }] if *remote == a.local_peer_id()
+		));
+		assert_eq!(simulator.stats().dropped, 1);
+		assert!(!simulator.connect(a.local_peer_id(), b.local_peer_id()));
+
+		simulator.heal();
+		assert!(simulator.connect(a.local_peer_id(), b.local_peer_id()));
+		simulator.run_until_idle();
+		a.write_notification(b.local_peer_id(), PROTOCOL.into(), vec![2]);
+		simulator.run_until_idle();
+		assert_eq!(received(drain(&mut b_events)), vec![(a.local_peer_id(), vec![2])]);
+	}
+
+	#[test]
+	fn requests_are_answered_by_the_inbound_queue() {
+		const REQUEST_PROTOCOL: &str = "/simulated/request/1";
+
+		let request_config = |inbound_queue| RequestResponseConfig {
+			name: REQUEST_PROTOCOL.into(),
+			fallback_names: Vec::new(),
+			max_request_size: 1024,
+			max_response_size: 1024,
+			request_timeout: Duration::from_secs(20),
+			inbound_queue,
+		};
+
+		let simulator = Simulator::new(Default::default());
+		let a = simulator.add_node(SimulatedNodeConfig {
+			request_protocols: vec![request_config(None)],
+			..node_config()
+		});
+		let (tx, mut requests) = mpsc::channel(10);
+		let b = simulator.add_node(SimulatedNodeConfig {
+			request_protocols: vec![request_config(Some(tx))],
+			..node_config()
+		});
+
+		let (tx, mut response) = oneshot::channel();
+		a.start_request(
+			b.local_peer_id(),
+			REQUEST_PROTOCOL.into(),
+			b"ping".to_vec(),
+			tx,
+			IfDisconnected::TryConnect,
+		);
+		simulator.run_until_idle();
+
+		let request = requests.next().now_or_never().flatten().unwrap();
+		assert_eq!(request.peer, a.local_peer_id());
+		assert_eq!(request.payload, b"ping");
+		request
+			.pending_response
+			.send(OutgoingResponse {
+				result: Ok(b"pong".to_vec()),
+				reputation_changes: vec![ReputationChange::new(10, "good")],
+				sent_feedback: None,
+			})
+			.unwrap();
+
+		simulator.advance(Duration::from_millis(9));
+		assert!(matches!(response.try_recv(), Ok(None)));
+		simulator.advance(Duration::from_millis(1));
+		assert!(matches!(response.try_recv(), Ok(Some(Ok(response))) if response == b"pong"));
+		assert_eq!(
+			simulator.reports(&b.local_peer_id()),
+			vec![(a.local_peer_id(), ReputationChange::new(10, "good"))],
+		);
+
+		// Requests towards a node that isn't connected fail right away if asked to.
+		let (tx, mut response) = oneshot::channel();
+		a.start_request(
+			b.local_peer_id(),
+			REQUEST_PROTOCOL.into(),
+			Vec::new(),
+			tx,
+			IfDisconnected::ImmediateError,
+		);
+		assert!(matches!(response.try_recv(), Ok(Some(Err(RequestFailure::NotConnected)))));
+	}
+
+	#[test]
+	fn refused_and_lost_responses_are_accounted_for() {
+		const REQUEST_PROTOCOL: &str = "/simulated/request/1";
+
+		let request_config = |inbound_queue| RequestResponseConfig {
+			name: REQUEST_PROTOCOL.into(),
+			fallback_names: Vec::new(),
+			max_request_size: 1024,
+			max_response_size: 1024,
+			request_timeout: Duration::from_secs(20),
+			inbound_queue,
+		};
+
+		let simulator = Simulator::new(Default::default());
+		let a = simulator.add_node(SimulatedNodeConfig {
+			request_protocols: vec![request_config(None)],
+			..node_config()
+		});
+		let (tx, mut requests) = mpsc::channel(10);
+		let b = simulator.add_node(SimulatedNodeConfig {
+			request_protocols: vec![request_config(Some(tx))],
+			..node_config()
+		});
+
+		let mut responses = Vec::new();
+		for _ in 0..2 {
+			let (tx, response) = oneshot::channel();
+			a.start_request(
+				b.local_peer_id(),
+				REQUEST_PROTOCOL.into(),
+				Vec::new(),
+				tx,
+				IfDisconnected::TryConnect,
+			);
+			responses.push(response);
+		}
+		simulator.run_until_idle();
+
+		// The first request is refused, the answer to the second one can't make it back.
+		let refused = requests.next().now_or_never().flatten().unwrap();
+		refused
+			.pending_response
+			.send(OutgoingResponse {
+				result: Err(()),
+				reputation_changes: Vec::new(),
+				sent_feedback: None,
+			})
+			.unwrap();
+		simulator.run_until_idle();
+
+		let lost = requests.next().now_or_never().flatten().unwrap();
+		lost.pending_response
+			.send(OutgoingResponse {
+				result: Ok(b"pong".to_vec()),
+				reputation_changes: Vec::new(),
+				sent_feedback: None,
+			})
+			.unwrap();
+		simulator.partition(vec![vec![a.local_peer_id()]]);
+		simulator.run_until_idle();
+
+		assert!(matches!(responses[0].try_recv(), Ok(Some(Err(RequestFailure::Refused)))));
+		assert!(matches!(
+			responses[1].try_recv(),
+			Ok(Some(Err(RequestFailure::Network(OutboundFailure::Timeout))))
+		));
+
+		let stats = simulator.stats();
+		assert_eq!(stats, SimulatorStats { sent: 4, delivered: 3, dropped: 1, bytes_sent: 4 });
+	}
+}
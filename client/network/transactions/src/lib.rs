@@ -150,6 +150,7 @@ impl TransactionsHandlerPrototype {
 		NonDefaultSetConfig {
 			notifications_protocol: self.protocol_name.clone(),
 			fallback_names: self.fallback_protocol_names.clone(),
+			versions: Vec::new(),
 			max_notification_size: MAX_TRANSACTIONS_SIZE,
 			handshake: None,
 			set_config: SetConfig {
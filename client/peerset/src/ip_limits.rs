@@ -0,0 +1,200 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! Limits on the number of peers connecting to us from the same IP address or subnet.
+//!
+//! Without them, a single host or a single /24 subnet can take every inbound slot of a set and
+//! eclipse the node. Only the peers whose incoming connection was accepted by the peerset are
+//! counted, as the peerset doesn't know the addresses of the peers it dials.
+
+use libp2p::PeerId;
+use std::{
+	collections::HashMap,
+	net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+/// Configuration of the limits on the peers connecting from the same IP address or subnet.
+///
+/// Reserved nodes are exempt from the limits and don't count towards them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpLimits {
+	/// Maximum number of peers connected to us from the same IP address, across all the sets.
+	pub max_peers_per_ip: Option<u32>,
+	/// Maximum number of peers connected to us from the same subnet, across all the sets.
+	pub max_peers_per_subnet: Option<u32>,
+	/// Maximum number of inbound slots of a set taken by peers from the same IP address.
+	pub max_inbound_per_ip: Option<u32>,
+	/// Maximum number of inbound slots of a set taken by peers from the same subnet.
+	pub max_inbound_per_subnet: Option<u32>,
+	/// Length of the prefix identifying an IPv4 subnet.
+	pub ipv4_subnet_prefix: u8,
+	/// Length of the prefix identifying an IPv6 subnet.
+	pub ipv6_subnet_prefix: u8,
+}
+
+impl Default for IpLimits {
+	fn default() -> Self {
+		Self {
+			max_peers_per_ip: None,
+			max_peers_per_subnet: None,
+			max_inbound_per_ip: None,
+			max_inbound_per_subnet: None,
+			ipv4_subnet_prefix: 24,
+			ipv6_subnet_prefix: 48,
+		}
+	}
+}
+
+impl IpLimits {
+	/// Returns the subnet `ip` belongs to, i.e. `ip` with the bits after the prefix zeroed.
+	fn subnet(&self, ip: IpAddr) -> IpAddr {
+		match ip {
+			IpAddr::V4(ip) => {
+				let prefix = u32::from(self.ipv4_subnet_prefix.min(32));
+				let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+				IpAddr::V4(Ipv4Addr::from(u32::from(ip) & mask))
+			},
+			IpAddr::V6(ip) => {
+				let prefix = u32::from(self.ipv6_subnet_prefix.min(128));
+				let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+				IpAddr::V6(Ipv6Addr::from(u128::from(ip) & mask))
+			},
+		}
+	}
+}
+
+/// Number of incoming connections rejected because of each of the [`IpLimits`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IpLimitRejections {
+	/// Rejected because of [`IpLimits::max_peers_per_ip`].
+	pub peers_per_ip: u64,
+	/// Rejected because of [`IpLimits::max_peers_per_subnet`].
+	pub peers_per_subnet: u64,
+	/// Rejected because of [`IpLimits::max_inbound_per_ip`].
+	pub inbound_per_ip: u64,
+	/// Rejected because of [`IpLimits::max_inbound_per_subnet`].
+	pub inbound_per_subnet: u64,
+}
+
+/// Tracks the IP addresses of the peers we accepted an incoming connection from.
+#[derive(Debug)]
+pub(crate) struct IpTracker {
+	limits: IpLimits,
+	/// IP address of each peer we accepted, and for each set whether the peer holds an inbound
+	/// slot in it.
+	peers: HashMap<PeerId, (IpAddr, Vec<bool>)>,
+	rejections: IpLimitRejections,
+}
+
+impl IpTracker {
+	pub(crate) fn new(limits: IpLimits) -> Self {
+		Self { limits, peers: HashMap::new(), rejections: Default::default() }
+	}
+
+	pub(crate) fn rejections(&self) -> IpLimitRejections {
+		self.rejections
+	}
+
+	/// Checks whether a peer connecting from `ip` can take an inbound slot in `set`.
+	///
+	/// Counts a rejection if it can't.
+	pub(crate) fn check(&mut self, set: usize, peer_id: &PeerId, ip: IpAddr) -> bool {
+		let subnet = self.limits.subnet(ip);
+		let (mut peers_ip, mut peers_subnet, mut inbound_ip, mut inbound_subnet) = (0, 0, 0, 0);
+
+		for (other_ip, sets) in self.peers.iter().filter(|(p, _)| *p != peer_id).map(|(_, v)| v) {
+			let same_ip = *other_ip == ip;
+			let same_subnet = self.limits.subnet(*other_ip) == subnet;
+			let in_set = sets.get(set).copied().unwrap_or(false);
+
+			peers_ip += u32::from(same_ip);
+			peers_subnet += u32::from(same_subnet);
+			inbound_ip += u32::from(same_ip && in_set);
+			inbound_subnet += u32::from(same_subnet && in_set);
+		}
+
+		// The peer might already be connected to us through another set, in which case it
+		// doesn't increase the number of peers.
+		let already_counted = matches!(self.peers.get(peer_id), Some((i, _)) if *i == ip);
+		let exceeds =
+			|count: u32, limit: Option<u32>| matches!(limit, Some(limit) if count >= limit);
+
+		if !already_counted && exceeds(peers_ip, self.limits.max_peers_per_ip) {
+			self.rejections.peers_per_ip += 1;
+			false
+		} else if !already_counted && exceeds(peers_subnet, self.limits.max_peers_per_subnet) {
+			self.rejections.peers_per_subnet += 1;
+			false
+		} else if exceeds(inbound_ip, self.limits.max_inbound_per_ip) {
+			self.rejections.inbound_per_ip += 1;
+			false
+		} else if exceeds(inbound_subnet, self.limits.max_inbound_per_subnet) {
+			self.rejections.inbound_per_subnet += 1;
+			false
+		} else {
+			true
+		}
+	}
+
+	/// Notes that the peer connecting from `ip` took an inbound slot in `set`.
+	pub(crate) fn accepted(&mut self, set: usize, num_sets: usize, peer_id: PeerId, ip: IpAddr) {
+		let entry = self.peers.entry(peer_id).or_insert_with(|| (ip, vec![false; num_sets]));
+		if entry.0 != ip {
+			*entry = (ip, vec![false; num_sets]);
+		}
+		entry.1[set] = true;
+	}
+
+	/// Forgets the inbound slots for which `holds_slot` returns `false`.
+	pub(crate) fn retain(&mut self, mut holds_slot: impl FnMut(usize, &PeerId) -> bool) {
+		self.peers.retain(|peer_id, (_, sets)| {
+			for (set, in_set) in sets.iter_mut().enumerate() {
+				*in_set = *in_set && holds_slot(set, peer_id);
+			}
+			sets.iter().any(|in_set| *in_set)
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn subnets() {
+		let limits = IpLimits::default();
+		assert_eq!(
+			limits.subnet("10.1.2.3".parse().unwrap()),
+			"10.1.2.0".parse::<IpAddr>().unwrap()
+		);
+		assert_eq!(
+			limits.subnet("2001:db8:1:2::3".parse().unwrap()),
+			"2001:db8:1::".parse::<IpAddr>().unwrap(),
+		);
+
+		let limits = IpLimits { ipv4_subnet_prefix: 0, ipv6_subnet_prefix: 128, ..limits };
+		assert_eq!(
+			limits.subnet("10.1.2.3".parse().unwrap()),
+			"0.0.0.0".parse::<IpAddr>().unwrap()
+		);
+		assert_eq!(
+			limits.subnet("2001:db8:1:2::3".parse().unwrap()),
+			"2001:db8:1:2::3".parse::<IpAddr>().unwrap(),
+		);
+	}
+}
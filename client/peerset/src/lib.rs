@@ -31,7 +31,14 @@
 //!
 //! In addition, for each, set, the peerset also holds a list of reserved nodes towards which it
 //! will at all time try to maintain a connection with.
+//!
+//! Optionally, the reputations of the nodes, their manual bans and the addresses we have last
+//! seen them at are persisted on disk, see [`PeersetConfig::peer_store`].
+//!
+//! Incoming connections can be limited per IP address and subnet, see [`IpLimits`].
 
+mod ip_limits;
+mod peer_store;
 mod peersstate;
 
 use futures::{channel::oneshot, prelude::*};
@@ -39,14 +46,19 @@ use log::{debug, error, trace};
 use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
 use serde_json::json;
 use std::{
+	cmp,
 	collections::{HashMap, HashSet, VecDeque},
+	net::IpAddr,
+	path::PathBuf,
 	pin::Pin,
 	task::{Context, Poll},
-	time::{Duration, Instant},
+	time::{Duration, Instant, SystemTime},
 };
 use wasm_timer::Delay;
 
-pub use libp2p::PeerId;
+pub use ip_limits::{IpLimitRejections, IpLimits};
+pub use libp2p::{Multiaddr, PeerId};
+pub use peer_store::{PeerBan, PeerRecord};
 
 /// We don't accept nodes whose reputation is under this value.
 pub const BANNED_THRESHOLD: i32 = 82 * (i32::MIN / 100);
@@ -55,6 +67,8 @@ const DISCONNECT_REPUTATION_CHANGE: i32 = -256;
 /// Amount of time between the moment we disconnect from a node and the moment we remove it from
 /// the list.
 const FORGET_AFTER: Duration = Duration::from_secs(3600);
+/// Interval at which the peer store is written to disk.
+const PEER_STORE_SAVE_INTERVAL: Duration = Duration::from_secs(300);
 
 #[derive(Debug)]
 enum Action {
@@ -66,6 +80,10 @@ enum Action {
 	AddToPeersSet(SetId, PeerId),
 	RemoveFromPeersSet(SetId, PeerId),
 	PeerReputation(PeerId, oneshot::Sender<i32>),
+	BanPeer(PeerId, String, Option<Duration>),
+	UnbanPeer(PeerId),
+	PeerSeen(PeerId, Option<Multiaddr>),
+	PeerRecords(oneshot::Sender<Vec<PeerRecord>>),
 }
 
 /// Identifier of a set in the peerset.
@@ -176,6 +194,42 @@ impl PeersetHandle {
 		// The channel can only be closed if the peerset no longer exists.
 		rx.await.map_err(|_| ())
 	}
+
+	/// Bans the peer for the given `duration`, or until it is unbanned if `None` or too large to be
+	/// represented.
+	///
+	/// The peer is disconnected from all sets and isn't accepted anymore, even if it is a reserved
+	/// peer. The ban is persisted in the peer store.
+	pub fn ban_peer(&self, peer_id: PeerId, reason: String, duration: Option<Duration>) {
+		let _ = self.tx.unbounded_send(Action::BanPeer(peer_id, reason, duration));
+	}
+
+	/// Lifts the ban of the peer and resets a negative reputation to zero.
+	///
+	/// This also forgives a peer that got banned because its reputation dropped under
+	/// [`BANNED_THRESHOLD`].
+	pub fn unban_peer(&self, peer_id: PeerId) {
+		let _ = self.tx.unbounded_send(Action::UnbanPeer(peer_id));
+	}
+
+	/// Notes that we are connected to the peer, optionally at the given dialable `address`.
+	///
+	/// The address is remembered in the peer store, so that we can connect to the peer again
+	/// after a restart.
+	pub fn note_peer_seen(&self, peer_id: PeerId, address: Option<Multiaddr>) {
+		let _ = self.tx.unbounded_send(Action::PeerSeen(peer_id, address));
+	}
+
+	/// Returns everything the peerset remembers about the peers: their reputation, ban and
+	/// addresses.
+	pub async fn peer_records(self) -> Result<Vec<PeerRecord>, ()> {
+		let (tx, rx) = oneshot::channel();
+
+		let _ = self.tx.unbounded_send(Action::PeerRecords(tx));
+
+		// The channel can only be closed if the peerset no longer exists.
+		rx.await.map_err(|_| ())
+	}
 }
 
 /// Message that can be sent by the peer set manager (PSM).
@@ -218,6 +272,16 @@ impl From<u64> for IncomingIndex {
 pub struct PeersetConfig {
 	/// List of sets of nodes the peerset manages.
 	pub sets: Vec<SetConfig>,
+
+	/// Path of the peer store file.
+	///
+	/// If set, the reputations, manual bans and last seen addresses of the peers are loaded from
+	/// this file on startup and written back to it periodically and when the [`Peerset`] is
+	/// dropped. `None` disables the persistence.
+	pub peer_store: Option<PathBuf>,
+
+	/// Limits on the incoming connections from the same IP address or subnet.
+	pub ip_limits: IpLimits,
 }
 
 /// Configuration for a single set of nodes.
@@ -270,6 +334,25 @@ pub struct Peerset {
 	/// Next time to do a periodic call to `alloc_slots` with all sets. This is done once per
 	/// second, to match the period of the reputation updates.
 	next_periodic_alloc_slots: Delay,
+	/// Manual bans of nodes. The reputation of a banned node is pinned to `i32::MIN`.
+	bans: HashMap<PeerId, PeerBan>,
+	/// Nodes we have been connected to.
+	seen: HashMap<PeerId, SeenPeer>,
+	/// Path of the peer store file, see [`PeersetConfig::peer_store`].
+	peer_store: Option<PathBuf>,
+	/// Next time to write the peer store to disk.
+	next_peer_store_save: Delay,
+	/// IP addresses of the peers that took inbound slots, see [`PeersetConfig::ip_limits`].
+	ip_tracker: ip_limits::IpTracker,
+}
+
+/// A node we have been connected to.
+#[derive(Debug)]
+struct SeenPeer {
+	/// Dialable addresses of the node, most recent first.
+	addresses: Vec<Multiaddr>,
+	/// When we were last connected to the node.
+	last_seen: SystemTime,
 }
 
 impl Peerset {
@@ -297,9 +380,26 @@ impl Peerset {
 				created: now,
 				latest_time_update: now,
 				next_periodic_alloc_slots: Delay::new(Duration::new(0, 0)),
+				bans: HashMap::new(),
+				seen: HashMap::new(),
+				peer_store: config.peer_store,
+				next_peer_store_save: Delay::new(PEER_STORE_SAVE_INTERVAL),
+				ip_tracker: ip_limits::IpTracker::new(config.ip_limits),
 			}
 		};
 
+		if let Some(path) = &peerset.peer_store {
+			match peer_store::load(path) {
+				Ok(records) => peerset.restore_peer_records(records),
+				Err(e) => error!(
+					target: "peerset",
+					"Failed to load the peer store from {}: {}",
+					path.display(),
+					e,
+				),
+			}
+		}
+
 		for (set, set_config) in config.sets.into_iter().enumerate() {
 			for node in set_config.reserved_nodes {
 				peerset.data.add_no_slot_node(set, node);
@@ -451,6 +551,11 @@ impl Peerset {
 
 		drop(reputation);
 
+		self.disconnect_from_all_sets(peer_id);
+	}
+
+	/// Disconnects the node in all the sets we are connected to it and fills the freed slots.
+	fn disconnect_from_all_sets(&mut self, peer_id: PeerId) {
 		for set_index in 0..self.data.num_sets() {
 			if let peersstate::Peer::Connected(peer) = self.data.peer(set_index, &peer_id) {
 				let peer = peer.disconnect();
@@ -469,6 +574,164 @@ impl Peerset {
 		let _ = pending_response.send(reputation.reputation());
 	}
 
+	fn on_ban_peer(&mut self, peer_id: PeerId, reason: String, duration: Option<Duration>) {
+		// We want reputations to be up-to-date before adjusting them.
+		self.update_time();
+
+		debug!(target: "peerset", "Ban {} for {:?}. Reason: {}, Disconnecting",
+			peer_id, duration, reason
+		);
+
+		// A ban lasting longer than what `SystemTime` can represent never expires.
+		let until = duration.and_then(|duration| SystemTime::now().checked_add(duration));
+		self.bans.insert(peer_id, PeerBan { reason, until });
+		self.data.peer_reputation(peer_id).set_reputation(i32::MIN);
+
+		self.disconnect_from_all_sets(peer_id);
+	}
+
+	fn on_unban_peer(&mut self, peer_id: PeerId) {
+		// We want reputations to be up-to-date before adjusting them.
+		self.update_time();
+
+		self.bans.remove(&peer_id);
+
+		let mut reputation = self.data.peer_reputation(peer_id);
+		if reputation.reputation() < 0 {
+			reputation.set_reputation(0);
+		}
+		debug!(target: "peerset", "Unban {}: reputation {}", peer_id, reputation.reputation());
+		drop(reputation);
+
+		for set_index in 0..self.data.num_sets() {
+			self.alloc_slots(SetId(set_index));
+		}
+	}
+
+	fn on_peer_seen(&mut self, peer_id: PeerId, address: Option<Multiaddr>) {
+		let now = SystemTime::now();
+		let seen = self
+			.seen
+			.entry(peer_id)
+			.or_insert_with(|| SeenPeer { addresses: Vec::new(), last_seen: now });
+
+		seen.last_seen = now;
+		if let Some(address) = address {
+			seen.addresses.retain(|a| *a != address);
+			seen.addresses.insert(0, address);
+			seen.addresses.truncate(peer_store::MAX_ADDRESSES_PER_PEER);
+		}
+
+		// Only the most recently seen nodes are persisted, don't let the list grow unbounded.
+		if self.seen.len() > 2 * peer_store::MAX_PERSISTED_PEERS {
+			let mut last_seen = self.seen.values().map(|seen| seen.last_seen).collect::<Vec<_>>();
+			last_seen.sort_unstable_by(|a, b| b.cmp(a));
+			let oldest_kept = last_seen[peer_store::MAX_PERSISTED_PEERS - 1];
+			self.seen.retain(|_, seen| seen.last_seen >= oldest_kept);
+		}
+	}
+
+	/// Returns the records of all the nodes with a non-zero reputation, a ban, or that we have
+	/// been connected to. The most recently seen nodes come first.
+	fn peer_records(&mut self) -> Vec<PeerRecord> {
+		// We want reputations to be up-to-date before reporting them.
+		self.update_time();
+
+		let peers = self
+			.data
+			.peers()
+			.chain(self.bans.keys())
+			.chain(self.seen.keys())
+			.cloned()
+			.collect::<HashSet<_>>();
+
+		let mut records = Vec::new();
+		for peer_id in peers {
+			let reputation = self.data.peer_reputation(peer_id).reputation();
+			let ban = self.bans.get(&peer_id).cloned();
+			let seen = self.seen.get(&peer_id);
+
+			if reputation == 0 && ban.is_none() && seen.is_none() {
+				continue
+			}
+
+			records.push(PeerRecord {
+				peer_id,
+				reputation,
+				ban,
+				addresses: seen.map(|seen| seen.addresses.clone()).unwrap_or_default(),
+				last_seen: seen.map(|seen| seen.last_seen),
+			});
+		}
+
+		records.sort_by_key(|record| cmp::Reverse(record.last_seen));
+		records
+	}
+
+	/// Restores the state loaded from the peer store.
+	fn restore_peer_records(&mut self, records: Vec<PeerRecord>) {
+		let now = SystemTime::now();
+
+		for record in records {
+			let ban = record.ban.filter(|ban| !ban.is_expired(now));
+			let reputation = if ban.is_some() { i32::MIN } else { record.reputation };
+			self.data.peer_reputation(record.peer_id).set_reputation(reputation);
+
+			if let Some(ban) = ban {
+				self.bans.insert(record.peer_id, ban);
+			}
+
+			if let Some(last_seen) = record.last_seen {
+				self.seen
+					.insert(record.peer_id, SeenPeer { addresses: record.addresses, last_seen });
+			}
+		}
+	}
+
+	/// Writes the peer store to disk, if enabled.
+	fn save_peer_store(&mut self) {
+		let Some(path) = self.peer_store.clone() else { return };
+
+		let mut records = self.peer_records();
+
+		// Banned nodes are always kept, no matter when we have last seen them.
+		let mut num_not_banned = 0;
+		records.retain(|record| {
+			if record.ban.is_some() {
+				return true
+			}
+			num_not_banned += 1;
+			num_not_banned <= peer_store::MAX_PERSISTED_PEERS
+		});
+
+		match peer_store::save(&path, &records) {
+			Ok(()) => trace!(target: "peerset", "Saved {} nodes to the peer store", records.len()),
+			Err(e) => error!(
+				target: "peerset",
+				"Failed to save the peer store to {}: {}",
+				path.display(),
+				e,
+			),
+		}
+	}
+
+	/// Lifts the manual bans that have expired.
+	fn expire_bans(&mut self) {
+		let now = SystemTime::now();
+		let expired = self
+			.bans
+			.iter()
+			.filter(|(_, ban)| ban.is_expired(now))
+			.map(|(peer_id, _)| *peer_id)
+			.collect::<Vec<_>>();
+
+		for peer_id in expired {
+			debug!(target: "peerset", "Ban of {} expired", peer_id);
+			self.bans.remove(&peer_id);
+			self.data.peer_reputation(peer_id).set_reputation(0);
+		}
+	}
+
 	/// Updates the value of `self.latest_time_update` and performs all the updates that happen
 	/// over time, such as reputation increases for staying connected.
 	fn update_time(&mut self) {
@@ -483,6 +746,10 @@ impl Peerset {
 			elapsed_now.as_secs() - elapsed_latest.as_secs()
 		};
 
+		if secs_diff > 0 && !self.bans.is_empty() {
+			self.expire_bans();
+		}
+
 		// For each elapsed second, move the node reputation towards zero.
 		// If we multiply each second the reputation by `k` (where `k` is between 0 and 1), it
 		// takes `ln(0.5) / ln(k)` seconds to reduce the reputation by half. Use this formula to
@@ -504,7 +771,9 @@ impl Peerset {
 				let mut peer_reputation = self.data.peer_reputation(peer_id);
 
 				let before = peer_reputation.reputation();
-				let after = reput_tick(before);
+				// The reputation of banned nodes stays pinned until the ban is lifted.
+				let after =
+					if self.bans.contains_key(&peer_id) { i32::MIN } else { reput_tick(before) };
 				trace!(target: "peerset", "Fleeting {}: {} -> {}", peer_id, before, after);
 				peer_reputation.set_reputation(after);
 
@@ -550,7 +819,7 @@ impl Peerset {
 			// remove that check. If necessary, the peerset should be refactored to give more
 			// control over what happens in that situation.
 			if entry.reputation() < BANNED_THRESHOLD {
-				break
+				continue
 			}
 
 			match entry.try_outgoing() {
@@ -608,22 +877,47 @@ impl Peerset {
 	/// Indicate that we received an incoming connection. Must be answered either with
 	/// a corresponding `Accept` or `Reject`, except if we were already connected to this peer.
 	///
+	/// `ip` is the IP address the peer connected from, if known. It is used to enforce the
+	/// [`PeersetConfig::ip_limits`].
+	///
 	/// Note that this mechanism is orthogonal to `Connect`/`Drop`. Accepting an incoming
 	/// connection implicitly means `Connect`, but incoming connections aren't cancelled by
 	/// `dropped`.
 	// Implementation note: because of concurrency issues, it is possible that we push a `Connect`
 	// message to the output channel with a `PeerId`, and that `incoming` gets called with the same
 	// `PeerId` before that message has been read by the user. In this situation we must not answer.
-	pub fn incoming(&mut self, set_id: SetId, peer_id: PeerId, index: IncomingIndex) {
-		trace!(target: "peerset", "Incoming {:?}", peer_id);
+	pub fn incoming(
+		&mut self,
+		set_id: SetId,
+		peer_id: PeerId,
+		ip: Option<IpAddr>,
+		index: IncomingIndex,
+	) {
+		trace!(target: "peerset", "Incoming {:?} from {:?}", peer_id, ip);
 
 		self.update_time();
 
-		if self.reserved_nodes[set_id.0].1 && !self.reserved_nodes[set_id.0].0.contains(&peer_id) {
+		let is_reserved = self.reserved_nodes[set_id.0].0.contains(&peer_id);
+		if self.reserved_nodes[set_id.0].1 && !is_reserved {
 			self.message_queue.push_back(Message::Reject(index));
 			return
 		}
 
+		// Reserved nodes are exempt from the IP limits.
+		let ip = ip.filter(|_| !is_reserved);
+		if let Some(ip) = ip {
+			let data = &self.data;
+			self.ip_tracker.retain(|set, peer_id| data.is_connected_in(set, peer_id));
+
+			if !matches!(self.data.peer(set_id.0, &peer_id), peersstate::Peer::Connected(_)) &&
+				!self.ip_tracker.check(set_id.0, &peer_id, ip)
+			{
+				debug!(target: "peerset", "Rejecting {:?}: too many peers from {}", peer_id, ip);
+				self.message_queue.push_back(Message::Reject(index));
+				return
+			}
+		}
+
 		let not_connected = match self.data.peer(set_id.0, &peer_id) {
 			// If we're already connected, don't answer, as the docs mention.
 			peersstate::Peer::Connected(_) => return,
@@ -640,7 +934,13 @@ impl Peerset {
 		}
 
 		match not_connected.try_accept_incoming() {
-			Ok(_) => self.message_queue.push_back(Message::Accept(index)),
+			Ok(_) => {
+				if let Some(ip) = ip {
+					let num_sets = self.data.num_sets();
+					self.ip_tracker.accepted(set_id.0, num_sets, peer_id, ip);
+				}
+				self.message_queue.push_back(Message::Accept(index))
+			},
 			Err(_) => self.message_queue.push_back(Message::Reject(index)),
 		}
 	}
@@ -713,10 +1013,31 @@ impl Peerset {
 		})
 	}
 
+	/// Returns the number of incoming connections rejected because of each of the
+	/// [`PeersetConfig::ip_limits`] since the peerset was created.
+	pub fn ip_limit_rejections(&self) -> IpLimitRejections {
+		self.ip_tracker.rejections()
+	}
+
 	/// Returns the number of peers that we have discovered.
 	pub fn num_discovered_peers(&self) -> usize {
 		self.data.peers().len()
 	}
+
+	/// Returns the addresses we have last seen the peers at, including the ones loaded from the
+	/// peer store. Banned peers are skipped.
+	pub fn known_addresses(&self) -> impl Iterator<Item = (PeerId, Multiaddr)> + '_ {
+		self.seen
+			.iter()
+			.filter(|(peer_id, _)| !self.bans.contains_key(peer_id))
+			.flat_map(|(peer_id, seen)| seen.addresses.iter().map(move |a| (*peer_id, a.clone())))
+	}
+}
+
+impl Drop for Peerset {
+	fn drop(&mut self) {
+		self.save_peer_store();
+	}
 }
 
 impl Stream for Peerset {
@@ -736,6 +1057,13 @@ impl Stream for Peerset {
 				}
 			}
 
+			if self.peer_store.is_some() &&
+				Future::poll(Pin::new(&mut self.next_peer_store_save), cx).is_ready()
+			{
+				self.next_peer_store_save = Delay::new(PEER_STORE_SAVE_INTERVAL);
+				self.save_peer_store();
+			}
+
 			let action = match Stream::poll_next(Pin::new(&mut self.rx), cx) {
 				Poll::Pending => return Poll::Pending,
 				Poll::Ready(Some(event)) => event,
@@ -758,6 +1086,13 @@ impl Stream for Peerset {
 					self.on_remove_from_peers_set(sets_name, peer_id),
 				Action::PeerReputation(peer_id, pending_response) =>
 					self.on_peer_reputation(peer_id, pending_response),
+				Action::BanPeer(peer_id, reason, duration) =>
+					self.on_ban_peer(peer_id, reason, duration),
+				Action::UnbanPeer(peer_id) => self.on_unban_peer(peer_id),
+				Action::PeerSeen(peer_id, address) => self.on_peer_seen(peer_id, address),
+				Action::PeerRecords(pending_response) => {
+					let _ = pending_response.send(self.peer_records());
+				},
 			}
 		}
 	}
@@ -777,12 +1112,12 @@ pub enum DropReason {
 #[cfg(test)]
 mod tests {
 	use super::{
-		IncomingIndex, Message, Peerset, PeersetConfig, ReputationChange, SetConfig, SetId,
-		BANNED_THRESHOLD,
+		DropReason, IncomingIndex, IpLimitRejections, IpLimits, Message, Peerset, PeersetConfig,
+		ReputationChange, SetConfig, SetId, BANNED_THRESHOLD,
 	};
 	use futures::prelude::*;
-	use libp2p::PeerId;
-	use std::{pin::Pin, task::Poll, thread, time::Duration};
+	use libp2p::{Multiaddr, PeerId};
+	use std::{path::PathBuf, pin::Pin, task::Poll, thread, time::Duration};
 
 	fn assert_messages(mut peerset: Peerset, messages: Vec<Message>) -> Peerset {
 		for expected_message in messages {
@@ -812,6 +1147,8 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: true,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		};
 
 		let (peerset, handle) = Peerset::from_config(config);
@@ -845,13 +1182,15 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
-		peerset.incoming(SetId::from(0), incoming, ii);
-		peerset.incoming(SetId::from(0), incoming, ii4);
-		peerset.incoming(SetId::from(0), incoming2, ii2);
-		peerset.incoming(SetId::from(0), incoming3, ii3);
+		peerset.incoming(SetId::from(0), incoming, None, ii);
+		peerset.incoming(SetId::from(0), incoming, None, ii4);
+		peerset.incoming(SetId::from(0), incoming2, None, ii2);
+		peerset.incoming(SetId::from(0), incoming3, None, ii3);
 
 		assert_messages(
 			peerset,
@@ -864,6 +1203,54 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn test_peerset_incoming_ip_limits() {
+		let peers = (0..5).map(|_| PeerId::random()).collect::<Vec<_>>();
+		let config = PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 0,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			peer_store: None,
+			ip_limits: IpLimits {
+				max_inbound_per_ip: Some(1),
+				max_inbound_per_subnet: Some(2),
+				..Default::default()
+			},
+		};
+		let ip = |ip: &str| Some(ip.parse().unwrap());
+
+		let (mut peerset, _handle) = Peerset::from_config(config);
+		peerset.incoming(SetId::from(0), peers[0], ip("10.0.0.1"), IncomingIndex(1));
+		peerset.incoming(SetId::from(0), peers[1], ip("10.0.0.1"), IncomingIndex(2));
+		peerset.incoming(SetId::from(0), peers[2], ip("10.0.0.2"), IncomingIndex(3));
+		peerset.incoming(SetId::from(0), peers[3], ip("10.0.0.3"), IncomingIndex(4));
+		peerset.incoming(SetId::from(0), peers[4], ip("10.0.1.1"), IncomingIndex(5));
+
+		let mut peerset = assert_messages(
+			peerset,
+			vec![
+				Message::Accept(IncomingIndex(1)),
+				Message::Reject(IncomingIndex(2)),
+				Message::Accept(IncomingIndex(3)),
+				Message::Reject(IncomingIndex(4)),
+				Message::Accept(IncomingIndex(5)),
+			],
+		);
+		assert_eq!(
+			peerset.ip_limit_rejections(),
+			IpLimitRejections { inbound_per_ip: 1, inbound_per_subnet: 1, ..Default::default() },
+		);
+
+		// The slot is released once the peer disconnects.
+		peerset.dropped(SetId::from(0), peers[0], DropReason::Unknown);
+		peerset.incoming(SetId::from(0), peers[1], ip("10.0.0.1"), IncomingIndex(6));
+		assert_messages(peerset, vec![Message::Accept(IncomingIndex(6))]);
+	}
+
 	#[test]
 	fn test_peerset_reject_incoming_in_reserved_only() {
 		let incoming = PeerId::random();
@@ -876,10 +1263,12 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: true,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		};
 
 		let (mut peerset, _) = Peerset::from_config(config);
-		peerset.incoming(SetId::from(0), incoming, ii);
+		peerset.incoming(SetId::from(0), incoming, None, ii);
 
 		assert_messages(peerset, vec![Message::Reject(ii)]);
 	}
@@ -897,6 +1286,8 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		};
 
 		let (mut peerset, _handle) = Peerset::from_config(config);
@@ -923,6 +1314,8 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		});
 
 		// We ban a node by setting its reputation under the threshold.
@@ -934,7 +1327,7 @@ mod tests {
 			assert_eq!(Stream::poll_next(Pin::new(&mut peerset), cx), Poll::Pending);
 
 			// Check that an incoming connection from that node gets refused.
-			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(1));
 			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
 				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(1)));
 			} else {
@@ -945,7 +1338,7 @@ mod tests {
 			thread::sleep(Duration::from_millis(1500));
 
 			// Try again. This time the node should be accepted.
-			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(2));
+			peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(2));
 			while let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
 				assert_eq!(msg.unwrap(), Message::Accept(IncomingIndex(2)));
 			}
@@ -966,6 +1359,8 @@ mod tests {
 				reserved_nodes: Default::default(),
 				reserved_only: false,
 			}],
+			peer_store: None,
+			ip_limits: Default::default(),
 		});
 
 		// We ban a node by setting its reputation under the threshold.
@@ -979,7 +1374,7 @@ mod tests {
 			// Check that an incoming connection from that node gets refused.
 			// This is already tested in other tests, but it is done again here because it doesn't
 			// hurt.
-			peerset.incoming(SetId::from(0), peer_id, IncomingIndex(1));
+			peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(1));
 			if let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut peerset), cx) {
 				assert_eq!(msg.unwrap(), Message::Reject(IncomingIndex(1)));
 			} else {
@@ -996,4 +1391,157 @@ mod tests {
 
 		futures::executor::block_on(fut);
 	}
+
+	fn peerset_with_peer_store(peer_store: Option<PathBuf>) -> Peerset {
+		Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			peer_store,
+			ip_limits: Default::default(),
+		})
+		.0
+	}
+
+	/// Processes all the pending actions and returns the emitted messages.
+	fn poll_messages(peerset: &mut Peerset) -> Vec<Message> {
+		futures::executor::block_on(futures::future::poll_fn(|cx| {
+			let mut messages = Vec::new();
+			while let Poll::Ready(msg) = Stream::poll_next(Pin::new(&mut *peerset), cx) {
+				messages.push(msg.unwrap());
+			}
+			Poll::Ready(messages)
+		}))
+	}
+
+	#[test]
+	fn test_peerset_ban_and_unban() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			peer_store: None,
+			ip_limits: Default::default(),
+		});
+
+		let peer_id = PeerId::random();
+		peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(1));
+		assert_eq!(poll_messages(&mut peerset), vec![Message::Accept(IncomingIndex(1))]);
+
+		// Banning disconnects the peer and refuses it afterwards, even after the reputation
+		// would have recovered from a reputation based ban.
+		handle.ban_peer(peer_id, "Misbehaving".into(), None);
+		assert_eq!(
+			poll_messages(&mut peerset),
+			vec![Message::Drop { set_id: SetId::from(0), peer_id }]
+		);
+		peerset.dropped(SetId::from(0), peer_id, super::DropReason::Unknown);
+		thread::sleep(Duration::from_millis(1500));
+		peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(2));
+		assert_eq!(poll_messages(&mut peerset), vec![Message::Reject(IncomingIndex(2))]);
+
+		let records = peerset.peer_records();
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].reputation, i32::MIN);
+		assert_eq!(records[0].ban.as_ref().unwrap().reason, "Misbehaving");
+
+		// Once unbanned, we are willing to connect to the peer again.
+		handle.unban_peer(peer_id);
+		assert_eq!(
+			poll_messages(&mut peerset),
+			vec![Message::Connect { set_id: SetId::from(0), peer_id }]
+		);
+	}
+
+	#[test]
+	fn test_peerset_ban_expires() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			peer_store: None,
+			ip_limits: Default::default(),
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, String::new(), Some(Duration::from_secs(1)));
+		poll_messages(&mut peerset);
+
+		peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(1));
+		assert_eq!(poll_messages(&mut peerset), vec![Message::Reject(IncomingIndex(1))]);
+
+		thread::sleep(Duration::from_millis(2100));
+
+		peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(2));
+		assert_eq!(poll_messages(&mut peerset), vec![Message::Accept(IncomingIndex(2))]);
+	}
+
+	#[test]
+	fn test_peerset_ban_with_huge_duration_never_expires() {
+		let (mut peerset, handle) = Peerset::from_config(PeersetConfig {
+			sets: vec![SetConfig {
+				in_peers: 25,
+				out_peers: 25,
+				bootnodes: vec![],
+				reserved_nodes: Default::default(),
+				reserved_only: false,
+			}],
+			peer_store: None,
+			ip_limits: Default::default(),
+		});
+
+		let peer_id = PeerId::random();
+		handle.ban_peer(peer_id, String::new(), Some(Duration::from_secs(u64::MAX)));
+		poll_messages(&mut peerset);
+
+		let records = peerset.peer_records();
+		assert_eq!(records.len(), 1);
+		assert_eq!(records[0].ban.as_ref().unwrap().until, None);
+
+		peerset.incoming(SetId::from(0), peer_id, None, IncomingIndex(1));
+		assert_eq!(poll_messages(&mut peerset), vec![Message::Reject(IncomingIndex(1))]);
+	}
+
+	#[test]
+	fn test_peer_store_survives_restart() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers.json");
+
+		let good_peer = PeerId::random();
+		let banned_peer = PeerId::random();
+		let address: Multiaddr = "/ip4/127.0.0.1/tcp/30333".parse().unwrap();
+
+		{
+			let mut peerset = peerset_with_peer_store(Some(path.clone()));
+			let handle = super::PeersetHandle { tx: peerset.tx.clone() };
+			handle.report_peer(good_peer, ReputationChange::new(1000, ""));
+			handle.note_peer_seen(good_peer, Some(address.clone()));
+			handle.ban_peer(banned_peer, "Sent bad blocks".into(), None);
+			poll_messages(&mut peerset);
+		}
+
+		let mut peerset = peerset_with_peer_store(Some(path));
+
+		assert_eq!(peerset.known_addresses().collect::<Vec<_>>(), vec![(good_peer, address)]);
+		assert!(peerset.data.peer_reputation(good_peer).reputation() > 0);
+
+		peerset.incoming(SetId::from(0), banned_peer, None, IncomingIndex(1));
+		peerset.incoming(SetId::from(0), good_peer, None, IncomingIndex(2));
+		assert_eq!(
+			poll_messages(&mut peerset),
+			vec![Message::Reject(IncomingIndex(1)), Message::Accept(IncomingIndex(2))]
+		);
+	}
 }
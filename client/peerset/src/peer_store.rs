@@ -0,0 +1,306 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: GPL-3.0-or-later WITH Classpath-exception-2.0
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! On-disk persistence of the peer reputations, bans and addresses.
+//!
+//! The peer store is a JSON file containing one [`PeerRecord`] per peer. It is loaded when the
+//! [`Peerset`](crate::Peerset) is created and written back periodically and when the
+//! [`Peerset`](crate::Peerset) is dropped.
+
+use libp2p::{Multiaddr, PeerId};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+	fs, io,
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Maximum number of peers written to the peer store, banned peers excluded.
+///
+/// The peers seen most recently are kept.
+pub(crate) const MAX_PERSISTED_PEERS: usize = 1000;
+
+/// Maximum number of addresses remembered per peer.
+pub(crate) const MAX_ADDRESSES_PER_PEER: usize = 4;
+
+/// Everything the peerset remembers about a peer across restarts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerRecord {
+	/// Identity of the peer.
+	pub peer_id: PeerId,
+	/// Reputation of the peer. See [`crate::BANNED_THRESHOLD`].
+	pub reputation: i32,
+	/// The manual ban of the peer, if any.
+	pub ban: Option<PeerBan>,
+	/// Addresses we connected to the peer at, most recent first.
+	pub addresses: Vec<Multiaddr>,
+	/// When we were last connected to the peer.
+	pub last_seen: Option<SystemTime>,
+}
+
+/// A manual ban of a peer, see [`crate::PeersetHandle::ban_peer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerBan {
+	/// Why the peer was banned.
+	pub reason: String,
+	/// When the ban expires. `None` if the ban never expires.
+	pub until: Option<SystemTime>,
+}
+
+impl PeerBan {
+	/// Returns `true` if the ban has expired at `now`.
+	pub fn is_expired(&self, now: SystemTime) -> bool {
+		matches!(self.until, Some(until) if until <= now)
+	}
+}
+
+/// JSON representation of the peer store file.
+#[derive(Default, Serialize, Deserialize)]
+struct PeerStoreFile {
+	peers: Vec<StoredPeer>,
+}
+
+/// JSON representation of a [`PeerRecord`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredPeer {
+	peer_id: String,
+	#[serde(default)]
+	reputation: i32,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	ban: Option<StoredBan>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	addresses: Vec<String>,
+	/// Seconds since the UNIX epoch.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	last_seen: Option<u64>,
+}
+
+/// JSON representation of a [`PeerBan`].
+#[derive(Serialize, Deserialize)]
+struct StoredBan {
+	reason: String,
+	/// Seconds since the UNIX epoch.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	until: Option<u64>,
+}
+
+fn to_unix_secs(time: SystemTime) -> u64 {
+	time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// Returns `None` if `secs` is too large to be represented as a `SystemTime`.
+fn from_unix_secs(secs: u64) -> Option<SystemTime> {
+	UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+impl From<&PeerRecord> for StoredPeer {
+	fn from(record: &PeerRecord) -> Self {
+		StoredPeer {
+			peer_id: record.peer_id.to_base58(),
+			reputation: record.reputation,
+			ban: record.ban.as_ref().map(|ban| StoredBan {
+				reason: ban.reason.clone(),
+				until: ban.until.map(to_unix_secs),
+			}),
+			addresses: record.addresses.iter().map(ToString::to_string).collect(),
+			last_seen: record.last_seen.map(to_unix_secs),
+		}
+	}
+}
+
+impl TryFrom<StoredPeer> for PeerRecord {
+	type Error = String;
+
+	fn try_from(stored: StoredPeer) -> Result<Self, Self::Error> {
+		let peer_id = stored
+			.peer_id
+			.parse::<PeerId>()
+			.map_err(|e| format!("Invalid peer id {}: {}", stored.peer_id, e))?;
+
+		Ok(PeerRecord {
+			peer_id,
+			reputation: stored.reputation,
+			ban: stored
+				.ban
+				// A corrupt expiry is treated as a ban that never expires.
+				.map(|ban| PeerBan {
+					reason: ban.reason,
+					until: ban.until.and_then(from_unix_secs),
+				}),
+			// Addresses that can't be parsed anymore are just forgotten.
+			addresses: stored
+				.addresses
+				.iter()
+				.filter_map(|addr| addr.parse().ok())
+				.take(MAX_ADDRESSES_PER_PEER)
+				.collect(),
+			last_seen: stored.last_seen.and_then(from_unix_secs),
+		})
+	}
+}
+
+/// Loads the peer records from the file at `path`.
+///
+/// Returns an empty list if the file doesn't exist. Invalid records are skipped.
+pub(crate) fn load(path: &Path) -> io::Result<Vec<PeerRecord>> {
+	let content = match fs::read(path) {
+		Ok(content) => content,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	let file: PeerStoreFile = serde_json::from_slice(&content)?;
+
+	Ok(file
+		.peers
+		.into_iter()
+		.filter_map(|stored| match PeerRecord::try_from(stored) {
+			Ok(record) => Some(record),
+			Err(e) => {
+				warn!(target: "peerset", "Skipping peer store entry: {}", e);
+				None
+			},
+		})
+		.collect())
+}
+
+/// Writes the given peer records to the file at `path`.
+///
+/// The records are written to a temporary file first, which then replaces the file at `path`, so
+/// that a crash while writing doesn't corrupt the peer store.
+pub(crate) fn save(path: &Path, records: &[PeerRecord]) -> io::Result<()> {
+	let file = PeerStoreFile { peers: records.iter().map(StoredPeer::from).collect() };
+	let content = serde_json::to_vec_pretty(&file)?;
+
+	let tmp_path = path.with_extension("tmp");
+	fs::write(&tmp_path, content)?;
+	fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn save_and_load_works() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers.json");
+
+		let records = vec![
+			PeerRecord {
+				peer_id: PeerId::random(),
+				reputation: -500,
+				ban: Some(PeerBan {
+					reason: "Sent bad blocks".into(),
+					until: Some(from_unix_secs(1_700_000_000).unwrap()),
+				}),
+				addresses: vec![],
+				last_seen: None,
+			},
+			PeerRecord {
+				peer_id: PeerId::random(),
+				reputation: 100,
+				ban: None,
+				addresses: vec!["/ip4/127.0.0.1/tcp/30333".parse().unwrap()],
+				last_seen: Some(from_unix_secs(1_600_000_000).unwrap()),
+			},
+		];
+
+		save(&path, &records).unwrap();
+		assert_eq!(load(&path).unwrap(), records);
+	}
+
+	#[test]
+	fn load_missing_file_returns_nothing() {
+		let dir = tempfile::tempdir().unwrap();
+		assert_eq!(load(&dir.path().join("peers.json")).unwrap(), vec![]);
+	}
+
+	#[test]
+	fn load_skips_invalid_entries() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers.json");
+		let peer_id = PeerId::random();
+
+		fs::write(
+			&path,
+			format!(
+				r#"{{"peers":[
+					{{"peerId":"invalid","reputation":5}},
+					{{"peerId":"{}","addresses":["invalid","/ip4/1.2.3.4/tcp/1"]}}
+				]}}"#,
+				peer_id.to_base58()
+			),
+		)
+		.unwrap();
+
+		assert_eq!(
+			load(&path).unwrap(),
+			vec![PeerRecord {
+				peer_id,
+				reputation: 0,
+				ban: None,
+				addresses: vec!["/ip4/1.2.3.4/tcp/1".parse().unwrap()],
+				last_seen: None,
+			}]
+		);
+	}
+
+	#[test]
+	fn load_ignores_out_of_range_times() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("peers.json");
+		let peer_id = PeerId::random();
+
+		fs::write(
+			&path,
+			format!(
+				r#"{{"peers":[
+					{{"peerId":"{}","ban":{{"reason":"Spam","until":{}}},"lastSeen":{}}}
+				]}}"#,
+				peer_id.to_base58(),
+				u64::MAX,
+				u64::MAX,
+			),
+		)
+		.unwrap();
+
+		assert_eq!(
+			load(&path).unwrap(),
+			vec![PeerRecord {
+				peer_id,
+				reputation: 0,
+				ban: Some(PeerBan { reason: "Spam".into(), until: None }),
+				addresses: vec![],
+				last_seen: None,
+			}]
+		);
+	}
+
+	#[test]
+	fn ban_expiry_works() {
+		let ban = PeerBan { reason: String::new(), until: from_unix_secs(100) };
+		assert!(!ban.is_expired(from_unix_secs(99).unwrap()));
+		assert!(ban.is_expired(from_unix_secs(100).unwrap()));
+
+		let ban = PeerBan { reason: String::new(), until: None };
+		assert!(!ban.is_expired(from_unix_secs(u32::MAX as u64).unwrap()));
+	}
+}
@@ -228,6 +228,18 @@ impl PeersState {
 			.map(|(p, _)| p)
 	}
 
+	/// Returns `true` if we are connected to the peer through an ingoing connection in the
+	/// context of a specific set.
+	///
+	/// # Panic
+	///
+	/// `set` must be within range of the sets passed to [`PeersState::new`].
+	pub fn is_connected_in(&self, set: usize, peer_id: &PeerId) -> bool {
+		assert!(set < self.sets.len());
+
+		matches!(self.nodes.get(peer_id).map(|p| p.sets[set]), Some(MembershipState::In))
+	}
+
 	/// Returns the peer with the highest reputation and that we are not connected to.
 	///
 	/// If multiple nodes have the same reputation, which one is returned is unspecified.
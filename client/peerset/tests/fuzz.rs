@@ -70,6 +70,8 @@ fn test_once() {
 			out_peers: Uniform::new_inclusive(0, 25).sample(&mut rng),
 			reserved_only: Uniform::new_inclusive(0, 10).sample(&mut rng) == 0,
 		}],
+		peer_store: None,
+		ip_limits: Default::default(),
 	});
 
 	futures::executor::block_on(futures::future::poll_fn(move |cx| {
@@ -142,7 +144,7 @@ fn test_once() {
 						})
 						.choose(&mut rng)
 					{
-						peerset.incoming(SetId::from(0), *id, next_incoming_id);
+						peerset.incoming(SetId::from(0), *id, None, next_incoming_id);
 						incoming_nodes.insert(next_incoming_id, *id);
 						next_incoming_id.0 += 1;
 					}
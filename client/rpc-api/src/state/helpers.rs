@@ -19,7 +19,10 @@
 //! Substrate state API helpers.
 
 use serde::{Deserialize, Serialize};
-use sp_core::Bytes;
+use sp_core::{
+	storage::{PrefixedStorageKey, StorageData, StorageKey},
+	Bytes,
+};
 
 /// ReadProof struct returned by the RPC
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
@@ -30,3 +33,35 @@ pub struct ReadProof<Hash> {
 	/// A proof used to prove that storage entries are included in the storage trie
 	pub proof: Vec<Bytes>,
 }
+
+/// CompactReadProof struct returned by the RPC
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReadProof<Hash> {
+	/// Hash of the last block of the range used to generate the proof
+	pub at: Hash,
+	/// The SCALE encoded `CompactProof` of every block in the range, oldest first, which together
+	/// form a `MultiRootCompactProof`
+	pub proofs: Vec<Bytes>,
+}
+
+/// The key of a [`StorageDiffEntry`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiffKey {
+	/// Prefixed storage key of the child trie, `None` for the main storage.
+	pub child_storage_key: Option<PrefixedStorageKey>,
+	/// The storage key.
+	pub key: StorageKey,
+}
+
+/// StorageDiffEntry struct returned by the RPC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageDiffEntry {
+	/// The key that was changed
+	#[serde(flatten)]
+	pub key: StorageDiffKey,
+	/// The value at the target block, `None` if the key was removed
+	pub value: Option<StorageData>,
+}
@@ -28,7 +28,7 @@ use sp_version::RuntimeVersion;
 pub mod error;
 pub mod helpers;
 
-pub use self::helpers::ReadProof;
+pub use self::helpers::{CompactReadProof, ReadProof, StorageDiffEntry, StorageDiffKey};
 
 /// Substrate state API
 #[rpc(client, server)]
@@ -107,10 +107,36 @@ pub trait StateApi<Hash> {
 		at: Option<Hash>,
 	) -> RpcResult<Vec<StorageChangeSet<Hash>>>;
 
+	/// Returns the storage entries that changed between the block `from` and the block `hash`.
+	///
+	/// `from` must be an ancestor of `hash`. The entries are sorted by their key, main storage
+	/// first. Up to `count` entries will be returned. If `start_key` is passed, return the entries
+	/// after it. Requires the node to index the storage changes of the blocks in the range.
+	#[method(name = "state_getStorageDiff", blocking)]
+	fn storage_diff(
+		&self,
+		from: Hash,
+		count: u32,
+		start_key: Option<StorageDiffKey>,
+		hash: Option<Hash>,
+	) -> RpcResult<Vec<StorageDiffEntry>>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	#[method(name = "state_getReadProof", blocking)]
 	fn read_proof(&self, keys: Vec<StorageKey>, hash: Option<Hash>) -> RpcResult<ReadProof<Hash>>;
 
+	/// Returns a compact proof of storage entries at the state of all blocks from `from` up to
+	/// `hash`.
+	///
+	/// `from` must be an ancestor of `hash`.
+	#[method(name = "state_getReadProofRange", blocking)]
+	fn read_proof_range(
+		&self,
+		keys: Vec<StorageKey>,
+		from: Hash,
+		hash: Option<Hash>,
+	) -> RpcResult<CompactReadProof<Hash>>;
+
 	/// New runtime version subscription
 	#[subscription(
 		name = "state_subscribeRuntimeVersion" => "state_runtimeVersion",
@@ -186,6 +212,9 @@ pub trait StateApi<Hash> {
 	/// do not reduce time for actual block re-execution, they reduce the response payload size.
 	///
 	/// Note: storage events primarily come from _primitives/state-machine/src/ext.rs_.
+	/// Log records the runtime emitted with structured fields (see
+	/// `RuntimeLogger::log_with_fields`) are only filtered by tracing targets; their fields show
+	/// up as event values.
 	/// The default filters can be overridden, see the [params section](#params) for details.
 	///
 	/// ### `curl` example
@@ -36,6 +36,9 @@ pub enum Error {
 	/// Peer argument is malformatted.
 	#[error("{0}")]
 	MalformattedPeerArg(String),
+	/// Ban duration is too large.
+	#[error("Ban duration of {0} seconds is out of range")]
+	InvalidBanDuration(u64),
 }
 
 // Base code for all system errors.
@@ -44,6 +47,8 @@ const BASE_ERROR: i32 = 2000;
 const NOT_HEALTHY_ERROR: i32 = BASE_ERROR + 1;
 // Peer argument is malformatted.
 const MALFORMATTED_PEER_ARG_ERROR: i32 = BASE_ERROR + 2;
+// Ban duration is too large.
+const INVALID_BAN_DURATION_ERROR: i32 = BASE_ERROR + 3;
 
 impl From<Error> for JsonRpseeError {
 	fn from(e: Error) -> Self {
@@ -55,6 +60,11 @@ impl From<Error> for JsonRpseeError {
 				e,
 				None::<()>,
 			)),
+			Error::InvalidBanDuration(_) => CallError::Custom(ErrorObject::owned(
+				INVALID_BAN_DURATION_ERROR,
+				e.to_string(),
+				None::<()>,
+			)),
 		}
 		.into()
 	}
@@ -71,6 +71,32 @@ pub struct PeerInfo<Hash, Number> {
 	pub best_number: Number,
 }
 
+/// Reputation, ban and last seen addresses of a peer, as remembered by the node.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+	/// Peer ID
+	pub peer_id: String,
+	/// Reputation of the peer. The node doesn't connect to peers with a very low reputation.
+	pub reputation: i32,
+	/// The manual ban of the peer, if any.
+	pub ban: Option<PeerBan>,
+	/// Addresses the node connected to the peer at, most recent first.
+	pub addresses: Vec<String>,
+	/// When the node was last connected to the peer, in seconds since the UNIX epoch.
+	pub last_seen: Option<u64>,
+}
+
+/// Manual ban of a peer.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerBan {
+	/// Why the peer was banned.
+	pub reason: String,
+	/// When the ban expires, in seconds since the UNIX epoch. `None` if it never expires.
+	pub until: Option<u64>,
+}
+
 /// The role the node is running as
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum NodeRole {
@@ -123,6 +149,21 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn should_serialize_peer_reputation() {
+		assert_eq!(
+			::serde_json::to_string(&PeerReputation {
+				peer_id: "2".into(),
+				reputation: -5,
+				ban: Some(PeerBan { reason: "a".into(), until: Some(10) }),
+				addresses: vec!["b".into()],
+				last_seen: None,
+			})
+			.unwrap(),
+			r#"{"peerId":"2","reputation":-5,"ban":{"reason":"a","until":10},"addresses":["b"],"lastSeen":null}"#,
+		);
+	}
+
 	#[test]
 	fn should_serialize_sync_state() {
 		assert_eq!(
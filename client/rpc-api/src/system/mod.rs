@@ -23,7 +23,9 @@ use jsonrpsee::{
 	proc_macros::rpc,
 };
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{
+	Health, NodeRole, PeerBan, PeerInfo, PeerReputation, SyncState, SystemInfo,
+};
 
 pub mod error;
 pub mod helpers;
@@ -100,6 +102,32 @@ pub trait SystemApi<Hash, Number> {
 	#[method(name = "system_reservedPeers")]
 	async fn system_reserved_peers(&self) -> RpcResult<Vec<String>>;
 
+	/// Returns the reputations, bans and last seen addresses of the peers known to the node.
+	///
+	/// These are persisted across restarts if the node runs with `--persistent-peer-store`.
+	#[method(name = "system_peerReputations")]
+	async fn system_peer_reputations(&self) -> RpcResult<Vec<PeerReputation>>;
+
+	/// Bans a peer for `duration` seconds, or until it is unbanned if no duration is given.
+	/// `peer_id` should encode only the PeerId e.g.
+	/// `QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV`, and `reason` is recorded alongside the
+	/// ban.
+	///
+	/// The node disconnects from the peer and refuses to connect to it, even if it is a reserved
+	/// peer. Fails if the peer id is malformatted or if the ban would end too far in the future.
+	#[method(name = "system_banPeer")]
+	async fn system_ban_peer(
+		&self,
+		peer_id: String,
+		reason: String,
+		duration: Option<u64>,
+	) -> RpcResult<()>;
+
+	/// Lifts the ban of a peer and resets its reputation to zero if it was negative, so that the
+	/// node is willing to connect to it again. Fails if the peer id is malformatted.
+	#[method(name = "system_unbanPeer")]
+	async fn system_unban_peer(&self, peer_id: String) -> RpcResult<()>;
+
 	/// Returns the roles the node is running as.
 	#[method(name = "system_nodeRoles")]
 	async fn system_node_roles(&self) -> RpcResult<Vec<NodeRole>>;
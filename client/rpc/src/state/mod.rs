@@ -33,7 +33,10 @@ use jsonrpsee::{
 	types::SubscriptionResult,
 };
 
-use sc_rpc_api::{state::ReadProof, DenyUnsafe};
+use sc_rpc_api::{
+	state::{CompactReadProof, ReadProof},
+	DenyUnsafe,
+};
 use sp_core::{
 	storage::{PrefixedStorageKey, StorageChangeSet, StorageData, StorageKey},
 	Bytes,
@@ -141,6 +144,15 @@ where
 		at: Option<Block::Hash>,
 	) -> Result<Vec<StorageChangeSet<Block::Hash>>, Error>;
 
+	/// Returns the storage entries that changed between the block `from` and the block `to`.
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		count: u32,
+		start_key: Option<StorageDiffKey>,
+	) -> Result<Vec<StorageDiffEntry>, Error>;
+
 	/// Returns proof of storage entries at a specific block's state.
 	fn read_proof(
 		&self,
@@ -148,6 +160,15 @@ where
 		keys: Vec<StorageKey>,
 	) -> Result<ReadProof<Block::Hash>, Error>;
 
+	/// Returns a compact proof of storage entries at the state of all blocks in the range
+	/// `from..=to`.
+	fn read_proof_range(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> Result<CompactReadProof<Block::Hash>, Error>;
+
 	/// Trace storage changes for block
 	fn trace_block(
 		&self,
@@ -299,6 +320,23 @@ where
 		self.backend.query_storage_at(keys, at).map_err(Into::into)
 	}
 
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		count: u32,
+		start_key: Option<StorageDiffKey>,
+		block: Option<Block::Hash>,
+	) -> RpcResult<Vec<StorageDiffEntry>> {
+		self.deny_unsafe.check_if_safe()?;
+		if count > STORAGE_KEYS_PAGED_MAX_COUNT {
+			return Err(JsonRpseeError::from(Error::InvalidCount {
+				value: count,
+				max: STORAGE_KEYS_PAGED_MAX_COUNT,
+			}))
+		}
+		self.backend.storage_diff(from, block, count, start_key).map_err(Into::into)
+	}
+
 	fn read_proof(
 		&self,
 		keys: Vec<StorageKey>,
@@ -307,6 +345,15 @@ where
 		self.backend.read_proof(block, keys).map_err(Into::into)
 	}
 
+	fn read_proof_range(
+		&self,
+		keys: Vec<StorageKey>,
+		from: Block::Hash,
+		block: Option<Block::Hash>,
+	) -> RpcResult<CompactReadProof<Block::Hash>> {
+		self.backend.read_proof_range(from, block, keys).map_err(Into::into)
+	}
+
 	/// Re-execute the given block with the tracing targets given in `targets`
 	/// and capture all state changes.
 	///
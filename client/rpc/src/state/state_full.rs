@@ -27,6 +27,7 @@ use super::{
 };
 use crate::{DenyUnsafe, SubscriptionTaskExecutor};
 
+use codec::Encode;
 use futures::{future, stream, FutureExt, StreamExt};
 use jsonrpsee::{
 	core::{async_trait, Error as JsonRpseeError},
@@ -34,9 +35,9 @@ use jsonrpsee::{
 };
 use sc_client_api::{
 	Backend, BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider, ProofProvider,
-	StorageProvider,
+	StorageProof, StorageProvider,
 };
-use sc_rpc_api::state::ReadProof;
+use sc_rpc_api::state::{CompactReadProof, ReadProof, StorageDiffEntry, StorageDiffKey};
 use sp_api::{CallApiAt, Metadata, ProvideRuntimeApi};
 use sp_blockchain::{
 	CachedHeaderMetadata, Error as ClientError, HeaderBackend, HeaderMetadata,
@@ -49,12 +50,15 @@ use sp_core::{
 	traits::CallContext,
 	Bytes,
 };
-use sp_runtime::traits::Block as BlockT;
+use sp_runtime::traits::{Block as BlockT, HashFor};
 use sp_version::RuntimeVersion;
 
 /// The maximum time allowed for an RPC call when running without unsafe RPC enabled.
 const MAXIMUM_SAFE_RPC_CALL_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// The maximum number of blocks a read proof can cover.
+const READ_PROOF_MAX_BLOCKS: u32 = 256;
+
 /// Ranges to query in state_queryStorage.
 struct QueryStorageRange<Block: BlockT> {
 	/// Hashes of all the blocks in the range.
@@ -362,6 +366,46 @@ where
 		self.query_storage(at, Some(at), keys)
 	}
 
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		count: u32,
+		start_key: Option<StorageDiffKey>,
+	) -> std::result::Result<Vec<StorageDiffEntry>, Error> {
+		let start_key = start_key
+			.map(|start_key| {
+				let child_storage_key = match start_key.child_storage_key {
+					Some(storage_key) => match ChildType::from_prefixed_key(&storage_key) {
+						Some((ChildType::ParentKeyId, storage_key)) =>
+							Some(StorageKey(storage_key.to_vec())),
+						None => return Err(sp_blockchain::Error::InvalidChildStorageKey),
+					},
+					None => None,
+				};
+				Ok(sc_client_api::StorageDiffKey { child_storage_key, key: start_key.key })
+			})
+			.transpose()
+			.map_err(client_err)?;
+
+		self.block_or_best(to)
+			.and_then(|to| self.client.storage_diff(from, to, start_key.as_ref(), count as usize))
+			.map(|diff| {
+				diff.into_iter()
+					.map(|entry| StorageDiffEntry {
+						key: StorageDiffKey {
+							child_storage_key: entry.key.child_storage_key.map(|storage_key| {
+								ChildInfo::new_default(&storage_key.0).prefixed_storage_key()
+							}),
+							key: entry.key.key,
+						},
+						value: entry.value,
+					})
+					.collect()
+			})
+			.map_err(client_err)
+	}
+
 	fn read_proof(
 		&self,
 		block: Option<Block::Hash>,
@@ -377,6 +421,52 @@ where
 			.map_err(client_err)
 	}
 
+	fn read_proof_range(
+		&self,
+		from: Block::Hash,
+		to: Option<Block::Hash>,
+		keys: Vec<StorageKey>,
+	) -> std::result::Result<CompactReadProof<Block::Hash>, Error> {
+		let to = self
+			.block_or_best(to)
+			.map_err(|e| invalid_block::<Block>(from, to, e.to_string()))?;
+
+		let invalid_block_err =
+			|e: ClientError| invalid_block::<Block>(from, Some(to), e.to_string());
+		let from_meta = self.client.header_metadata(from).map_err(invalid_block_err)?;
+		let to_meta = self.client.header_metadata(to).map_err(invalid_block_err)?;
+		if to_meta.number > from_meta.number &&
+			to_meta.number - from_meta.number >= READ_PROOF_MAX_BLOCKS.into()
+		{
+			return Err(invalid_block_range(
+				&from_meta,
+				&to_meta,
+				format!("range exceeds the maximum of {} blocks", READ_PROOF_MAX_BLOCKS),
+			))
+		}
+
+		let range = self.query_storage_range(from, Some(to))?;
+		let mut roots = Vec::with_capacity(range.hashes.len());
+		let mut proofs = Vec::with_capacity(range.hashes.len());
+		for hash in range.hashes {
+			roots.push(self.client.header_metadata(hash).map_err(client_err)?.state_root);
+			proofs.push(
+				self.client
+					.read_proof(hash, &mut keys.iter().map(|key| key.0.as_ref()))
+					.map_err(client_err)?,
+			);
+		}
+
+		let proof = StorageProof::merge(proofs)
+			.into_multi_root_compact_proof::<HashFor<Block>>(&roots)
+			.map_err(|e| client_err(ClientError::Application(Box::new(e))))?;
+
+		Ok(CompactReadProof {
+			at: to,
+			proofs: proof.proofs.into_iter().map(|proof| proof.encode().into()).collect(),
+		})
+	}
+
 	fn subscribe_runtime_version(&self, mut sink: SubscriptionSink) {
 		let client = self.client.clone();
 
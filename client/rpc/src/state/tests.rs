@@ -28,7 +28,10 @@ use jsonrpsee::{
 use sc_block_builder::BlockBuilderProvider;
 use sc_rpc_api::DenyUnsafe;
 use sp_consensus::BlockOrigin;
-use sp_core::{hash::H256, storage::ChildInfo};
+use sp_core::{
+	hash::H256,
+	storage::{well_known_keys, ChildInfo},
+};
 use sp_io::hashing::blake2_256;
 use std::sync::Arc;
 use substrate_test_runtime_client::{prelude::*, runtime};
@@ -477,6 +480,122 @@ async fn should_query_storage() {
 	run_tests(Arc::new(TestClientBuilder::new().build())).await;
 }
 
+#[tokio::test]
+async fn should_return_storage_diff() {
+	let mut client = Arc::new(TestClientBuilder::with_storage_changes_index().build());
+	let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No);
+
+	let mut add_block = |changes: Vec<(Vec<u8>, Option<Vec<u8>>)>| {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		for (key, value) in changes {
+			builder.push_storage_change(key, value).unwrap();
+		}
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		hash
+	};
+	let block1_hash = add_block(vec![(vec![1], Some(vec![1])), (vec![2], Some(vec![2]))]);
+	let block2_hash = add_block(vec![(vec![1], None), (vec![3], Some(vec![3]))]);
+	let genesis_hash = client.genesis_hash();
+
+	let entry = |key: u8, value: Option<u8>| StorageDiffEntry {
+		key: StorageDiffKey { child_storage_key: None, key: StorageKey(vec![key]) },
+		value: value.map(|value| StorageData(vec![value])),
+	};
+
+	// The storage changes of the genesis block are not indexed, but they are not needed either.
+	let diff = api.storage_diff(genesis_hash, 100, None, Some(block2_hash)).unwrap();
+	assert!(diff.contains(&entry(1, None)));
+	assert!(diff.contains(&entry(2, Some(2))));
+	assert!(diff.contains(&entry(3, Some(3))));
+
+	let diff = api.storage_diff(block1_hash, 100, None, Some(block2_hash)).unwrap();
+	assert!(diff.contains(&entry(1, None)));
+	assert!(!diff.contains(&entry(2, Some(2))));
+
+	// Paging through the diff returns all entries exactly once.
+	let mut paged = Vec::new();
+	let mut start_key = None;
+	loop {
+		let page = api.storage_diff(genesis_hash, 1, start_key, Some(block2_hash)).unwrap();
+		match page.last() {
+			Some(last) => start_key = Some(last.key.clone()),
+			None => break,
+		}
+		paged.extend(page);
+	}
+	assert_eq!(paged, api.storage_diff(genesis_hash, 100, None, Some(block2_hash)).unwrap());
+
+	// `from` must be an ancestor of the target block.
+	assert!(api.storage_diff(block2_hash, 100, None, Some(block1_hash)).is_err());
+	assert_matches!(
+		api.storage_diff(genesis_hash, STORAGE_KEYS_PAGED_MAX_COUNT + 1, None, None),
+		Err(RpcError::Call(RpcCallError::Custom(err))) if err.message().contains("count exceeds maximum value")
+	);
+}
+
+#[tokio::test]
+async fn should_return_read_proof_of_block_range() {
+	let mut client = Arc::new(substrate_test_runtime_client::new());
+	let (api, _child) = new_full(client.clone(), test_executor(), DenyUnsafe::No);
+
+	let mut add_block = |value: u8| {
+		let mut builder = client.new_block(Default::default()).unwrap();
+		builder.push_storage_change(vec![1], Some(vec![value])).unwrap();
+		let block = builder.build().unwrap().block;
+		let hash = block.header.hash();
+		executor::block_on(client.import(BlockOrigin::Own, block)).unwrap();
+		hash
+	};
+	let block1_hash = add_block(1);
+	let block2_hash = add_block(2);
+	let genesis_hash = client.genesis_hash();
+
+	let keys = vec![StorageKey(vec![1]), StorageKey(well_known_keys::CODE.to_vec())];
+	let read_proof = api.read_proof_range(keys.clone(), genesis_hash, Some(block2_hash)).unwrap();
+	assert_eq!(read_proof.at, block2_hash);
+	assert_eq!(read_proof.proofs.len(), 3);
+
+	let proof = sc_client_api::MultiRootCompactProof {
+		proofs: read_proof
+			.proofs
+			.iter()
+			.map(|proof| codec::Decode::decode(&mut &proof[..]).unwrap())
+			.collect(),
+	};
+	let roots = [genesis_hash, block1_hash, block2_hash]
+		.map(|hash| client.header(hash).unwrap().unwrap().state_root);
+	let values = sp_state_machine::read_multi_root_proof_check::<runtime::Hashing, _>(
+		&roots,
+		proof,
+		keys.iter().map(|key| &key.0),
+	)
+	.unwrap();
+	assert_eq!(values[0].get(&vec![1]), Some(&None));
+	assert_eq!(values[1].get(&vec![1]), Some(&Some(vec![1])));
+	assert_eq!(values[2].get(&vec![1]), Some(&Some(vec![2])));
+
+	// `from` must be an ancestor of the target block.
+	assert!(api.read_proof_range(keys.clone(), block2_hash, Some(block1_hash)).is_err());
+
+	// The plain read proof is unaffected.
+	let read_proof = api.read_proof(keys, Some(block2_hash)).unwrap();
+	assert_eq!(read_proof.at, block2_hash);
+	assert!(!read_proof.proof.is_empty());
+	let proof = sc_client_api::StorageProof::new(read_proof.proof.into_iter().map(|node| node.0));
+	assert_eq!(
+		sp_state_machine::read_proof_check::<runtime::Hashing, _>(
+			roots[2],
+			proof,
+			[vec![1]].iter()
+		)
+		.unwrap()
+		.get(&vec![1]),
+		Some(&Some(vec![2]))
+	);
+}
+
 #[tokio::test]
 async fn should_return_runtime_version() {
 	let client = Arc::new(substrate_test_runtime_client::new());
@@ -33,7 +33,9 @@ use sp_runtime::traits::{self, Header as HeaderT};
 
 use self::error::Result;
 
-pub use self::helpers::{Health, NodeRole, PeerInfo, SyncState, SystemInfo};
+pub use self::helpers::{
+	Health, NodeRole, PeerBan, PeerInfo, PeerReputation, SyncState, SystemInfo,
+};
 pub use sc_rpc_api::system::*;
 
 /// System API implementation
@@ -62,6 +64,12 @@ pub enum Request<B: traits::Block> {
 	NetworkRemoveReservedPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the list of reserved peers
 	NetworkReservedPeers(oneshot::Sender<Vec<String>>),
+	/// Must return the reputations, bans and addresses of the known peers.
+	NetworkPeerReputations(oneshot::Sender<Vec<PeerReputation>>),
+	/// Must return any potential parse error.
+	NetworkBanPeer(String, String, Option<u64>, oneshot::Sender<Result<()>>),
+	/// Must return any potential parse error.
+	NetworkUnbanPeer(String, oneshot::Sender<Result<()>>),
 	/// Must return the node role.
 	NodeRoles(oneshot::Sender<Vec<NodeRole>>),
 	/// Must return the state of the node syncing.
@@ -166,6 +174,49 @@ impl<B: traits::Block> SystemApiServer<B::Hash, <B::Header as HeaderT>::Number>
 		rx.await.map_err(|e| JsonRpseeError::to_call_error(e))
 	}
 
+	async fn system_peer_reputations(&self) -> RpcResult<Vec<PeerReputation>> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkPeerReputations(tx));
+		rx.await.map_err(|e| JsonRpseeError::to_call_error(e))
+	}
+
+	async fn system_ban_peer(
+		&self,
+		peer_id: String,
+		reason: String,
+		duration: Option<u64>,
+	) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		if let Some(duration) = duration {
+			let until =
+				std::time::SystemTime::now().checked_add(std::time::Duration::from_secs(duration));
+			if until.is_none() {
+				return Err(error::Error::InvalidBanDuration(duration).into())
+			}
+		}
+		let (tx, rx) = oneshot::channel();
+		let _ = self
+			.send_back
+			.unbounded_send(Request::NetworkBanPeer(peer_id, reason, duration, tx));
+		match rx.await {
+			Ok(Ok(())) => Ok(()),
+			Ok(Err(e)) => Err(JsonRpseeError::from(e)),
+			Err(e) => Err(JsonRpseeError::to_call_error(e)),
+		}
+	}
+
+	async fn system_unban_peer(&self, peer_id: String) -> RpcResult<()> {
+		self.deny_unsafe.check_if_safe()?;
+		let (tx, rx) = oneshot::channel();
+		let _ = self.send_back.unbounded_send(Request::NetworkUnbanPeer(peer_id, tx));
+		match rx.await {
+			Ok(Ok(())) => Ok(()),
+			Ok(Err(e)) => Err(JsonRpseeError::from(e)),
+			Err(e) => Err(JsonRpseeError::to_call_error(e)),
+		}
+	}
+
 	async fn system_node_roles(&self) -> RpcResult<Vec<NodeRole>> {
 		let (tx, rx) = oneshot::channel();
 		let _ = self.send_back.unbounded_send(Request::NodeRoles(tx));
@@ -25,7 +25,7 @@ use jsonrpsee::{
 	RpcModule,
 };
 use sc_network::{self, config::Role, PeerId};
-use sc_rpc_api::system::helpers::PeerInfo;
+use sc_rpc_api::system::helpers::{PeerBan, PeerInfo, PeerReputation};
 use sc_utils::mpsc::tracing_unbounded;
 use sp_core::H256;
 use std::{
@@ -116,6 +116,23 @@ fn api<T: Into<Option<Status>>>(sync: T) -> RpcModule<System<Block>> {
 					let _ = sender
 						.send(vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()]);
 				},
+				Request::NetworkPeerReputations(sender) => {
+					let _ = sender.send(vec![PeerReputation {
+						peer_id: "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+						reputation: i32::MIN,
+						ban: Some(PeerBan { reason: "Sent bad blocks".into(), until: None }),
+						addresses: vec!["/ip4/198.51.100.19/tcp/30333".to_string()],
+						last_seen: Some(1_600_000_000),
+					}]);
+				},
+				Request::NetworkBanPeer(peer, _, _, sender) |
+				Request::NetworkUnbanPeer(peer, sender) => {
+					let _ = match peer.parse::<PeerId>() {
+						Ok(_) => sender.send(Ok(())),
+						Err(s) =>
+							sender.send(Err(error::Error::MalformattedPeerArg(s.to_string()))),
+					};
+				},
 				Request::NodeRoles(sender) => {
 					let _ = sender.send(vec![NodeRole::Authority]);
 				},
@@ -338,6 +355,48 @@ async fn system_network_reserved_peers() {
 	assert_eq!(reserved_peers, vec!["QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string()],);
 }
 
+#[tokio::test]
+async fn system_network_peer_reputations() {
+	let reputations: Vec<PeerReputation> =
+		api(None).call("system_peerReputations", EmptyParams::new()).await.unwrap();
+	assert_eq!(
+		reputations,
+		vec![PeerReputation {
+			peer_id: "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV".to_string(),
+			reputation: i32::MIN,
+			ban: Some(PeerBan { reason: "Sent bad blocks".into(), until: None }),
+			addresses: vec!["/ip4/198.51.100.19/tcp/30333".to_string()],
+			last_seen: Some(1_600_000_000),
+		}]
+	);
+}
+
+#[tokio::test]
+async fn system_network_ban_and_unban_peer() {
+	let peer_id = "QmSk5HQbn6LhUwDiNMseVUjuRYhEtYj4aUZ6WfWoGURpdV";
+	let _good: () = api(None)
+		.call("system_banPeer", (peer_id, "Sent bad blocks", 3600))
+		.await
+		.expect("call with good peer id works");
+	let _good: () = api(None)
+		.call("system_banPeer", (peer_id, "Sent bad blocks"))
+		.await
+		.expect("duration is optional");
+	let _good: () = api(None)
+		.call("system_unbanPeer", [peer_id])
+		.await
+		.expect("call with good peer id works");
+
+	assert_matches!(
+		api(None).call::<_, ()>("system_banPeer", ("/ip4/198.51.100.19/tcp/30333", "")).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.message().contains("base-58 decode error")
+	);
+	assert_matches!(
+		api(None).call::<_, ()>("system_banPeer", (peer_id, "", u64::MAX)).await,
+		Err(RpcError::Call(CallError::Custom(err))) if err.message().contains("out of range")
+	);
+}
+
 #[test]
 fn test_add_reset_log_filter() {
 	const EXPECTED_BEFORE_ADD: &'static str = "EXPECTED_BEFORE_ADD";
@@ -25,13 +25,14 @@ use crate::{
 	start_rpc_servers, BuildGenesisBlock, GenesisBlockBuilder, RpcHandlers, SpawnTaskHandle,
 	TaskManager, TransactionPoolAdapter,
 };
+use codec::DecodeAll;
 use futures::{channel::oneshot, future::ready, FutureExt, StreamExt};
 use jsonrpsee::RpcModule;
 use log::info;
 use prometheus_endpoint::Registry;
 use sc_chain_spec::get_extension;
 use sc_client_api::{
-	execution_extensions::ExecutionExtensions, proof_provider::ProofProvider, BadBlocks,
+	execution_extensions::ExecutionExtensions, proof_provider::ProofProvider, AuxStore, BadBlocks,
 	BlockBackend, BlockchainEvents, ExecutorProvider, ForkBlocks, StorageProvider, UsageProvider,
 };
 use sc_client_db::{Backend, DatabaseSettings};
@@ -220,6 +221,7 @@ where
 					SyncMode::Fast { .. } | SyncMode::Warp { .. }
 				),
 				wasm_runtime_substitutes,
+				overlay_memory_limit: config.overlay_memory_limit,
 			},
 		)?;
 
@@ -732,6 +734,7 @@ where
 		+ ProofProvider<TBl>
 		+ HeaderBackend<TBl>
 		+ BlockchainEvents<TBl>
+		+ AuxStore
 		+ 'static,
 	TExPool: MaintainedTransactionPool<Block = TBl, Hash = <TBl as BlockT>::Hash> + 'static,
 	TImpQu: ImportQueue<TBl> + 'static,
@@ -748,7 +751,10 @@ where
 
 	let mut request_response_protocol_configs = Vec::new();
 
-	if warp_sync_params.is_none() && config.network.sync_mode.is_warp() {
+	if warp_sync_params.is_none() &&
+		config.network.warp_sync_target.is_none() &&
+		config.network.sync_mode.is_warp()
+	{
 		return Err("Warp sync enabled, but no warp sync provider configured.".into())
 	}
 
@@ -812,6 +818,19 @@ where
 		_ => None,
 	};
 
+	// An explicit target block takes precedence over the warp sync proofs, but we keep serving
+	// the proofs to other peers.
+	let warp_sync_params = match config.network.warp_sync_target.as_ref() {
+		Some(target) => {
+			let hash = TBl::Hash::decode_all(&mut &target.hash[..])
+				.map_err(|e| format!("Invalid warp sync target hash: {}", e))?;
+			let number = NumberFor::<TBl>::try_from(target.number)
+				.map_err(|_| format!("Invalid warp sync target number: {}", target.number))?;
+			Some(WarpSyncParams::WithTargetBlock { hash, number })
+		},
+		None => warp_sync_params,
+	};
+
 	let light_client_request_protocol_config = {
 		// Allow both outgoing and incoming requests.
 		let (handler, protocol_config) = LightClientRequestHandler::new(
@@ -40,6 +40,7 @@ pub struct LocalCallExecutor<Block: BlockT, B, E> {
 	wasm_override: Arc<Option<WasmOverride>>,
 	wasm_substitutes: WasmSubstitutes<Block, E, B>,
 	execution_extensions: Arc<ExecutionExtensions<Block>>,
+	overlay_memory_limit: Option<usize>,
 }
 
 impl<Block: BlockT, B, E> LocalCallExecutor<Block, B, E>
@@ -72,6 +73,7 @@ where
 			wasm_override: Arc::new(wasm_override),
 			wasm_substitutes,
 			execution_extensions: Arc::new(execution_extensions),
+			overlay_memory_limit: client_config.overlay_memory_limit,
 		})
 	}
 
@@ -141,6 +143,7 @@ where
 			wasm_override: self.wasm_override.clone(),
 			wasm_substitutes: self.wasm_substitutes.clone(),
 			execution_extensions: self.execution_extensions.clone(),
+			overlay_memory_limit: self.overlay_memory_limit,
 		}
 	}
 }
@@ -226,6 +229,19 @@ where
 
 		let changes = &mut *changes.borrow_mut();
 
+		// Block import and authoring run the whole block on the same overlay.
+		if let (CallContext::Onchain, Some(limit)) = (call_context, self.overlay_memory_limit) {
+			if changes.memory_limit() != Some(limit) {
+				if let Err(e) = changes.set_memory_limit(limit) {
+					log::warn!(
+						target: "overlay",
+						"Failed to limit the memory of the storage overlay: {}",
+						e,
+					);
+				}
+			}
+		}
+
 		// It is important to extract the runtime code here before we create the proof
 		// recorder to not record it. We also need to fetch the runtime code from `state` to
 		// make sure we use the caching layers.
@@ -429,6 +445,7 @@ mod tests {
 				None,
 				Arc::new(executor.clone()),
 			)),
+			overlay_memory_limit: None,
 		};
 
 		let check = call_executor
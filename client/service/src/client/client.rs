@@ -29,7 +29,8 @@ use sc_chain_spec::{resolve_state_version_from_wasm, BuildGenesisBlock};
 use sc_client_api::{
 	backend::{
 		self, apply_aux, BlockImportOperation, ClientImportOperation, FinalizeSummary, Finalizer,
-		ImportNotificationAction, ImportSummary, LockImportRun, NewBlockState, StorageProvider,
+		ImportNotificationAction, ImportSummary, LockImportRun, NewBlockState, StorageDiffEntry,
+		StorageDiffKey, StorageProvider,
 	},
 	client::{
 		BadBlocks, BlockBackend, BlockImportNotification, BlockOf, BlockchainEvents, ClientInfo,
@@ -52,7 +53,7 @@ use sp_api::{
 };
 use sp_blockchain::{
 	self as blockchain, Backend as ChainBackend, CachedHeaderMetadata, Error,
-	HeaderBackend as ChainHeaderBackend, HeaderMetadata,
+	HeaderBackend as ChainHeaderBackend, HeaderMetadata, TransactionHashAlgorithm,
 };
 use sp_consensus::{BlockOrigin, BlockStatus, Error as ConsensusError};
 
@@ -75,15 +76,16 @@ use sp_runtime::{
 	Digest, Justification, Justifications, StateVersion,
 };
 use sp_state_machine::{
-	prove_child_read, prove_range_read_with_child_with_size, prove_read,
-	read_range_proof_check_with_child_on_proving_backend, Backend as StateBackend,
+	prove_child_read, prove_range_read_with_child_with_size, prove_range_read_with_limit,
+	prove_read, read_range_proof_check_with_child_on_proving_backend, Backend as StateBackend,
 	ChildStorageCollection, KeyValueStates, KeyValueStorageLevel, StorageCollection,
 	MAX_NESTED_TRIE_DEPTH,
 };
 use sp_trie::{CompactProof, StorageProof};
 use std::{
-	collections::{HashMap, HashSet},
+	collections::{BTreeMap, HashMap, HashSet},
 	marker::PhantomData,
+	ops::Bound,
 	path::PathBuf,
 	sync::Arc,
 };
@@ -95,6 +97,9 @@ use {
 
 type NotificationSinks<T> = Mutex<Vec<TracingUnboundedSender<T>>>;
 
+/// The changes between two blocks, ordered by key.
+type StorageDiff = BTreeMap<StorageDiffKey, Option<StorageData>>;
+
 /// Substrate Client
 pub struct Client<B, E, Block, RA>
 where
@@ -118,6 +123,8 @@ where
 	config: ClientConfig<Block>,
 	telemetry: Option<TelemetryHandle>,
 	unpin_worker_sender: TracingUnboundedSender<Block::Hash>,
+	// The last diff returned by `storage_diff`, so that further pages of it are read from memory.
+	storage_diff_cache: Mutex<Option<(Block::Hash, Block::Hash, Arc<StorageDiff>)>>,
 	_phantom: PhantomData<RA>,
 }
 
@@ -203,6 +210,9 @@ pub struct ClientConfig<Block: BlockT> {
 	/// Map of WASM runtime substitute starting at the child of the given block until the runtime
 	/// version doesn't match anymore.
 	pub wasm_runtime_substitutes: HashMap<NumberFor<Block>, Vec<u8>>,
+	/// Number of bytes the storage changes of a block may hold in memory before committed
+	/// values are moved to disk.
+	pub overlay_memory_limit: Option<usize>,
 }
 
 impl<Block: BlockT> Default for ClientConfig<Block> {
@@ -213,6 +223,7 @@ impl<Block: BlockT> Default for ClientConfig<Block> {
 			wasm_runtime_overrides: None,
 			no_genesis: false,
 			wasm_runtime_substitutes: HashMap::new(),
+			overlay_memory_limit: None,
 		}
 	}
 }
@@ -458,6 +469,7 @@ where
 			config,
 			telemetry,
 			unpin_worker_sender,
+			storage_diff_cache: Default::default(),
 			_phantom: Default::default(),
 		})
 	}
@@ -494,6 +506,58 @@ where
 		CallExecutor::runtime_version(&self.executor, hash)
 	}
 
+	/// Merge the indexed storage changes of the blocks after `from` up to and including `to`.
+	fn build_storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+	) -> sp_blockchain::Result<StorageDiff> {
+		let from_number = self.backend.blockchain().header_metadata(from)?.number;
+
+		// Collect the blocks after `from` up to and including `to`.
+		let mut blocks = Vec::new();
+		let mut current = self.backend.blockchain().header_metadata(to)?;
+		while current.number > from_number {
+			blocks.push(current.hash);
+			current = self.backend.blockchain().header_metadata(current.parent)?;
+		}
+
+		if current.hash != from {
+			return Err(Error::Backend(format!("{:?} is not an ancestor of {:?}", from, to)))
+		}
+
+		// Apply the changes from the oldest to the newest block, so that later changes
+		// overwrite earlier ones.
+		let mut diff = BTreeMap::new();
+		for hash in blocks.into_iter().rev() {
+			let (main, children) = self
+				.backend
+				.storage_changes(hash)?
+				.ok_or_else(|| Error::StorageChangesNotIndexed(format!("{:?}", hash)))?;
+
+			for (key, value) in main {
+				diff.insert(
+					StorageDiffKey { child_storage_key: None, key: StorageKey(key) },
+					value.map(StorageData),
+				);
+			}
+
+			for (child_storage_key, changes) in children {
+				for (key, value) in changes {
+					diff.insert(
+						StorageDiffKey {
+							child_storage_key: Some(StorageKey(child_storage_key.clone())),
+							key: StorageKey(key),
+						},
+						value.map(StorageData),
+					);
+				}
+			}
+		}
+
+		Ok(diff)
+	}
+
 	/// Apply a checked and validated block to an operation. If a justification is provided
 	/// then `finalized` *must* be true.
 	fn apply_block(
@@ -1277,6 +1341,28 @@ where
 		Ok((proof, count))
 	}
 
+	fn read_range_proof(
+		&self,
+		hash: Block::Hash,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_key: Option<&[u8]>,
+		end_key: Option<&[u8]>,
+		limit: u32,
+		size_limit: usize,
+	) -> sp_blockchain::Result<CompactProof> {
+		let state = self.state_at(hash)?;
+		// this is a read proof, using version V0 or V1 is equivalent.
+		let root = state.storage_root(std::iter::empty(), StateVersion::V0).0;
+
+		let proof = prove_range_read_with_limit::<_, HashFor<Block>>(
+			state, child_info, prefix, start_key, end_key, limit, size_limit,
+		)?;
+		proof
+			.into_compact_proof::<HashFor<Block>>(root)
+			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
+	}
+
 	fn storage_collection(
 		&self,
 		hash: Block::Hash,
@@ -1556,6 +1642,38 @@ where
 			.child_storage_hash(child_info, &key.0)
 			.map_err(|e| sp_blockchain::Error::from_state(Box::new(e)))
 	}
+
+	fn storage_diff(
+		&self,
+		from: Block::Hash,
+		to: Block::Hash,
+		start_key: Option<&StorageDiffKey>,
+		count: usize,
+	) -> sp_blockchain::Result<Vec<StorageDiffEntry>> {
+		let cached = match &*self.storage_diff_cache.lock() {
+			Some((cached_from, cached_to, diff)) if *cached_from == from && *cached_to == to =>
+				Some(diff.clone()),
+			_ => None,
+		};
+		let diff = match cached {
+			Some(diff) => diff,
+			None => {
+				let diff = Arc::new(self.build_storage_diff(from, to)?);
+				*self.storage_diff_cache.lock() = Some((from, to, diff.clone()));
+				diff
+			},
+		};
+
+		let range = match start_key {
+			Some(start_key) => diff.range((Bound::Excluded(start_key), Bound::Unbounded)),
+			None => diff.range::<StorageDiffKey, _>(..),
+		};
+
+		Ok(range
+			.take(count)
+			.map(|(key, value)| StorageDiffEntry { key: key.clone(), value: value.clone() })
+			.collect())
+	}
 }
 
 impl<B, E, Block, RA> HeaderMetadata<Block> for Client<B, E, Block, RA>
@@ -2021,6 +2139,22 @@ where
 		self.backend.blockchain().has_indexed_transaction(hash)
 	}
 
+	fn indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> sp_blockchain::Result<Option<Vec<u8>>> {
+		self.backend.blockchain().indexed_transaction_by(algorithm, digest)
+	}
+
+	fn has_indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> sp_blockchain::Result<bool> {
+		self.backend.blockchain().has_indexed_transaction_by(algorithm, digest)
+	}
+
 	fn block_indexed_body(&self, hash: Block::Hash) -> sp_blockchain::Result<Option<Vec<Vec<u8>>>> {
 		self.backend.blockchain().block_indexed_body(hash)
 	}
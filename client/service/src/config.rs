@@ -20,7 +20,9 @@
 
 pub use sc_client_api::execution_extensions::{ExecutionStrategies, ExecutionStrategy};
 pub use sc_client_db::{BlocksPruning, Database, DatabaseSource, PruningMode};
-pub use sc_executor::{WasmExecutionMethod, WasmtimeInstantiationStrategy};
+pub use sc_executor::{
+	AllocatorKind, DeterministicStackLimit, WasmExecutionMethod, WasmtimeInstantiationStrategy,
+};
 pub use sc_network::{
 	config::{
 		MultiaddrWithPeerId, NetworkConfiguration, NodeKeyConfig, NonDefaultSetConfig, ProtocolId,
@@ -67,6 +69,10 @@ pub struct Configuration {
 	///
 	/// If `None` is given the cache is disabled.
 	pub trie_cache_maximum_size: Option<usize>,
+	/// Persist the hottest trie cache entries across restarts.
+	pub trie_cache_persistence: bool,
+	/// Index the storage changes of every imported block.
+	pub index_storage_changes: bool,
 	/// State pruning settings.
 	pub state_pruning: Option<PruningMode>,
 	/// Number of blocks to keep in the db.
@@ -81,6 +87,20 @@ pub struct Configuration {
 	/// over on-chain runtimes when the spec version matches. Set to `None` to
 	/// disable overrides (default).
 	pub wasm_runtime_overrides: Option<PathBuf>,
+	/// File the host function profiles of the runtime calls are exported to. Set to `None` to
+	/// disable profiling (default).
+	pub wasm_profile: Option<PathBuf>,
+	/// Aggregate the host function profiles of the runtime calls into Prometheus metrics.
+	pub wasm_profile_metrics: bool,
+	/// Deterministic stack limit to instrument the runtime code with. Must be the same for all
+	/// nodes of a network. Set to `None` to only limit the stack depth by the execution engine
+	/// (default).
+	pub wasm_deterministic_stack_limit: Option<DeterministicStackLimit>,
+	/// Allocator for the runtime heap. Must be the same for all nodes of a network.
+	pub wasm_allocator: AllocatorKind,
+	/// Number of bytes the storage changes of a block may hold in memory before committed
+	/// values are moved to disk. Set to `None` to keep everything in memory (default).
+	pub overlay_memory_limit: Option<usize>,
 	/// Execution strategies.
 	pub execution_strategies: ExecutionStrategies,
 	/// RPC over HTTP binding address. `None` if disabled.
@@ -247,6 +267,8 @@ impl Configuration {
 	pub fn db_config(&self) -> sc_client_db::DatabaseSettings {
 		sc_client_db::DatabaseSettings {
 			trie_cache_maximum_size: self.trie_cache_maximum_size,
+			trie_cache_persistence: self.trie_cache_persistence,
+			index_storage_changes: self.index_storage_changes,
 			state_pruning: self.state_pruning.clone(),
 			source: self.database.clone(),
 			blocks_pruning: self.blocks_pruning,
@@ -318,6 +318,55 @@ async fn build_system_rpc_future<
 					break
 				}
 			},
+			sc_rpc::system::Request::NetworkPeerReputations(sender) => {
+				use sc_rpc::system::{PeerBan, PeerReputation};
+
+				let to_unix_secs = |time: std::time::SystemTime| {
+					time.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+				};
+
+				if let Ok(records) = network_service.peer_records().await {
+					let reputations = records
+						.into_iter()
+						.map(|record| PeerReputation {
+							peer_id: record.peer_id.to_base58(),
+							reputation: record.reputation,
+							ban: record.ban.map(|ban| PeerBan {
+								reason: ban.reason,
+								until: ban.until.map(to_unix_secs),
+							}),
+							addresses: record.addresses.iter().map(ToString::to_string).collect(),
+							last_seen: record.last_seen.map(to_unix_secs),
+						})
+						.collect();
+					let _ = sender.send(reputations);
+				} else {
+					break
+				}
+			},
+			sc_rpc::system::Request::NetworkBanPeer(peer_id, reason, duration, sender) => {
+				let _ = match peer_id.parse::<PeerId>() {
+					Ok(peer_id) => {
+						let duration = duration.map(std::time::Duration::from_secs);
+						network_service.ban_peer(peer_id, reason, duration);
+						sender.send(Ok(()))
+					},
+					Err(e) => sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(
+						e.to_string(),
+					))),
+				};
+			},
+			sc_rpc::system::Request::NetworkUnbanPeer(peer_id, sender) => {
+				let _ = match peer_id.parse::<PeerId>() {
+					Ok(peer_id) => {
+						network_service.unban_peer(peer_id);
+						sender.send(Ok(()))
+					},
+					Err(e) => sender.send(Err(sc_rpc::system::error::Error::MalformattedPeerArg(
+						e.to_string(),
+					))),
+				};
+			},
 			sc_rpc::system::Request::NodeRoles(sender) => {
 				use sc_rpc::system::NodeRole;
 
@@ -245,11 +245,18 @@ fn node_config<
 		keystore: KeystoreConfig::Path { path: root.join("key"), password: None },
 		database: DatabaseSource::RocksDb { path: root.join("db"), cache_size: 128 },
 		trie_cache_maximum_size: Some(16 * 1024 * 1024),
+		trie_cache_persistence: false,
+		index_storage_changes: false,
 		state_pruning: Default::default(),
 		blocks_pruning: BlocksPruning::KeepFinalized,
 		chain_spec: Box::new((*spec).clone()),
 		wasm_method: sc_service::config::WasmExecutionMethod::Interpreted,
 		wasm_runtime_overrides: Default::default(),
+		wasm_profile: None,
+		wasm_profile_metrics: false,
+		wasm_deterministic_stack_limit: None,
+		wasm_allocator: Default::default(),
+		overlay_memory_limit: None,
 		execution_strategies: Default::default(),
 		rpc_http: None,
 		rpc_ipc: None,
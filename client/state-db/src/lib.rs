@@ -40,13 +40,18 @@
 //! # Pruning.
 //! See `RefWindow` for pruning algorithm details. `StateDb` prunes on each canonicalization until
 //! pruning constraints are satisfied.
+//!
+//! # Checkpoints.
+//! [`PruningMode::Checkpoints`] prunes like [`PruningMode::Constrained`], but keeps the state of
+//! every block whose number is a multiple of the checkpoint interval. This requires a database
+//! that supports reference counting.
 
 mod noncanonical;
 mod pruning;
 #[cfg(test)]
 mod test;
 
-use codec::Codec;
+use codec::{Codec, Decode, Encode};
 use log::trace;
 use noncanonical::NonCanonicalOverlay;
 use parking_lot::RwLock;
@@ -54,6 +59,7 @@ use pruning::{HaveBlock, RefWindow};
 use std::{
 	collections::{hash_map::Entry, HashMap},
 	fmt,
+	num::NonZeroU32,
 };
 
 const LOG_TARGET: &str = "state-db";
@@ -62,6 +68,8 @@ const PRUNING_MODE: &[u8] = b"mode";
 const PRUNING_MODE_ARCHIVE: &[u8] = b"archive";
 const PRUNING_MODE_ARCHIVE_CANON: &[u8] = b"archive_canonical";
 const PRUNING_MODE_CONSTRAINED: &[u8] = b"constrained";
+const PRUNING_MODE_CHECKPOINTS: &[u8] = b"checkpoints";
+const CHECKPOINT_INTERVAL: &[u8] = b"checkpoint_interval";
 pub(crate) const DEFAULT_MAX_BLOCK_CONSTRAINT: u32 = 256;
 
 /// Database value type.
@@ -145,6 +153,8 @@ pub enum StateDbError {
 	BlockUnavailable,
 	/// Block record is missing from the pruning window
 	BlockMissing,
+	/// Checkpoints are not supported by databases without reference counting.
+	CheckpointsUnsupported,
 }
 
 impl<E> From<StateDbError> for Error<E> {
@@ -194,6 +204,8 @@ impl fmt::Debug for StateDbError {
 			Self::BlockUnavailable =>
 				write!(f, "Trying to get a block record from db while it is not commit to db yet"),
 			Self::BlockMissing => write!(f, "Block record is missing from the pruning window"),
+			Self::CheckpointsUnsupported =>
+				write!(f, "State checkpoints require a database with reference counting"),
 		}
 	}
 }
@@ -233,6 +245,14 @@ pub enum PruningMode {
 	ArchiveAll,
 	/// Canonicalization discards non-canonical nodes. All the canonical nodes are kept in the DB.
 	ArchiveCanonical,
+	/// Maintain a pruning window, but keep the state of every block whose number is a multiple of
+	/// `interval`.
+	Checkpoints {
+		/// Constraints of the pruning window.
+		constraints: Constraints,
+		/// Number of blocks between two checkpoints.
+		interval: NonZeroU32,
+	},
 }
 
 impl PruningMode {
@@ -241,11 +261,16 @@ impl PruningMode {
 		PruningMode::Constrained(Constraints { max_blocks: Some(n) })
 	}
 
+	/// Create a mode that keeps given number of blocks and the state of every `interval`th block.
+	pub fn checkpoints(n: u32, interval: NonZeroU32) -> PruningMode {
+		PruningMode::Checkpoints { constraints: Constraints { max_blocks: Some(n) }, interval }
+	}
+
 	/// Is this an archive (either ArchiveAll or ArchiveCanonical) pruning mode?
 	pub fn is_archive(&self) -> bool {
 		match *self {
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => true,
-			PruningMode::Constrained(_) => false,
+			PruningMode::Constrained(_) | PruningMode::Checkpoints { .. } => false,
 		}
 	}
 
@@ -255,6 +280,7 @@ impl PruningMode {
 			PruningMode::ArchiveAll => PRUNING_MODE_ARCHIVE,
 			PruningMode::ArchiveCanonical => PRUNING_MODE_ARCHIVE_CANON,
 			PruningMode::Constrained(_) => PRUNING_MODE_CONSTRAINED,
+			PruningMode::Checkpoints { .. } => PRUNING_MODE_CHECKPOINTS,
 		}
 	}
 
@@ -317,6 +343,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 		let pruning: Option<RefWindow<BlockHash, Key, D>> = match mode {
 			PruningMode::Constrained(Constraints { max_blocks }) =>
 				Some(RefWindow::new(db, max_blocks.unwrap_or(0), ref_counting)?),
+			PruningMode::Checkpoints { constraints: Constraints { max_blocks }, interval } => {
+				if ref_counting {
+					return Err(StateDbError::CheckpointsUnsupported.into())
+				}
+				Some(RefWindow::new_with_checkpoints(db, max_blocks.unwrap_or(0), interval)?)
+			},
 			PruningMode::ArchiveAll | PruningMode::ArchiveCanonical => None,
 		};
 
@@ -336,7 +368,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 				// write changes immediately
 				Ok(CommitSet { data: changeset, meta: Default::default() })
 			},
-			PruningMode::Constrained(_) | PruningMode::ArchiveCanonical => self
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpoints { .. } |
+			PruningMode::ArchiveCanonical => self
 				.non_canonical
 				.insert(hash, number, parent_hash, changeset)
 				.map_err(Into::into),
@@ -378,7 +412,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	fn is_pruned(&self, hash: &BlockHash, number: u64) -> IsPruned {
 		match self.mode {
 			PruningMode::ArchiveAll => IsPruned::NotPruned,
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpoints { .. } => {
 				if self
 					.non_canonical
 					.last_canonicalized_block_number()
@@ -406,8 +442,10 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	}
 
 	fn prune(&mut self, commit: &mut CommitSet<Key>) -> Result<(), Error<D::Error>> {
-		if let (&mut Some(ref mut pruning), PruningMode::Constrained(constraints)) =
-			(&mut self.pruning, &self.mode)
+		if let (
+			&mut Some(ref mut pruning),
+			PruningMode::Constrained(constraints) | PruningMode::Checkpoints { constraints, .. },
+		) = (&mut self.pruning, &self.mode)
 		{
 			loop {
 				if pruning.window_size() <= constraints.max_blocks.unwrap_or(0) as u64 {
@@ -440,16 +478,18 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	fn revert_one(&mut self) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.revert_one(),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpoints { .. } => self.non_canonical.revert_one(),
 		}
 	}
 
 	fn remove(&mut self, hash: &BlockHash) -> Option<CommitSet<Key>> {
 		match self.mode {
 			PruningMode::ArchiveAll => Some(CommitSet::default()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) =>
-				self.non_canonical.remove(hash),
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpoints { .. } => self.non_canonical.remove(hash),
 		}
 	}
 
@@ -459,7 +499,9 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDbSync<BlockHash, Key, D> {
 	{
 		match self.mode {
 			PruningMode::ArchiveAll => Ok(()),
-			PruningMode::ArchiveCanonical | PruningMode::Constrained(_) => {
+			PruningMode::ArchiveCanonical |
+			PruningMode::Constrained(_) |
+			PruningMode::Checkpoints { .. } => {
 				let have_block = self.non_canonical.have_block(hash) ||
 					self.pruning.as_ref().map_or_else(
 						|| hint(),
@@ -562,6 +604,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> StateDb<BlockHash, Key, D> {
 
 			cs.meta.inserted.push((key, value));
 
+			if let PruningMode::Checkpoints { interval, .. } = selected_mode {
+				cs.meta
+					.inserted
+					.push((to_meta_key(CHECKPOINT_INTERVAL, &()), interval.encode()));
+			}
+
 			cs
 		} else {
 			Default::default()
@@ -672,7 +720,14 @@ pub enum IsPruned {
 fn fetch_stored_pruning_mode<D: MetaDb>(db: &D) -> Result<Option<PruningMode>, Error<D::Error>> {
 	let meta_key_mode = to_meta_key(PRUNING_MODE, &());
 	if let Some(stored_mode) = db.get_meta(&meta_key_mode).map_err(Error::Db)? {
-		if let Some(mode) = PruningMode::from_id(&stored_mode) {
+		if stored_mode == PRUNING_MODE_CHECKPOINTS {
+			let interval = db
+				.get_meta(&to_meta_key(CHECKPOINT_INTERVAL, &()))
+				.map_err(Error::Db)?
+				.ok_or_else(|| StateDbError::Metadata("Missing CHECKPOINT_INTERVAL".into()))?;
+			let interval = NonZeroU32::decode(&mut interval.as_slice())?;
+			Ok(Some(PruningMode::Checkpoints { constraints: Default::default(), interval }))
+		} else if let Some(mode) = PruningMode::from_id(&stored_mode) {
 			Ok(Some(mode))
 		} else {
 			Err(StateDbError::Metadata(format!(
@@ -696,6 +751,10 @@ fn choose_pruning_mode(
 			Ok(PruningMode::ArchiveCanonical),
 		(PruningMode::Constrained(_), PruningMode::Constrained(requested)) =>
 			Ok(PruningMode::Constrained(requested)),
+		(
+			PruningMode::Checkpoints { interval: stored_interval, .. },
+			PruningMode::Checkpoints { constraints, interval },
+		) if stored_interval == interval => Ok(PruningMode::Checkpoints { constraints, interval }),
 		(stored, requested) => Err(StateDbError::IncompatiblePruningModes { requested, stored }),
 	}
 }
@@ -707,6 +766,7 @@ mod tests {
 		Constraints, Error, IsPruned, PruningMode, StateDb, StateDbError,
 	};
 	use sp_core::H256;
+	use std::num::NonZeroU32;
 
 	fn make_test_db(settings: PruningMode) -> (TestDb, StateDb<H256, H256, TestDb>) {
 		let mut db = make_db(&[91, 921, 922, 93, 94]);
@@ -845,6 +905,25 @@ mod tests {
 		assert!(db.data_eq(&make_db(&[1, 21, 3, 921, 922, 93, 94])));
 	}
 
+	#[test]
+	fn prune_window_1_with_checkpoints() {
+		let (db, sdb) = make_test_db(PruningMode::checkpoints(1, NonZeroU32::new(2).unwrap()));
+		assert_eq!(sdb.is_pruned(&H256::from_low_u64_be(1), 1), IsPruned::Pruned);
+		assert_eq!(sdb.is_pruned(&H256::from_low_u64_be(21), 2), IsPruned::MaybePruned);
+		// the state of block 21 is kept, only `1` was inserted and deleted since checkpoint 0
+		assert!(db.data_eq(&make_db(&[21, 3, 91, 921, 922, 93, 94])));
+	}
+
+	#[test]
+	fn checkpoints_require_ref_counting() {
+		let db = make_db(&[]);
+		let mode = PruningMode::checkpoints(256, NonZeroU32::new(2).unwrap());
+		assert!(matches!(
+			StateDb::<H256, H256, TestDb>::open(db, Some(mode), true, true),
+			Err(Error::StateDb(StateDbError::CheckpointsUnsupported))
+		));
+	}
+
 	#[test]
 	fn detects_incompatible_mode() {
 		let mut db = make_db(&[]);
@@ -940,6 +1019,31 @@ mod tests {
 				Some(PruningMode::ArchiveCanonical),
 				Ok(PruningMode::ArchiveCanonical),
 			),
+			(
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+				None,
+				Ok(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+			),
+			(
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+				Some(PruningMode::checkpoints(128, NonZeroU32::new(10).unwrap())),
+				Ok(PruningMode::checkpoints(128, NonZeroU32::new(10).unwrap())),
+			),
+			(
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(20).unwrap())),
+				Err(()),
+			),
+			(
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+				Some(PruningMode::blocks_pruning(256)),
+				Err(()),
+			),
+			(
+				Some(PruningMode::blocks_pruning(256)),
+				Some(PruningMode::checkpoints(256, NonZeroU32::new(10).unwrap())),
+				Err(()),
+			),
 		] {
 			check_stored_and_requested_mode_compatibility(created, reopened, expected);
 		}
@@ -23,17 +23,24 @@
 //! If a node is re-inserted into the window it gets removed from
 //! the death list.
 //! The changes are journaled in the DB.
+//!
+//! With checkpoints enabled, the state of every block whose number is a multiple of the
+//! checkpoint interval is never pruned. See `Checkpoints` for details.
 
 use crate::{
-	noncanonical::LAST_CANONICAL, to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError,
-	DEFAULT_MAX_BLOCK_CONSTRAINT, LOG_TARGET,
+	noncanonical::LAST_CANONICAL, to_meta_key, ChangeSet, CommitSet, DBValue, Error, Hash, MetaDb,
+	StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT, LOG_TARGET,
 };
 use codec::{Decode, Encode};
 use log::trace;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+	collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+	num::NonZeroU32,
+};
 
 pub(crate) const LAST_PRUNED: &[u8] = b"last_pruned";
 const PRUNING_JOURNAL: &[u8] = b"pruning_journal";
+const CHECKPOINT_JOURNAL: &[u8] = b"checkpoint_journal";
 
 /// See module documentation.
 pub struct RefWindow<BlockHash: Hash, Key: Hash, D: MetaDb> {
@@ -42,6 +49,128 @@ pub struct RefWindow<BlockHash: Hash, Key: Hash, D: MetaDb> {
 	queue: DeathRowQueue<BlockHash, Key, D>,
 	/// Block number that is next to be pruned.
 	base: u64,
+	/// Tracks the nodes that must be kept for the checkpoint states, if enabled.
+	checkpoints: Option<Checkpoints<Key>>,
+}
+
+/// Keeps the state of every block whose number is a multiple of `interval`.
+///
+/// A node deleted by a block after the last checkpoint is only removed from the database if it was
+/// inserted after that checkpoint as well. Otherwise the node was already present when the
+/// checkpoint was taken and may still be referenced by the checkpoint state, so the deletion is
+/// skipped and the node is kept forever. This relies on the database counting references, as
+/// every skipped deletion leaves one reference to the node in place.
+///
+/// The insertions since the last checkpoint are journaled per block and replayed on startup.
+struct Checkpoints<Key: Hash> {
+	/// Number of blocks between two checkpoints.
+	interval: u64,
+	/// The nodes inserted since the last checkpoint that were not deleted again, with their
+	/// number of references.
+	inserted: HashMap<Key, u32>,
+}
+
+#[derive(Encode, Decode)]
+struct CheckpointJournalRecord<Key: Hash> {
+	inserted: Vec<Key>,
+	deleted: Vec<Key>,
+}
+
+fn to_checkpoint_journal_key(block: u64) -> Vec<u8> {
+	to_meta_key(CHECKPOINT_JOURNAL, &block)
+}
+
+impl<Key: Hash> Checkpoints<Key> {
+	/// Restore the insertions since the last checkpoint before `last_canonicalized` from `db`.
+	fn new<D: MetaDb>(
+		db: &D,
+		interval: NonZeroU32,
+		last_canonicalized: Option<u64>,
+	) -> Result<Self, Error<D::Error>> {
+		let mut checkpoints =
+			Checkpoints { interval: interval.get() as u64, inserted: HashMap::new() };
+		if let Some(last_canonicalized) = last_canonicalized {
+			let last_checkpoint = last_canonicalized - last_canonicalized % checkpoints.interval;
+			for block in last_checkpoint + 1..=last_canonicalized {
+				let journal_key = to_checkpoint_journal_key(block);
+				if let Some(record) = db.get_meta(&journal_key).map_err(Error::Db)? {
+					let CheckpointJournalRecord::<Key> { inserted, deleted } =
+						Decode::decode(&mut record.as_slice())?;
+					checkpoints.insert(inserted.iter());
+					for k in deleted {
+						checkpoints.remove(&k);
+					}
+				}
+			}
+		}
+		trace!(
+			target: LOG_TARGET,
+			"Restored {} nodes inserted since the last checkpoint",
+			checkpoints.inserted.len(),
+		);
+		Ok(checkpoints)
+	}
+
+	fn is_checkpoint(&self, number: u64) -> bool {
+		number % self.interval == 0
+	}
+
+	fn insert<'a>(&mut self, keys: impl Iterator<Item = &'a Key>) {
+		for k in keys {
+			*self.inserted.entry(k.clone()).or_default() += 1;
+		}
+	}
+
+	/// Remove one reference to `key`. Returns `false` if it wasn't inserted since the last
+	/// checkpoint.
+	fn remove(&mut self, key: &Key) -> bool {
+		match self.inserted.entry(key.clone()) {
+			Entry::Occupied(mut entry) => {
+				*entry.get_mut() -= 1;
+				if *entry.get() == 0 {
+					entry.remove();
+				}
+				true
+			},
+			Entry::Vacant(_) => false,
+		}
+	}
+
+	/// Note the canonicalization of block `number`, which inserted and deleted the given nodes.
+	///
+	/// Returns the nodes that can be deleted once the block is pruned and adds the journal changes
+	/// to `meta`.
+	fn note_canonical(
+		&mut self,
+		number: u64,
+		inserted: &[(Key, DBValue)],
+		deleted: Vec<Key>,
+		meta: &mut ChangeSet<Vec<u8>>,
+	) -> Vec<Key> {
+		let deleted: Vec<Key> = deleted.into_iter().filter(|k| self.remove(k)).collect();
+
+		if self.is_checkpoint(number) {
+			// Everything inserted so far is part of the checkpoint state.
+			trace!(
+				target: LOG_TARGET,
+				"Checkpoint at #{}, keeping {} nodes",
+				number,
+				self.inserted.len(),
+			);
+			self.inserted.clear();
+			let first = number.saturating_sub(self.interval) + 1;
+			meta.deleted.extend((first..number).map(to_checkpoint_journal_key));
+		} else {
+			self.insert(inserted.iter().map(|(k, _)| k));
+			let record = CheckpointJournalRecord {
+				inserted: inserted.iter().map(|(k, _)| k.clone()).collect(),
+				deleted: deleted.clone(),
+			};
+			meta.inserted.push((to_checkpoint_journal_key(number), record.encode()));
+		}
+
+		deleted
+	}
 }
 
 /// `DeathRowQueue` used to keep track of blocks in the pruning window, there are two flavors:
@@ -305,6 +434,27 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 		db: D,
 		window_size: u32,
 		count_insertions: bool,
+	) -> Result<RefWindow<BlockHash, Key, D>, Error<D::Error>> {
+		Self::new_inner(db, window_size, count_insertions, None)
+	}
+
+	/// Create a pruning window that never prunes the state of the blocks whose number is a
+	/// multiple of `checkpoint_interval`.
+	///
+	/// Requires a database that counts references.
+	pub fn new_with_checkpoints(
+		db: D,
+		window_size: u32,
+		checkpoint_interval: NonZeroU32,
+	) -> Result<RefWindow<BlockHash, Key, D>, Error<D::Error>> {
+		Self::new_inner(db, window_size, false, Some(checkpoint_interval))
+	}
+
+	fn new_inner(
+		db: D,
+		window_size: u32,
+		count_insertions: bool,
+		checkpoint_interval: Option<NonZeroU32>,
 	) -> Result<RefWindow<BlockHash, Key, D>, Error<D::Error>> {
 		// the block number of the first block in the queue or the next block number if the queue is
 		// empty
@@ -319,6 +469,10 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 				None => None,
 			};
 
+		let checkpoints = checkpoint_interval
+			.map(|interval| Checkpoints::new(&db, interval, last_canonicalized_number))
+			.transpose()?;
+
 		let queue = if count_insertions {
 			// Highly scientific crafted number for deciding when to print the warning!
 			//
@@ -347,7 +501,7 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 			DeathRowQueue::new_db_backed(db, base, last, window_size)?
 		};
 
-		Ok(RefWindow { queue, base })
+		Ok(RefWindow { queue, base, checkpoints })
 	}
 
 	pub fn window_size(&self) -> u64 {
@@ -374,6 +528,12 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 
 	// Check if a block is in the pruning window and not be pruned yet
 	pub fn have_block(&self, hash: &BlockHash, number: u64) -> HaveBlock {
+		// the state of a pruned checkpoint is kept if the block is canonical
+		if number < self.base &&
+			self.checkpoints.as_ref().map_or(false, |c| c.is_checkpoint(number))
+		{
+			return HaveBlock::Maybe
+		}
 		// if the queue is empty or the block number exceed the pruning window, we definitely
 		// do not have this block
 		if self.is_empty() || number < self.base || number >= self.base + self.window_size() {
@@ -417,7 +577,15 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 		} else {
 			Default::default()
 		};
-		let deleted = std::mem::take(&mut commit.data.deleted);
+		let mut deleted = std::mem::take(&mut commit.data.deleted);
+		if let Some(checkpoints) = &mut self.checkpoints {
+			deleted = checkpoints.note_canonical(
+				number,
+				&commit.data.inserted,
+				deleted,
+				&mut commit.meta,
+			);
+		}
 		let journal_record = JournalRecord { hash: hash.clone(), inserted, deleted };
 		commit.meta.inserted.push((to_journal_key(number), journal_record.encode()));
 		self.queue.import(self.base, number, journal_record);
@@ -427,14 +595,18 @@ impl<BlockHash: Hash, Key: Hash, D: MetaDb> RefWindow<BlockHash, Key, D> {
 
 #[cfg(test)]
 mod tests {
-	use super::{to_journal_key, DeathRowQueue, HaveBlock, JournalRecord, RefWindow, LAST_PRUNED};
+	use super::{
+		to_checkpoint_journal_key, to_journal_key, DeathRowQueue, HaveBlock, JournalRecord,
+		RefWindow, LAST_PRUNED,
+	};
 	use crate::{
 		noncanonical::LAST_CANONICAL,
 		test::{make_commit, make_db, TestDb},
-		to_meta_key, CommitSet, Error, Hash, StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT,
+		to_meta_key, CommitSet, Error, Hash, MetaDb, StateDbError, DEFAULT_MAX_BLOCK_CONSTRAINT,
 	};
 	use codec::Encode;
 	use sp_core::H256;
+	use std::num::NonZeroU32;
 
 	fn check_journal(pruning: &RefWindow<H256, H256, TestDb>, db: &TestDb) {
 		let count_insertions = matches!(pruning.queue, DeathRowQueue::Mem { .. });
@@ -869,4 +1041,59 @@ mod tests {
 		pruning.prune_one(&mut commit).unwrap();
 		db.commit(&commit);
 	}
+
+	#[test]
+	fn checkpoints_survive_pruning() {
+		let mut db = make_db(&[1, 2, 3]);
+		let interval = NonZeroU32::new(2).unwrap();
+		let mut pruning: RefWindow<u64, H256, TestDb> =
+			RefWindow::new_with_checkpoints(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, interval)
+				.unwrap();
+
+		let mut commit = make_commit(&[], &[]);
+		pruning.note_canonical(&0, 0, &mut commit).unwrap();
+		push_last_canonicalized(0, &mut commit);
+		db.commit(&commit);
+		// `1` is part of the state of checkpoint 0
+		let mut commit = make_commit(&[4], &[1]);
+		pruning.note_canonical(&1, 1, &mut commit).unwrap();
+		push_last_canonicalized(1, &mut commit);
+		db.commit(&commit);
+		// `4` was inserted after checkpoint 0
+		let mut commit = make_commit(&[5], &[4]);
+		pruning.note_canonical(&2, 2, &mut commit).unwrap();
+		push_last_canonicalized(2, &mut commit);
+		db.commit(&commit);
+		// `5` and `2` are part of the state of checkpoint 2
+		let mut commit = make_commit(&[6], &[5, 2]);
+		pruning.note_canonical(&3, 3, &mut commit).unwrap();
+		push_last_canonicalized(3, &mut commit);
+		db.commit(&commit);
+
+		// the insertions since the last checkpoint are restored from the journal
+		let mut restored: RefWindow<u64, H256, TestDb> =
+			RefWindow::new_with_checkpoints(db.clone(), DEFAULT_MAX_BLOCK_CONSTRAINT, interval)
+				.unwrap();
+		assert_eq!(
+			restored.checkpoints.as_ref().unwrap().inserted,
+			[(H256::from_low_u64_be(6), 1)].into_iter().collect(),
+		);
+
+		// `6` was inserted after checkpoint 2
+		let mut commit = make_commit(&[7], &[6]);
+		restored.note_canonical(&4, 4, &mut commit).unwrap();
+		push_last_canonicalized(4, &mut commit);
+		db.commit(&commit);
+		assert!(db.get_meta(&to_checkpoint_journal_key(3)).unwrap().is_none());
+
+		let mut commit = CommitSet::default();
+		while restored.window_size() > 0 {
+			restored.prune_one(&mut commit).unwrap();
+		}
+		db.commit(&commit);
+		assert!(db.data_eq(&make_db(&[1, 2, 3, 5, 7])));
+
+		assert_eq!(restored.have_block(&2, 2), HaveBlock::Maybe);
+		assert_eq!(restored.have_block(&3, 3), HaveBlock::No);
+	}
 }
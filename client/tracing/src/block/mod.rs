@@ -42,7 +42,7 @@ use sp_runtime::{
 	generic::BlockId,
 	traits::{Block as BlockT, Header},
 };
-use sp_tracing::{WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
+use sp_tracing::{RUNTIME_LOG_IDENTIFIER, WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
 
 // Default to only pallet, frame support and state related traces
 const DEFAULT_TARGETS: &str = "pallet,frame,state";
@@ -91,7 +91,12 @@ impl BlockSubscriber {
 
 impl Subscriber for BlockSubscriber {
 	fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
-		if !metadata.is_span() && metadata.fields().field(REQUIRED_EVENT_FIELD).is_none() {
+		// Runtime log records are checked against their actual target, see
+		// `sp_tracing::runtime_log_enabled`.
+		if !metadata.is_span() &&
+			metadata.name() != RUNTIME_LOG_IDENTIFIER &&
+			metadata.fields().field(REQUIRED_EVENT_FIELD).is_none()
+		{
 			return false
 		}
 		for (target, level) in &self.targets {
@@ -139,13 +144,14 @@ impl Subscriber for BlockSubscriber {
 		let mut values = crate::Values::default();
 		event.record(&mut values);
 		let parent_id = event.parent().cloned();
-		let trace_event = TraceEvent {
+		let mut trace_event = TraceEvent {
 			name: event.metadata().name().to_owned(),
 			target: event.metadata().target().to_owned(),
 			level: *event.metadata().level(),
 			values,
 			parent_id,
 		};
+		crate::patch_runtime_log(&mut trace_event);
 		self.events.lock().push(trace_event);
 	}
 
@@ -252,16 +258,18 @@ where
 			.lock()
 			.drain(..)
 			.filter(|e| {
+				// Runtime log records are only filtered by their target.
+				if e.name == RUNTIME_LOG_IDENTIFIER {
+					return check_target(targets, &e.target, &e.level)
+				}
 				self.storage_keys
 					.as_ref()
 					.map(|keys| event_values_filter(e, "key", keys))
-					.unwrap_or(false)
-			})
-			.filter(|e| {
-				self.methods
-					.as_ref()
-					.map(|methods| event_values_filter(e, "method", methods))
-					.unwrap_or(false)
+					.unwrap_or(false) &&
+					self.methods
+						.as_ref()
+						.map(|methods| event_values_filter(e, "method", methods))
+						.unwrap_or(false)
 			})
 			.map(|s| s.into())
 			.collect();
@@ -32,8 +32,14 @@ pub mod block;
 pub mod logging;
 
 use rustc_hash::FxHashMap;
-use serde::ser::{Serialize, SerializeMap, Serializer};
-use sp_tracing::{WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
+use serde::{
+	de::{Deserialize, Deserializer, MapAccess, Visitor},
+	ser::{Serialize, SerializeMap, Serializer},
+};
+use sp_tracing::{
+	RUNTIME_LOG_FIELDS_KEY, RUNTIME_LOG_IDENTIFIER, WASM_NAME_KEY, WASM_TARGET_KEY,
+	WASM_TRACE_IDENTIFIER,
+};
 use std::{
 	fmt,
 	time::{Duration, Instant},
@@ -212,6 +218,63 @@ impl fmt::Display for Values {
 	}
 }
 
+/// Structured fields of a runtime log record, in the order they were given by the runtime.
+struct RuntimeLogFields(Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for RuntimeLogFields {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct FieldsVisitor;
+
+		impl<'de> Visitor<'de> for FieldsVisitor {
+			type Value = RuntimeLogFields;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a map of strings")
+			}
+
+			fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+				let mut fields = Vec::with_capacity(map.size_hint().unwrap_or(0));
+				while let Some(field) = map.next_entry()? {
+					fields.push(field);
+				}
+				Ok(RuntimeLogFields(fields))
+			}
+		}
+
+		deserializer.deserialize_map(FieldsVisitor)
+	}
+}
+
+/// Parses the structured fields of a runtime log record, see [`sp_tracing::emit_runtime_log`].
+///
+/// Returns no fields if they are malformed.
+pub(crate) fn parse_runtime_log_fields(fields: &str) -> Vec<(String, String)> {
+	serde_json::from_str::<RuntimeLogFields>(fields)
+		.map(|f| f.0)
+		.unwrap_or_default()
+}
+
+/// If the event is a runtime log record, replace its `target` by the target given by the runtime
+/// and expand its structured fields into individual values.
+///
+/// Runtime log records are emitted with static metadata, like the wasm tracing events are.
+pub(crate) fn patch_runtime_log(event: &mut TraceEvent) {
+	if event.name != RUNTIME_LOG_IDENTIFIER {
+		return
+	}
+	if let Some(t) = event.values.string_values.remove(WASM_TARGET_KEY) {
+		event.target = t;
+	}
+	if let Some(fields) = event.values.string_values.remove(RUNTIME_LOG_FIELDS_KEY) {
+		for (key, value) in parse_runtime_log_fields(&fields) {
+			event.values.string_values.entry(key).or_insert(value);
+		}
+	}
+}
+
 /// Trace handler event types.
 #[derive(Debug)]
 pub enum TraceHandlerEvents {
@@ -19,8 +19,14 @@
 use crate::logging::fast_local_time::FastLocalTime;
 use ansi_term::Colour;
 use regex::Regex;
+use sp_tracing::{
+	RUNTIME_LOG_FIELDS_KEY, RUNTIME_LOG_IDENTIFIER, RUNTIME_LOG_MESSAGE_KEY, WASM_TARGET_KEY,
+};
 use std::fmt::{self, Write};
-use tracing::{Event, Level, Subscriber};
+use tracing::{
+	field::{Field, Visit},
+	Event, Level, Subscriber,
+};
 use tracing_log::NormalizeEvent;
 use tracing_subscriber::{
 	field::RecordFields,
@@ -85,8 +91,16 @@ where
 			}
 		}
 
+		// Runtime log records carry their actual target and their structured fields as values.
+		let runtime_log = (meta.name() == RUNTIME_LOG_IDENTIFIER).then(|| {
+			let mut record = RuntimeLogRecord::default();
+			event.record(&mut record);
+			record
+		});
+
 		if self.display_target {
-			write!(writer, "{}: ", meta.target())?;
+			let target = runtime_log.as_ref().map_or(meta.target(), |r| r.target.as_str());
+			write!(writer, "{}: ", target)?;
 		}
 
 		// Custom code to display node name
@@ -108,7 +122,10 @@ where
 			writer.sanitize = true;
 		}
 
-		ctx.format_fields(writer, event)?;
+		match runtime_log {
+			Some(record) => write!(writer, "{}", record)?,
+			None => ctx.format_fields(writer, event)?,
+		}
 		writeln!(writer)?;
 
 		writer.flush()
@@ -146,6 +163,39 @@ where
 	}
 }
 
+/// The values of a runtime log record, see [`sp_tracing::emit_runtime_log`].
+#[derive(Default)]
+struct RuntimeLogRecord {
+	target: String,
+	message: String,
+	fields: String,
+}
+
+impl Visit for RuntimeLogRecord {
+	fn record_str(&mut self, field: &Field, value: &str) {
+		match field.name() {
+			name if name == WASM_TARGET_KEY => self.target = value.to_owned(),
+			name if name == RUNTIME_LOG_MESSAGE_KEY => self.message = value.to_owned(),
+			name if name == RUNTIME_LOG_FIELDS_KEY => self.fields = value.to_owned(),
+			_ => {},
+		}
+	}
+
+	fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+		self.record_str(field, &format!("{:?}", value))
+	}
+}
+
+impl fmt::Display for RuntimeLogRecord {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(&self.message)?;
+		for (key, value) in crate::parse_runtime_log_fields(&self.fields) {
+			write!(f, " {}={}", key, value)?;
+		}
+		Ok(())
+	}
+}
+
 struct FmtLevel<'a> {
 	level: &'a Level,
 	ansi: bool,
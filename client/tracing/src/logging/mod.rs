@@ -675,4 +675,40 @@ mod tests {
 			assert_eq!(stderr.find(SANITIZED_LINE), stderr.rfind(SANITIZED_LINE));
 		}
 	}
+
+	/// This is not an actual test, it is used by the `runtime_log_records_are_formatted` test.
+	#[test]
+	fn runtime_log_records_are_formatted_entrypoint() {
+		if env::var("ENABLE_LOGGING").is_ok() {
+			let _guard = init_logger("runtime::test=debug");
+			assert!(sp_tracing::runtime_log_enabled(Level::DEBUG, "runtime::test"));
+			assert!(!sp_tracing::runtime_log_enabled(Level::DEBUG, "runtime::other"));
+
+			sp_tracing::emit_runtime_log(
+				Level::DEBUG,
+				"runtime::test",
+				EXPECTED_LOG_MESSAGE,
+				&[("who", "alice"), ("amount", "42")],
+			);
+			sp_tracing::emit_runtime_log(Level::DEBUG, "runtime::other", "filtered out", &[]);
+		}
+	}
+
+	#[test]
+	fn runtime_log_records_are_formatted() {
+		let re = regex::Regex::new(&format!(
+			r"^.* DEBUG .* runtime::test: {} who=alice amount=42$",
+			EXPECTED_LOG_MESSAGE,
+		))
+		.unwrap();
+		let executable = env::current_exe().unwrap();
+		let output = Command::new(executable)
+			.env("ENABLE_LOGGING", "1")
+			.args(["--nocapture", "runtime_log_records_are_formatted_entrypoint"])
+			.output()
+			.unwrap();
+
+		let output = String::from_utf8(output.stderr).unwrap();
+		assert!(re.is_match(output.trim()), "Expected:\n{}\nGot:\n{}", re, output);
+	}
 }
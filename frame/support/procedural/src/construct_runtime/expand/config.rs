@@ -93,6 +93,26 @@ pub fn expand_outer_config(
 				Ok(())
 			}
 		}
+
+		// Pallet genesis configs are only available with `std`, so this impl is as well. Runtimes
+		// that need to build their genesis from wasm implement `BuildGenesisConfig` on a config type
+		// that supports `no_std`, see `substrate_test_runtime::genesis_builder`.
+		#[cfg(any(feature = "std", test))]
+		impl #scrate::traits::BuildGenesisConfig for GenesisConfig {
+			fn build(&self) {
+				let storage = <Self as #scrate::sp_runtime::BuildStorage>::build_storage(self)
+					.expect("Failed to build the genesis storage");
+
+				for (key, value) in storage.top {
+					#scrate::storage::unhashed::put_raw(&key, &value);
+				}
+				for (_, child) in storage.children_default {
+					for (key, value) in child.data {
+						#scrate::storage::child::put_raw(&child.child_info, &key, &value);
+					}
+				}
+			}
+		}
 	}
 }
 
@@ -75,8 +75,25 @@ pub fn expand_runtime_metadata(
 		.collect::<Vec<_>>();
 
 	quote! {
+		// The runtime api metadata is provided by `impl_runtime_apis!`, which implements
+		// `InternalImplRuntimeApis` for the runtime. Runtimes without runtime apis fall back to
+		// this implementation for `&Runtime`, which is only picked by the method call below when
+		// the runtime doesn't implement `InternalImplRuntimeApis` itself.
+		#[doc(hidden)]
+		trait InternalConstructRuntime {
+			fn runtime_api_metadata(
+				&self,
+			) -> #scrate::sp_std::vec::Vec<#scrate::metadata_ir::RuntimeApiMetadataIR> {
+				Default::default()
+			}
+		}
+		impl InternalConstructRuntime for &#runtime {}
+
 		impl #runtime {
 			fn metadata_ir() -> #scrate::metadata_ir::MetadataIR {
+				#[allow(unused_imports)]
+				use #scrate::metadata_ir::InternalImplRuntimeApis;
+
 				#scrate::metadata_ir::MetadataIR {
 					pallets: #scrate::sp_std::vec![ #(#pallets),* ],
 					extrinsic: #scrate::metadata_ir::ExtrinsicMetadataIR {
@@ -95,10 +112,17 @@ pub fn expand_runtime_metadata(
 								})
 								.collect(),
 					},
-					ty: #scrate::scale_info::meta_type::<#runtime>()
+					ty: #scrate::scale_info::meta_type::<#runtime>(),
+					apis: (&#runtime).runtime_api_metadata(),
 				}
 			}
 
+			pub fn apis_metadata() -> #scrate::OpaqueMetadata {
+				#scrate::OpaqueMetadata::new(
+					#scrate::codec::Encode::encode(&#runtime::metadata_ir().apis),
+				)
+			}
+
 			pub fn metadata() -> #scrate::metadata::RuntimeMetadataPrefixed {
 				#scrate::metadata_ir::into_latest(#runtime::metadata_ir())
 			}
@@ -0,0 +1,109 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helper functions for implementing [`sp_genesis_builder::GenesisBuilder`] for runtimes.
+//!
+//! Provides common logic. For more info refer to [`sp_genesis_builder::GenesisBuilder`].
+//!
+//! ```ignore
+//! impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+//! 	fn create_default_config() -> Vec<u8> {
+//! 		create_default_config::<RuntimeGenesisConfig>()
+//! 	}
+//!
+//! 	fn build_config(config: Vec<u8>) -> sp_genesis_builder::Result {
+//! 		build_config::<RuntimeGenesisConfig>(config)
+//! 	}
+//!
+//! 	fn get_preset(id: sp_genesis_builder::PresetId) -> Option<Vec<u8>> {
+//! 		genesis_config_presets::get_preset(&id)
+//! 	}
+//! }
+//! ```
+
+use crate::traits::BuildGenesisConfig;
+use sp_runtime::format_runtime_string;
+use sp_std::vec::Vec;
+
+/// Get the default `GenesisConfig` as a JSON blob.
+///
+/// For more info refer to [`sp_genesis_builder::GenesisBuilder::create_default_config`].
+pub fn create_default_config<GC: BuildGenesisConfig>() -> Vec<u8> {
+	serde_json::to_vec(&GC::default()).expect("Serialization to JSON is expected to work; qed")
+}
+
+/// Build `GenesisConfig` from a JSON blob not using any defaults and store it in the storage.
+///
+/// For more info refer to [`sp_genesis_builder::GenesisBuilder::build_config`].
+pub fn build_config<GC: BuildGenesisConfig>(json: Vec<u8>) -> sp_genesis_builder::Result {
+	let gc = serde_json::from_slice::<GC>(&json)
+		.map_err(|e| format_runtime_string!("Invalid JSON blob: {}", e))?;
+	<GC as BuildGenesisConfig>::build(&gc);
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use serde::{Deserialize, Serialize};
+
+	#[derive(Default, Serialize, Deserialize)]
+	#[serde(rename_all = "camelCase")]
+	#[serde(deny_unknown_fields)]
+	struct TestConfig {
+		value: u32,
+		accounts: Vec<u64>,
+	}
+
+	impl BuildGenesisConfig for TestConfig {
+		fn build(&self) {
+			crate::storage::unhashed::put(b"value", &self.value);
+			crate::storage::unhashed::put(b"accounts", &self.accounts);
+		}
+	}
+
+	#[test]
+	fn create_default_config_works() {
+		assert_eq!(create_default_config::<TestConfig>(), br#"{"value":0,"accounts":[]}"#.to_vec());
+	}
+
+	#[test]
+	fn build_config_works() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			assert_eq!(
+				build_config::<TestConfig>(br#"{"value":5,"accounts":[1,2]}"#.to_vec()),
+				Ok(())
+			);
+
+			assert_eq!(crate::storage::unhashed::get(b"value"), Some(5u32));
+			assert_eq!(crate::storage::unhashed::get(b"accounts"), Some(vec![1u64, 2]));
+		});
+	}
+
+	#[test]
+	fn build_config_rejects_invalid_json() {
+		sp_io::TestExternalities::default().execute_with(|| {
+			// Partial configs are not accepted.
+			assert!(build_config::<TestConfig>(br#"{"value":5}"#.to_vec()).is_err());
+			assert!(build_config::<TestConfig>(br#"{"value":5,"accounts":[],"other":1}"#.to_vec())
+				.is_err());
+			assert!(build_config::<TestConfig>(b"not json".to_vec()).is_err());
+
+			assert_eq!(crate::storage::unhashed::get::<u32>(b"value"), None);
+		});
+	}
+}
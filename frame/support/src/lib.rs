@@ -48,7 +48,7 @@ pub use once_cell;
 pub use paste;
 #[doc(hidden)]
 pub use scale_info;
-#[cfg(feature = "std")]
+#[cfg(feature = "serde")]
 pub use serde;
 pub use sp_core::{OpaqueMetadata, Void};
 #[doc(hidden)]
@@ -79,6 +79,8 @@ pub mod inherent;
 pub mod error;
 pub mod crypto;
 pub mod dispatch_context;
+#[cfg(feature = "genesis-builder")]
+pub mod genesis_builder_helper;
 pub mod instances;
 pub mod metadata_ir;
 pub mod migrations;
@@ -0,0 +1,136 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkleized digest of the runtime metadata.
+//!
+//! The digest commits to every part of the metadata an offline signer needs to decode an
+//! extrinsic. Each entry of the type registry, each pallet, the extrinsic metadata and the type
+//! of the runtime are SCALE encoded and used as leaves of a binary merkle tree. The digest is the
+//! `blake2_256` hash of the [`METADATA_DIGEST_VERSION`] followed by the root of this tree.
+//!
+//! Keeping the leaves separate allows a signer to verify the parts of the metadata it uses against
+//! the digest with a merkle proof, without having to obtain the full metadata.
+
+use super::{into_latest, MetadataIR};
+use codec::Encode;
+use frame_metadata::{v14::RuntimeMetadataV14, RuntimeMetadata, RuntimeMetadataPrefixed};
+use sp_core::{hashing::blake2_256, Blake2Hasher};
+use sp_std::vec::Vec;
+
+/// Version of the digest format, bumped whenever the way the digest is computed changes.
+pub const METADATA_DIGEST_VERSION: u8 = 1;
+
+/// Returns the SCALE encoded leaves the merkle tree of the metadata digest is built from.
+///
+/// The leaves are ordered as: all entries of the type registry, all pallets, the extrinsic
+/// metadata and finally the type of the runtime.
+pub fn metadata_digest_leaves(metadata: &RuntimeMetadataV14) -> Vec<Vec<u8>> {
+	metadata
+		.types
+		.types()
+		.iter()
+		.map(Encode::encode)
+		.chain(metadata.pallets.iter().map(Encode::encode))
+		.chain(sp_std::iter::once(metadata.extrinsic.encode()))
+		.chain(sp_std::iter::once(metadata.ty.encode()))
+		.collect()
+}
+
+/// Calculate the digest of the given `metadata`.
+///
+/// Returns `None` if the metadata version is not supported.
+pub fn metadata_digest(metadata: &RuntimeMetadataPrefixed) -> Option<[u8; 32]> {
+	match &metadata.1 {
+		RuntimeMetadata::V14(v14) => Some(metadata_digest_v14(v14)),
+		_ => None,
+	}
+}
+
+/// Calculate the digest of the given metadata in the intermediate representation.
+///
+/// The digest is calculated over the latest stable metadata version.
+pub fn metadata_ir_digest(metadata: MetadataIR) -> [u8; 32] {
+	metadata_digest(&into_latest(metadata))
+		.expect("`into_latest` returns a metadata version supported by the digest; qed")
+}
+
+fn metadata_digest_v14(metadata: &RuntimeMetadataV14) -> [u8; 32] {
+	let root = binary_merkle_tree::merkle_root::<Blake2Hasher, _>(metadata_digest_leaves(metadata));
+
+	blake2_256(&(METADATA_DIGEST_VERSION, root).encode())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::metadata_ir::{ExtrinsicMetadataIR, PalletMetadataIR};
+	use frame_metadata::{v14::META_RESERVED, OpaqueMetadata};
+	use scale_info::meta_type;
+
+	fn ir_metadata(pallets: Vec<PalletMetadataIR>) -> MetadataIR {
+		MetadataIR {
+			pallets,
+			extrinsic: ExtrinsicMetadataIR {
+				ty: meta_type::<()>(),
+				version: 4,
+				signed_extensions: vec![],
+			},
+			ty: meta_type::<u32>(),
+			apis: vec![],
+		}
+	}
+
+	fn pallet(name: &'static str, index: u8) -> PalletMetadataIR {
+		PalletMetadataIR {
+			name,
+			storage: None,
+			calls: None,
+			event: None,
+			constants: vec![],
+			error: None,
+			index,
+			docs: vec![],
+		}
+	}
+
+	#[test]
+	fn digest_covers_every_leaf() {
+		let metadata: RuntimeMetadataV14 = ir_metadata(vec![pallet("System", 0)]).into();
+		let leaves = metadata_digest_leaves(&metadata);
+
+		// `()`, `u32`, the pallet, the extrinsic and the runtime type.
+		assert_eq!(leaves.len(), metadata.types.types().len() + 3);
+		assert_eq!(leaves[leaves.len() - 3], metadata.pallets[0].encode());
+	}
+
+	#[test]
+	fn digest_depends_on_the_metadata() {
+		let digest = metadata_ir_digest(ir_metadata(vec![pallet("System", 0)]));
+
+		assert_eq!(digest, metadata_ir_digest(ir_metadata(vec![pallet("System", 0)])));
+		assert_ne!(digest, metadata_ir_digest(ir_metadata(vec![pallet("System", 1)])));
+		assert_ne!(digest, metadata_ir_digest(ir_metadata(vec![])));
+	}
+
+	#[test]
+	fn unsupported_versions_have_no_digest() {
+		let metadata =
+			RuntimeMetadataPrefixed(META_RESERVED, RuntimeMetadata::V13(OpaqueMetadata(vec![])));
+
+		assert_eq!(metadata_digest(&metadata), None);
+	}
+}
@@ -21,8 +21,16 @@ mod types;
 use frame_metadata::{RuntimeMetadataPrefixed, RuntimeMetadataV14};
 pub use types::*;
 
+#[doc(hidden)]
+pub use sp_api::metadata::InternalImplRuntimeApis;
+
 mod v14;
 
+mod digest;
+pub use digest::{
+	metadata_digest, metadata_digest_leaves, metadata_ir_digest, METADATA_DIGEST_VERSION,
+};
+
 /// Metadata V14.
 const V14: u32 = 14;
 
@@ -67,6 +75,7 @@ mod test {
 				signed_extensions: vec![],
 			},
 			ty: meta_type::<()>(),
+			apis: vec![],
 		}
 	}
 
@@ -37,8 +37,19 @@ pub struct MetadataIR<T: Form = MetaForm> {
 	pub extrinsic: ExtrinsicMetadataIR<T>,
 	/// The type of the `Runtime`.
 	pub ty: T::Type,
+	/// Metadata of the runtime apis implemented by the runtime.
+	pub apis: Vec<RuntimeApiMetadataIR>,
 }
 
+/// The intermediate representation for the metadata of a runtime api.
+///
+/// Runtime api metadata doesn't contain any types, so the metadata generated by
+/// `decl_runtime_apis!` is used as is.
+pub use sp_api::metadata::{
+	DeprecationStatus as DeprecationStatusIR, RuntimeApiMetadata as RuntimeApiMetadataIR,
+	RuntimeApiMethodMetadata as RuntimeApiMethodMetadataIR,
+};
+
 /// The intermediate representation for a pallet metadata.
 #[derive(Clone, PartialEq, Eq, Encode, Debug)]
 pub struct PalletMetadataIR<T: Form = MetaForm> {
@@ -33,6 +33,7 @@ use frame_metadata::v14::{
 
 impl From<MetadataIR> for RuntimeMetadataV14 {
 	fn from(ir: MetadataIR) -> Self {
+		// V14 has no runtime api metadata, it is only exposed through the `Metadata` runtime api.
 		RuntimeMetadataV14::new(
 			ir.pallets.into_iter().map(Into::into).collect(),
 			ir.extrinsic.into(),
@@ -80,6 +80,8 @@ pub use metadata::{
 };
 
 mod hooks;
+#[cfg(feature = "genesis-builder")]
+pub use hooks::BuildGenesisConfig;
 #[cfg(feature = "std")]
 pub use hooks::GenesisBuild;
 pub use hooks::{
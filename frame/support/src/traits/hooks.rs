@@ -328,6 +328,17 @@ pub trait Hooks<BlockNumber> {
 	fn integrity_test() {}
 }
 
+/// A trait to define the build function of the genesis config of a runtime.
+///
+/// Unlike [`GenesisBuild`], this trait doesn't require `std`, only the `genesis-builder` feature.
+/// It is used by [`genesis_builder_helper`](crate::genesis_builder_helper) to implement the
+/// `sp_genesis_builder::GenesisBuilder` runtime api.
+#[cfg(feature = "genesis-builder")]
+pub trait BuildGenesisConfig: Default + serde::Serialize + serde::de::DeserializeOwned {
+	/// The build function puts initial `GenesisConfig` keys/values pairs into the storage.
+	fn build(&self);
+}
+
 /// A trait to define the build function of a genesis config, T and I are placeholder for pallet
 /// trait and pallet instance.
 #[cfg(feature = "std")]
@@ -1237,6 +1237,25 @@ fn pallet_on_genesis() {
 	})
 }
 
+#[test]
+fn genesis_config_builds_with_genesis_builder_helper() {
+	use frame_support::genesis_builder_helper::{build_config, create_default_config};
+
+	let config = create_default_config::<GenesisConfig>();
+	TestExternalities::default().execute_with(|| {
+		assert_eq!(build_config::<GenesisConfig>(config), Ok(()));
+		assert_eq!(
+			pallet::Pallet::<Runtime>::on_chain_storage_version(),
+			pallet::Pallet::<Runtime>::current_storage_version(),
+		);
+	});
+
+	TestExternalities::default().execute_with(|| {
+		assert!(build_config::<GenesisConfig>(br#"{"unknownPallet":{}}"#.to_vec()).is_err());
+		assert_eq!(pallet::Pallet::<Runtime>::on_chain_storage_version(), StorageVersion::new(0));
+	});
+}
+
 #[test]
 fn migrate_from_pallet_version_to_storage_version() {
 	const PALLET_VERSION_STORAGE_KEY_POSTFIX: &[u8] = b":__PALLET_VERSION__:";
@@ -0,0 +1,148 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests that the metadata of the runtime apis is part of the runtime metadata.
+
+use codec::Encode;
+use frame_support::{metadata_ir::DeprecationStatusIR, traits::ConstU32};
+use sp_core::OpaqueMetadata;
+use sp_runtime::traits::Block as BlockT;
+
+pub type Header = sp_runtime::generic::Header<u32, sp_runtime::traits::BlakeTwo256>;
+pub type Block = sp_runtime::generic::Block<Header, UncheckedExtrinsic>;
+pub type UncheckedExtrinsic = sp_runtime::generic::UncheckedExtrinsic<u32, RuntimeCall, (), ()>;
+
+impl frame_system::Config for Runtime {
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type BaseCallFilter = frame_support::traits::Everything;
+	type RuntimeOrigin = RuntimeOrigin;
+	type Index = u64;
+	type BlockNumber = u32;
+	type RuntimeCall = RuntimeCall;
+	type Hash = sp_runtime::testing::H256;
+	type Hashing = sp_runtime::traits::BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = sp_runtime::traits::IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type RuntimeEvent = RuntimeEvent;
+	type BlockHashCount = ConstU32<250>;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = ();
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = ();
+	type OnSetCode = ();
+	type MaxConsumers = ConstU32<16>;
+}
+
+frame_support::construct_runtime!(
+	pub struct Runtime where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic
+	{
+		System: frame_system::{Pallet, Call, Storage, Event<T>},
+	}
+);
+
+sp_api::decl_runtime_apis! {
+	/// An api with a deprecated method.
+	pub trait Api {
+		/// Still fine.
+		fn current() -> u64;
+		/// Superseded by `current`.
+		#[deprecated(note = "Use `current`", since = "2.0.0")]
+		fn legacy() -> u32;
+	}
+}
+
+sp_api::impl_runtime_apis! {
+	impl self::Api<Block> for Runtime {
+		fn current() -> u64 {
+			1
+		}
+
+		fn legacy() -> u32 {
+			1
+		}
+	}
+
+	impl sp_api::Core<Block> for Runtime {
+		fn version() -> sp_version::RuntimeVersion {
+			unimplemented!()
+		}
+
+		fn execute_block(_: Block) {
+			unimplemented!()
+		}
+
+		fn initialize_block(_: &<Block as BlockT>::Header) {
+			unimplemented!()
+		}
+	}
+
+	#[api_version(3)]
+	impl sp_api::Metadata<Block> for Runtime {
+		fn metadata() -> OpaqueMetadata {
+			OpaqueMetadata::new(Runtime::metadata().into())
+		}
+
+		fn metadata_at_version(version: u32) -> Option<OpaqueMetadata> {
+			Runtime::metadata_at_version(version)
+		}
+
+		fn metadata_versions() -> Vec<u32> {
+			Runtime::metadata_versions()
+		}
+
+		fn apis_metadata() -> OpaqueMetadata {
+			Runtime::apis_metadata()
+		}
+	}
+}
+
+#[test]
+fn runtime_api_metadata_is_part_of_the_metadata_ir() {
+	let apis = Runtime::metadata_ir().apis;
+	assert_eq!(apis, Runtime::runtime_api_metadata());
+
+	let api = apis.iter().find(|api| api.name == "Api").expect("Api is implemented");
+	assert_eq!(api.methods[0].deprecation, DeprecationStatusIR::NotDeprecated);
+	assert_eq!(
+		api.methods[1].deprecation,
+		DeprecationStatusIR::Deprecated { note: Some("Use `current`"), since: Some("2.0.0") },
+	);
+	assert!(apis.iter().any(|api| api.name == "Metadata" && api.version == 3));
+}
+
+#[test]
+fn runtime_api_metadata_is_exposed_by_the_metadata_api() {
+	use sp_api::runtime_decl_for_metadata::MetadataV3;
+
+	let apis = <Runtime as MetadataV3<Block>>::apis_metadata();
+	assert_eq!(*apis, Runtime::metadata_ir().apis.encode());
+
+	let version = RUNTIME_API_VERSIONS
+		.iter()
+		.find(|(id, _)| *id == <dyn sp_api::Metadata<Block>>::ID)
+		.map(|(_, version)| *version);
+	assert_eq!(version, Some(3));
+}
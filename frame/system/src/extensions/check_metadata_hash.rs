@@ -0,0 +1,285 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::Config;
+use codec::{Decode, Encode};
+use scale_info::TypeInfo;
+use sp_runtime::{
+	traits::{DispatchInfoOf, SignedExtension},
+	transaction_validity::TransactionValidityError,
+};
+
+/// The metadata digest of the runtime, passed by `substrate-wasm-builder` as hex string in the
+/// `RUNTIME_METADATA_HASH` environment variable.
+const RUNTIME_METADATA_HASH: Option<[u8; 32]> = match option_env!("RUNTIME_METADATA_HASH") {
+	Some(hex) => Some(parse_metadata_hash(hex)),
+	None => None,
+};
+
+/// Parse a `0x` prefixed hex string of 32 bytes at compile time.
+const fn parse_metadata_hash(hex: &str) -> [u8; 32] {
+	let hex = hex.as_bytes();
+	assert!(
+		hex.len() == 66 && hex[0] == b'0' && hex[1] == b'x',
+		"`RUNTIME_METADATA_HASH` needs to be a `0x` prefixed hex string of 32 bytes",
+	);
+
+	let mut hash = [0; 32];
+	let mut i = 0;
+	while i < 32 {
+		hash[i] = hex_digit(hex[2 + 2 * i]) << 4 | hex_digit(hex[3 + 2 * i]);
+		i += 1;
+	}
+
+	hash
+}
+
+const fn hex_digit(c: u8) -> u8 {
+	match c {
+		b'0'..=b'9' => c - b'0',
+		b'a'..=b'f' => c - b'a' + 10,
+		b'A'..=b'F' => c - b'A' + 10,
+		_ => panic!("`RUNTIME_METADATA_HASH` contains an invalid hex digit"),
+	}
+}
+
+/// The mode of [`CheckMetadataHash`], included in the extrinsic.
+#[derive(Encode, Decode, Clone, Copy, Eq, PartialEq, Debug, TypeInfo)]
+pub enum Mode {
+	/// The metadata digest is not part of the signed payload.
+	Disabled,
+	/// The metadata digest is part of the signed payload.
+	Enabled,
+}
+
+/// Where [`CheckMetadataHash`] gets the metadata digest from.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+enum MetadataHash {
+	/// Use the digest passed to the build of the runtime.
+	#[default]
+	FetchFromEnv,
+	/// Use the given digest.
+	Custom([u8; 32]),
+}
+
+impl MetadataHash {
+	fn hash(&self) -> Option<[u8; 32]> {
+		match self {
+			Self::FetchFromEnv => RUNTIME_METADATA_HASH,
+			Self::Custom(hash) => Some(*hash),
+		}
+	}
+}
+
+/// Include the digest of the runtime metadata in the signed payload.
+///
+/// Offline signers, like hardware wallets, use the metadata to decode the transaction they are
+/// asked to sign. When the [`Mode`] of this extension is [`Mode::Enabled`], the metadata digest
+/// (see `frame_support::metadata_ir::metadata_digest`) is part of the `additional_signed`
+/// payload. A signature created with metadata that doesn't match the one of the runtime is thus
+/// invalid.
+///
+/// The digest is passed to the build of the runtime by `substrate-wasm-builder` when
+/// `enable_metadata_hash` is used. If the runtime was built without a digest, the
+/// `additional_signed` payload is `None` for both modes and transactions signed in
+/// [`Mode::Enabled`] are rejected.
+///
+/// # Transaction Validity
+///
+/// A transaction signed over a different metadata digest fails the signature check. The
+/// validity is not affected in any other way.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckMetadataHash<T: Config + Send + Sync> {
+	mode: Mode,
+	#[codec(skip)]
+	metadata_hash: MetadataHash,
+	_phantom: sp_std::marker::PhantomData<T>,
+}
+
+impl<T: Config + Send + Sync> sp_std::fmt::Debug for CheckMetadataHash<T> {
+	#[cfg(feature = "std")]
+	fn fmt(&self, f: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		write!(f, "CheckMetadataHash({:?})", self.mode)
+	}
+
+	#[cfg(not(feature = "std"))]
+	fn fmt(&self, _: &mut sp_std::fmt::Formatter) -> sp_std::fmt::Result {
+		Ok(())
+	}
+}
+
+impl<T: Config + Send + Sync> CheckMetadataHash<T> {
+	/// Create new `SignedExtension` to check the metadata digest.
+	///
+	/// If `enable` is `true`, the metadata digest the runtime was built with is included in the
+	/// signed payload.
+	pub fn new(enable: bool) -> Self {
+		Self {
+			mode: if enable { Mode::Enabled } else { Mode::Disabled },
+			metadata_hash: MetadataHash::FetchFromEnv,
+			_phantom: sp_std::marker::PhantomData,
+		}
+	}
+
+	/// Create new `SignedExtension` that includes the given metadata digest in the signed
+	/// payload.
+	///
+	/// Useful for signing transactions natively, where the digest of the runtime is not known at
+	/// compile time.
+	pub fn new_with_custom_hash(metadata_hash: [u8; 32]) -> Self {
+		Self {
+			mode: Mode::Enabled,
+			metadata_hash: MetadataHash::Custom(metadata_hash),
+			_phantom: sp_std::marker::PhantomData,
+		}
+	}
+
+	/// Returns the [`Mode`] of this extension.
+	pub fn mode(&self) -> Mode {
+		self.mode
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckMetadataHash<T> {
+	type AccountId = T::AccountId;
+	type Call = <T as Config>::RuntimeCall;
+	type AdditionalSigned = Option<[u8; 32]>;
+	type Pre = ();
+	const IDENTIFIER: &'static str = "CheckMetadataHash";
+
+	fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+		match self.mode {
+			Mode::Disabled => Ok(None),
+			Mode::Enabled => Ok(self.metadata_hash.hash()),
+		}
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		self.validate(who, call, info, len).map(|_| ())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{Test, CALL};
+	use frame_support::metadata_ir::{metadata_ir_digest, ExtrinsicMetadataIR, MetadataIR};
+	use sp_core::{sr25519, Pair};
+	use sp_runtime::generic::SignedPayload;
+
+	/// Metadata the test vectors are derived from.
+	fn test_metadata(version: u8) -> MetadataIR {
+		MetadataIR {
+			pallets: vec![],
+			extrinsic: ExtrinsicMetadataIR {
+				ty: scale_info::meta_type::<()>(),
+				version,
+				signed_extensions: vec![],
+			},
+			ty: scale_info::meta_type::<u32>(),
+			apis: vec![],
+		}
+	}
+
+	#[test]
+	fn metadata_digest_test_vectors() {
+		assert_eq!(
+			metadata_ir_digest(test_metadata(4)),
+			array_bytes::hex2array_unchecked::<32>(
+				"c50946ec71df4b960b4e4bb349c43d69c998bf2a1206f2930554703ed4efbcdd"
+			),
+		);
+		assert_eq!(
+			metadata_ir_digest(test_metadata(5)),
+			array_bytes::hex2array_unchecked::<32>(
+				"d4866b0a667da11da7f63f860dda194eaf7b1d37a5947f917b63136038a39fdf"
+			),
+		);
+	}
+
+	#[test]
+	fn encoding_test_vectors() {
+		let hash = metadata_ir_digest(test_metadata(4));
+
+		// Only the mode byte is part of the extrinsic.
+		assert_eq!(CheckMetadataHash::<Test>::new(false).encode(), vec![0]);
+		assert_eq!(CheckMetadataHash::<Test>::new(true).encode(), vec![1]);
+		assert_eq!(CheckMetadataHash::<Test>::new_with_custom_hash(hash).encode(), vec![1]);
+		assert_eq!(CheckMetadataHash::<Test>::decode(&mut &[1][..]).unwrap().mode(), Mode::Enabled);
+		assert!(CheckMetadataHash::<Test>::decode(&mut &[2][..]).is_err());
+
+		// The digest is part of the signed payload when enabled.
+		let additional_signed =
+			|ext: CheckMetadataHash<Test>| ext.additional_signed().expect("Never fails").encode();
+		assert_eq!(additional_signed(CheckMetadataHash::new(false)), vec![0]);
+		assert_eq!(
+			additional_signed(CheckMetadataHash::new_with_custom_hash(hash)),
+			[&[1][..], &hash[..]].concat(),
+		);
+		// The tests are not built with `RUNTIME_METADATA_HASH`.
+		assert_eq!(additional_signed(CheckMetadataHash::new(true)), vec![0]);
+	}
+
+	#[test]
+	fn parse_metadata_hash_works() {
+		let hash = metadata_ir_digest(test_metadata(4));
+		let hex = array_bytes::bytes2hex("0x", &hash);
+
+		assert_eq!(parse_metadata_hash(&hex), hash);
+		assert_eq!(parse_metadata_hash(&hex.to_uppercase().replacen("0X", "0x", 1)), hash);
+	}
+
+	#[test]
+	#[should_panic(expected = "needs to be a `0x` prefixed hex string of 32 bytes")]
+	fn parse_metadata_hash_rejects_invalid_length() {
+		parse_metadata_hash("0x00");
+	}
+
+	#[test]
+	fn signature_over_wrong_metadata_is_rejected() {
+		let pair = sr25519::Pair::from_seed(&[1; 32]);
+		let hash = metadata_ir_digest(test_metadata(4));
+		let wrong_hash = metadata_ir_digest(test_metadata(5));
+
+		let sign = |ext: CheckMetadataHash<Test>| {
+			SignedPayload::new(CALL.clone(), ext)
+				.expect("Never fails")
+				.using_encoded(|payload| pair.sign(payload))
+		};
+		let verify = |signature: &sr25519::Signature, ext: CheckMetadataHash<Test>| {
+			SignedPayload::new(CALL.clone(), ext)
+				.expect("Never fails")
+				.using_encoded(|payload| sr25519::Pair::verify(signature, payload, &pair.public()))
+		};
+
+		let signature = sign(CheckMetadataHash::new_with_custom_hash(hash));
+		assert!(verify(&signature, CheckMetadataHash::new_with_custom_hash(hash)));
+		assert!(!verify(&signature, CheckMetadataHash::new_with_custom_hash(wrong_hash)));
+		assert!(!verify(&signature, CheckMetadataHash::new(false)));
+
+		let signature = sign(CheckMetadataHash::new(false));
+		assert!(verify(&signature, CheckMetadataHash::new(false)));
+		assert!(!verify(&signature, CheckMetadataHash::new_with_custom_hash(hash)));
+	}
+}
@@ -58,6 +58,8 @@
 //!     the transaction.
 //!   - [`CheckTxVersion`]: Checks that the transaction version is the same as the one used to sign
 //!     the transaction.
+//!   - [`CheckMetadataHash`]: Includes the digest of the runtime metadata in the signed payload, so
+//!     that offline signers can prove the metadata they used to decode the transaction.
 //!
 //! Lookup the runtime aggregator file (e.g. `node/runtime`) to see the full list of signed
 //! extensions included in a chain.
@@ -119,9 +121,13 @@ pub mod weights;
 pub mod migrations;
 
 pub use extensions::{
-	check_genesis::CheckGenesis, check_mortality::CheckMortality,
-	check_non_zero_sender::CheckNonZeroSender, check_nonce::CheckNonce,
-	check_spec_version::CheckSpecVersion, check_tx_version::CheckTxVersion,
+	check_genesis::CheckGenesis,
+	check_metadata_hash::{CheckMetadataHash, Mode as CheckMetadataHashMode},
+	check_mortality::CheckMortality,
+	check_non_zero_sender::CheckNonZeroSender,
+	check_nonce::CheckNonce,
+	check_spec_version::CheckSpecVersion,
+	check_tx_version::CheckTxVersion,
 	check_weight::CheckWeight,
 };
 // Backward compatible re-export.
@@ -37,8 +37,8 @@ use syn::{
 	parse_macro_input, parse_quote,
 	spanned::Spanned,
 	visit::{self, Visit},
-	Attribute, FnArg, GenericParam, Generics, Ident, ItemTrait, Lit, Meta, NestedMeta, TraitBound,
-	TraitItem, TraitItemMethod,
+	Attribute, FnArg, GenericParam, Generics, Ident, ItemTrait, Lit, LitStr, Meta, MetaNameValue,
+	NestedMeta, TraitBound, TraitItem, TraitItemMethod,
 };
 
 use std::collections::{BTreeMap, HashMap};
@@ -176,6 +176,81 @@ fn generate_versioned_api_traits(
 	result
 }
 
+/// Generate the metadata of the given runtime api `method`, added in `version`.
+fn generate_method_metadata(method: &TraitItemMethod, version: u64) -> Result<TokenStream> {
+	let crate_ = generate_crate_access();
+	let name = method.sig.ident.to_string();
+	let version = version as u32;
+	let docs = extract_docs(&method.attrs);
+	let deprecation = generate_deprecation_status(&method.attrs, &crate_)?;
+
+	Ok(quote!(
+		#crate_::metadata::RuntimeApiMethodMetadata {
+			name: #name,
+			version: #version,
+			docs: #crate_::vec::Vec::from([ #( #docs ),* ]),
+			deprecation: #deprecation,
+		}
+	))
+}
+
+/// Extract the documentation from the given `attrs`.
+fn extract_docs(attrs: &[Attribute]) -> Vec<LitStr> {
+	attrs
+		.iter()
+		.filter(|a| a.path.is_ident("doc"))
+		.filter_map(|a| match a.parse_meta() {
+			Ok(Meta::NameValue(MetaNameValue { lit: Lit::Str(doc), .. })) => Some(doc),
+			_ => None,
+		})
+		.collect()
+}
+
+/// Generate the `DeprecationStatus` from the `deprecated` attribute in the given `attrs`.
+fn generate_deprecation_status(attrs: &[Attribute], crate_: &TokenStream) -> Result<TokenStream> {
+	let deprecated = match attrs.iter().find(|a| a.path.is_ident("deprecated")) {
+		Some(deprecated) => deprecated,
+		None => return Ok(quote!( #crate_::metadata::DeprecationStatus::NotDeprecated )),
+	};
+
+	let err = |span| {
+		Error::new(
+			span,
+			"Unexpected `deprecated` attribute. The supported formats are `deprecated`, \
+			 `deprecated = \"note\"` and `deprecated(note = \"note\", since = \"version\")`",
+		)
+	};
+
+	let mut note = None;
+	let mut since = None;
+	match deprecated.parse_meta()? {
+		Meta::Path(_) => {},
+		Meta::NameValue(MetaNameValue { lit: Lit::Str(lit), .. }) => note = Some(lit),
+		Meta::List(list) =>
+			for nested in list.nested {
+				match nested {
+					NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+						path,
+						lit: Lit::Str(lit),
+						..
+					})) if path.is_ident("note") => note = Some(lit),
+					NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+						path,
+						lit: Lit::Str(lit),
+						..
+					})) if path.is_ident("since") => since = Some(lit),
+					other => return Err(err(other.span())),
+				}
+			},
+		other => return Err(err(other.span())),
+	}
+
+	let note = note.map(|n| quote!( Some(#n) )).unwrap_or_else(|| quote!(None));
+	let since = since.map(|s| quote!( Some(#s) )).unwrap_or_else(|| quote!(None));
+
+	Ok(quote!( #crate_::metadata::DeprecationStatus::Deprecated { note: #note, since: #since } ))
+}
+
 /// Try to parse the given `Attribute` as `renamed` attribute.
 fn parse_renamed_attribute(renamed: &Attribute) -> Result<(String, u32)> {
 	let meta = renamed.parse_meta()?;
@@ -228,6 +303,7 @@ fn generate_runtime_decls(decls: &[ItemTrait]) -> Result<TokenStream> {
 		let trait_api_version = get_api_version(&found_attributes)?;
 
 		let mut methods_by_version: BTreeMap<u64, Vec<TraitItemMethod>> = BTreeMap::new();
+		let mut methods_metadata = Vec::new();
 
 		// Process the items in the declaration. The filter_map function below does a lot of stuff
 		// because the method attributes are stripped at this point
@@ -278,6 +354,11 @@ fn generate_runtime_decls(decls: &[ItemTrait]) -> Result<TokenStream> {
 					// Make sure we replace all the wild card parameter names.
 					replace_wild_card_parameter_names(&mut method.sig);
 
+					match generate_method_metadata(method, method_version) {
+						Ok(metadata) => methods_metadata.push(metadata),
+						Err(e) => result.push(e.to_compile_error()),
+					}
+
 					// partition methods by api version
 					methods_by_version.entry(method_version).or_default().push(method.clone());
 				}
@@ -293,6 +374,12 @@ fn generate_runtime_decls(decls: &[ItemTrait]) -> Result<TokenStream> {
 			.expect("There should always be at least one version.")
 			.ident;
 
+		let crate_ = generate_crate_access();
+		let api_name = main_api_ident.to_string();
+		let api_metadata_version = trait_api_version as u32;
+		let docs = extract_docs(&decl.attrs);
+		let deprecation = generate_deprecation_status(&decl.attrs, &crate_)?;
+
 		result.push(quote!(
 			#[doc(hidden)]
 			#[allow(dead_code)]
@@ -307,6 +394,19 @@ fn generate_runtime_decls(decls: &[ItemTrait]) -> Result<TokenStream> {
 				pub #api_version
 
 				pub #id
+
+				/// Returns the metadata of this runtime api.
+				///
+				/// The metadata contains the methods of all declared versions.
+				pub fn runtime_metadata() -> #crate_::metadata::RuntimeApiMetadata {
+					#crate_::metadata::RuntimeApiMetadata {
+						name: #api_name,
+						version: #api_metadata_version,
+						methods: #crate_::vec::Vec::from([ #( #methods_metadata ),* ]),
+						docs: #crate_::vec::Vec::from([ #( #docs ),* ]),
+						deprecation: #deprecation,
+					}
+				}
 			}
 		));
 	}
@@ -322,6 +422,10 @@ struct ToClientSideDecl<'a> {
 	/// Any error that we found while converting this declaration.
 	errors: &'a mut Vec<TokenStream>,
 	trait_: &'a Ident,
+	/// The `deprecated` attribute of the trait, if any.
+	///
+	/// Applied to all methods that are not deprecated on their own, to warn about calls of them.
+	trait_deprecated: Option<Attribute>,
 }
 
 impl<'a> ToClientSideDecl<'a> {
@@ -426,6 +530,14 @@ impl<'a> ToClientSideDecl<'a> {
 
 		let found_attributes = remove_supported_attributes(&mut method.attrs);
 
+		if let Some(deprecated) = &self.trait_deprecated {
+			if !method.attrs.iter().any(|a| a.path.is_ident("deprecated")) {
+				method.attrs.push(deprecated.clone());
+			}
+			// The default implementation calls into the deprecated trait.
+			method.attrs.push(parse_quote!( #[allow(deprecated)] ));
+		}
+
 		// Parse the renamed attributes.
 		let mut renames = Vec::new();
 		for (_, a) in found_attributes.iter().filter(|a| a.0 == &RENAMED_ATTRIBUTE) {
@@ -529,6 +641,7 @@ impl<'a> Fold for ToClientSideDecl<'a> {
 		extend_generics_with_block(&mut input.generics);
 
 		*self.found_attributes = remove_supported_attributes(&mut input.attrs);
+		self.trait_deprecated = input.attrs.iter().find(|a| a.path.is_ident("deprecated")).cloned();
 		// Check if this is the `Core` runtime api trait.
 		let is_core_trait = self.found_attributes.contains_key(CORE_TRAIT_ATTRIBUTE);
 		let block_ident = Ident::new(BLOCK_GENERIC_IDENT, Span::call_site());
@@ -588,6 +701,7 @@ fn generate_runtime_info_impl(trait_: &ItemTrait, version: u64) -> TokenStream {
 
 	quote!(
 		#[cfg(any(feature = "std", test))]
+		#[allow(deprecated)]
 		impl < #( #impl_generics, )* > #crate_::RuntimeApiInfo
 			for dyn #trait_name < #( #ty_generics, )* >
 		{
@@ -632,6 +746,7 @@ fn generate_client_side_decls(decls: &[ItemTrait]) -> Result<TokenStream> {
 			found_attributes: &mut found_attributes,
 			errors: &mut errors,
 			trait_: &trait_,
+			trait_deprecated: None,
 		}
 		.process(decl);
 
@@ -33,7 +33,7 @@ use syn::{
 	parse::{Error, Parse, ParseStream, Result},
 	parse_macro_input, parse_quote,
 	spanned::Spanned,
-	Attribute, Ident, ImplItem, ItemImpl, Path, Signature, Type, TypePath,
+	Attribute, Ident, ImplItem, ItemImpl, Meta, NestedMeta, Path, Signature, Type, TypePath,
 };
 
 use std::collections::HashSet;
@@ -450,6 +450,8 @@ fn generate_api_impl_for_runtime(impls: &[ItemImpl]) -> Result<TokenStream> {
 
 		impl_.trait_.as_mut().unwrap().1 = trait_;
 		impl_.attrs = filter_cfg_attrs(&impl_.attrs);
+		// The runtime api may be deprecated, but it still needs to be implemented.
+		impl_.attrs.push(parse_quote!( #[allow(deprecated)] ));
 		impls_prepared.push(impl_);
 	}
 
@@ -572,6 +574,7 @@ impl<'a> Fold for ApiRuntimeImplToApiRuntimeApiImpl<'a> {
 		// The implementation for the `RuntimeApiImpl` is only required when compiling with
 		// the feature `std` or `test`.
 		input.attrs.push(parse_quote!( #[cfg(any(feature = "std", test))] ));
+		input.attrs.push(parse_quote!( #[allow(deprecated)] ));
 
 		fold::fold_item_impl(self, input)
 	}
@@ -644,7 +647,9 @@ fn generate_runtime_api_versions(impls: &[ItemImpl]) -> Result<TokenStream> {
 			.ident;
 
 		let span = trait_.span();
-		if !processed_traits.insert(trait_) {
+		// Feature gated implementations are split into two implementations with exclusive `cfg`s.
+		let cfgs = filter_cfg_attrs(&impl_.attrs);
+		if !processed_traits.insert((trait_, quote!( #( #cfgs )* ).to_string())) {
 			return Err(Error::new(
 				span,
 				"Two traits with the same name detected! \
@@ -668,6 +673,185 @@ fn generate_runtime_api_versions(impls: &[ItemImpl]) -> Result<TokenStream> {
 	))
 }
 
+/// Generates the `runtime_api_metadata` function that returns the metadata of all implemented
+/// runtime apis.
+fn generate_runtime_api_metadata(impls: &[ItemImpl]) -> Result<TokenStream> {
+	let c = generate_crate_access();
+	let runtime = &impls
+		.first()
+		.ok_or_else(|| Error::new(Span::call_site(), "No api implementation given!"))?
+		.self_ty;
+
+	let metadata = impls
+		.iter()
+		.map(|impl_| {
+			let api_ver = extract_api_version(&impl_.attrs, impl_.span())?.map(|a| a as u32);
+
+			let mut path = extend_with_runtime_decl_path(
+				extract_impl_trait(impl_, RequireQualifiedTraitPath::Yes)?.clone(),
+			);
+			// Remove the trait
+			path.segments.pop();
+
+			let version = api_ver.map(|a| quote!( #a )).unwrap_or_else(|| quote!( #path VERSION ));
+			let attrs = filter_cfg_attrs(&impl_.attrs);
+
+			Ok(quote!(
+				#( #attrs )*
+				metadata.push(#path runtime_metadata().for_version(#version));
+			))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok(quote!(
+		impl #runtime {
+			/// Returns the metadata of all runtime apis implemented by the runtime.
+			pub fn runtime_api_metadata() -> #c::vec::Vec<#c::metadata::RuntimeApiMetadata> {
+				let mut metadata = #c::vec::Vec::new();
+				#( #metadata )*
+				metadata
+			}
+		}
+
+		impl #c::metadata::InternalImplRuntimeApis for #runtime {
+			fn runtime_api_metadata(&self) -> #c::vec::Vec<#c::metadata::RuntimeApiMetadata> {
+				<#runtime>::runtime_api_metadata()
+			}
+		}
+	))
+}
+
+/// Split implementations with a feature gated `api_version` into two implementations.
+///
+/// An implementation annotated with `#[cfg_attr(feature = "name", api_version(N))]` implements
+/// version `N` of the runtime api when the feature `name` is enabled. Methods that only exist in
+/// this version need to be annotated with `#[cfg(feature = "name")]`. The implementation is split
+/// into one implementation of version `N` that is only compiled with the feature enabled and one
+/// implementation of the default version, without the feature gated methods, that is only
+/// compiled with the feature disabled.
+fn expand_feature_gated_impls(impls: &[ItemImpl]) -> Result<Vec<ItemImpl>> {
+	let mut result = Vec::with_capacity(impls.len());
+
+	for impl_ in impls {
+		let (predicate, version) = match extract_feature_gated_api_version(&impl_.attrs)? {
+			Some(gated) => gated,
+			None => {
+				result.push(impl_.clone());
+				continue
+			},
+		};
+
+		if let Some(default_version) = extract_api_version(&impl_.attrs, impl_.span())? {
+			if default_version >= version {
+				return Err(Error::new(
+					impl_.span(),
+					format!(
+						"The feature gated `{}` ({}) needs to be greater than the default one ({}).",
+						API_VERSION_ATTRIBUTE, version, default_version,
+					),
+				))
+			}
+		}
+
+		let attrs = impl_
+			.attrs
+			.iter()
+			.filter(|a| !is_feature_gated_api_version(a))
+			.cloned()
+			.collect::<Vec<_>>();
+
+		let mut gated = impl_.clone();
+		gated.attrs = attrs
+			.iter()
+			.filter(|a| !a.path.is_ident(API_VERSION_ATTRIBUTE))
+			.cloned()
+			.collect();
+		gated.attrs.push(parse_quote!( #[cfg(#predicate)] ));
+		gated.attrs.push(parse_quote!( #[api_version(#version)] ));
+
+		let gate = quote!( #predicate ).to_string();
+		let mut default = impl_.clone();
+		default.attrs = attrs;
+		default.attrs.push(parse_quote!( #[cfg(not(#predicate))] ));
+		default.items.retain(|item| match item {
+			ImplItem::Method(method) => !method.attrs.iter().any(|a| {
+				a.path.is_ident("cfg") &&
+					matches!(a.parse_args::<NestedMeta>(), Ok(p) if quote!( #p ).to_string() == gate)
+			}),
+			_ => true,
+		});
+
+		result.push(gated);
+		result.push(default);
+	}
+
+	Ok(result)
+}
+
+/// Returns `true` if the given attribute is a `#[cfg_attr(predicate, api_version(N))]`.
+fn is_feature_gated_api_version(attr: &Attribute) -> bool {
+	matches!(parse_feature_gated_api_version(attr), Ok(Some(_)))
+}
+
+/// Try to parse the given attribute as `#[cfg_attr(predicate, api_version(N))]`.
+fn parse_feature_gated_api_version(attr: &Attribute) -> Result<Option<(NestedMeta, u64)>> {
+	if !attr.path.is_ident("cfg_attr") {
+		return Ok(None)
+	}
+
+	let list = match attr.parse_meta()? {
+		Meta::List(list) => list,
+		_ => return Ok(None),
+	};
+
+	let mut nested = list.nested.into_iter();
+	let (predicate, api_version) = match (nested.next(), nested.next(), nested.next()) {
+		(Some(predicate), Some(NestedMeta::Meta(Meta::List(api_version))), None)
+			if api_version.path.is_ident(API_VERSION_ATTRIBUTE) =>
+			(predicate, api_version),
+		_ => return Ok(None),
+	};
+
+	let version = match api_version.nested.first() {
+		Some(NestedMeta::Lit(syn::Lit::Int(version))) if api_version.nested.len() == 1 =>
+			version.base10_parse()?,
+		_ =>
+			return Err(Error::new(
+				api_version.span(),
+				format!(
+					"Unexpected `{api_version}` attribute. The supported format is \
+					 `cfg_attr(feature = \"name\", {api_version}(1))`",
+					api_version = API_VERSION_ATTRIBUTE
+				),
+			)),
+	};
+
+	Ok(Some((predicate, version)))
+}
+
+/// Extract the feature gated api version from the given `attrs`.
+fn extract_feature_gated_api_version(attrs: &[Attribute]) -> Result<Option<(NestedMeta, u64)>> {
+	let mut gated = Vec::new();
+	for attr in attrs {
+		if let Some(version) = parse_feature_gated_api_version(attr)? {
+			gated.push((attr, version));
+		}
+	}
+
+	if gated.len() > 1 {
+		return Err(Error::new(
+			gated[1].0.span(),
+			format!(
+				"Found multiple feature gated #[{}] attributes for an API implementation. \
+				 Each runtime API can have only one feature gated version.",
+				API_VERSION_ATTRIBUTE
+			),
+		))
+	}
+
+	Ok(gated.pop().map(|(_, version)| version))
+}
+
 /// The implementation of the `impl_runtime_apis!` macro.
 pub fn impl_runtime_apis_impl(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 	// Parse all impl blocks
@@ -679,12 +863,14 @@ pub fn impl_runtime_apis_impl(input: proc_macro::TokenStream) -> proc_macro::Tok
 }
 
 fn impl_runtime_apis_impl_inner(api_impls: &[ItemImpl]) -> Result<TokenStream> {
+	let api_impls = &expand_feature_gated_impls(api_impls)?;
 	let dispatch_impl = generate_dispatch_function(api_impls)?;
 	let api_impls_for_runtime = generate_api_impl_for_runtime(api_impls)?;
 	let base_runtime_api = generate_runtime_api_base_structures()?;
 	let runtime_api_versions = generate_runtime_api_versions(api_impls)?;
 	let wasm_interface = generate_wasm_interface(api_impls)?;
 	let api_impls_for_runtime_api = generate_api_impl_for_runtime_api(api_impls)?;
+	let runtime_api_metadata = generate_runtime_api_metadata(api_impls)?;
 
 	let impl_ = quote!(
 		#base_runtime_api
@@ -695,6 +881,8 @@ fn impl_runtime_apis_impl_inner(api_impls: &[ItemImpl]) -> Result<TokenStream> {
 
 		#runtime_api_versions
 
+		#runtime_api_metadata
+
 		pub mod api {
 			use super::*;
 
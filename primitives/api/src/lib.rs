@@ -101,12 +101,14 @@ pub use sp_state_machine::{
 	StorageProof, TrieBackend, TrieBackendBuilder,
 };
 #[doc(hidden)]
-pub use sp_std::{mem, slice};
+pub use sp_std::{mem, slice, vec};
 #[doc(hidden)]
 pub use sp_version::{create_apis_vec, ApiId, ApisVec, RuntimeVersion};
 #[cfg(feature = "std")]
 use std::cell::RefCell;
 
+pub mod metadata;
+
 /// Maximum nesting level for extrinsics.
 pub const MAX_EXTRINSIC_DEPTH: u32 = 256;
 
@@ -234,6 +236,26 @@ pub const MAX_EXTRINSIC_DEPTH: u32 = 256;
 /// ```
 /// Note that the latest version (4 in our example above) always contains all methods from all
 /// the versions before.
+///
+/// # Deprecation
+///
+/// Runtime api traits and methods can be marked with the `#[deprecated]` attribute. Calling a
+/// deprecated api or method through the client side trait emits the usual deprecation warning.
+/// The deprecation status, including the optional `note` and `since`, is also part of the
+/// [`metadata`] of the runtime api:
+/// ```rust
+/// sp_api::decl_runtime_apis! {
+///     pub trait Balance {
+///         /// Get the balance.
+///         fn get_balance() -> u64;
+///         /// Get the balance of the old account format.
+///         #[deprecated(note = "Use `get_balance`", since = "2.0.0")]
+///         fn get_legacy_balance() -> u64;
+///     }
+/// }
+///
+/// # fn main() {}
+/// ```
 pub use sp_api_proc_macro::decl_runtime_apis;
 
 /// Tags given trait implementations as runtime apis.
@@ -339,6 +361,41 @@ pub use sp_api_proc_macro::decl_runtime_apis;
 /// ```
 /// In this case `Balance` api version 3 is being implemented for `Runtime`. The `impl` block
 /// must contain all methods declared in version 3 and below.
+///
+/// # Staging api versions
+///
+/// Methods of a new api version can be implemented behind a cargo feature, for example to test
+/// them on a testnet before enabling them on a production network. The version implemented
+/// with the feature enabled is declared with `cfg_attr` and the staging methods are gated with
+/// `cfg`:
+/// ```ignore
+/// sp_api::impl_runtime_apis! {
+///     #[cfg_attr(feature = "enable-staging-api", api_version(3))]
+///     impl self::Balance<Block> for Runtime {
+///         fn get_balance() -> u64 {
+///             1
+///         }
+///         fn set_balance(_bal: u64) {
+///             // Store the balance
+///         }
+///
+///         #[cfg(feature = "enable-staging-api")]
+///         fn transfer_balance(_uid: u64) {
+///             // Transfer the balance
+///         }
+///     }
+/// }
+/// ```
+/// With `enable-staging-api` enabled, version 3 of `Balance` is implemented and reported in
+/// `RUNTIME_API_VERSIONS`. Otherwise the base version (or the version given by a plain
+/// `api_version` attribute) is implemented, without the methods gated by the same feature.
+///
+/// # Metadata
+///
+/// The inherent function `Runtime::runtime_api_metadata` returns the [`metadata`] of all
+/// implemented runtime apis, for the versions they are implemented in. `construct_runtime!`
+/// includes it in the metadata of the runtime, which is exposed by version 3 of the
+/// [`Metadata`] runtime api.
 pub use sp_api_proc_macro::impl_runtime_apis;
 
 /// Mocks given trait implementations as runtime apis.
@@ -744,5 +801,12 @@ decl_runtime_apis! {
 		///
 		/// This can be used to call `metadata_at_version`.
 		fn metadata_versions() -> sp_std::vec::Vec<u32>;
+
+		/// Returns the metadata of the runtime apis implemented by the runtime.
+		///
+		/// This is the SCALE encoded list of [`metadata::RuntimeApiMetadata`], which includes the
+		/// deprecation status of the runtime apis and their methods.
+		#[api_version(3)]
+		fn apis_metadata() -> OpaqueMetadata;
 	}
 }
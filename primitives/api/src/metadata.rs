@@ -0,0 +1,101 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metadata of the runtime apis.
+//!
+//! [`decl_runtime_apis!`](crate::decl_runtime_apis) generates a `runtime_metadata` function for
+//! every declared api and [`impl_runtime_apis!`](crate::impl_runtime_apis) collects the metadata
+//! of all implemented apis in `runtime_api_metadata`, an inherent function of the runtime type.
+
+use codec::Encode;
+use sp_core::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// Metadata of a runtime api.
+#[derive(Clone, PartialEq, Eq, Encode, RuntimeDebug)]
+pub struct RuntimeApiMetadata {
+	/// The name of the runtime api trait.
+	pub name: &'static str,
+	/// The implemented version of the runtime api.
+	///
+	/// When generated by `decl_runtime_apis!`, this is the default version of the runtime api and
+	/// `methods` contains the methods of all declared versions.
+	pub version: u32,
+	/// The methods of the runtime api.
+	pub methods: Vec<RuntimeApiMethodMetadata>,
+	/// The documentation of the runtime api trait.
+	pub docs: Vec<&'static str>,
+	/// The deprecation status of the runtime api trait.
+	pub deprecation: DeprecationStatus,
+}
+
+impl RuntimeApiMetadata {
+	/// Returns the metadata for the given implemented `version`.
+	///
+	/// Methods that were added after `version` are removed.
+	pub fn for_version(mut self, version: u32) -> Self {
+		self.version = version;
+		self.methods.retain(|m| m.version <= version);
+		self
+	}
+}
+
+/// Metadata of a runtime api method.
+#[derive(Clone, PartialEq, Eq, Encode, RuntimeDebug)]
+pub struct RuntimeApiMethodMetadata {
+	/// The name of the method.
+	pub name: &'static str,
+	/// The version of the runtime api that added the method.
+	pub version: u32,
+	/// The documentation of the method.
+	pub docs: Vec<&'static str>,
+	/// The deprecation status of the method.
+	pub deprecation: DeprecationStatus,
+}
+
+/// The deprecation status of a runtime api or runtime api method, as declared by the
+/// `#[deprecated]` attribute.
+#[derive(Clone, PartialEq, Eq, Encode, RuntimeDebug)]
+pub enum DeprecationStatus {
+	/// Not deprecated.
+	NotDeprecated,
+	/// Deprecated with an optional `note` and `since`.
+	Deprecated {
+		/// The `note` of the `#[deprecated]` attribute.
+		note: Option<&'static str>,
+		/// The `since` of the `#[deprecated]` attribute.
+		since: Option<&'static str>,
+	},
+}
+
+impl DeprecationStatus {
+	/// Returns `true` if deprecated.
+	pub fn is_deprecated(&self) -> bool {
+		matches!(self, Self::Deprecated { .. })
+	}
+}
+
+/// Implemented by [`impl_runtime_apis!`](crate::impl_runtime_apis) for the runtime type, to make
+/// the [`RuntimeApiMetadata`] available to `construct_runtime!`.
+///
+/// `construct_runtime!` falls back to an empty list for runtimes that don't implement any
+/// runtime api.
+#[doc(hidden)]
+pub trait InternalImplRuntimeApis {
+	/// Returns the metadata of all runtime apis implemented by the runtime.
+	fn runtime_api_metadata(&self) -> Vec<RuntimeApiMetadata>;
+}
@@ -53,6 +53,25 @@ decl_runtime_apis! {
 		#[api_version(4)]
 		fn glory_one();
 	}
+
+	/// An api with deprecated methods.
+	pub trait ApiWithDeprecatedMethods {
+		/// Still fine.
+		fn current();
+		#[deprecated(note = "Use `current`", since = "2.0.0")]
+		fn old();
+	}
+
+	#[deprecated = "Use `ApiWithDeprecatedMethods`"]
+	pub trait DeprecatedApi {
+		fn method();
+	}
+
+	pub trait ApiWithStagingMethod {
+		fn stable_one();
+		#[api_version(99)]
+		fn staging_one();
+	}
 }
 
 impl_runtime_apis! {
@@ -85,6 +104,24 @@ impl_runtime_apis! {
 		fn new_one() {}
 	}
 
+	impl self::ApiWithDeprecatedMethods<Block> for Runtime {
+		fn current() {}
+
+		fn old() {}
+	}
+
+	impl self::DeprecatedApi<Block> for Runtime {
+		fn method() {}
+	}
+
+	#[cfg_attr(feature = "enable-staging-api", api_version(99))]
+	impl self::ApiWithStagingMethod<Block> for Runtime {
+		fn stable_one() {}
+
+		#[cfg(feature = "enable-staging-api")]
+		fn staging_one() {}
+	}
+
 	impl sp_api::Core<Block> for Runtime {
 		fn version() -> sp_version::RuntimeVersion {
 			unimplemented!()
@@ -198,6 +235,62 @@ fn check_runtime_api_versions() {
 	check_runtime_api_versions_contains::<dyn sp_api::Core<Block>>();
 }
 
+#[test]
+fn check_staging_runtime_api_versions() {
+	let version = RUNTIME_API_VERSIONS
+		.iter()
+		.find(|v| v.0 == <dyn ApiWithStagingMethod<Block>>::ID)
+		.map(|v| v.1);
+
+	#[cfg(feature = "enable-staging-api")]
+	assert_eq!(version, Some(99));
+	#[cfg(not(feature = "enable-staging-api"))]
+	assert_eq!(version, Some(<dyn ApiWithStagingMethod<Block>>::VERSION));
+}
+
+#[test]
+fn runtime_api_metadata_works() {
+	use sp_api::metadata::DeprecationStatus;
+
+	let metadata = Runtime::runtime_api_metadata();
+	let api = |name| metadata.iter().find(|m| m.name == name).expect("Api is implemented");
+
+	let multiple_versions = api("ApiWithMultipleVersions");
+	assert_eq!(multiple_versions.version, 3);
+	assert_eq!(
+		multiple_versions
+			.methods
+			.iter()
+			.map(|m| (m.name, m.version))
+			.collect::<Vec<_>>(),
+		vec![("stable_one", 2), ("new_one", 3)],
+	);
+
+	let deprecated_methods = api("ApiWithDeprecatedMethods");
+	assert_eq!(deprecated_methods.docs, vec![" An api with deprecated methods."]);
+	assert_eq!(deprecated_methods.deprecation, DeprecationStatus::NotDeprecated);
+	assert_eq!(deprecated_methods.methods[0].docs, vec![" Still fine."]);
+	assert_eq!(deprecated_methods.methods[0].deprecation, DeprecationStatus::NotDeprecated);
+	assert_eq!(
+		deprecated_methods.methods[1].deprecation,
+		DeprecationStatus::Deprecated { note: Some("Use `current`"), since: Some("2.0.0") },
+	);
+
+	assert_eq!(
+		api("DeprecatedApi").deprecation,
+		DeprecationStatus::Deprecated { note: Some("Use `ApiWithDeprecatedMethods`"), since: None },
+	);
+
+	let staging = api("ApiWithStagingMethod");
+	#[cfg(feature = "enable-staging-api")]
+	assert_eq!(staging.methods.len(), 2);
+	#[cfg(not(feature = "enable-staging-api"))]
+	assert_eq!(staging.methods.len(), 1);
+
+	// `changed_in` methods are not part of the runtime.
+	assert_eq!(api("ApiWithCustomVersion").methods.len(), 1);
+}
+
 #[test]
 fn mock_runtime_api_has_api() {
 	let mock = MockApi { block: None };
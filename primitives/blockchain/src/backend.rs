@@ -256,9 +256,52 @@ pub trait Backend<Block: BlockT>:
 		Ok(self.indexed_transaction(hash)?.is_some())
 	}
 
+	/// Get single indexed transaction by its digest under the given hashing algorithm.
+	///
+	/// By default only [`TransactionHashAlgorithm::Blake2b256`] digests, which are the content
+	/// hashes used by [`Self::indexed_transaction`], are supported.
+	fn indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> Result<Option<Vec<u8>>> {
+		let mut hash = Block::Hash::default();
+		if algorithm != TransactionHashAlgorithm::Blake2b256 || digest.len() != hash.as_ref().len()
+		{
+			return Ok(None)
+		}
+
+		hash.as_mut().copy_from_slice(digest);
+		self.indexed_transaction(hash)
+	}
+
+	/// Check if indexed transaction exists under the given digest.
+	fn has_indexed_transaction_by(
+		&self,
+		algorithm: TransactionHashAlgorithm,
+		digest: &[u8],
+	) -> Result<bool> {
+		Ok(self.indexed_transaction_by(algorithm, digest)?.is_some())
+	}
+
 	fn block_indexed_body(&self, hash: Block::Hash) -> Result<Option<Vec<Vec<u8>>>>;
 }
 
+/// Hashing algorithm under which an indexed transaction can be looked up.
+///
+/// Indexed transactions are always stored by their Blake2b-256 content hash. The other digests
+/// are only available if the backend indexes them, which allows serving the data to clients
+/// addressing content with other hashes, like IPFS does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransactionHashAlgorithm {
+	/// Blake2b with a 256-bit output.
+	Blake2b256,
+	/// SHA2 with a 256-bit output.
+	Sha2_256,
+	/// Keccak with a 256-bit output.
+	Keccak256,
+}
+
 /// Blockchain info
 #[derive(Debug, Eq, PartialEq)]
 pub struct Info<Block: BlockT> {
@@ -162,6 +162,9 @@ pub enum Error {
 	#[error("Failed to set the chain head to a block that's too old.")]
 	SetHeadTooOld,
 
+	#[error("Storage changes of block {0} are not indexed")]
+	StorageChangesNotIndexed(String),
+
 	#[error(transparent)]
 	Application(#[from] Box<dyn std::error::Error + Send + Sync + 'static>),
 
@@ -0,0 +1,93 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Substrate genesis config builder
+//!
+//! This crate contains the [`GenesisBuilder`], a runtime api that allows to build the genesis
+//! storage of a runtime from a JSON representation of its `GenesisConfig`. Together with the
+//! named presets provided by the runtime, this allows to create a chain spec from just the wasm
+//! blob of the runtime, without having the `GenesisConfig` type available natively.
+//!
+//! The JSON representation of the `GenesisConfig` is the one produced by its `serde`
+//! implementation.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Decode, Encode};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// The result type alias, used in build methods. `Err` contains formatted error message.
+pub type Result = core::result::Result<(), sp_runtime::RuntimeString>;
+
+/// The name of the preset for development chains.
+pub const DEV_RUNTIME_PRESET: &str = "development";
+
+/// The name of the preset for local testnets.
+pub const LOCAL_TESTNET_RUNTIME_PRESET: &str = "local_testnet";
+
+/// The identifier of a genesis config preset, usually an UTF-8 encoded name.
+#[derive(Clone, PartialEq, Eq, Encode, Decode, RuntimeDebug)]
+pub struct PresetId(Vec<u8>);
+
+impl PresetId {
+	/// Returns the raw bytes of the identifier.
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.0
+	}
+}
+
+impl From<&str> for PresetId {
+	fn from(name: &str) -> Self {
+		Self(name.as_bytes().to_vec())
+	}
+}
+
+impl PartialEq<&str> for PresetId {
+	fn eq(&self, other: &&str) -> bool {
+		self.0 == other.as_bytes()
+	}
+}
+
+sp_api::decl_runtime_apis! {
+	/// API to interact with `GenesisConfig` for the runtime.
+	pub trait GenesisBuilder {
+		/// Creates the default `GenesisConfig` and returns it as a JSON blob.
+		///
+		/// This function instantiates the default `GenesisConfig` struct for the runtime and
+		/// serializes it into a JSON blob. It returns a `Vec<u8>` containing the JSON
+		/// representation of the default `GenesisConfig`.
+		fn create_default_config() -> Vec<u8>;
+
+		/// Build `GenesisConfig` from a JSON blob not using any defaults and store it in the
+		/// storage.
+		///
+		/// The JSON blob needs to contain the full `GenesisConfig`, partial configs are rejected.
+		/// A partial config (patch) has to be merged into the default config returned by
+		/// [`Self::create_default_config`] by the caller.
+		///
+		/// Returns `Ok(())` if successful, or an error message otherwise.
+		fn build_config(json: Vec<u8>) -> Result;
+
+		/// Returns the JSON patch of the named preset with the given `id`.
+		///
+		/// A preset is a patch for the default `GenesisConfig`, for example
+		/// [`DEV_RUNTIME_PRESET`] or [`LOCAL_TESTNET_RUNTIME_PRESET`]. Returns `None` if the
+		/// runtime doesn't provide a preset with the given `id`.
+		fn get_preset(id: PresetId) -> Option<Vec<u8>>;
+	}
+}
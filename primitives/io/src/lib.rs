@@ -1487,6 +1487,70 @@ pub trait Logging {
 	fn max_level() -> LogLevelFilter {
 		log::max_level().into()
 	}
+
+	/// Returns the max log level the host will display for the given `target`.
+	///
+	/// In contrast to [`max_level`](Self::max_level) this respects per-target directives, so the
+	/// runtime can skip formatting log messages that would be discarded anyway.
+	fn max_level_for(target: &str) -> LogLevelFilter {
+		let enabled = |level: log::Level| {
+			let metadata = log::Metadata::builder().level(level).target(target).build();
+			let log_enabled = level <= log::max_level() && log::logger().enabled(&metadata);
+
+			// Records with fields are emitted as `tracing` events, that may be enabled even if
+			// plain log messages are not, e.g. while tracing a block.
+			log_enabled || sp_tracing::runtime_log_enabled(tracing_level(level), target)
+		};
+
+		let levels = [
+			log::Level::Trace,
+			log::Level::Debug,
+			log::Level::Info,
+			log::Level::Warn,
+			log::Level::Error,
+		];
+
+		levels
+			.into_iter()
+			.find(|level| enabled(*level))
+			.map_or(log::LevelFilter::Off, |level| level.to_level_filter())
+			.into()
+	}
+
+	/// Request to print a log message with structured key/value `fields` on the host.
+	///
+	/// The message is emitted as `tracing` event, which allows the node to display the fields
+	/// next to the message and to include them in block traces.
+	///
+	/// Instead of using directly, prefer using `RuntimeLogger::log_with_fields`.
+	fn log_with_fields(
+		level: LogLevel,
+		target: &str,
+		message: &[u8],
+		fields: Vec<(Vec<u8>, Vec<u8>)>,
+	) {
+		if let Ok(message) = std::str::from_utf8(message) {
+			let fields = fields
+				.iter()
+				.map(|(key, value)| (String::from_utf8_lossy(key), String::from_utf8_lossy(value)))
+				.collect::<Vec<_>>();
+			let fields = fields.iter().map(|(key, value)| (&**key, &**value)).collect::<Vec<_>>();
+
+			sp_tracing::emit_runtime_log(tracing_level(level.into()), target, message, &fields)
+		}
+	}
+}
+
+/// Converts a `log` level into the matching `tracing` level.
+#[cfg(feature = "std")]
+fn tracing_level(level: log::Level) -> tracing::Level {
+	match level {
+		log::Level::Error => tracing::Level::ERROR,
+		log::Level::Warn => tracing::Level::WARN,
+		log::Level::Info => tracing::Level::INFO,
+		log::Level::Debug => tracing::Level::DEBUG,
+		log::Level::Trace => tracing::Level::TRACE,
+	}
 }
 
 #[derive(Encode, Decode)]
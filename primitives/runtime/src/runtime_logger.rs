@@ -43,16 +43,52 @@ impl RuntimeLogger {
 		// Use the same max log level as used by the host.
 		log::set_max_level(sp_io::logging::max_level().into());
 	}
+
+	/// Log a message with structured key/value `fields`.
+	///
+	/// Neither the message nor the fields are formatted if the host would discard the record for
+	/// the given `level` and `target`.
+	pub fn log_with_fields(
+		level: log::Level,
+		target: &str,
+		message: sp_std::fmt::Arguments,
+		fields: &[(&str, &dyn sp_std::fmt::Display)],
+	) {
+		if !Self::enabled_for(level, target) {
+			return
+		}
+
+		let format = |args: &dyn sp_std::fmt::Display| {
+			use sp_std::fmt::Write;
+			let mut w = sp_std::Writer::default();
+			let _ = ::core::write!(&mut w, "{}", args);
+			w.into_inner()
+		};
+		let fields = fields
+			.iter()
+			.map(|(key, value)| (key.as_bytes().to_vec(), format(value)))
+			.collect();
+
+		sp_io::logging::log_with_fields(level.into(), target, &format(&message), fields);
+	}
+
+	/// Returns whether the host displays log messages of the given `level` and `target`.
+	fn enabled_for(level: log::Level, target: &str) -> bool {
+		level <= log::LevelFilter::from(sp_io::logging::max_level_for(target))
+	}
 }
 
 impl log::Log for RuntimeLogger {
-	fn enabled(&self, _: &log::Metadata) -> bool {
-		// The final filtering is done by the host. This is not perfect, as we would still call into
-		// the host for log lines that will be thrown away.
-		true
+	fn enabled(&self, metadata: &log::Metadata) -> bool {
+		Self::enabled_for(metadata.level(), metadata.target())
 	}
 
 	fn log(&self, record: &log::Record) {
+		// Ask the host before formatting, the message would be thrown away anyway.
+		if !self.enabled(record.metadata()) {
+			return
+		}
+
 		use sp_std::fmt::Write;
 		let mut w = sp_std::Writer::default();
 		let _ = ::core::write!(&mut w, "{}", record.args());
@@ -65,6 +101,7 @@ impl log::Log for RuntimeLogger {
 
 #[cfg(test)]
 mod tests {
+	use super::RuntimeLogger;
 	use sp_api::ProvideRuntimeApi;
 	use std::{env, str::FromStr};
 	use substrate_test_runtime_client::{
@@ -100,4 +137,37 @@ mod tests {
 			}
 		}
 	}
+
+	#[test]
+	fn runtime_logger_respects_host_log_level_per_target() {
+		if env::var("RUN_TEST").is_ok() {
+			sp_tracing::try_init_simple();
+
+			RuntimeLogger::log_with_fields(
+				log::Level::Debug,
+				"runtime::verbose",
+				format_args!("Hey I'm verbose"),
+				&[("who", &"alice"), ("amount", &42)],
+			);
+			RuntimeLogger::log_with_fields(
+				log::Level::Debug,
+				"runtime::quiet",
+				format_args!("Hey I'm quiet"),
+				&[],
+			);
+		} else {
+			let executable = std::env::current_exe().unwrap();
+			let output = std::process::Command::new(executable)
+				.env("RUN_TEST", "1")
+				.env("RUST_LOG", "warn,runtime::verbose=trace")
+				.args(["--nocapture", "runtime_logger_respects_host_log_level_per_target"])
+				.output()
+				.unwrap();
+
+			let output = String::from_utf8(output.stderr).unwrap();
+			assert!(output.contains("Hey I'm verbose"));
+			assert!(output.contains(r#"{"who":"alice","amount":"42"}"#));
+			assert!(!output.contains("Hey I'm quiet"));
+		}
+	}
 }
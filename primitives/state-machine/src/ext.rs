@@ -325,7 +325,7 @@ where
 					// If `backend_key` is less than the `overlay_key`, we found out next key.
 					if cmp == Some(Ordering::Less) {
 						return next_backend_key
-					} else if overlay_key.1.value().is_some() {
+					} else if overlay_key.1.value_slice().is_some() {
 						// If there exists a value for the `overlay_key` in the overlay
 						// (aka the key is still valid), it means we have found our next key.
 						return Some(overlay_key.0.to_vec())
@@ -344,7 +344,7 @@ where
 			},
 			(None, Some(_)) => {
 				// Find the next overlay key that has a value attached.
-				overlay_changes.find_map(|k| k.1.value().as_ref().map(|_| k.0.to_vec()))
+				overlay_changes.find_map(|k| k.1.value_slice().map(|_| k.0.to_vec()))
 			},
 		}
 	}
@@ -366,7 +366,7 @@ where
 					// If `backend_key` is less than the `overlay_key`, we found out next key.
 					if cmp == Some(Ordering::Less) {
 						return next_backend_key
-					} else if overlay_key.1.value().is_some() {
+					} else if overlay_key.1.value_slice().is_some() {
 						// If there exists a value for the `overlay_key` in the overlay
 						// (aka the key is still valid), it means we have found our next key.
 						return Some(overlay_key.0.to_vec())
@@ -385,7 +385,7 @@ where
 			},
 			(None, Some(_)) => {
 				// Find the next overlay key that has a value attached.
-				overlay_changes.find_map(|k| k.1.value().as_ref().map(|_| k.0.to_vec()))
+				overlay_changes.find_map(|k| k.1.value_slice().map(|_| k.0.to_vec()))
 			},
 		}
 	}
@@ -582,7 +582,7 @@ where
 			root.encode()
 		} else {
 			let root = if let Some((changes, info)) = self.overlay.child_changes(storage_key) {
-				let delta = changes.map(|(k, v)| (k.as_ref(), v.value().map(AsRef::as_ref)));
+				let delta = changes.map(|(k, v)| (k.as_ref(), v.value_slice()));
 				Some(self.backend.child_storage_root(info, delta, state_version))
 			} else {
 				None
@@ -128,8 +128,8 @@ pub use crate::{
 	ext::Ext,
 	overlayed_changes::{
 		ChildStorageCollection, IndexOperation, OffchainChangesCollection,
-		OffchainOverlayedChanges, OverlayedChanges, StorageChanges, StorageCollection, StorageKey,
-		StorageTransactionCache, StorageValue,
+		OffchainOverlayedChanges, OverlayedChanges, OverlayedStorageValue, StorageChanges,
+		StorageCollection, StorageKey, StorageTransactionCache, StorageValue,
 	},
 	stats::{StateMachineStats, UsageInfo, UsageUnit},
 	trie_backend::{TrieBackend, TrieBackendBuilder},
@@ -142,13 +142,15 @@ mod std_reexport {
 		basic::BasicExternalities,
 		error::{Error, ExecutionError},
 		in_memory_backend::{new_in_mem, new_in_mem_hash_key},
+		overlayed_changes::SpilledValue,
 		read_only::{InspectState, ReadOnlyExternalities},
 		testing::TestExternalities,
 		trie_backend::create_proof_check_backend,
 	};
 	pub use sp_trie::{
 		trie_types::{TrieDBMutV0, TrieDBMutV1},
-		CompactProof, DBValue, LayoutV0, LayoutV1, MemoryDB, StorageProof, TrieMut,
+		CompactProof, DBValue, LayoutV0, LayoutV1, MemoryDB, MultiRootCompactProof, StorageProof,
+		TrieMut,
 	};
 }
 
@@ -951,6 +953,97 @@ mod execution {
 		Ok((proof, count))
 	}
 
+	/// Generate a storage read proof of at most `limit` keys of a range.
+	///
+	/// The range covers the keys starting with `prefix` that are strictly after `start_at` and
+	/// strictly before `end_at`. The key following the last one read is part of the proof as
+	/// well, so that the checker can tell whether the end of the range has been reached. This
+	/// notably allows proving that no key starts with `prefix`.
+	///
+	/// No more keys are read once the proof exceeds `size_limit` bytes, but the first key of the
+	/// range is always read so that the range can be resumed after it.
+	pub fn prove_range_read_with_limit<B, H>(
+		backend: B,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_at: Option<&[u8]>,
+		end_at: Option<&[u8]>,
+		limit: u32,
+		size_limit: usize,
+	) -> Result<StorageProof, Box<dyn Error>>
+	where
+		B: AsTrieBackend<H>,
+		H: Hasher,
+		H::Out: Ord + Codec,
+	{
+		let trie_backend = backend.as_trie_backend();
+		let recorder = sp_trie::recorder::Recorder::default();
+		let proving_backend =
+			TrieBackendBuilder::wrap(trie_backend).with_recorder(recorder.clone()).build();
+		read_range_with_limit(
+			&proving_backend,
+			child_info,
+			prefix,
+			start_at,
+			end_at,
+			limit,
+			|| recorder.estimate_encoded_size() > size_limit,
+			false,
+		)?;
+
+		Ok(proving_backend
+			.extract_proof()
+			.expect("A recorder was set and thus, a storage proof can be extracted; qed"))
+	}
+
+	/// Reads at most `limit` key-value pairs of a range, see [`prove_range_read_with_limit`].
+	///
+	/// Returns the pairs and whether the end of the range has been reached. Once at least one
+	/// pair was read, reading stops as well when `is_full` returns true. When
+	/// `stop_on_incomplete_database` is set, a missing trie node ends the read without reaching
+	/// the end of the range.
+	fn read_range_with_limit<B, H>(
+		backend: &B,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_at: Option<&[u8]>,
+		end_at: Option<&[u8]>,
+		limit: u32,
+		is_full: impl Fn() -> bool,
+		stop_on_incomplete_database: bool,
+	) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool), Box<dyn Error>>
+	where
+		B: Backend<H>,
+		H: Hasher,
+	{
+		let mut values = Vec::new();
+		// NOTE: The value of the key following the range is never returned, but it *must* be
+		//       fetched as well so that the proof is correct.
+		let mut iter = backend
+			.pairs(IterArgs {
+				child_info: child_info.cloned(),
+				prefix,
+				start_at,
+				start_at_exclusive: true,
+				stop_on_incomplete_database,
+			})
+			.map_err(|e| Box::new(e) as Box<dyn Error>)?;
+
+		while let Some(item) = iter.next() {
+			let (key, value) = item.map_err(|e| Box::new(e) as Box<dyn Error>)?;
+			if end_at.map_or(false, |end_at| key.as_slice() >= end_at) {
+				return Ok((values, true))
+			}
+			if values.len() as u32 >= limit || (!values.is_empty() && is_full()) {
+				return Ok((values, false))
+			}
+			values.push((key, value));
+		}
+
+		let completed = iter.was_complete();
+		Ok((values, completed))
+	}
+
 	/// Generate child storage read proof.
 	pub fn prove_child_read<B, H, I>(
 		backend: B,
@@ -1040,6 +1133,39 @@ mod execution {
 		Ok(result)
 	}
 
+	/// Check storage read proof of multiple state roots, generated by
+	/// [`StorageProof::into_multi_root_compact_proof`].
+	///
+	/// Returns the values of `keys` at every root, in the order of `roots`.
+	pub fn read_multi_root_proof_check<H, I>(
+		roots: &[H::Out],
+		proof: MultiRootCompactProof,
+		keys: I,
+	) -> Result<Vec<HashMap<Vec<u8>, Option<Vec<u8>>>>, Box<dyn Error>>
+	where
+		H: Hasher + 'static,
+		H::Out: Ord + Codec,
+		I: IntoIterator,
+		I::Item: AsRef<[u8]>,
+	{
+		let db = proof.to_memory_db::<H>(roots).map_err(|e| Box::new(e) as Box<dyn Error>)?;
+		let keys = keys.into_iter().collect::<Vec<_>>();
+		roots
+			.iter()
+			.map(|root| {
+				let proving_backend = TrieBackendBuilder::new(&db, *root).build();
+				let mut result = HashMap::new();
+				for key in keys.iter() {
+					let value = proving_backend
+						.storage(key.as_ref())
+						.map_err(|e| Box::new(e) as Box<dyn Error>)?;
+					result.insert(key.as_ref().to_vec(), value);
+				}
+				Ok(result)
+			})
+			.collect()
+	}
+
 	/// Check storage range proof with child trie included, generated by
 	/// `prove_range_read_with_child_with_size` call.
 	///
@@ -1081,6 +1207,37 @@ mod execution {
 		)
 	}
 
+	/// Check storage range proof, generated by `prove_range_read_with_limit` call.
+	///
+	/// Returns the key-value pairs of the range contained in the proof, and whether the end of
+	/// the range has been reached. A proof that stops before `limit` keys is valid: the read can
+	/// be resumed from the last returned key.
+	pub fn read_range_proof_check_with_limit<H>(
+		root: H::Out,
+		proof: StorageProof,
+		child_info: Option<&ChildInfo>,
+		prefix: Option<&[u8]>,
+		start_at: Option<&[u8]>,
+		end_at: Option<&[u8]>,
+		limit: u32,
+	) -> Result<(Vec<(Vec<u8>, Vec<u8>)>, bool), Box<dyn Error>>
+	where
+		H: Hasher + 'static,
+		H::Out: Ord + Codec,
+	{
+		let proving_backend = create_proof_check_backend::<H>(root, proof)?;
+		read_range_with_limit(
+			&proving_backend,
+			child_info,
+			prefix,
+			start_at,
+			end_at,
+			limit,
+			|| false,
+			true,
+		)
+	}
+
 	/// Check child storage read proof, generated by `prove_child_read` call.
 	pub fn read_child_proof_check<H, I>(
 		root: H::Out,
@@ -1961,6 +2118,119 @@ mod tests {
 		assert_eq!(completed, true);
 	}
 
+	#[test]
+	fn prove_range_read_with_limit_works() {
+		let state_version = StateVersion::V1;
+		let remote_backend = trie_backend::tests::test_trie(state_version, None, None);
+		let remote_root = remote_backend.storage_root(::std::iter::empty(), state_version).0;
+		let check = |proof, child_info, prefix, start_at, end_at, limit| {
+			let proof = test_compact(proof, &remote_root);
+			read_range_proof_check_with_limit::<BlakeTwo256>(
+				remote_root,
+				proof,
+				child_info,
+				prefix,
+				start_at,
+				end_at,
+				limit,
+			)
+			.unwrap()
+		};
+
+		// Limit reached before the end of the prefix.
+		let proof = prove_range_read_with_limit(
+			&remote_backend,
+			None,
+			Some(b"value"),
+			None,
+			None,
+			1,
+			usize::MAX,
+		)
+		.unwrap();
+		assert_eq!(
+			check(proof, None, Some(&b"value"[..]), None, None, 1),
+			(vec![(b"value1".to_vec(), vec![42])], false),
+		);
+
+		// Resuming after the last key reaches the end of the prefix.
+		let proof = prove_range_read_with_limit(
+			&remote_backend,
+			None,
+			Some(b"value"),
+			Some(b"value1"),
+			None,
+			10,
+			usize::MAX,
+		)
+		.unwrap();
+		assert_eq!(
+			check(proof, None, Some(&b"value"[..]), Some(&b"value1"[..]), None, 10),
+			(vec![(b"value2".to_vec(), vec![24])], true),
+		);
+
+		// The end of the range is exclusive.
+		let proof = prove_range_read_with_limit(
+			&remote_backend,
+			None,
+			None,
+			Some(b"key"),
+			Some(b"value2"),
+			10,
+			usize::MAX,
+		)
+		.unwrap();
+		assert_eq!(
+			check(proof, None, None, Some(&b"key"[..]), Some(&b"value2"[..]), 10),
+			(vec![(b"value1".to_vec(), vec![42])], true),
+		);
+
+		// Nonexistence of keys with a given prefix.
+		let proof = prove_range_read_with_limit(
+			&remote_backend,
+			None,
+			Some(b"nope"),
+			None,
+			None,
+			10,
+			usize::MAX,
+		)
+		.unwrap();
+		assert_eq!(check(proof, None, Some(&b"nope"[..]), None, None, 10), (vec![], true));
+
+		// Child tries.
+		let child_info = ChildInfo::new_default(b"sub1");
+		let proof = prove_range_read_with_limit(
+			&remote_backend,
+			Some(&child_info),
+			None,
+			None,
+			None,
+			10,
+			usize::MAX,
+		)
+		.unwrap();
+		assert_eq!(
+			check(proof, Some(&child_info), None, None, None, 10),
+			(vec![(b"value3".to_vec(), vec![142; 33]), (b"value4".to_vec(), vec![124; 33])], true),
+		);
+
+		// A proof of fewer keys than asked for is valid, but doesn't reach the end of the range.
+		let proof =
+			prove_range_read_with_limit(&remote_backend, None, None, None, None, 1, usize::MAX)
+				.unwrap();
+		let (values, completed) = check(proof, None, None, None, None, 1000);
+		assert!(!values.is_empty());
+		assert!(!completed);
+
+		// Reading stops once the proof exceeds its size limit, but the first key is always read.
+		let proof =
+			prove_range_read_with_limit(&remote_backend, None, None, None, None, 1000, 0).unwrap();
+		let (values, completed) = check(proof, None, None, None, None, 1000);
+		assert!(!values.is_empty() && values.len() < 10, "{}", values.len());
+		assert!(!completed);
+	}
+
 	#[test]
 	fn prove_read_with_size_limit_proof_size() {
 		let mut root = H256::default();
@@ -2145,6 +2415,54 @@ mod tests {
 		size
 	}
 
+	#[test]
+	fn prove_read_and_multi_root_proof_check_works() {
+		let state_version = StateVersion::V1;
+		let entries: Vec<(Vec<u8>, Vec<u8>)> =
+			(0u32..256).map(|i| (i.encode(), vec![i as u8; 64])).collect();
+		let remote_backend = trie_backend::tests::test_trie(state_version, None, None);
+		let (root1, transaction) = remote_backend
+			.storage_root(entries.iter().map(|(k, v)| (&k[..], Some(&v[..]))), state_version);
+		let mut storage1 = remote_backend.backend_storage().clone();
+		storage1.consolidate(transaction);
+		let remote_backend = TrieBackendBuilder::new(storage1.clone(), root1).build();
+		let (root2, transaction) = remote_backend
+			.storage_root(vec![(&b"key"[..], Some(&b"new_value"[..]))].into_iter(), state_version);
+		let mut storage2 = storage1.clone();
+		storage2.consolidate(transaction);
+
+		let keys = [&b"key"[..], &entries[0].0[..], &entries[100].0[..]];
+		let proof1 = prove_read(TrieBackendBuilder::new(storage1, root1).build(), &keys).unwrap();
+		let proof2 = prove_read(TrieBackendBuilder::new(storage2, root2).build(), &keys).unwrap();
+		let separate_size = proof1.clone().encoded_compact_size::<BlakeTwo256>(root1).unwrap() +
+			proof2.clone().encoded_compact_size::<BlakeTwo256>(root2).unwrap();
+
+		let roots = [root1, root2, root1];
+		let proof = StorageProof::merge([proof1, proof2])
+			.into_multi_root_compact_proof::<BlakeTwo256>(&roots)
+			.unwrap();
+		// the nodes shared between the roots are only included once
+		assert!(proof.proofs[2].encoded_nodes.is_empty());
+		assert!(proof.encoded_size() < separate_size);
+
+		let results =
+			read_multi_root_proof_check::<BlakeTwo256, _>(&roots, proof.clone(), &keys).unwrap();
+		assert_eq!(results.len(), 3);
+		assert_eq!(results[0].get(&b"key"[..]), Some(&Some(b"value".to_vec())));
+		assert_eq!(results[1].get(&b"key"[..]), Some(&Some(b"new_value".to_vec())));
+		assert_eq!(results[0].get(&entries[100].0), Some(&Some(entries[100].1.clone())));
+		assert_eq!(results[1].get(&entries[100].0), Some(&Some(entries[100].1.clone())));
+		assert_eq!(results[2], results[0]);
+
+		assert!(read_multi_root_proof_check::<BlakeTwo256, _>(
+			&[root2, root1, root1],
+			proof.clone(),
+			&keys
+		)
+		.is_err());
+		assert!(read_multi_root_proof_check::<BlakeTwo256, _>(&roots[..2], proof, &keys).is_err());
+	}
+
 	#[test]
 	fn child_storage_uuid() {
 		let state_version = StateVersion::V0;
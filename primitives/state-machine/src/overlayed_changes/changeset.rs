@@ -17,6 +17,8 @@
 
 //! Houses the code that implements the transactional overlay storage.
 
+#[cfg(feature = "std")]
+use super::spill::{SpillStore, SpilledValue};
 use super::{Extrinsics, StorageKey, StorageValue};
 
 #[cfg(not(feature = "std"))]
@@ -87,11 +89,108 @@ impl<V> Default for OverlayedEntry<V> {
 	}
 }
 
+/// A storage value held by the overlay.
+///
+/// Committed values might have been moved out of memory when spilling is enabled.
+#[derive(Debug, Clone)]
+pub enum OverlayedStorageValue {
+	/// The value is held in memory. `None` if the value has been deleted.
+	InMemory(Option<StorageValue>),
+	/// The value was spilled to disk.
+	#[cfg(feature = "std")]
+	Spilled(SpilledValue),
+}
+
+impl OverlayedStorageValue {
+	/// Borrow the value. `None` if the value has been deleted.
+	pub fn as_deref(&self) -> Option<&[u8]> {
+		match self {
+			Self::InMemory(value) => value.as_deref(),
+			#[cfg(feature = "std")]
+			Self::Spilled(value) => Some(value.as_slice()),
+		}
+	}
+
+	/// Borrow the value as a `Vec`, copying it back into memory if it was spilled.
+	pub fn as_value(&self) -> Option<&StorageValue> {
+		match self {
+			Self::InMemory(value) => value.as_ref(),
+			#[cfg(feature = "std")]
+			Self::Spilled(value) => Some(value.as_vec()),
+		}
+	}
+
+	/// Returns true if the value was not deleted.
+	pub fn is_some(&self) -> bool {
+		match self {
+			Self::InMemory(value) => value.is_some(),
+			#[cfg(feature = "std")]
+			Self::Spilled(_) => true,
+		}
+	}
+
+	/// Take the value, reading it back into memory if it was spilled.
+	pub fn into_value(self) -> Option<StorageValue> {
+		match self {
+			Self::InMemory(value) => value,
+			#[cfg(feature = "std")]
+			Self::Spilled(value) => Some(value.as_slice().to_vec()),
+		}
+	}
+
+	/// Mutable access to the value, reading it back into memory if it was spilled.
+	fn in_memory_mut(&mut self) -> &mut Option<StorageValue> {
+		#[cfg(feature = "std")]
+		if let Self::Spilled(value) = self {
+			let value = value.as_slice().to_vec();
+			*self = Self::InMemory(Some(value));
+		}
+		match self {
+			Self::InMemory(value) => value,
+			#[cfg(feature = "std")]
+			Self::Spilled(_) => unreachable!("Spilled values were read back into memory above; qed"),
+		}
+	}
+
+	/// Returns true if the value is in memory and worth moving to disk.
+	#[cfg(feature = "std")]
+	fn can_spill(&self) -> bool {
+		matches!(self, Self::InMemory(Some(value)) if !value.is_empty())
+	}
+
+	/// Number of bytes of the value held in memory.
+	#[cfg(feature = "std")]
+	fn resident_size(&self) -> usize {
+		match self {
+			Self::InMemory(value) => value.as_ref().map_or(0, Vec::len),
+			Self::Spilled(value) => value.loaded_len(),
+		}
+	}
+}
+
+impl Default for OverlayedStorageValue {
+	fn default() -> Self {
+		Self::InMemory(None)
+	}
+}
+
+impl From<Option<StorageValue>> for OverlayedStorageValue {
+	fn from(value: Option<StorageValue>) -> Self {
+		Self::InMemory(value)
+	}
+}
+
+impl PartialEq for OverlayedStorageValue {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_deref() == other.as_deref()
+	}
+}
+
 /// History of value, with removal support.
-pub type OverlayedValue = OverlayedEntry<Option<StorageValue>>;
+pub type OverlayedValue = OverlayedEntry<OverlayedStorageValue>;
 
 /// Change set for basic key value with extrinsics index recording and removal support.
-pub type OverlayedChangeSet = OverlayedMap<StorageKey, Option<StorageValue>>;
+pub type OverlayedChangeSet = OverlayedMap<StorageKey, OverlayedStorageValue>;
 
 /// Holds a set of changes with the ability modify them using nested transactions.
 #[derive(Debug, Clone)]
@@ -122,7 +221,7 @@ impl<K, V> Default for OverlayedMap<K, V> {
 }
 
 #[cfg(feature = "std")]
-impl From<sp_core::storage::StorageMap> for OverlayedMap<StorageKey, Option<StorageValue>> {
+impl From<sp_core::storage::StorageMap> for OverlayedChangeSet {
 	fn from(storage: sp_core::storage::StorageMap) -> Self {
 		Self {
 			changes: storage
@@ -132,7 +231,7 @@ impl From<sp_core::storage::StorageMap> for OverlayedMap<StorageKey, Option<Stor
 						k,
 						OverlayedEntry {
 							transactions: SmallVec::from_iter([InnerValue {
-								value: Some(v),
+								value: Some(v).into(),
 								extrinsics: Default::default(),
 							}]),
 						},
@@ -204,10 +303,18 @@ impl<V> OverlayedEntry<V> {
 	}
 }
 
-impl OverlayedEntry<Option<StorageValue>> {
+impl OverlayedValue {
 	/// The value as seen by the current transaction.
+	///
+	/// A spilled value is copied back into memory, where it stays until the overlay spills
+	/// again. Use [`Self::value_slice`] to borrow it without copying.
 	pub fn value(&self) -> Option<&StorageValue> {
-		self.value_ref().as_ref()
+		self.value_ref().as_value()
+	}
+
+	/// The value as seen by the current transaction, borrowed from wherever it is held.
+	pub fn value_slice(&self) -> Option<&[u8]> {
+		self.value_ref().as_deref()
 	}
 }
 
@@ -251,9 +358,9 @@ impl<K: Ord + Hash + Clone, V> OverlayedMap<K, V> {
 	/// Set a new value for the specified key.
 	///
 	/// Can be rolled back or committed when called inside a transaction.
-	pub fn set(&mut self, key: K, value: V, at_extrinsic: Option<u32>) {
+	pub fn set(&mut self, key: K, value: impl Into<V>, at_extrinsic: Option<u32>) {
 		let overlayed = self.changes.entry(key.clone()).or_default();
-		overlayed.set(value, insert_dirty(&mut self.dirty_keys, key), at_extrinsic);
+		overlayed.set(value.into(), insert_dirty(&mut self.dirty_keys, key), at_extrinsic);
 	}
 
 	/// Get a list of all changes as seen by current transaction.
@@ -419,13 +526,13 @@ impl OverlayedChangeSet {
 				None
 			}
 		} else {
-			Some(Some(init()))
+			Some(Some(init()).into())
 		};
 
 		if let Some(cloned) = clone_into_new_tx {
 			overlayed.set(cloned, first_write_in_tx, at_extrinsic);
 		}
-		overlayed.value_mut()
+		overlayed.value_mut().in_memory_mut()
 	}
 
 	/// Set all values to deleted which are matched by the predicate.
@@ -441,7 +548,7 @@ impl OverlayedChangeSet {
 			if val.value_ref().is_some() {
 				count += 1;
 			}
-			val.set(None, insert_dirty(&mut self.dirty_keys, key.clone()), at_extrinsic);
+			val.set(None.into(), insert_dirty(&mut self.dirty_keys, key.clone()), at_extrinsic);
 		}
 		count
 	}
@@ -452,6 +559,52 @@ impl OverlayedChangeSet {
 		let range = (Bound::Excluded(key), Bound::Unbounded);
 		self.changes.range::<[u8], _>(range).map(|(k, v)| (k.as_slice(), v))
 	}
+
+	/// Move all committed values into `store`.
+	///
+	/// Values written by any of the open transactions stay in memory, so that they can be
+	/// rolled back or modified cheaply. Keys are always kept in memory.
+	#[cfg(feature = "std")]
+	pub fn spill(&mut self, store: &mut SpillStore) -> std::io::Result<()> {
+		let Self { changes, dirty_keys, .. } = self;
+		let mut values = Vec::new();
+		for (key, overlayed) in changes.iter_mut() {
+			// Every open transaction that wrote to the key added exactly one version. If there
+			// is an additional version, the first one is committed.
+			let open_versions = dirty_keys.iter().filter(|keys| keys.contains(key)).count();
+			if overlayed.transactions.len() > open_versions {
+				let committed = &mut overlayed.transactions[0].value;
+				if let OverlayedStorageValue::Spilled(spilled) = committed {
+					spilled.unload();
+				} else if committed.can_spill() {
+					values.push(committed);
+				}
+			}
+		}
+
+		let spilled =
+			store.append(values.iter().map(|value| value.as_deref().unwrap_or_default()))?;
+		for (value, spilled) in values.into_iter().zip(spilled) {
+			*value = OverlayedStorageValue::Spilled(spilled);
+		}
+		Ok(())
+	}
+
+	/// Number of bytes held in memory by the keys and the values of all the versions.
+	#[cfg(feature = "std")]
+	pub fn resident_size(&self) -> usize {
+		self.changes
+			.iter()
+			.map(|(key, overlayed)| {
+				key.len() +
+					overlayed
+						.transactions
+						.iter()
+						.map(|version| version.value.resident_size())
+						.sum::<usize>()
+			})
+			.sum()
+	}
 }
 
 #[cfg(test)]
@@ -473,7 +626,7 @@ mod test {
 	}
 
 	fn assert_drained_changes(is: OverlayedChangeSet, expected: Changes) {
-		let is = is.drain_commited().collect::<Vec<_>>();
+		let is = is.drain_commited().map(|(k, v)| (k, v.into_value())).collect::<Vec<_>>();
 		let expected = expected
 			.iter()
 			.map(|(k, v)| (k.to_vec(), v.0.map(From::from)))
@@ -482,7 +635,7 @@ mod test {
 	}
 
 	fn assert_drained(is: OverlayedChangeSet, expected: Drained) {
-		let is = is.drain_commited().collect::<Vec<_>>();
+		let is = is.drain_commited().map(|(k, v)| (k, v.into_value())).collect::<Vec<_>>();
 		let expected = expected
 			.iter()
 			.map(|(k, v)| (k.to_vec(), v.map(From::from)))
@@ -804,6 +957,45 @@ mod test {
 		assert_eq!(changeset.commit_transaction(), Err(NoOpenTransaction));
 	}
 
+	#[test]
+	fn spill_only_moves_committed_values() {
+		let mut store = SpillStore::new().unwrap();
+		let mut changeset = OverlayedChangeSet::default();
+		changeset.set(b"key0".to_vec(), Some(b"val0".to_vec()), Some(1));
+		changeset.set(b"key1".to_vec(), Some(b"val1".to_vec()), Some(2));
+		changeset.set(b"empty".to_vec(), Some(vec![]), None);
+		changeset.set(b"deleted".to_vec(), None, None);
+
+		changeset.start_transaction();
+		changeset.set(b"key1".to_vec(), Some(b"val1-rolled".to_vec()), Some(3));
+		changeset.set(b"key2".to_vec(), Some(b"val2".to_vec()), Some(4));
+		changeset.spill(&mut store).unwrap();
+
+		let is_spilled = |key: &[u8], version: usize| {
+			matches!(
+				changeset.get(key).unwrap().transactions[version].value,
+				OverlayedStorageValue::Spilled(_)
+			)
+		};
+		assert!(is_spilled(b"key0", 0));
+		assert!(is_spilled(b"key1", 0));
+		assert!(!is_spilled(b"key1", 1));
+		assert!(!is_spilled(b"key2", 0));
+		assert!(!is_spilled(b"empty", 0));
+		assert!(!is_spilled(b"deleted", 0));
+
+		changeset.rollback_transaction().unwrap();
+		assert_drained(
+			changeset,
+			vec![
+				(b"deleted", None),
+				(b"empty", Some(b"")),
+				(b"key0", Some(b"val0")),
+				(b"key1", Some(b"val1")),
+			],
+		);
+	}
+
 	#[test]
 	#[should_panic]
 	fn drain_with_open_transaction_panics() {
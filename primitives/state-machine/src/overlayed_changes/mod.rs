@@ -19,8 +19,12 @@
 
 mod changeset;
 mod offchain;
+#[cfg(feature = "std")]
+mod spill;
 
 use self::changeset::OverlayedChangeSet;
+#[cfg(feature = "std")]
+use crate::warn;
 use crate::{backend::Backend, stats::StateMachineStats, DefaultError};
 use codec::{Decode, Encode};
 use hash_db::Hasher;
@@ -42,7 +46,11 @@ use std::{
 	boxed::Box,
 };
 
-pub use self::changeset::{AlreadyInRuntime, NoOpenTransaction, NotInRuntime, OverlayedValue};
+pub use self::changeset::{
+	AlreadyInRuntime, NoOpenTransaction, NotInRuntime, OverlayedStorageValue, OverlayedValue,
+};
+#[cfg(feature = "std")]
+pub use self::spill::SpilledValue;
 
 /// Changes that are made outside of extrinsics are marked with this index;
 pub const NO_EXTRINSIC_INDEX: u32 = 0xffffffff;
@@ -102,6 +110,26 @@ pub struct OverlayedChanges {
 	collect_extrinsics: bool,
 	/// Collect statistic on this execution.
 	stats: StateMachineStats,
+	/// Moves committed values to disk once enabled by [`OverlayedChanges::set_memory_limit`].
+	#[cfg(feature = "std")]
+	spill: Option<OverlaySpill>,
+}
+
+/// Book keeping for spilling committed values to disk.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+struct OverlaySpill {
+	/// The store is shared with all clones of the overlay, which only ever append to it.
+	store: std::sync::Arc<parking_lot::Mutex<spill::SpillStore>>,
+	/// Number of bytes the overlay should at most keep in memory.
+	memory_limit: usize,
+	/// Estimate of the number of bytes held in memory by the keys and values of the overlay.
+	resident: usize,
+	/// Resident size above which committed values are spilled.
+	///
+	/// Raised above `memory_limit` when values of open transactions alone exceed it, so that
+	/// not every write triggers a spill.
+	threshold: usize,
 }
 
 /// Transaction index operation.
@@ -222,15 +250,87 @@ impl OverlayedChanges {
 		self.collect_extrinsics = collect_extrinsics;
 	}
 
+	/// Limit the memory held by the overlay to roughly `memory_limit` bytes.
+	///
+	/// Whenever the keys and values held in memory grow above `memory_limit` bytes, all
+	/// committed values are moved to a temporary file and mapped back into memory on access.
+	/// Keys and values written by open transactions always stay in memory. This is transparent
+	/// to any reader of the overlay.
+	#[cfg(feature = "std")]
+	pub fn set_memory_limit(&mut self, memory_limit: usize) -> std::io::Result<()> {
+		let store = match self.spill.take() {
+			Some(spill) => spill.store,
+			None => std::sync::Arc::new(parking_lot::Mutex::new(spill::SpillStore::new()?)),
+		};
+		let resident = self.resident_size();
+		self.spill = Some(OverlaySpill { store, memory_limit, resident, threshold: memory_limit });
+		self.note_written(0);
+		Ok(())
+	}
+
+	/// The limit set by [`Self::set_memory_limit`], if spilling is enabled.
+	#[cfg(feature = "std")]
+	pub fn memory_limit(&self) -> Option<usize> {
+		self.spill.as_ref().map(|spill| spill.memory_limit)
+	}
+
+	/// Number of bytes held in memory by the keys and values of the top and child changesets.
+	#[cfg(feature = "std")]
+	fn resident_size(&self) -> usize {
+		self.top.resident_size() +
+			self.children
+				.iter()
+				.map(|(key, (changeset, _))| key.len() + changeset.resident_size())
+				.sum::<usize>()
+	}
+
+	/// Account for `written` bytes and spill all committed values if the memory limit is hit.
+	fn note_written(&mut self, written: usize) {
+		#[cfg(feature = "std")]
+		if let Some(spill) = &mut self.spill {
+			spill.resident = spill.resident.saturating_add(written);
+			if spill.resident > spill.threshold {
+				self.spill_committed();
+			}
+		}
+		#[cfg(not(feature = "std"))]
+		let _ = written;
+	}
+
+	/// Move all committed values of the top and the child changesets to disk.
+	#[cfg(feature = "std")]
+	fn spill_committed(&mut self) {
+		let Some(spill) = &mut self.spill else { return };
+		let mut store = spill.store.lock();
+		let result = self.top.spill(&mut store).and_then(|()| {
+			self.children
+				.values_mut()
+				.try_for_each(|(changeset, _)| changeset.spill(&mut store))
+		});
+		drop(store);
+
+		if let Err(e) = result {
+			warn!("Failed to spill storage changes to disk, keeping them in memory: {}", e);
+			self.spill = None;
+			return
+		}
+
+		let resident = self.resident_size();
+		if let Some(spill) = &mut self.spill {
+			spill.resident = resident;
+			spill.threshold = spill.memory_limit.max(resident.saturating_mul(2));
+		}
+	}
+
 	/// Returns a double-Option: None if the key is unknown (i.e. and the query should be referred
 	/// to the backend); Some(None) if the key has been deleted. Some(Some(...)) for a key whose
 	/// value has been set.
 	pub fn storage(&self, key: &[u8]) -> Option<Option<&[u8]>> {
 		self.top.get(key).map(|x| {
-			let value = x.value();
+			let value = x.value_slice();
 			let size_read = value.map(|x| x.len() as u64).unwrap_or(0);
 			self.stats.tally_read_modified(size_read);
-			value.map(AsRef::as_ref)
+			value
 		})
 	}
 
@@ -245,6 +345,9 @@ impl OverlayedChanges {
 		key: &[u8],
 		init: impl Fn() -> StorageValue,
 	) -> &mut StorageValue {
+		// Spill before handing out the reference, as the value will grow afterwards.
+		let size = self.top.get(key).and_then(|v| v.value_slice()).map_or(0, |v| v.len());
+		self.note_written(key.len() + size);
 		let value = self.top.modify(key.to_vec(), init, self.extrinsic_index());
 
 		// if the value was deleted initialise it back with an empty vec
@@ -256,10 +359,10 @@ impl OverlayedChanges {
 	/// value has been set.
 	pub fn child_storage(&self, child_info: &ChildInfo, key: &[u8]) -> Option<Option<&[u8]>> {
 		let map = self.children.get(child_info.storage_key())?;
-		let value = map.0.get(key)?.value();
+		let value = map.0.get(key)?.value_slice();
 		let size_read = value.map(|x| x.len() as u64).unwrap_or(0);
 		self.stats.tally_read_modified(size_read);
-		Some(value.map(AsRef::as_ref))
+		Some(value)
 	}
 
 	/// Set a new value for the specified key.
@@ -268,7 +371,9 @@ impl OverlayedChanges {
 	pub fn set_storage(&mut self, key: StorageKey, val: Option<StorageValue>) {
 		let size_write = val.as_ref().map(|x| x.len() as u64).unwrap_or(0);
 		self.stats.tally_write_overlay(size_write);
+		let key_len = key.len();
 		self.top.set(key, val, self.extrinsic_index());
+		self.note_written(key_len + size_write as usize);
 	}
 
 	/// Set a new value for the specified key and child.
@@ -293,7 +398,9 @@ impl OverlayedChanges {
 			.or_insert_with(|| (top.spawn_child(), child_info.clone()));
 		let updatable = info.try_update(child_info);
 		debug_assert!(updatable);
+		let key_len = key.len();
 		changeset.set(key, val, extrinsic_index);
+		self.note_written(key_len + size_write as usize);
 	}
 
 	/// Clear child storage of given storage key.
@@ -393,6 +500,8 @@ impl OverlayedChanges {
 			.overlay_mut()
 			.commit_transaction()
 			.expect("Top and offchain changesets are started in lockstep; qed");
+		// Committing might have made values eligible for spilling.
+		self.note_written(0);
 		Ok(())
 	}
 
@@ -451,10 +560,10 @@ impl OverlayedChanges {
 	) {
 		use sp_std::mem::take;
 		(
-			take(&mut self.top).drain_commited(),
-			take(&mut self.children)
-				.into_iter()
-				.map(|(key, (val, info))| (key, (val.drain_commited(), info))),
+			take(&mut self.top).drain_commited().map(|(k, v)| (k, v.into_value())),
+			take(&mut self.children).into_iter().map(|(key, (val, info))| {
+				(key, (val.drain_commited().map(|(k, v)| (k, v.into_value())), info))
+			}),
 		)
 	}
 
@@ -585,10 +694,10 @@ impl OverlayedChanges {
 	where
 		H::Out: Ord + Encode,
 	{
-		let delta = self.changes().map(|(k, v)| (&k[..], v.value().map(|v| &v[..])));
-		let child_delta = self.children().map(|(changes, info)| {
-			(info, changes.map(|(k, v)| (&k[..], v.value().map(|v| &v[..]))))
-		});
+		let delta = self.changes().map(|(k, v)| (&k[..], v.value_slice()));
+		let child_delta = self
+			.children()
+			.map(|(changes, info)| (info, changes.map(|(k, v)| (&k[..], v.value_slice()))));
 
 		let (root, transaction) = backend.full_storage_root(delta, child_delta, state_version);
 
@@ -876,6 +985,84 @@ mod tests {
 		assert_eq!(&ext.storage_root(state_version)[..], &root);
 	}
 
+	#[test]
+	fn spilled_changes_are_transparent() {
+		let state_version = StateVersion::default();
+		let child_info = ChildInfo::new_default(b"child");
+		let backend = InMemoryBackend::<Blake2Hasher>::from((
+			vec![(b"doe".to_vec(), b"reindeer".to_vec())]
+				.into_iter()
+				.collect::<BTreeMap<_, _>>(),
+			state_version,
+		));
+
+		let apply = |overlay: &mut OverlayedChanges| {
+			overlay.set_storage(b"dog".to_vec(), Some(b"puppy".to_vec()));
+			overlay.set_storage(b"dogglesworth".to_vec(), Some(b"catYYY".to_vec()));
+			overlay.set_child_storage(&child_info, b"doug".to_vec(), Some(b"notadog".to_vec()));
+
+			overlay.start_transaction();
+			overlay.set_storage(b"dogglesworth".to_vec(), Some(b"cat".to_vec()));
+			overlay.set_storage(b"doe".to_vec(), Some(b"deer".to_vec()));
+			overlay.commit_transaction().unwrap();
+
+			overlay.start_transaction();
+			overlay.set_storage(b"dog".to_vec(), None);
+			overlay.value_mut_or_insert_with(b"doe", Default::default).push(1);
+			overlay.set_child_storage(&child_info, b"doug".to_vec(), None);
+			overlay.rollback_transaction().unwrap();
+
+			overlay.start_transaction();
+			overlay.set_storage(b"pending".to_vec(), Some(b"in memory".to_vec()));
+		};
+
+		let mut overlay = OverlayedChanges::default();
+		apply(&mut overlay);
+		let mut spilling = OverlayedChanges::default();
+		spilling.set_memory_limit(8).unwrap();
+		apply(&mut spilling);
+
+		let is_spilled =
+			|value: &OverlayedValue| matches!(value.value_ref(), OverlayedStorageValue::Spilled(_));
+		assert!(is_spilled(spilling.top.get(&b"dog"[..]).unwrap()));
+		assert!(is_spilled(spilling.top.get(&b"doe"[..]).unwrap()));
+		assert!(is_spilled(
+			spilling.children.get(&b"child"[..]).unwrap().0.get(&b"doug"[..]).unwrap()
+		));
+		assert!(!is_spilled(spilling.top.get(&b"pending"[..]).unwrap()));
+
+		for key in [&b"dog"[..], b"dogglesworth", b"doe", b"pending"] {
+			assert_eq!(spilling.storage(key), overlay.storage(key));
+		}
+		assert_eq!(spilling.child_storage(&child_info, b"doug"), Some(Some(&b"notadog"[..])));
+
+		let root = overlay.storage_root(&backend, &mut Default::default(), state_version);
+		assert_eq!(spilling.storage_root(&backend, &mut Default::default(), state_version), root);
+
+		overlay.commit_transaction().unwrap();
+		spilling.commit_transaction().unwrap();
+		let changes =
+			overlay.drain_storage_changes(&backend, &mut Default::default(), state_version);
+		let spilled_changes =
+			spilling.drain_storage_changes(&backend, &mut Default::default(), state_version);
+		let (changes, spilled_changes) = (changes.unwrap(), spilled_changes.unwrap());
+		assert_eq!(spilled_changes.main_storage_changes, changes.main_storage_changes);
+		assert_eq!(spilled_changes.child_storage_changes, changes.child_storage_changes);
+	}
+
+	#[test]
+	fn resident_memory_stays_below_limit() {
+		const LIMIT: usize = 64 * 1024;
+		let mut overlay = OverlayedChanges::default();
+		overlay.set_memory_limit(LIMIT).unwrap();
+
+		for i in 0u32..1000 {
+			overlay.set_storage(i.to_le_bytes().to_vec(), Some(vec![i as u8; 1000]));
+			assert!(overlay.resident_size() <= LIMIT + 1004, "{}", overlay.resident_size());
+		}
+		assert_eq!(overlay.storage(&999u32.to_le_bytes()), Some(Some(&[231u8; 1000][..])));
+	}
+
 	#[test]
 	fn extrinsic_changes_are_collected() {
 		let mut overlay = OverlayedChanges::default();
@@ -0,0 +1,133 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Temporary on-disk storage for committed overlay values.
+
+use memmap2::{Mmap, MmapOptions};
+use once_cell::sync::OnceCell;
+use std::{
+	fmt,
+	fs::File,
+	io::{self, BufWriter, Write},
+	sync::Arc,
+};
+
+/// A value that was moved out of memory into a [`SpillStore`].
+#[derive(Clone)]
+pub struct SpilledValue {
+	/// The mapped part of the spill file this value was written to.
+	segment: Arc<Mmap>,
+	/// Offset of the value inside `segment`.
+	offset: usize,
+	/// Length of the value in bytes.
+	len: usize,
+	/// Copy of the value, made when it has been requested as an owned `Vec`.
+	loaded: OnceCell<Vec<u8>>,
+}
+
+impl SpilledValue {
+	/// The spilled bytes.
+	///
+	/// The operating system pages them back in from disk on access.
+	pub fn as_slice(&self) -> &[u8] {
+		&self.segment[self.offset..self.offset + self.len]
+	}
+
+	/// The spilled bytes, copied into memory on first access.
+	pub fn as_vec(&self) -> &Vec<u8> {
+		self.loaded.get_or_init(|| self.as_slice().to_vec())
+	}
+
+	/// Number of bytes of the value copied into memory by [`Self::as_vec`].
+	pub(crate) fn loaded_len(&self) -> usize {
+		self.loaded.get().map_or(0, Vec::len)
+	}
+
+	/// Drop the in-memory copy of the value, if any.
+	pub(crate) fn unload(&mut self) {
+		self.loaded.take();
+	}
+}
+
+impl fmt::Debug for SpilledValue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("SpilledValue").field("len", &self.len).finish()
+	}
+}
+
+/// An append only temporary file holding the values spilled out of an overlay.
+///
+/// Every call to [`Self::append`] maps the newly written part of the file into memory. The
+/// returned values can therefore be borrowed just like in-memory ones, while the operating
+/// system is free to evict them. The file is removed as soon as the store and all values
+/// spilled into it are dropped.
+#[derive(Debug)]
+pub struct SpillStore {
+	file: File,
+	/// Number of bytes written to `file` so far.
+	len: u64,
+}
+
+impl SpillStore {
+	/// Create a new store backed by an anonymous temporary file.
+	pub fn new() -> io::Result<Self> {
+		Ok(Self { file: tempfile::tempfile()?, len: 0 })
+	}
+
+	/// Write `values` to the end of the spill file.
+	///
+	/// Returns one [`SpilledValue`] per input, in the same order. Empty values can't be mapped
+	/// and must therefore be kept in memory by the caller.
+	pub fn append<'a>(
+		&mut self,
+		values: impl Iterator<Item = &'a [u8]>,
+	) -> io::Result<Vec<SpilledValue>> {
+		let start = self.len;
+		let mut ranges = Vec::new();
+		let mut written = 0;
+
+		let mut writer = BufWriter::new(&self.file);
+		for value in values {
+			writer.write_all(value)?;
+			ranges.push((written, value.len()));
+			written += value.len();
+		}
+		writer.flush()?;
+		drop(writer);
+
+		if ranges.is_empty() {
+			return Ok(Vec::new())
+		}
+		self.len += written as u64;
+
+		// SAFETY: The file is an unnamed temporary file that is only accessible through this
+		// store. It is only ever appended to, so the mapped range is never modified or
+		// truncated while mapped.
+		let segment =
+			Arc::new(unsafe { MmapOptions::new().offset(start).len(written).map(&self.file)? });
+
+		Ok(ranges
+			.into_iter()
+			.map(|(offset, len)| SpilledValue {
+				segment: segment.clone(),
+				offset,
+				len,
+				loaded: OnceCell::new(),
+			})
+			.collect())
+	}
+}
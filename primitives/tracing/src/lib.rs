@@ -50,6 +50,11 @@ pub use crate::types::{
 };
 #[cfg(feature = "std")]
 pub use crate::types::{WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
+#[cfg(feature = "std")]
+pub use runtime_log::{
+	emit_runtime_log, runtime_log_enabled, RUNTIME_LOG_FIELDS_KEY, RUNTIME_LOG_IDENTIFIER,
+	RUNTIME_LOG_MESSAGE_KEY,
+};
 
 /// Tracing facilities and helpers.
 ///
@@ -107,6 +112,9 @@ pub use crate::types::{WASM_NAME_KEY, WASM_TARGET_KEY, WASM_TRACE_IDENTIFIER};
 /// will not be recorded!
 mod types;
 
+#[cfg(feature = "std")]
+mod runtime_log;
+
 /// Try to init a simple tracing subscriber with log compatibility layer.
 ///
 /// Ignores any error. Useful for testing.
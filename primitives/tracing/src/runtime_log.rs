@@ -0,0 +1,152 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Native side of runtime log records that carry structured key/value fields.
+//!
+//! Like for the wasm tracing events, the target of a runtime log record is only known at runtime,
+//! while `tracing` requires static metadata. Runtime log records are therefore emitted with one of
+//! the static metadata entries below, carrying the actual target in the [`WASM_TARGET_KEY`] field
+//! and the structured fields as JSON object in the [`RUNTIME_LOG_FIELDS_KEY`] field.
+
+use crate::types::WASM_TARGET_KEY;
+use std::fmt::{self, Write};
+use tracing_core::{callsite, field::FieldSet, metadata::Kind, Level, Metadata};
+
+/// Static entry used for runtime log records.
+struct RuntimeLogCallsite;
+impl callsite::Callsite for RuntimeLogCallsite {
+	fn set_interest(&self, _: tracing_core::Interest) {
+		unimplemented!()
+	}
+	fn metadata(&self) -> &Metadata<'_> {
+		unimplemented!()
+	}
+}
+static CALLSITE: RuntimeLogCallsite = RuntimeLogCallsite;
+
+/// The name of the events runtime log records are emitted as.
+pub static RUNTIME_LOG_IDENTIFIER: &str = "runtime_log";
+/// The fieldname for the message of a runtime log record.
+pub static RUNTIME_LOG_MESSAGE_KEY: &str = "message";
+/// The fieldname for the structured fields of a runtime log record, encoded as JSON object.
+pub static RUNTIME_LOG_FIELDS_KEY: &str = "fields";
+/// The list of all field names of a runtime log record.
+static RUNTIME_LOG_FIELDS: &[&str] =
+	&[RUNTIME_LOG_MESSAGE_KEY, WASM_TARGET_KEY, RUNTIME_LOG_FIELDS_KEY];
+
+macro_rules! runtime_log_metadata {
+	($name:ident, $level:expr) => {
+		static $name: Metadata<'static> = Metadata::new(
+			RUNTIME_LOG_IDENTIFIER,
+			RUNTIME_LOG_IDENTIFIER,
+			$level,
+			None,
+			None,
+			None,
+			FieldSet::new(RUNTIME_LOG_FIELDS, tracing_core::identify_callsite!(&CALLSITE)),
+			Kind::EVENT,
+		);
+	};
+}
+
+runtime_log_metadata!(ERROR_METADATA, Level::ERROR);
+runtime_log_metadata!(WARN_METADATA, Level::WARN);
+runtime_log_metadata!(INFO_METADATA, Level::INFO);
+runtime_log_metadata!(DEBUG_METADATA, Level::DEBUG);
+runtime_log_metadata!(TRACE_METADATA, Level::TRACE);
+
+fn static_metadata(level: Level) -> &'static Metadata<'static> {
+	match level {
+		Level::ERROR => &ERROR_METADATA,
+		Level::WARN => &WARN_METADATA,
+		Level::INFO => &INFO_METADATA,
+		Level::DEBUG => &DEBUG_METADATA,
+		Level::TRACE => &TRACE_METADATA,
+	}
+}
+
+/// Returns whether the current dispatcher is interested in runtime log records of the given
+/// `level` and `target`.
+///
+/// The check is done against metadata carrying the actual `target`, so that per-target
+/// directives of the subscriber are respected.
+pub fn runtime_log_enabled(level: Level, target: &str) -> bool {
+	let metadata = Metadata::new(
+		RUNTIME_LOG_IDENTIFIER,
+		target,
+		level,
+		None,
+		None,
+		None,
+		FieldSet::new(RUNTIME_LOG_FIELDS, tracing_core::identify_callsite!(&CALLSITE)),
+		Kind::EVENT,
+	);
+
+	tracing::dispatcher::get_default(|dispatch| dispatch.enabled(&metadata))
+}
+
+/// Emit a runtime log record with the given structured `fields` as `tracing` event.
+///
+/// Nothing is emitted if the current dispatcher is not interested in the record, see
+/// [`runtime_log_enabled`].
+pub fn emit_runtime_log(level: Level, target: &str, message: &str, fields: &[(&str, &str)]) {
+	if !runtime_log_enabled(level, target) {
+		return
+	}
+
+	let metadata = static_metadata(level);
+	let fields = JsonFields(fields);
+
+	tracing_core::Event::dispatch(
+		metadata,
+		&tracing::valueset! { metadata.fields(), %message, target, %fields },
+	)
+}
+
+/// Formats key/value pairs as JSON object.
+struct JsonFields<'a>(&'a [(&'a str, &'a str)]);
+
+impl fmt::Display for JsonFields<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_char('{')?;
+		for (i, (key, value)) in self.0.iter().enumerate() {
+			if i > 0 {
+				f.write_char(',')?;
+			}
+			write_json_str(f, key)?;
+			f.write_char(':')?;
+			write_json_str(f, value)?;
+		}
+		f.write_char('}')
+	}
+}
+
+fn write_json_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+	f.write_char('"')?;
+	for c in s.chars() {
+		match c {
+			'"' => f.write_str("\\\"")?,
+			'\\' => f.write_str("\\\\")?,
+			'\n' => f.write_str("\\n")?,
+			'\r' => f.write_str("\\r")?,
+			'\t' => f.write_str("\\t")?,
+			c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+			c => f.write_char(c)?,
+		}
+	}
+	f.write_char('"')
+}
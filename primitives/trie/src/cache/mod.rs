@@ -50,8 +50,10 @@ use std::{
 };
 use trie_db::{node::NodeOwned, CachedValue};
 
+mod persistence;
 mod shared_cache;
 
+pub use persistence::PersistenceError;
 pub use shared_cache::SharedTrieCache;
 
 use self::shared_cache::ValueCacheKeyHash;
@@ -946,6 +948,78 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn cache_export_import_works() {
+		// Big enough to hold the entire trie.
+		const SIZE: usize = 1024 * 1024;
+
+		let (db, root) = create_trie();
+
+		let shared_cache = Cache::new(CacheSize::new(SIZE));
+
+		{
+			let local_cache = shared_cache.local_cache();
+
+			let mut cache = local_cache.as_trie_db_cache(root);
+			let trie = TrieDBBuilder::<Layout>::new(&db, &root).with_cache(&mut cache).build();
+
+			for (k, _) in TEST_DATA {
+				trie.get(k).unwrap().unwrap();
+			}
+			assert!(trie.get(b"missing").unwrap().is_none());
+		}
+
+		let snapshot = shared_cache.export(root, SIZE);
+
+		// Snapshots taken at an unknown root are not imported.
+		let imported_cache = Cache::new(CacheSize::new(SIZE));
+		assert!(matches!(
+			imported_cache.import(&snapshot, SIZE, |r| *r != root),
+			Err(PersistenceError::UnknownStorageRoot)
+		));
+		assert!(imported_cache.read_lock_inner().node_cache().lru.is_empty());
+
+		// Snapshots that exceed the size limit are not imported.
+		assert!(matches!(
+			imported_cache.import(&snapshot, snapshot.len() - 1, |_| true),
+			Err(PersistenceError::TooBig(..))
+		));
+
+		// Corrupted snapshots are not imported.
+		let mut corrupted = snapshot.clone();
+		*corrupted.last_mut().unwrap() ^= 1;
+		assert!(matches!(
+			imported_cache.import(&corrupted, SIZE, |_| true),
+			Err(PersistenceError::ChecksumMismatch)
+		));
+
+		imported_cache.import(&snapshot, SIZE, |r| *r == root).unwrap();
+
+		assert_eq!(
+			shared_cache.read_lock_inner().node_cache().lru.len(),
+			imported_cache.read_lock_inner().node_cache().lru.len(),
+		);
+		assert!(matches!(
+			imported_cache
+				.read_lock_inner()
+				.value_cache()
+				.lru
+				.peek(&ValueCacheKey::new_value(&b"missing"[..], root)),
+			Some(CachedValue::NonExisting)
+		));
+
+		// All nodes are in the cache, so we don't need the database anymore.
+		let empty_db = MemoryDB::default();
+		let local_cache = imported_cache.local_cache();
+		let mut cache = local_cache.as_trie_db_cache(root);
+		let trie = TrieDBBuilder::<Layout>::new(&empty_db, &root).with_cache(&mut cache).build();
+
+		for (k, v) in TEST_DATA {
+			assert_eq!(v.to_vec(), trie.get(k).unwrap().unwrap());
+		}
+		assert!(trie.get(b"missing").unwrap().is_none());
+	}
+
 	#[test]
 	fn cache_respects_bounds() {
 		let (mut db, root) = create_trie();
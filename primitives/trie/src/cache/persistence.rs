@@ -0,0 +1,185 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The on-disk format of a [`SharedTrieCache`](super::SharedTrieCache) snapshot.
+//!
+//! A snapshot is laid out as `version ++ checksum ++ payload`, where `checksum` is the
+//! `blake2_256` hash of the SCALE encoded [`PersistedTrieCache`] `payload`. Only the entries that
+//! can be reconstructed without any other state are persisted: trie nodes are stored in their
+//! encoded form and every value cache entry is stored without its data.
+
+use codec::{Decode, Encode};
+use hash_db::Hasher;
+use trie_db::{node::NodeOwned, CachedValue, NodeCodec as _};
+
+use crate::{LayoutV1, NodeCodec};
+
+/// The version of the snapshot format.
+const PERSISTED_CACHE_VERSION: u8 = 1;
+
+/// Length of the header that precedes the payload: version byte plus checksum.
+const HEADER_LEN: usize = 1 + 32;
+
+/// Errors that can occur while loading a snapshot of the shared trie cache.
+#[derive(Debug, thiserror::Error)]
+pub enum PersistenceError {
+	/// The snapshot is shorter than its header.
+	#[error("Trie cache snapshot is truncated")]
+	Truncated,
+	/// The snapshot was written with a format that is not supported.
+	#[error("Unsupported trie cache snapshot version {0}")]
+	UnsupportedVersion(u8),
+	/// The snapshot is bigger than the caller allows.
+	#[error("Trie cache snapshot of {0} bytes exceeds the limit of {1} bytes")]
+	TooBig(usize, usize),
+	/// The checksum stored in the snapshot doesn't match its payload.
+	#[error("Trie cache snapshot checksum mismatch")]
+	ChecksumMismatch,
+	/// The payload could not be decoded.
+	#[error("Failed to decode trie cache snapshot: {0}")]
+	Decode(#[from] codec::Error),
+	/// The snapshot belongs to a storage root that was rejected by the caller.
+	#[error("Trie cache snapshot was taken at an unknown storage root")]
+	UnknownStorageRoot,
+}
+
+/// A trie node as stored in a snapshot.
+#[derive(Encode, Decode)]
+pub(super) enum PersistedNode {
+	/// A trie node in its encoded form.
+	Encoded(Vec<u8>),
+	/// The data of a value node.
+	Value(Vec<u8>),
+}
+
+impl PersistedNode {
+	/// Create a new instance from the given cached `node`.
+	pub(super) fn from_node<H: Hasher>(node: &NodeOwned<H::Out>) -> Self {
+		match node {
+			NodeOwned::Value(data, _) => Self::Value(data.to_vec()),
+			node => Self::Encoded(node.to_encoded::<NodeCodec<H>>()),
+		}
+	}
+
+	/// The number of bytes this node takes up in the snapshot.
+	pub(super) fn encoded_len(&self) -> usize {
+		match self {
+			Self::Encoded(data) | Self::Value(data) => data.len(),
+		}
+	}
+
+	/// Convert back into a [`NodeOwned`].
+	///
+	/// Returns `None` if the node doesn't hash to `hash` or can not be decoded.
+	pub(super) fn into_node<H: Hasher>(self, hash: &H::Out) -> Option<NodeOwned<H::Out>> {
+		match self {
+			Self::Value(data) =>
+				(H::hash(&data) == *hash).then(|| NodeOwned::Value(data.into(), *hash)),
+			Self::Encoded(data) => {
+				if H::hash(&data) != *hash {
+					return None
+				}
+
+				NodeCodec::<H>::decode(&data).ok()?.to_owned_node::<LayoutV1<H>>().ok()
+			},
+		}
+	}
+}
+
+/// A value cache entry as stored in a snapshot.
+#[derive(Encode, Decode)]
+pub(super) enum PersistedValue<H> {
+	/// See [`CachedValue::NonExisting`].
+	NonExisting,
+	/// See [`CachedValue::ExistingHash`].
+	ExistingHash(H),
+}
+
+impl<H: Copy> PersistedValue<H> {
+	/// Create a new instance from the given cached `value`.
+	///
+	/// Returns `None` for values that can not be persisted. [`CachedValue::Existing`] only holds a
+	/// weak reference to its data and its hash may refer to an inline value, so it is skipped.
+	pub(super) fn from_cached(value: &CachedValue<H>) -> Option<Self> {
+		match value {
+			CachedValue::NonExisting => Some(Self::NonExisting),
+			CachedValue::ExistingHash(hash) => Some(Self::ExistingHash(*hash)),
+			CachedValue::Existing { .. } => None,
+		}
+	}
+
+	/// Convert back into a [`CachedValue`].
+	pub(super) fn into_cached(self) -> CachedValue<H> {
+		match self {
+			Self::NonExisting => CachedValue::NonExisting,
+			Self::ExistingHash(hash) => CachedValue::ExistingHash(hash),
+		}
+	}
+}
+
+/// The payload of a snapshot.
+///
+/// All entries are ordered from the most recently used to the least recently used one.
+#[derive(Encode, Decode)]
+pub(super) struct PersistedTrieCache<H> {
+	/// The storage root the snapshot was taken at.
+	pub storage_root: H,
+	/// The cached trie nodes, keyed by their hash.
+	pub nodes: Vec<(H, PersistedNode)>,
+	/// The cached values of `storage_root`, keyed by their storage key.
+	pub values: Vec<(Vec<u8>, PersistedValue<H>)>,
+}
+
+impl<H: Encode + Decode> PersistedTrieCache<H> {
+	/// Encode `self` into a snapshot, including the header.
+	pub(super) fn to_snapshot(&self) -> Vec<u8> {
+		let payload = self.encode();
+
+		let mut snapshot = Vec::with_capacity(HEADER_LEN + payload.len());
+		snapshot.push(PERSISTED_CACHE_VERSION);
+		snapshot.extend_from_slice(&sp_core::hashing::blake2_256(&payload));
+		snapshot.extend_from_slice(&payload);
+		snapshot
+	}
+
+	/// Decode a snapshot created by [`Self::to_snapshot`].
+	///
+	/// Snapshots bigger than `max_size` are rejected without looking at their content.
+	pub(super) fn from_snapshot(
+		snapshot: &[u8],
+		max_size: usize,
+	) -> Result<Self, PersistenceError> {
+		if snapshot.len() > max_size {
+			return Err(PersistenceError::TooBig(snapshot.len(), max_size))
+		}
+
+		if snapshot.len() < HEADER_LEN {
+			return Err(PersistenceError::Truncated)
+		}
+
+		let (header, payload) = snapshot.split_at(HEADER_LEN);
+		if header[0] != PERSISTED_CACHE_VERSION {
+			return Err(PersistenceError::UnsupportedVersion(header[0]))
+		}
+
+		if header[1..] != sp_core::hashing::blake2_256(payload) {
+			return Err(PersistenceError::ChecksumMismatch)
+		}
+
+		Ok(Self::decode(&mut &payload[..])?)
+	}
+}
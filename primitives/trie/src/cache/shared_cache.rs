@@ -17,7 +17,11 @@
 
 ///! Provides the [`SharedNodeCache`], the [`SharedValueCache`] and the [`SharedTrieCache`]
 ///! that combines both caches and is exported to the outside.
-use super::{CacheSize, NodeCached};
+use super::{
+	persistence::{PersistedNode, PersistedTrieCache, PersistedValue, PersistenceError},
+	CacheSize, NodeCached,
+};
+use codec::{Decode, Encode};
 use hash_db::Hasher;
 use hashbrown::{hash_set::Entry as SetEntry, HashSet};
 use nohash_hasher::BuildNoHashHasher;
@@ -310,6 +314,18 @@ impl<H: AsRef<[u8]> + Eq + std::hash::Hash> SharedNodeCache<H> {
 		);
 	}
 
+	/// Restore the cache from the `list` of nodes, ordered from the most recently used one.
+	///
+	/// Unlike [`Self::update`], this will evict as many items as required to fit all of them.
+	fn restore(&mut self, list: Vec<(H, NodeOwned<H>)>) {
+		self.lru.limiter_mut().items_evicted = 0;
+		self.lru.limiter_mut().max_items_evicted = usize::MAX;
+
+		for (key, node) in list.into_iter().rev() {
+			self.lru.insert(key, node);
+		}
+	}
+
 	/// Reset the cache.
 	fn reset(&mut self) {
 		self.lru.clear();
@@ -399,7 +415,6 @@ where
 
 impl<H> ValueCacheKey<H> {
 	/// Constructs [`Self::Value`].
-	#[cfg(test)] // Only used in tests.
 	pub fn new_value(storage_key: impl Into<Arc<[u8]>>, storage_root: H) -> Self
 	where
 		H: AsRef<[u8]>,
@@ -557,6 +572,18 @@ impl<H: Eq + std::hash::Hash + Clone + Copy + AsRef<[u8]>> SharedValueCache<H> {
 		);
 	}
 
+	/// Restore the cache from the `list` of values, ordered from the most recently used one.
+	///
+	/// Unlike [`Self::update`], this will evict as many items as required to fit all of them.
+	fn restore(&mut self, list: Vec<(ValueCacheKey<H>, CachedValue<H>)>) {
+		self.lru.limiter_mut().items_evicted = 0;
+		self.lru.limiter_mut().max_items_evicted = usize::MAX;
+
+		for (key, value) in list.into_iter().rev() {
+			self.lru.insert(key, value);
+		}
+	}
+
 	/// Reset the cache.
 	fn reset(&mut self) {
 		self.lru.clear();
@@ -731,6 +758,112 @@ impl<H: Hasher> SharedTrieCache<H> {
 		self.reset_value_cache();
 	}
 
+	/// Export the most recently used entries of this cache as a snapshot.
+	///
+	/// Only the values that belong to `storage_root` are exported, while trie nodes are exported
+	/// independently of the state they were read from. The snapshot will be at most about
+	/// `max_size` bytes big. It can be loaded again with [`Self::import`].
+	pub fn export(&self, storage_root: H::Out, max_size: usize) -> Vec<u8>
+	where
+		H::Out: Encode + Decode,
+	{
+		// Split the budget the same way as the memory budget of the cache.
+		let value_budget = (max_size as f32 * 0.20) as usize;
+		let node_budget = max_size - value_budget;
+
+		let inner = self.inner.read();
+
+		let mut size = 0;
+		let nodes = inner
+			.node_cache
+			.lru
+			.iter()
+			.map(|(hash, node)| (*hash, PersistedNode::from_node::<H>(node)))
+			.take_while(|(hash, node)| {
+				size += hash.as_ref().len() + node.encoded_len();
+				size <= node_budget
+			})
+			.collect::<Vec<_>>();
+
+		let mut size = 0;
+		let values = inner
+			.value_cache
+			.lru
+			.iter()
+			.filter(|(key, _)| key.storage_root == storage_root)
+			.filter_map(|(key, value)| {
+				PersistedValue::from_cached(value).map(|value| (key.storage_key.to_vec(), value))
+			})
+			.take_while(|(key, _)| {
+				size += key.len() + storage_root.as_ref().len() + 1;
+				size <= value_budget
+			})
+			.collect::<Vec<_>>();
+
+		drop(inner);
+
+		tracing::debug!(
+			target: super::LOG_TARGET,
+			"Exporting {} nodes and {} values of the shared trie cache at {:?}",
+			nodes.len(),
+			values.len(),
+			storage_root,
+		);
+
+		PersistedTrieCache { storage_root, nodes, values }.to_snapshot()
+	}
+
+	/// Import a `snapshot` created by [`Self::export`] into this cache.
+	///
+	/// Snapshots bigger than `max_size` bytes are rejected. `is_valid_root` is called with the
+	/// storage root the snapshot was taken at and should only return `true` if the state at this
+	/// root is still relevant, otherwise nothing is imported. Trie nodes that don't match their
+	/// hash are skipped.
+	///
+	/// This is meant to be called right after creating the cache. Imported entries become the most
+	/// recently used ones and may evict entries that are already cached.
+	pub fn import(
+		&self,
+		snapshot: &[u8],
+		max_size: usize,
+		is_valid_root: impl FnOnce(&H::Out) -> bool,
+	) -> Result<(), PersistenceError>
+	where
+		H::Out: Encode + Decode,
+	{
+		let PersistedTrieCache { storage_root, nodes, values } =
+			PersistedTrieCache::<H::Out>::from_snapshot(snapshot, max_size)?;
+
+		if !is_valid_root(&storage_root) {
+			return Err(PersistenceError::UnknownStorageRoot)
+		}
+
+		let num_nodes = nodes.len();
+		let nodes = nodes
+			.into_iter()
+			.filter_map(|(hash, node)| node.into_node::<H>(&hash).map(|node| (hash, node)))
+			.collect::<Vec<_>>();
+		let values = values
+			.into_iter()
+			.map(|(key, value)| (ValueCacheKey::new_value(key, storage_root), value.into_cached()))
+			.collect::<Vec<_>>();
+
+		tracing::debug!(
+			target: super::LOG_TARGET,
+			"Importing {} nodes ({} skipped) and {} values into the shared trie cache at {:?}",
+			nodes.len(),
+			num_nodes - nodes.len(),
+			values.len(),
+			storage_root,
+		);
+
+		let mut inner = self.inner.write();
+		inner.node_cache.restore(nodes);
+		inner.value_cache.restore(values);
+
+		Ok(())
+	}
+
 	/// Returns the read locked inner.
 	#[cfg(test)]
 	pub(super) fn read_lock_inner(
@@ -40,10 +40,13 @@ pub use memory_db::{prefixed_key, HashKey, KeyFunction, PrefixedKey};
 /// The Substrate format implementation of `NodeCodec`.
 pub use node_codec::NodeCodec;
 use sp_std::{borrow::Borrow, boxed::Box, marker::PhantomData, vec::Vec};
-pub use storage_proof::{CompactProof, StorageProof};
+pub use storage_proof::{CompactProof, MultiRootCompactProof, StorageProof};
 /// Trie codec reexport, mainly child trie support
 /// for trie compact proof.
-pub use trie_codec::{decode_compact, encode_compact, Error as CompactProofError};
+pub use trie_codec::{
+	decode_compact, decode_multi_root_compact, encode_compact, encode_multi_root_compact,
+	Error as CompactProofError,
+};
 pub use trie_db::proof::VerifyError;
 use trie_db::proof::{generate_proof, verify_proof};
 /// Various re-exports from the `trie-db` crate.
@@ -117,6 +117,17 @@ impl StorageProof {
 		crate::encode_compact::<Layout<H>, crate::MemoryDB<H>>(&db, &root)
 	}
 
+	/// Encode as a compact proof of multiple roots with default trie layout.
+	///
+	/// `self` needs to contain the nodes of all `roots`, for example by merging their proofs with
+	/// [`Self::merge`]. Nodes shared between the roots are only encoded once.
+	pub fn into_multi_root_compact_proof<H: Hasher>(
+		self,
+		roots: &[H::Out],
+	) -> Result<MultiRootCompactProof, crate::CompactProofError<H::Out, crate::Error<H::Out>>> {
+		crate::encode_multi_root_compact::<Layout<H>>(self.into_memory_db(), roots)
+	}
+
 	/// Returns the estimated encoded size of the compact proof.
 	///
 	/// Running this operation is a slow operation (build the whole compact proof) and should only
@@ -200,3 +211,42 @@ impl CompactProof {
 		Ok((db, root))
 	}
 }
+
+/// Storage proof of multiple roots in compact form.
+///
+/// Contains a [`CompactProof`] for every root, in the order of the roots. A node that is part of
+/// the proof of an earlier root is omitted and only referenced by its hash, so nodes shared
+/// between the states, like those of consecutive blocks, are only included once. The proof of a
+/// root that is entirely covered by the earlier proofs is empty.
+#[derive(Debug, PartialEq, Eq, Clone, Encode, Decode, TypeInfo)]
+pub struct MultiRootCompactProof {
+	pub proofs: Vec<CompactProof>,
+}
+
+impl MultiRootCompactProof {
+	/// Decode to a full storage proof covering all `expected_roots`.
+	pub fn to_storage_proof<H: Hasher>(
+		&self,
+		expected_roots: &[H::Out],
+	) -> Result<StorageProof, crate::CompactProofError<H::Out, crate::Error<H::Out>>> {
+		let mut db = self.to_memory_db::<H>(expected_roots)?;
+		Ok(StorageProof::new(db.drain().into_iter().filter_map(|kv| {
+			if (kv.1).1 > 0 {
+				Some((kv.1).0)
+			} else {
+				None
+			}
+		})))
+	}
+
+	/// Convert self into a [`MemoryDB`](crate::MemoryDB) containing the nodes of all
+	/// `expected_roots`.
+	pub fn to_memory_db<H: Hasher>(
+		&self,
+		expected_roots: &[H::Out],
+	) -> Result<crate::MemoryDB<H>, crate::CompactProofError<H::Out, crate::Error<H::Out>>> {
+		let mut db = crate::MemoryDB::<H>::new(&[]);
+		crate::decode_multi_root_compact::<Layout<H>, _>(&mut db, self, expected_roots)?;
+		Ok(db)
+	}
+}
@@ -20,8 +20,11 @@
 //! This uses compact proof from trie crate and extends
 //! it to substrate specific layout and child trie system.
 
-use crate::{CompactProof, HashDBT, TrieConfiguration, TrieHash, EMPTY_PREFIX};
-use sp_std::{boxed::Box, vec::Vec};
+use crate::{
+	CompactProof, HashDBT, MemoryDB, MultiRootCompactProof, TrieConfiguration, TrieHash,
+	EMPTY_PREFIX,
+};
+use sp_std::{boxed::Box, collections::btree_set::BTreeSet, vec::Vec};
 use trie_db::{CError, Trie};
 
 /// Error for trie node decoding.
@@ -40,6 +43,8 @@ pub enum Error<H, CodecError> {
 	InvalidChildRoot(Vec<u8>, Vec<u8>),
 	#[cfg_attr(feature = "std", error("Trie error: {0:?}"))]
 	TrieError(Box<trie_db::TrieError<H, CodecError>>),
+	#[cfg_attr(feature = "std", error("Proof covers {0} roots, expected {1}"))]
+	RootCountMismatch(usize, usize),
 }
 
 impl<H, CodecError> From<Box<trie_db::TrieError<H, CodecError>>> for Error<H, CodecError> {
@@ -205,3 +210,72 @@ where
 
 	Ok(CompactProof { encoded_nodes: compact_proof })
 }
+
+/// Decode a compact proof of multiple roots.
+///
+/// The proofs are decoded in order into `db`, each one is checked against the expected root at
+/// the same position in `expected_roots`.
+pub fn decode_multi_root_compact<L, DB>(
+	db: &mut DB,
+	proof: &MultiRootCompactProof,
+	expected_roots: &[TrieHash<L>],
+) -> Result<(), Error<TrieHash<L>, CError<L>>>
+where
+	L: TrieConfiguration,
+	DB: HashDBT<L::Hash, trie_db::DBValue> + hash_db::HashDBRef<L::Hash, trie_db::DBValue>,
+{
+	if proof.proofs.len() != expected_roots.len() {
+		return Err(Error::RootCountMismatch(proof.proofs.len(), expected_roots.len()))
+	}
+
+	for (proof, expected_root) in proof.proofs.iter().zip(expected_roots) {
+		if proof.encoded_nodes.is_empty() {
+			// The root must be part of a previous proof.
+			if !HashDBT::<L::Hash, _>::contains(db, expected_root, EMPTY_PREFIX) {
+				return Err(Error::IncompleteProof)
+			}
+		} else {
+			decode_compact::<L, _, _>(db, proof.iter_compact_encoded_nodes(), Some(expected_root))?;
+		}
+	}
+
+	Ok(())
+}
+
+/// Encode a compact proof of multiple roots.
+///
+/// Takes as input all full encoded nodes of the proofs of all `roots`. The roots are encoded in
+/// the given order, every node is only encoded for the first root it is reachable from.
+pub fn encode_multi_root_compact<L>(
+	mut partial_db: MemoryDB<L::Hash>,
+	roots: &[TrieHash<L>],
+) -> Result<MultiRootCompactProof, Error<TrieHash<L>, CError<L>>>
+where
+	L: TrieConfiguration,
+{
+	let mut decoded = MemoryDB::<L::Hash>::new(&[]);
+	let mut encoded = BTreeSet::new();
+	let mut proofs = Vec::with_capacity(roots.len());
+
+	for root in roots {
+		if !HashDBT::<L::Hash, _>::contains(&partial_db, root, EMPTY_PREFIX) {
+			// Either covered by a previous proof or missing, which is detected when decoding.
+			proofs.push(CompactProof { encoded_nodes: Vec::new() });
+			continue
+		}
+
+		let proof = encode_compact::<L, _>(&partial_db, root)?;
+
+		// Remove the encoded nodes, so that the following proofs only refer to them by hash.
+		decode_compact::<L, _, _>(&mut decoded, proof.iter_compact_encoded_nodes(), Some(root))?;
+		for (hash, _) in decoded.keys().into_iter().filter(|(_, rc)| *rc > 0) {
+			if encoded.insert(hash) {
+				HashDBT::<L::Hash, _>::remove(&mut partial_db, &hash, EMPTY_PREFIX);
+			}
+		}
+
+		proofs.push(proof);
+	}
+
+	Ok(MultiRootCompactProof { proofs })
+}
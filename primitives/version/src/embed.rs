@@ -15,10 +15,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-//! Provides functionality to embed a [`RuntimeVersion`](crate::RuntimeVersion) as custom section
-//! into a WASM file.
+//! Provides functionality to embed a [`RuntimeVersion`](crate::RuntimeVersion) and [`BuildInfo`]
+//! as custom sections into a WASM file.
 
-use codec::Encode;
+use codec::{Decode, Encode};
 use parity_wasm::elements::{deserialize_buffer, serialize, Module};
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug, thiserror::Error)]
@@ -54,3 +54,35 @@ pub fn embed_runtime_version(
 
 	serialize(module).map_err(|_| Error::Serialize)
 }
+
+/// Name of the custom section containing the [`BuildInfo`].
+pub const BUILD_INFO_SECTION: &str = "runtime_build_info";
+
+/// Information about the build of a runtime WASM blob.
+///
+/// Embedded by `substrate-wasm-builder` into the [`BUILD_INFO_SECTION`] custom section, so that
+/// it can be read without executing the runtime.
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+pub struct BuildInfo {
+	/// Digest of the runtime metadata, see `frame_support::metadata_ir::metadata_digest`.
+	pub metadata_hash: [u8; 32],
+	/// The git commit of the runtime sources, if they were built from a git checkout.
+	pub git_commit: Option<String>,
+	/// The features the runtime was built with.
+	pub features: Vec<String>,
+	/// The profile the runtime was built with.
+	pub profile: String,
+}
+
+/// Embed the given `build_info` to the given `wasm` blob.
+///
+/// If there was already a build info embedded, this will be overwritten.
+///
+/// Returns the new WASM blob.
+pub fn embed_build_info(wasm: &[u8], build_info: &BuildInfo) -> Result<Vec<u8>, Error> {
+	let mut module: Module = deserialize_buffer(wasm).map_err(|_| Error::Deserialize)?;
+
+	module.set_custom_section(BUILD_INFO_SECTION, build_info.encode());
+
+	serialize(module).map_err(|_| Error::Serialize)
+}
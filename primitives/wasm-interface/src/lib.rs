@@ -349,6 +349,19 @@ if_wasmtime_is_enabled! {
 			callback: impl FnOnce(&mut dyn FunctionContext) -> R,
 		) -> R;
 
+		/// Same as [`Self::with_function_context`], but also passes the name of the called host
+		/// function.
+		///
+		/// This allows the executor to observe individual host function calls, e.g. to profile
+		/// them. By default this forwards to [`Self::with_function_context`].
+		fn with_host_function_context<R>(
+			_name: &'static str,
+			caller: wasmtime::Caller<Self::State>,
+			callback: impl FnOnce(&mut dyn FunctionContext) -> R,
+		) -> R {
+			Self::with_function_context(caller, callback)
+		}
+
 		/// Registers a given host function with the WASM executor.
 		///
 		/// The function has to be statically callable, and all of its arguments
@@ -445,6 +458,14 @@ where
 					T::with_function_context(caller, callback)
 				}
 
+				fn with_host_function_context<R>(
+					name: &'static str,
+					caller: wasmtime::Caller<Self::State>,
+					callback: impl FnOnce(&mut dyn FunctionContext) -> R,
+				) -> R {
+					T::with_host_function_context(name, caller, callback)
+				}
+
 				fn register_static<Params, Results>(
 					&mut self,
 					fn_name: &str,
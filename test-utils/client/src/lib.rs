@@ -106,6 +106,13 @@ impl<Block: BlockT, ExecutorDispatch, G: GenesisInit>
 			Arc::new(Backend::new_test_with_tx_storage(BlocksPruning::Some(blocks_pruning), 0));
 		Self::with_backend(backend)
 	}
+
+	/// Create new `TestClientBuilder` with default backend that indexes the storage changes of
+	/// every block.
+	pub fn with_storage_changes_index() -> Self {
+		let backend = Arc::new(Backend::new_test_with_storage_changes_index(std::u32::MAX));
+		Self::with_backend(backend)
+	}
 }
 
 impl<Block: BlockT, ExecutorDispatch, Backend, G: GenesisInit>
@@ -0,0 +1,175 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Genesis config of the test runtime, built from JSON by the `GenesisBuilder` runtime api.
+//!
+//! Unlike [`crate::genesismap::GenesisConfig`], this works without `std`, so that chain specs can
+//! be created from the wasm blob of the runtime alone.
+
+use crate::{system, AuthorityId};
+use codec::Encode;
+use frame_support::traits::BuildGenesisConfig;
+use serde::{Deserialize, Serialize};
+use sp_application_crypto::sr25519;
+use sp_core::storage::well_known_keys;
+use sp_genesis_builder::{PresetId, DEV_RUNTIME_PRESET, LOCAL_TESTNET_RUNTIME_PRESET};
+use sp_io::hashing::{blake2_256, twox_128};
+use sp_std::prelude::*;
+
+/// Public key of the well known `Alice` sr25519 development account.
+pub const ALICE: [u8; 32] = [
+	212, 53, 147, 199, 21, 253, 211, 28, 97, 20, 26, 189, 4, 169, 159, 214, 130, 44, 133, 88, 133,
+	76, 205, 227, 154, 86, 132, 231, 165, 109, 162, 125,
+];
+/// Public key of the well known `Bob` sr25519 development account.
+pub const BOB: [u8; 32] = [
+	142, 175, 4, 21, 22, 135, 115, 99, 38, 201, 254, 161, 126, 37, 252, 82, 135, 97, 54, 147, 201,
+	18, 144, 156, 178, 38, 170, 71, 148, 242, 106, 72,
+];
+/// Public key of the well known `Charlie` sr25519 development account.
+pub const CHARLIE: [u8; 32] = [
+	144, 181, 171, 32, 92, 105, 116, 201, 234, 132, 27, 230, 136, 134, 70, 51, 220, 156, 168, 163,
+	87, 132, 62, 234, 207, 35, 20, 100, 153, 101, 254, 34,
+];
+
+/// Balance of the accounts endowed by the presets.
+const PRESET_BALANCE: u64 = 1_000_000_000_000;
+
+/// Genesis config of the test runtime.
+///
+/// Accounts and authorities are raw sr25519 public keys.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeGenesisConfig {
+	/// Initial authorities.
+	pub authorities: Vec<[u8; 32]>,
+	/// Endowed accounts and their balance.
+	pub balances: Vec<([u8; 32], u64)>,
+	/// Number of heap pages, 16 if not set.
+	pub heap_pages: Option<u64>,
+}
+
+impl BuildGenesisConfig for RuntimeGenesisConfig {
+	fn build(&self) {
+		for (account, balance) in &self.balances {
+			let key = system::balance_of_key(sr25519::Public::from_raw(*account));
+			sp_io::storage::set(&blake2_256(&key), &balance.encode());
+		}
+		sp_io::storage::set(well_known_keys::HEAP_PAGES, &self.heap_pages.unwrap_or(16).encode());
+
+		let authorities = self
+			.authorities
+			.iter()
+			.map(|key| AuthorityId::from(sr25519::Public::from_raw(*key)))
+			.collect::<Vec<_>>();
+		sp_io::storage::set(&twox_128(b"sys:auth"), &authorities.encode());
+		<system::Authorities<crate::Runtime>>::put(authorities);
+	}
+}
+
+/// Returns the JSON patch of the preset with the given `id`, see
+/// [`sp_genesis_builder::GenesisBuilder::get_preset`].
+pub fn get_preset(id: &PresetId) -> Option<Vec<u8>> {
+	let (authorities, endowed) = if *id == DEV_RUNTIME_PRESET {
+		(vec![ALICE], vec![ALICE, BOB])
+	} else if *id == LOCAL_TESTNET_RUNTIME_PRESET {
+		(vec![ALICE, BOB], vec![ALICE, BOB, CHARLIE])
+	} else {
+		return None
+	};
+
+	let patch = RuntimeGenesisConfig {
+		authorities,
+		balances: endowed.into_iter().map(|account| (account, PRESET_BALANCE)).collect(),
+		heap_pages: None,
+	};
+	Some(serde_json::to_vec(&patch).expect("Serialization to JSON is expected to work; qed"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wasm_binary_unwrap;
+	use codec::Decode;
+	use sc_chain_spec::{GenericChainSpec, GenesisConfigBuilderRuntimeCaller, NoExtension};
+	use sp_keyring::AccountKeyring;
+	use sp_runtime::BuildStorage;
+
+	#[test]
+	fn well_known_accounts_match_the_keyring() {
+		assert_eq!(ALICE, AccountKeyring::Alice.public().0);
+		assert_eq!(BOB, AccountKeyring::Bob.public().0);
+		assert_eq!(CHARLIE, AccountKeyring::Charlie.public().0);
+	}
+
+	#[test]
+	fn default_config_builds_from_wasm() {
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(wasm_binary_unwrap());
+
+		let config = caller.get_default_config().unwrap();
+		assert_eq!(
+			config,
+			serde_json::json!({ "authorities": [], "balances": [], "heapPages": null })
+		);
+
+		let storage = caller.get_storage_for_config(config).unwrap();
+		assert_eq!(storage.top[well_known_keys::HEAP_PAGES], 16u64.encode());
+	}
+
+	#[test]
+	fn unknown_preset_is_rejected() {
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(wasm_binary_unwrap());
+
+		assert!(caller.get_named_preset("unknown").is_err());
+	}
+
+	#[test]
+	fn chain_spec_builds_from_wasm_and_preset() {
+		let spec = GenericChainSpec::<(), NoExtension>::builder(wasm_binary_unwrap(), None)
+			.with_name("Test")
+			.with_id("test")
+			.with_genesis_config_preset_name(LOCAL_TESTNET_RUNTIME_PRESET)
+			.build();
+		let storage = spec.build_storage().unwrap();
+
+		let balance_key = |account: [u8; 32]| {
+			blake2_256(&system::balance_of_key(sr25519::Public::from_raw(account))).to_vec()
+		};
+		assert_eq!(storage.top[&balance_key(CHARLIE)], PRESET_BALANCE.encode());
+		assert_eq!(
+			storage.top[&twox_128(b"sys:auth").to_vec()],
+			vec![
+				AuthorityId::from(AccountKeyring::Alice.public()),
+				AuthorityId::from(AccountKeyring::Bob.public())
+			]
+			.encode(),
+		);
+		assert_eq!(storage.top[well_known_keys::CODE], wasm_binary_unwrap().to_vec());
+	}
+
+	#[test]
+	fn development_preset_builds_from_wasm() {
+		let caller = GenesisConfigBuilderRuntimeCaller::<()>::new(wasm_binary_unwrap());
+
+		let storage = caller.get_storage_for_named_preset(DEV_RUNTIME_PRESET).unwrap();
+		let authorities =
+			Vec::<AuthorityId>::decode(&mut &storage.top[&twox_128(b"sys:auth").to_vec()][..])
+				.unwrap();
+		assert_eq!(authorities, vec![AuthorityId::from(AccountKeyring::Alice.public())]);
+	}
+}
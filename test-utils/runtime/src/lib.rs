@@ -19,6 +19,7 @@
 
 #![cfg_attr(not(feature = "std"), no_std)]
 
+pub mod genesis_builder;
 #[cfg(feature = "std")]
 pub mod genesismap;
 pub mod system;
@@ -38,6 +39,7 @@ use trie_db::{Trie, TrieMut};
 use cfg_if::cfg_if;
 use frame_support::{
 	dispatch::RawOrigin,
+	genesis_builder_helper::{build_config, create_default_config},
 	parameter_types,
 	traits::{CallerTrait, ConstU32, ConstU64, CrateVersion},
 	weights::{RuntimeDbWeight, Weight},
@@ -932,6 +934,20 @@ cfg_if! {
 				}
 			}
 
+			impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+				fn create_default_config() -> Vec<u8> {
+					create_default_config::<genesis_builder::RuntimeGenesisConfig>()
+				}
+
+				fn build_config(config: Vec<u8>) -> sp_genesis_builder::Result {
+					build_config::<genesis_builder::RuntimeGenesisConfig>(config)
+				}
+
+				fn get_preset(id: sp_genesis_builder::PresetId) -> Option<Vec<u8>> {
+					genesis_builder::get_preset(&id)
+				}
+			}
+
 			impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
 				fn offchain_worker(header: &<Block as BlockT>::Header) {
 					let ex = Extrinsic::IncludeData(header.number.encode());
@@ -1236,6 +1252,20 @@ cfg_if! {
 				}
 			}
 
+			impl sp_genesis_builder::GenesisBuilder<Block> for Runtime {
+				fn create_default_config() -> Vec<u8> {
+					create_default_config::<genesis_builder::RuntimeGenesisConfig>()
+				}
+
+				fn build_config(config: Vec<u8>) -> sp_genesis_builder::Result {
+					build_config::<genesis_builder::RuntimeGenesisConfig>(config)
+				}
+
+				fn get_preset(id: sp_genesis_builder::PresetId) -> Option<Vec<u8>> {
+					genesis_builder::get_preset(&id)
+				}
+			}
+
 			impl sp_offchain::OffchainWorkerApi<Block> for Runtime {
 				fn offchain_worker(header: &<Block as BlockT>::Header) {
 					let ex = Extrinsic::IncludeData(header.number.encode());
@@ -135,6 +135,7 @@ where
 		&payload,
 		full_extensions(executor.clone()),
 		shared.export_proof,
+		shared.overlay_memory_limit,
 	)?;
 
 	Ok(())
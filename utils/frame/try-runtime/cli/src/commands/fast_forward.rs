@@ -97,6 +97,7 @@ async fn dry_run<T: Decode, Block: BlockT, HostFns: HostFunctions>(
 	executor: &WasmExecutor<HostFns>,
 	method: &'static str,
 	data: &[u8],
+	overlay_memory_limit: Option<usize>,
 ) -> Result<T> {
 	let (_, result) = state_machine_call::<Block, HostFns>(
 		externalities,
@@ -104,6 +105,7 @@ async fn dry_run<T: Decode, Block: BlockT, HostFns: HostFunctions>(
 		method,
 		data,
 		full_extensions(executor.clone()),
+		overlay_memory_limit,
 	)?;
 
 	Ok(<T>::decode(&mut &*result)?)
@@ -115,6 +117,7 @@ async fn run<Block: BlockT, HostFns: HostFunctions>(
 	executor: &WasmExecutor<HostFns>,
 	method: &'static str,
 	data: &[u8],
+	overlay_memory_limit: Option<usize>,
 ) -> Result<()> {
 	let (mut changes, _) = state_machine_call::<Block, HostFns>(
 		externalities,
@@ -122,6 +125,7 @@ async fn run<Block: BlockT, HostFns: HostFunctions>(
 		method,
 		data,
 		full_extensions(executor.clone()),
+		overlay_memory_limit,
 	)?;
 
 	let storage_changes = changes.drain_storage_changes(
@@ -149,6 +153,7 @@ async fn next_empty_block<
 	parent_hash: Block::Hash,
 	block_building_info_provider: &Option<BBIP>,
 	previous_block_building_info: Option<(InherentData, Digest)>,
+	overlay_memory_limit: Option<usize>,
 ) -> Result<(Block, Option<(InherentData, Digest)>)> {
 	let (maybe_inherent_data, pre_digest) = match &block_building_info_provider {
 		None => (None, Default::default()),
@@ -174,7 +179,14 @@ async fn next_empty_block<
 	);
 	let mut extrinsics = <Vec<Block::Extrinsic>>::new();
 
-	run::<Block, _>(externalities, executor, "Core_initialize_block", &header.encode()).await?;
+	run::<Block, _>(
+		externalities,
+		executor,
+		"Core_initialize_block",
+		&header.encode(),
+		overlay_memory_limit,
+	)
+	.await?;
 
 	if let Some(ref inherent_data) = maybe_inherent_data {
 		extrinsics = dry_run::<Vec<Block::Extrinsic>, Block, _>(
@@ -182,13 +194,20 @@ async fn next_empty_block<
 			executor,
 			"BlockBuilder_inherent_extrinsics",
 			&inherent_data.encode(),
+			overlay_memory_limit,
 		)
 		.await?;
 	}
 
 	for xt in &extrinsics {
-		run::<Block, _>(externalities, executor, "BlockBuilder_apply_extrinsic", &xt.encode())
-			.await?;
+		run::<Block, _>(
+			externalities,
+			executor,
+			"BlockBuilder_apply_extrinsic",
+			&xt.encode(),
+			overlay_memory_limit,
+		)
+		.await?;
 	}
 
 	let header = dry_run::<Block::Header, Block, _>(
@@ -196,10 +215,18 @@ async fn next_empty_block<
 		executor,
 		"BlockBuilder_finalize_block",
 		&[0u8; 0],
+		overlay_memory_limit,
 	)
 	.await?;
 
-	run::<Block, _>(externalities, executor, "BlockBuilder_finalize_block", &[0u8; 0]).await?;
+	run::<Block, _>(
+		externalities,
+		executor,
+		"BlockBuilder_finalize_block",
+		&[0u8; 0],
+		overlay_memory_limit,
+	)
+	.await?;
 
 	Ok((Block::new(header, extrinsics), (maybe_inherent_data.map(|id| (id, pre_digest)))))
 }
@@ -242,6 +269,7 @@ where
 			last_block_hash,
 			&block_building_info_provider,
 			prev_block_building_info,
+			shared.overlay_memory_limit,
 		)
 		.await?;
 
@@ -255,7 +283,14 @@ where
 		let payload =
 			(next_block.clone(), state_root_check, signature_check, command.try_state.clone())
 				.encode();
-		run::<Block, _>(&mut ext, &executor, "TryRuntime_execute_block", &payload).await?;
+		run::<Block, _>(
+			&mut ext,
+			&executor,
+			"TryRuntime_execute_block",
+			&payload,
+			shared.overlay_memory_limit,
+		)
+		.await?;
 
 		log::info!("Executed the new block");
 
@@ -158,6 +158,7 @@ where
 				.export_proof
 				.as_ref()
 				.map(|path| path.as_path().join(&format!("{}.json", number))),
+			shared.overlay_memory_limit,
 		);
 
 		if let Err(why) = result {
@@ -98,6 +98,7 @@ where
 		"OffchainWorkerApi_offchain_worker",
 		&payload,
 		full_extensions(executor.clone()),
+		shared.overlay_memory_limit,
 	)?;
 
 	Ok(())
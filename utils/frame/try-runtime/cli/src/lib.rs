@@ -545,6 +545,13 @@ pub struct SharedParams {
 	/// Otherwise `remote-externalities` will automatically set the correct state version.
 	#[arg(long, value_parser = parse::state_version)]
 	pub overwrite_state_version: Option<StateVersion>,
+
+	/// Limit the memory used for the storage changes of a runtime call, in MiB.
+	///
+	/// Once exceeded, committed storage changes are moved to a temporary file. Useful for
+	/// migrations that modify more state than fits into memory.
+	#[arg(long, value_name = "MiB")]
+	pub overlay_memory_limit: Option<usize>,
 }
 
 /// Our `try-runtime` command.
@@ -864,8 +871,9 @@ pub(crate) fn state_machine_call<Block: BlockT, HostFns: HostFunctions>(
 	method: &'static str,
 	data: &[u8],
 	extensions: Extensions,
+	overlay_memory_limit: Option<usize>,
 ) -> sc_cli::Result<(OverlayedChanges, Vec<u8>)> {
-	let mut changes = Default::default();
+	let mut changes = new_overlay(overlay_memory_limit)?;
 	let encoded_results = StateMachine::new(
 		&ext.backend,
 		&mut changes,
@@ -883,6 +891,18 @@ pub(crate) fn state_machine_call<Block: BlockT, HostFns: HostFunctions>(
 	Ok((changes, encoded_results))
 }
 
+/// Create the overlay for a runtime call, limiting its memory to `overlay_memory_limit` MiB.
+fn new_overlay(overlay_memory_limit: Option<usize>) -> sc_cli::Result<OverlayedChanges> {
+	let mut changes = OverlayedChanges::default();
+	if let Some(limit) = overlay_memory_limit {
+		changes
+			.set_memory_limit(limit.saturating_mul(1024 * 1024))
+			.map_err(|e| format!("failed to create overlay spill file: {}", e))
+			.map_err::<sc_cli::Error, _>(Into::into)?;
+	}
+	Ok(changes)
+}
+
 /// Same as [`state_machine_call`], but it also computes and prints the storage proof in different
 /// size and formats.
 ///
@@ -894,10 +914,11 @@ pub(crate) fn state_machine_call_with_proof<Block: BlockT, HostFns: HostFunction
 	data: &[u8],
 	extensions: Extensions,
 	maybe_export_proof: Option<PathBuf>,
+	overlay_memory_limit: Option<usize>,
 ) -> sc_cli::Result<(OverlayedChanges, Vec<u8>)> {
 	use parity_scale_codec::Encode;
 
-	let mut changes = Default::default();
+	let mut changes = new_overlay(overlay_memory_limit)?;
 	let backend = ext.backend.clone();
 	let runtime_code_backend = sp_state_machine::backend::BackendRuntimeCode::new(&backend);
 	let proving_backend =
@@ -48,6 +48,7 @@ impl WasmBuilderSelectProject {
 			file_name: None,
 			project_cargo_toml: get_manifest_dir().join("Cargo.toml"),
 			features_to_enable: Vec::new(),
+			enable_metadata_hash: false,
 		}
 	}
 
@@ -63,6 +64,7 @@ impl WasmBuilderSelectProject {
 				file_name: None,
 				project_cargo_toml: path,
 				features_to_enable: Vec::new(),
+				enable_metadata_hash: false,
 			})
 		} else {
 			Err("Project path must point to the `Cargo.toml` of the project")
@@ -93,6 +95,8 @@ pub struct WasmBuilder {
 	project_cargo_toml: PathBuf,
 	/// Features that should be enabled when building the wasm binary.
 	features_to_enable: Vec<String>,
+	/// Should the build info including the metadata digest be embedded into the wasm binary?
+	enable_metadata_hash: bool,
 }
 
 impl WasmBuilder {
@@ -143,6 +147,18 @@ impl WasmBuilder {
 		self
 	}
 
+	/// Embed the metadata digest and build information into the wasm binary.
+	///
+	/// After building, the `Metadata_metadata` runtime api of the wasm binary is executed to
+	/// calculate the digest of the metadata, see `frame_support::metadata_ir::metadata_digest`.
+	/// The digest is embedded together with the git commit, the enabled features and the profile
+	/// as `sp_version::embed::BuildInfo` into a custom section of the wasm binary.
+	#[cfg(feature = "metadata-hash")]
+	pub fn enable_metadata_hash(mut self) -> Self {
+		self.enable_metadata_hash = true;
+		self
+	}
+
 	/// Build the WASM binary.
 	pub fn build(self) {
 		let out_dir = PathBuf::from(env::var("OUT_DIR").expect("`OUT_DIR` is set by cargo!"));
@@ -165,6 +181,7 @@ impl WasmBuilder {
 			self.rust_flags.into_iter().map(|f| format!("{} ", f)).collect(),
 			self.features_to_enable,
 			self.file_name,
+			self.enable_metadata_hash,
 		);
 
 		// As last step we need to generate our `rerun-if-changed` stuff. If a build fails, we don't
@@ -226,12 +243,15 @@ fn generate_rerun_if_changed_instructions() {
 /// `wasm_binary_name` - The optional wasm binary name that is extended with
 ///
 /// `.compact.compressed.wasm`. If `None`, the project name will be used.
+///
+/// `enable_metadata_hash` - Embed the metadata digest and build information into the wasm binary.
 fn build_project(
 	file_name: PathBuf,
 	project_cargo_toml: PathBuf,
 	default_rustflags: String,
 	features_to_enable: Vec<String>,
 	wasm_binary_name: Option<String>,
+	enable_metadata_hash: bool,
 ) {
 	let cargo_cmd = match crate::prerequisites::check() {
 		Ok(cmd) => cmd,
@@ -247,6 +267,7 @@ fn build_project(
 		cargo_cmd,
 		features_to_enable,
 		wasm_binary_name,
+		enable_metadata_hash,
 	);
 
 	let (wasm_binary, wasm_binary_bloaty) = if let Some(wasm_binary) = wasm_binary {
@@ -0,0 +1,384 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Deterministic builds of the wasm binary.
+//!
+//! When [`crate::WASM_BUILD_DETERMINISTIC`] is set, the wasm binary is built with the toolchain
+//! pinned in the `rust-toolchain.toml` (or `rust-toolchain`) file of the project, with all local
+//! paths remapped and without any codegen option that depends on the machine doing the build.
+//! Next to the wasm binary a [`BuildManifest`] is written, which can be checked with [`verify`].
+
+use crate::CargoCommand;
+
+use std::{
+	env, fs,
+	path::{Path, PathBuf},
+	process::Command,
+};
+use toml::value::Table;
+
+/// The extension of the build manifest that is written next to the wasm binary.
+const BUILD_MANIFEST_EXTENSION: &str = "build-manifest.toml";
+
+/// Channels that do not pin a specific toolchain version.
+const UNPINNED_CHANNELS: &[&str] = &["stable", "beta", "nightly"];
+
+/// Returns `true` when deterministic builds are enabled.
+pub(crate) fn enabled() -> bool {
+	env::var(crate::WASM_BUILD_DETERMINISTIC).is_ok()
+}
+
+/// Returns the file name of the build manifest for the wasm binary with the given `name`.
+pub(crate) fn build_manifest_file_name(name: &str) -> String {
+	format!("{}.{}", name, BUILD_MANIFEST_EXTENSION)
+}
+
+/// Returns the cargo command using the toolchain pinned by the project.
+///
+/// Returns `Ok(None)` if deterministic builds are not enabled.
+pub(crate) fn pinned_cargo_command() -> Result<Option<CargoCommand>, String> {
+	if !enabled() {
+		return Ok(None)
+	}
+
+	let toolchain = toolchain()?;
+
+	if let Ok(requested) = env::var(crate::WASM_BUILD_TOOLCHAIN) {
+		if requested != toolchain {
+			return Err(format!(
+				"`{}` requests toolchain `{}`, but the project pins `{}` for deterministic builds.",
+				crate::WASM_BUILD_TOOLCHAIN,
+				requested,
+				toolchain,
+			))
+		}
+	}
+
+	Ok(Some(CargoCommand::new_with_args("rustup", &["run", &toolchain, "cargo"])))
+}
+
+/// Returns the toolchain pinned by the project that is currently being built.
+pub(crate) fn toolchain() -> Result<String, String> {
+	let manifest_dir = PathBuf::from(
+		env::var("CARGO_MANIFEST_DIR")
+			.expect("`CARGO_MANIFEST_DIR` is always set for `build.rs` files; qed"),
+	);
+
+	pinned_toolchain(&manifest_dir)
+}
+
+/// Find the toolchain file of the project in `dir` or any of its parents and return the pinned
+/// toolchain.
+fn pinned_toolchain(dir: &Path) -> Result<String, String> {
+	let (path, content) = dir
+		.ancestors()
+		.flat_map(|dir| [dir.join("rust-toolchain.toml"), dir.join("rust-toolchain")])
+		.find_map(|path| fs::read_to_string(&path).ok().map(|content| (path, content)))
+		.ok_or_else(|| {
+			format!(
+				"Deterministic builds require a `rust-toolchain.toml` file pinning the toolchain, \
+				 but none was found in `{}` or any of its parents.",
+				dir.display(),
+			)
+		})?;
+	build_helper::rerun_if_changed(&path);
+
+	let toolchain = parse_toolchain_file(&content)
+		.ok_or_else(|| format!("`{}` does not specify a toolchain channel.", path.display()))?;
+
+	if UNPINNED_CHANNELS.contains(&toolchain.as_str()) {
+		return Err(format!(
+			"`{}` needs to pin a specific toolchain version for deterministic builds, found `{}`.",
+			path.display(),
+			toolchain,
+		))
+	}
+
+	Ok(toolchain)
+}
+
+/// Parse the toolchain channel from the content of a toolchain file.
+///
+/// Supports the toml format as well as the legacy format that only contains the channel.
+fn parse_toolchain_file(content: &str) -> Option<String> {
+	match toml::from_str::<Table>(content) {
+		Ok(file) => file
+			.get("toolchain")
+			.and_then(|t| t.get("channel"))
+			.and_then(|c| c.as_str())
+			.map(ToOwned::to_owned),
+		Err(_) => {
+			let channel = content.trim();
+			(!channel.is_empty() && !channel.contains(char::is_whitespace))
+				.then(|| channel.to_owned())
+		},
+	}
+}
+
+/// Returns the `RUSTFLAGS` that make the build independent of the machine doing it.
+///
+/// All local paths that end up in the binary are remapped to fixed prefixes and the crates are
+/// compiled as a single codegen unit without debug info.
+pub(crate) fn rustflags(workspace_root: &Path, wasm_workspace: &Path) -> String {
+	let cargo_home = env::var("CARGO_HOME")
+		.map(PathBuf::from)
+		.ok()
+		.or_else(|| env::var("HOME").ok().map(|home| PathBuf::from(home).join(".cargo")));
+
+	// The last matching remapping wins, so more specific prefixes need to come last.
+	let mut remaps = Vec::new();
+	if let Some(cargo_home) = cargo_home {
+		remaps.push((cargo_home, "/cargo"));
+	}
+	remaps.push((workspace_root.to_path_buf(), "/build"));
+	remaps.push((wasm_workspace.to_path_buf(), "/wbuild"));
+
+	let mut flags = remaps
+		.into_iter()
+		.map(|(from, to)| format!("--remap-path-prefix={}={}", from.display(), to))
+		.collect::<Vec<_>>();
+	flags.push("-C codegen-units=1".into());
+	flags.push("-C debuginfo=0".into());
+
+	flags.join(" ")
+}
+
+/// Describes how a wasm binary was built deterministically.
+///
+/// Written next to the wasm binary as `<name>.build-manifest.toml`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildManifest {
+	/// The toolchain pinned by the project.
+	pub toolchain: String,
+	/// The version of `rustc` as reported by the compiler.
+	pub rustc_version: String,
+	/// The cargo profile the wasm binary was built with.
+	pub profile: String,
+	/// The features that were enabled for the wasm build, sorted.
+	pub features: Vec<String>,
+	/// Additional `RUSTFLAGS` given through [`crate::WASM_BUILD_RUSTFLAGS_ENV`].
+	pub extra_rustflags: String,
+	/// The file name of the wasm binary.
+	pub wasm: String,
+	/// The hex encoded blake2-256 hash of the wasm binary.
+	pub blake2_256: String,
+}
+
+impl BuildManifest {
+	/// Read the build manifest from the given `path`.
+	pub fn read(path: &Path) -> Result<Self, String> {
+		let content = fs::read_to_string(path)
+			.map_err(|e| format!("Failed to read `{}`: {}", path.display(), e))?;
+
+		Self::from_toml(&content).map_err(|e| format!("Invalid `{}`: {}", path.display(), e))
+	}
+
+	fn from_toml(content: &str) -> Result<Self, String> {
+		let table = toml::from_str::<Table>(content).map_err(|e| e.to_string())?;
+		let string = |key: &str| {
+			table
+				.get(key)
+				.and_then(|v| v.as_str())
+				.map(ToOwned::to_owned)
+				.ok_or_else(|| format!("missing `{}`", key))
+		};
+		let features = table
+			.get("features")
+			.and_then(|v| v.as_array())
+			.ok_or_else(|| "missing `features`".to_string())?
+			.iter()
+			.map(|f| f.as_str().map(ToOwned::to_owned).ok_or_else(|| "invalid feature".to_string()))
+			.collect::<Result<_, _>>()?;
+
+		Ok(Self {
+			toolchain: string("toolchain")?,
+			rustc_version: string("rustc_version")?,
+			profile: string("profile")?,
+			features,
+			extra_rustflags: string("extra_rustflags")?,
+			wasm: string("wasm")?,
+			blake2_256: string("blake2_256")?,
+		})
+	}
+
+	fn to_toml(&self) -> String {
+		let mut table = Table::new();
+		table.insert("toolchain".into(), self.toolchain.clone().into());
+		table.insert("rustc_version".into(), self.rustc_version.clone().into());
+		table.insert("profile".into(), self.profile.clone().into());
+		table.insert("features".into(), self.features.clone().into());
+		table.insert("extra_rustflags".into(), self.extra_rustflags.clone().into());
+		table.insert("wasm".into(), self.wasm.clone().into());
+		table.insert("blake2_256".into(), self.blake2_256.clone().into());
+
+		toml::to_string_pretty(&table).expect("Build manifest toml is valid; qed")
+	}
+
+	/// Write the build manifest to the given `path`.
+	pub(crate) fn write(&self, path: &Path) {
+		crate::write_file_if_changed(path, self.to_toml());
+	}
+
+	/// Returns the fields in which `self` and `other` differ.
+	fn differences(&self, other: &Self) -> Vec<String> {
+		let fields = [
+			("toolchain", &self.toolchain, &other.toolchain),
+			("rustc_version", &self.rustc_version, &other.rustc_version),
+			("profile", &self.profile, &other.profile),
+			("extra_rustflags", &self.extra_rustflags, &other.extra_rustflags),
+			("blake2_256", &self.blake2_256, &other.blake2_256),
+		];
+
+		let mut differences = fields
+			.iter()
+			.filter(|(_, a, b)| a != b)
+			.map(|(name, a, b)| format!("{}: expected `{}`, got `{}`", name, a, b))
+			.collect::<Vec<_>>();
+		if self.features != other.features {
+			differences
+				.push(format!("features: expected {:?}, got {:?}", self.features, other.features));
+		}
+
+		differences
+	}
+}
+
+/// Returns the hex encoded blake2-256 hash of the given wasm binary.
+pub(crate) fn hash_wasm(path: &Path) -> String {
+	let wasm = fs::read(path).expect("Failed to read WASM binary");
+
+	let hash = sp_core_hashing::blake2_256(&wasm);
+	format!("0x{}", hash.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// Rebuild the wasm binary of the project at `project_cargo_toml` deterministically and compare
+/// it with the given build manifest.
+///
+/// The project is built from scratch in a temporary target directory, using the profile and the
+/// additional `RUSTFLAGS` recorded in the manifest. Returns the manifest of the rebuild on
+/// success and a description of all differences otherwise.
+pub fn verify(project_cargo_toml: &Path, manifest: &Path) -> Result<BuildManifest, String> {
+	let expected = BuildManifest::read(manifest)?;
+	let crate_name = crate::wasm_project::get_crate_name(project_cargo_toml);
+
+	let build_dir =
+		tempfile::tempdir().map_err(|e| format!("Failed to create the build directory: {}", e))?;
+	let out_dir = build_dir.path().join("out");
+
+	let features = expected
+		.features
+		.iter()
+		.filter(|f| *f != "runtime-wasm")
+		.cloned()
+		.collect::<Vec<_>>()
+		.join(",");
+
+	let mut build_cmd = Command::new("cargo");
+	build_cmd
+		.args(["build", "--manifest-path"])
+		.arg(project_cargo_toml)
+		.arg(format!("--features={}", features))
+		.env("CARGO_TARGET_DIR", build_dir.path().join("target"))
+		.env(crate::WASM_BUILD_DETERMINISTIC, "1")
+		.env(crate::WASM_BUILD_TYPE_ENV, &expected.profile)
+		.env(crate::WASM_BUILD_RUSTFLAGS_ENV, &expected.extra_rustflags)
+		.env(crate::WASM_TARGET_DIRECTORY, &out_dir)
+		.env_remove(crate::SKIP_BUILD_ENV)
+		.env_remove(crate::WASM_BUILD_TOOLCHAIN);
+
+	if crate::wasm_project::offline_build() {
+		build_cmd.arg("--offline");
+	}
+
+	match build_cmd.status() {
+		Ok(status) if status.success() => {},
+		Ok(status) => return Err(format!("Rebuilding `{}` failed: {}", crate_name, status)),
+		Err(e) => return Err(format!("Failed to run `cargo`: {}", e)),
+	}
+
+	let rebuilt = BuildManifest::read(
+		&out_dir.join(build_manifest_file_name(&crate_name.replace('-', "_"))),
+	)?;
+
+	let differences = expected.differences(&rebuilt);
+	if differences.is_empty() {
+		Ok(rebuilt)
+	} else {
+		Err(format!(
+			"The rebuilt wasm binary of `{}` does not match the build manifest:\n{}",
+			crate_name,
+			differences.join("\n"),
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_toolchain_file_works() {
+		assert_eq!(
+			parse_toolchain_file(
+				"[toolchain]\nchannel = \"1.70.0\"\ntargets = [\"wasm32-unknown-unknown\"]\n"
+			),
+			Some("1.70.0".into()),
+		);
+		assert_eq!(parse_toolchain_file("nightly-2023-05-22\n"), Some("nightly-2023-05-22".into()));
+		assert_eq!(parse_toolchain_file("[toolchain]\nprofile = \"minimal\"\n"), None);
+		assert_eq!(parse_toolchain_file(""), None);
+	}
+
+	#[test]
+	fn pinned_toolchain_rejects_unpinned_channels() {
+		let dir = tempfile::tempdir().unwrap();
+		let project = dir.path().join("runtime");
+		fs::create_dir_all(&project).unwrap();
+
+		assert!(pinned_toolchain(&project).is_err());
+
+		fs::write(dir.path().join("rust-toolchain.toml"), "[toolchain]\nchannel = \"stable\"\n")
+			.unwrap();
+		assert!(pinned_toolchain(&project).unwrap_err().contains("`stable`"));
+
+		fs::write(dir.path().join("rust-toolchain.toml"), "[toolchain]\nchannel = \"1.70.0\"\n")
+			.unwrap();
+		assert_eq!(pinned_toolchain(&project).unwrap(), "1.70.0");
+	}
+
+	#[test]
+	fn build_manifest_roundtrip_and_differences() {
+		let manifest = BuildManifest {
+			toolchain: "1.70.0".into(),
+			rustc_version: "rustc 1.70.0 (90c541806 2023-05-31)".into(),
+			profile: "production".into(),
+			features: vec!["runtime-wasm".into()],
+			extra_rustflags: String::new(),
+			wasm: "node_runtime.compact.compressed.wasm".into(),
+			blake2_256: format!("0x{}", "00".repeat(32)),
+		};
+
+		assert_eq!(BuildManifest::from_toml(&manifest.to_toml()).unwrap(), manifest);
+		assert!(manifest.differences(&manifest).is_empty());
+
+		let rebuilt =
+			BuildManifest { blake2_256: format!("0x{}", "11".repeat(32)), ..manifest.clone() };
+		let differences = manifest.differences(&rebuilt);
+		assert_eq!(differences.len(), 1);
+		assert!(differences[0].starts_with("blake2_256"));
+	}
+}
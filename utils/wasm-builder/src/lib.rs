@@ -89,11 +89,35 @@
 //!   actual workspace.
 //! - `CARGO_NET_OFFLINE` - If `true`, `--offline` will be passed to all processes launched to
 //!   prevent network access. Useful in offline environments.
+//! - `WASM_BUILD_DETERMINISTIC` - Build the Wasm binary deterministically, see [Deterministic
+//!   builds](#deterministic-builds).
 //!
 //! Each project can be skipped individually by using the environment variable
 //! `SKIP_PROJECT_NAME_WASM_BUILD`. Where `PROJECT_NAME` needs to be replaced by the name of the
 //! cargo project, e.g. `kitchensink-runtime` will be `NODE_RUNTIME`.
 //!
+//! ## Deterministic builds
+//!
+//! By default the Wasm binary is built with whatever toolchain is found on the host, which makes
+//! it hard to reproduce a given binary. When `WASM_BUILD_DETERMINISTIC` is set, the Wasm binary is
+//! built with the toolchain pinned in the `rust-toolchain.toml` file of the project, all local
+//! paths are remapped and it defaults to the `production` profile. Next to the compressed Wasm
+//! binary a `<name>.build-manifest.toml` is written that records the toolchain, the enabled
+//! features and the blake2-256 hash of the binary. [`verify`] rebuilds a project from scratch and
+//! compares the result with a given build manifest.
+//!
+//! ## Metadata hash
+//!
+//! With the `metadata-hash` feature enabled, [`WasmBuilder::enable_metadata_hash`] executes the
+//! `Metadata_metadata` runtime api of the built Wasm binary and calculates the merkleized digest
+//! of the metadata. The digest is embedded together with the git commit, the enabled features and
+//! the profile as `runtime_build_info` custom section into the Wasm binary. It can be read without
+//! executing the runtime using `sc_executor::read_embedded_build_info`.
+//!
+//! To make the digest available to the runtime, e.g. for `frame_system::CheckMetadataHash`, the
+//! Wasm binary is built a second time with the digest set as hex string in the
+//! `RUNTIME_METADATA_HASH` environment variable.
+//!
 //! ## Prerequisites:
 //!
 //! Wasm builder requires the following prerequisites for building the Wasm binary:
@@ -118,11 +142,15 @@ use std::{
 use version::Version;
 
 mod builder;
+mod deterministic;
+#[cfg(feature = "metadata-hash")]
+mod metadata_hash;
 mod prerequisites;
 mod version;
 mod wasm_project;
 
 pub use builder::{WasmBuilder, WasmBuilderSelectProject};
+pub use deterministic::{verify, BuildManifest};
 
 /// Environment variable that tells us to skip building the wasm binary.
 const SKIP_BUILD_ENV: &str = "SKIP_WASM_BUILD";
@@ -158,6 +186,12 @@ const FORCE_WASM_BUILD_ENV: &str = "FORCE_WASM_BUILD";
 /// Environment variable that hints the workspace we are building.
 const WASM_BUILD_WORKSPACE_HINT: &str = "WASM_BUILD_WORKSPACE_HINT";
 
+/// Environment variable that enables deterministic builds of the wasm binary.
+const WASM_BUILD_DETERMINISTIC: &str = "WASM_BUILD_DETERMINISTIC";
+
+/// Environment variable that passes the metadata digest to the build of the wasm binary.
+const RUNTIME_METADATA_HASH_ENV: &str = "RUNTIME_METADATA_HASH";
+
 /// Write to the given `file` if the `content` is different.
 fn write_file_if_changed(file: impl AsRef<Path>, content: impl AsRef<str>) {
 	if fs::read_to_string(file.as_ref()).ok().as_deref() != Some(content.as_ref()) {
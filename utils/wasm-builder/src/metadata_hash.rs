@@ -0,0 +1,93 @@
+// This file is part of Substrate.
+
+// Copyright (C) Parity Technologies (UK) Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Calculation of the metadata digest and embedding of the build info into the wasm binary.
+
+use codec::Decode;
+use frame_support::metadata::RuntimeMetadataPrefixed;
+use sc_executor::{WasmExecutionMethod, WasmExecutor};
+use sc_executor_common::runtime_blob::RuntimeBlob;
+use sp_version::embed::BuildInfo;
+use std::{fs, path::Path, process::Command};
+
+/// Collect the [`BuildInfo`] of the given runtime `wasm` binary.
+///
+/// The metadata digest is calculated from the metadata returned by executing the
+/// `Metadata_metadata` runtime api.
+pub(crate) fn build_info(
+	wasm: &Path,
+	cargo_manifest: &Path,
+	profile: &str,
+	features: &[String],
+) -> Result<BuildInfo, String> {
+	let metadata = runtime_metadata(wasm)?;
+	let metadata_hash = frame_support::metadata_ir::metadata_digest(&metadata)
+		.ok_or_else(|| "The runtime metadata version is not supported by the digest".to_owned())?;
+
+	Ok(BuildInfo {
+		metadata_hash,
+		git_commit: git_commit(cargo_manifest),
+		features: features.to_vec(),
+		profile: profile.into(),
+	})
+}
+
+/// Embed the given `build_info` into the wasm binary at `wasm`, overwriting the file.
+pub(crate) fn embed(wasm: &Path, build_info: &BuildInfo) -> Result<(), String> {
+	let code = fs::read(wasm).map_err(|e| format!("Failed to read `{}`: {}", wasm.display(), e))?;
+	let code = sp_version::embed::embed_build_info(&code, build_info)
+		.map_err(|e| format!("Failed to embed the build info: {}", e))?;
+
+	fs::write(wasm, code).map_err(|e| format!("Failed to write `{}`: {}", wasm.display(), e))
+}
+
+/// Execute the `Metadata_metadata` runtime api of the given runtime `wasm` binary.
+fn runtime_metadata(wasm: &Path) -> Result<RuntimeMetadataPrefixed, String> {
+	let code = fs::read(wasm).map_err(|e| format!("Failed to read `{}`: {}", wasm.display(), e))?;
+	let blob = RuntimeBlob::uncompress_if_needed(&code)
+		.map_err(|e| format!("Failed to load the runtime: {}", e))?;
+
+	let executor =
+		WasmExecutor::<sp_io::SubstrateHostFunctions>::builder(WasmExecutionMethod::Interpreted)
+			.with_allow_missing_host_functions(true)
+			.build();
+
+	let mut ext = sp_io::TestExternalities::default();
+	let encoded = executor
+		.uncached_call(blob, &mut ext.ext(), true, "Metadata_metadata", &[])
+		.map_err(|e| format!("Failed to call `Metadata_metadata`: {}", e))?;
+
+	let metadata = <Vec<u8>>::decode(&mut &encoded[..])
+		.map_err(|e| format!("Failed to decode the metadata: {}", e))?;
+	RuntimeMetadataPrefixed::decode(&mut &metadata[..])
+		.map_err(|e| format!("Failed to decode the metadata: {}", e))
+}
+
+/// Returns the git commit of the checkout containing `cargo_manifest`, if any.
+fn git_commit(cargo_manifest: &Path) -> Option<String> {
+	let output = Command::new("git")
+		.args(["rev-parse", "HEAD"])
+		.current_dir(cargo_manifest.parent()?)
+		.output()
+		.ok()?;
+
+	if !output.status.success() {
+		return None
+	}
+
+	String::from_utf8(output.stdout).ok().map(|commit| commit.trim().to_owned())
+}
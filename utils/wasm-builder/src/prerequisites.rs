@@ -35,7 +35,11 @@ fn print_error_message(message: &str) -> String {
 ///
 /// Returns the versioned cargo command on success.
 pub(crate) fn check() -> Result<CargoCommandVersioned, String> {
-	let cargo_command = crate::get_cargo_command();
+	let cargo_command = match crate::deterministic::pinned_cargo_command() {
+		Ok(Some(cargo_command)) => cargo_command,
+		Ok(None) => crate::get_cargo_command(),
+		Err(err) => return Err(print_error_message(&err)),
+	};
 
 	if !cargo_command.supports_substrate_wasm_env() {
 		return Err(print_error_message(
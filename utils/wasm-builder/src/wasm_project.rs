@@ -116,13 +116,14 @@ pub(crate) fn create_and_compile(
 	cargo_cmd: CargoCommandVersioned,
 	features_to_enable: Vec<String>,
 	wasm_binary_name: Option<String>,
+	enable_metadata_hash: bool,
 ) -> (Option<WasmBinary>, WasmBinaryBloaty) {
 	let wasm_workspace_root = get_wasm_workspace_root();
 	let wasm_workspace = wasm_workspace_root.join("wbuild");
 
 	let crate_metadata = crate_metadata(project_cargo_toml);
 
-	let project = create_project(
+	let (project, enabled_features) = create_project(
 		project_cargo_toml,
 		&wasm_workspace,
 		&crate_metadata,
@@ -130,9 +131,37 @@ pub(crate) fn create_and_compile(
 		features_to_enable,
 	);
 
-	let profile = build_project(&project, default_rustflags, cargo_cmd);
+	let deterministic = crate::deterministic::enabled();
+	let rustflags = if deterministic {
+		let workspace_root = crate_metadata.workspace_root.as_ref();
+		format!(
+			"{} {}",
+			default_rustflags,
+			crate::deterministic::rustflags(workspace_root, &wasm_workspace)
+		)
+	} else {
+		default_rustflags.to_owned()
+	};
+	let rustc_version = cargo_cmd.rustc_version().to_owned();
+
+	let profile = build_project(&project, &rustflags, &cargo_cmd, None);
+	let profile_name = profile.name();
 	let (wasm_binary, wasm_binary_compressed, bloaty) =
-		compact_wasm_file(&project, profile, project_cargo_toml, wasm_binary_name);
+		compact_wasm_file(&project, profile, project_cargo_toml, wasm_binary_name.clone());
+
+	let (wasm_binary, wasm_binary_compressed, bloaty) = if enable_metadata_hash {
+		build_with_metadata_hash(
+			&project,
+			&rustflags,
+			&cargo_cmd,
+			project_cargo_toml,
+			wasm_binary_name.clone(),
+			&bloaty,
+			&enabled_features,
+		)
+	} else {
+		(wasm_binary, wasm_binary_compressed, bloaty)
+	};
 
 	wasm_binary
 		.as_ref()
@@ -144,6 +173,18 @@ pub(crate) fn create_and_compile(
 
 	let final_wasm_binary = wasm_binary_compressed.or(wasm_binary);
 
+	if let (true, Some(final_wasm_binary)) = (deterministic, final_wasm_binary.as_ref()) {
+		write_build_manifest(
+			project_cargo_toml,
+			&project,
+			wasm_binary_name,
+			final_wasm_binary,
+			rustc_version,
+			profile_name,
+			enabled_features,
+		);
+	}
+
 	generate_rerun_if_changed_instructions(
 		project_cargo_toml,
 		&project,
@@ -159,6 +200,121 @@ pub(crate) fn create_and_compile(
 	(final_wasm_binary, bloaty)
 }
 
+/// Rebuild the project with the metadata digest of the given `bloaty` wasm binary and embed the
+/// build info into the resulting wasm binaries.
+///
+/// The digest is passed to the build in the `RUNTIME_METADATA_HASH` environment variable, to make
+/// it available to the runtime. As the digest is not part of the metadata, it is the same for both
+/// builds.
+#[cfg(feature = "metadata-hash")]
+fn build_with_metadata_hash(
+	project: &Path,
+	rustflags: &str,
+	cargo_cmd: &CargoCommandVersioned,
+	project_cargo_toml: &Path,
+	wasm_binary_name: Option<String>,
+	bloaty: &WasmBinaryBloaty,
+	features: &[String],
+) -> (Option<WasmBinary>, Option<WasmBinary>, WasmBinaryBloaty) {
+	let profile = Profile::detect(project);
+	let build_info = crate::metadata_hash::build_info(
+		bloaty.wasm_binary_bloaty_path(),
+		project_cargo_toml,
+		profile.name(),
+		features,
+	)
+	.unwrap_or_else(|e| panic!("Failed to collect the build info: {}", e));
+
+	let profile = build_project(project, rustflags, cargo_cmd, Some(&build_info.metadata_hash));
+	let (wasm_binary, wasm_binary_compressed, bloaty) =
+		compact_wasm_file(project, profile, project_cargo_toml, wasm_binary_name);
+
+	let embed = |path: &Path| {
+		crate::metadata_hash::embed(path, &build_info)
+			.unwrap_or_else(|e| panic!("Failed to embed the build info: {}", e))
+	};
+
+	embed(bloaty.wasm_binary_bloaty_path());
+
+	if let Some(wasm_binary) = wasm_binary.as_ref() {
+		embed(wasm_binary.wasm_binary_path());
+
+		// The compressed wasm binary needs to be recreated from the compact one.
+		if let Some(wasm_binary_compressed) = wasm_binary_compressed.as_ref() {
+			compress_wasm(
+				wasm_binary.wasm_binary_path(),
+				wasm_binary_compressed.wasm_binary_path(),
+			);
+		}
+	}
+
+	(wasm_binary, wasm_binary_compressed, bloaty)
+}
+
+#[cfg(not(feature = "metadata-hash"))]
+fn build_with_metadata_hash(
+	_: &Path,
+	_: &str,
+	_: &CargoCommandVersioned,
+	_: &Path,
+	_: Option<String>,
+	_: &WasmBinaryBloaty,
+	_: &[String],
+) -> (Option<WasmBinary>, Option<WasmBinary>, WasmBinaryBloaty) {
+	unreachable!("`WasmBuilder::enable_metadata_hash` requires the `metadata-hash` feature; qed")
+}
+
+/// Write the build manifest of a deterministic build next to the final wasm binary and copy it to
+/// the target directory.
+fn write_build_manifest(
+	project_cargo_toml: &Path,
+	project: &Path,
+	wasm_binary_name: Option<String>,
+	final_wasm_binary: &WasmBinary,
+	rustc_version: String,
+	profile: &str,
+	features: Vec<String>,
+) {
+	let manifest = crate::deterministic::BuildManifest {
+		toolchain: crate::deterministic::toolchain()
+			.expect("The pinned toolchain was checked as part of the prerequisites; qed"),
+		rustc_version,
+		profile: profile.into(),
+		features,
+		extra_rustflags: env::var(crate::WASM_BUILD_RUSTFLAGS_ENV).unwrap_or_default(),
+		wasm: final_wasm_binary
+			.wasm_binary_path()
+			.file_name()
+			.expect("The wasm binary is a file; qed")
+			.to_string_lossy()
+			.into_owned(),
+		blake2_256: crate::deterministic::hash_wasm(final_wasm_binary.wasm_binary_path()),
+	};
+
+	let manifest_name = crate::deterministic::build_manifest_file_name(
+		&wasm_binary_name.unwrap_or_else(|| get_wasm_binary_name(project_cargo_toml)),
+	);
+	let manifest_path = project.join(manifest_name);
+	manifest.write(&manifest_path);
+
+	println!(
+		"{} {} ({})",
+		colorize_info_message("Deterministic build of"),
+		manifest.wasm,
+		manifest.blake2_256,
+	);
+
+	if let Ok(target_dir) = env::var(crate::WASM_TARGET_DIRECTORY) {
+		fs::copy(
+			&manifest_path,
+			PathBuf::from(target_dir).join(crate::deterministic::build_manifest_file_name(
+				&get_wasm_binary_name(project_cargo_toml),
+			)),
+		)
+		.expect("Copies build manifest to `WASM_TARGET_DIRECTORY`.");
+	}
+}
+
 /// Adjust the mtime of the bloaty and compressed/compact wasm files.
 ///
 /// We add the bloaty and the compressed/compact wasm file to the `rerun-if-changed` files.
@@ -232,7 +388,7 @@ fn find_cargo_lock(cargo_manifest: &Path) -> Option<PathBuf> {
 }
 
 /// Extract the crate name from the given `Cargo.toml`.
-fn get_crate_name(cargo_manifest: &Path) -> String {
+pub(crate) fn get_crate_name(cargo_manifest: &Path) -> String {
 	let cargo_toml: Table = toml::from_str(
 		&fs::read_to_string(cargo_manifest).expect("File exists as checked before; qed"),
 	)
@@ -462,14 +618,14 @@ fn has_runtime_wasm_feature_declared(
 ///
 /// # Returns
 ///
-/// The path to the created wasm project.
+/// The path to the created wasm project and the sorted features enabled for it.
 fn create_project(
 	project_cargo_toml: &Path,
 	wasm_workspace: &Path,
 	crate_metadata: &Metadata,
 	workspace_root_path: &Path,
 	features_to_enable: Vec<String>,
-) -> PathBuf {
+) -> (PathBuf, Vec<String>) {
 	let crate_name = get_crate_name(project_cargo_toml);
 	let crate_path = project_cargo_toml.parent().expect("Parent path exists; qed");
 	let wasm_binary = get_wasm_binary_name(project_cargo_toml);
@@ -487,6 +643,8 @@ fn create_project(
 
 	let mut enabled_features = enabled_features.into_iter().collect::<HashSet<_>>();
 	enabled_features.extend(features_to_enable.into_iter());
+	let mut enabled_features = enabled_features.into_iter().collect::<Vec<_>>();
+	enabled_features.sort();
 
 	create_project_cargo_toml(
 		&wasm_project_folder,
@@ -494,7 +652,7 @@ fn create_project(
 		&crate_name,
 		crate_path,
 		&wasm_binary,
-		enabled_features.into_iter(),
+		enabled_features.iter().cloned(),
 	);
 
 	write_file_if_changed(
@@ -507,7 +665,7 @@ fn create_project(
 		crate::copy_file_if_changed(crate_lock_file, wasm_project_folder.join("Cargo.lock"));
 	}
 
-	wasm_project_folder
+	(wasm_project_folder, enabled_features)
 }
 
 /// The cargo profile that is used to build the wasm project.
@@ -535,6 +693,9 @@ impl Profile {
 	fn detect(wasm_project: &Path) -> Profile {
 		let (name, overriden) = if let Ok(name) = env::var(crate::WASM_BUILD_TYPE_ENV) {
 			(name, true)
+		} else if crate::deterministic::enabled() {
+			// Deterministic builds are meant for the binaries that are put on chain.
+			return Profile::Production
 		} else {
 			// First go backwards to the beginning of the target directory.
 			// Then go forwards to find the "wbuild" directory.
@@ -618,15 +779,19 @@ impl Profile {
 }
 
 /// Check environment whether we should build without network
-fn offline_build() -> bool {
+pub(crate) fn offline_build() -> bool {
 	env::var(OFFLINE).map_or(false, |v| v == "true")
 }
 
 /// Build the project to create the WASM binary.
+///
+/// If given, `metadata_hash` is passed to the build in the `RUNTIME_METADATA_HASH` environment
+/// variable as hex string.
 fn build_project(
 	project: &Path,
 	default_rustflags: &str,
-	cargo_cmd: CargoCommandVersioned,
+	cargo_cmd: &CargoCommandVersioned,
+	metadata_hash: Option<&[u8; 32]>,
 ) -> Profile {
 	let manifest_path = project.join("Cargo.toml");
 	let mut build_cmd = cargo_cmd.command();
@@ -652,6 +817,19 @@ fn build_project(
 		// We don't want to call ourselves recursively
 		.env(crate::SKIP_BUILD_ENV, "");
 
+	if crate::deterministic::enabled() {
+		build_cmd.env("CARGO_INCREMENTAL", "0");
+	}
+
+	match metadata_hash {
+		Some(hash) => {
+			let hash = hash.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+			build_cmd.env(crate::RUNTIME_METADATA_HASH_ENV, format!("0x{}", hash))
+		},
+		// Don't leak a hash of an outer build into this one.
+		None => build_cmd.env_remove(crate::RUNTIME_METADATA_HASH_ENV),
+	};
+
 	if super::color_output_enabled() {
 		build_cmd.arg("--color=always");
 	}
@@ -848,6 +1026,7 @@ fn generate_rerun_if_changed_instructions(
 	println!("cargo:rerun-if-env-changed={}", crate::WASM_BUILD_RUSTFLAGS_ENV);
 	println!("cargo:rerun-if-env-changed={}", crate::WASM_TARGET_DIRECTORY);
 	println!("cargo:rerun-if-env-changed={}", crate::WASM_BUILD_TOOLCHAIN);
+	println!("cargo:rerun-if-env-changed={}", crate::WASM_BUILD_DETERMINISTIC);
 }
 
 /// Track files and paths related to the given package to rerun `build.rs` on any relevant change.